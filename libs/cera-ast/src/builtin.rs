@@ -1,32 +1,762 @@
-use crate::values::Value;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::fmt;
 
-#[derive(Debug, Clone)]
+use hashbrown::HashMap;
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    eval::{coerce, EvaluationErrorKind},
+    expressions::Expression,
+    layout::Target,
+    types::{
+        ArrayType, ContainerType, ContainerVariant, ErrorUnionType, FileId, IntType, OptionType,
+        PointerType, SliceType, Type,
+    },
+    values::{ComptimeFloat, Float, Int, Slice, Value},
+};
+
+/// Functions provided by the compiler, either called with `@name(...)` in source or produced by
+/// lowering operators and types
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BuiltinFunction {
     TypeOf,
+    /// `@import("path")`, the struct declared by another file
+    Import,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    ShiftLeft,
+    ShiftRight,
+    BitAnd,
+    BitXor,
+    BitOr,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+    /// `!`, which is an inferred error union when applied to a type
+    Not,
+    Negate,
+    BitNot,
+    AddressOf,
+    Deref,
+    /// `base[index]`
+    Index,
+    /// `base[start..end]`, where `end` defaults to the length of `base`
+    Slice,
+    /// The amount of values of an array or slice
+    Len,
+    /// Whether an optional is `null`
+    IsNull,
+    /// The value of an optional which isn't `null`
+    UnwrapOptional,
+    /// Whether a value is an error, or an error union holding one
+    IsError,
+    /// The error held by an error union
+    ErrorOf,
+    /// The value held by an error union which isn't an error
+    UnwrapPayload,
+    /// `?T`
+    OptionalType,
+    /// `E!T`
+    ErrorUnionType,
+    /// `*T`, `[*]T` or `[]T`, and `[*:sentinel]T` or `[:sentinel]T` with the sentinel as second
+    /// argument
+    PointerType {
+        size: PointerSize,
+        is_const: bool,
+    },
+    /// `[len]T`, and `[len:sentinel]T` with the sentinel as third argument
+    ArrayType,
+    /// `fn (A, B) -> R`, with the return type as last argument
+    FnType,
+    /// `@sizeOf(T)`, the stride of values of a type on the target
+    SizeOf,
+    /// `@alignOf(T)`
+    AlignOf,
+}
+
+/// How many values a pointer type points to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PointerSize {
+    One,
+    Many,
+    Slice,
+}
+
+/// How the builtin is written in source, the operator or construct lowered to it if it isn't
+/// called with `@name(...)`
+impl fmt::Display for BuiltinFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use BuiltinFunction as B;
+        let text = match self {
+            B::TypeOf => "@TypeOf",
+            B::Import => "@import",
+            B::SizeOf => "@sizeOf",
+            B::AlignOf => "@alignOf",
+            B::Add => "+",
+            B::Sub | B::Negate => "-",
+            B::Mul => "*",
+            B::Div => "/",
+            B::Mod => "%",
+            B::ShiftLeft => "<<",
+            B::ShiftRight => ">>",
+            B::BitAnd | B::AddressOf => "&",
+            B::BitXor => "^",
+            B::BitOr => "|",
+            B::Equal => "==",
+            B::NotEqual => "!=",
+            B::LessThan => "<",
+            B::LessOrEqual => "<=",
+            B::GreaterThan => ">",
+            B::GreaterOrEqual => ">=",
+            B::Not | B::ErrorUnionType => "!",
+            B::BitNot => "~",
+            B::Deref => ".*",
+            B::Index => "[index]",
+            B::Slice => "[start..end]",
+            B::Len => "for",
+            B::IsNull => "orelse",
+            B::UnwrapOptional => ".?",
+            B::IsError | B::ErrorOf | B::UnwrapPayload => "catch",
+            B::OptionalType => "?",
+            B::PointerType { size, is_const } => {
+                let size = match size {
+                    PointerSize::One => "*",
+                    PointerSize::Many => "[*]",
+                    PointerSize::Slice => "[]",
+                };
+                return match is_const {
+                    true => write!(f, "{size}const"),
+                    false => write!(f, "{size}"),
+                };
+            }
+            B::ArrayType => "[len]",
+            B::FnType => "fn",
+        };
+        write!(f, "{text}")
+    }
 }
 
 impl BuiltinFunction {
-    pub fn try_exec(&self, args: &[&Value], context: &mut EvalExecScope) -> Option<Value> {
-        match self {
-            BuiltinFunction::TypeOf => {
-                if args.len() != 1 {
-                    return None;
+    /// The builtin called as `@name(...)`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "TypeOf" => Some(BuiltinFunction::TypeOf),
+            "import" => Some(BuiltinFunction::Import),
+            "sizeOf" => Some(BuiltinFunction::SizeOf),
+            "alignOf" => Some(BuiltinFunction::AlignOf),
+            _ => None,
+        }
+    }
+
+    /// Whether the operands are converted to the same type, which literals among them take
+    pub fn has_same_type_operands(&self) -> bool {
+        use BuiltinFunction as B;
+        matches!(
+            self,
+            B::Add
+                | B::Sub
+                | B::Mul
+                | B::Div
+                | B::Mod
+                | B::ShiftLeft
+                | B::ShiftRight
+                | B::BitAnd
+                | B::BitXor
+                | B::BitOr
+                | B::Equal
+                | B::NotEqual
+                | B::LessThan
+                | B::LessOrEqual
+                | B::GreaterThan
+                | B::GreaterOrEqual
+        )
+    }
+
+    /// Whether the result has the type of the operands, which is then the expected one
+    pub fn is_arithmetic(&self) -> bool {
+        use BuiltinFunction as B;
+        self.has_same_type_operands() && !self.is_comparison()
+            || matches!(self, B::Negate | B::BitNot)
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        use BuiltinFunction as B;
+        matches!(
+            self,
+            B::Equal
+                | B::NotEqual
+                | B::LessThan
+                | B::LessOrEqual
+                | B::GreaterThan
+                | B::GreaterOrEqual
+        )
+    }
+
+    pub fn try_exec(
+        &self,
+        args: &[&Value],
+        context: &EvalExecScope,
+    ) -> Result<Value, EvaluationErrorKind> {
+        use BuiltinFunction as B;
+        match (self, args) {
+            (B::TypeOf, [value]) => Ok(Value::Type(value.get_type())),
+            (_, [Value::Int(lhs), Value::Int(rhs)])
+                if self.has_same_type_operands() && lhs.int_type == rhs.int_type =>
+            {
+                self.exec_int(lhs, rhs)
+            }
+            (_, [Value::Float(lhs), Value::Float(rhs)])
+                if self.has_same_type_operands() && lhs.float_type == rhs.float_type =>
+            {
+                self.exec_float(lhs, rhs)
+            }
+            (_, [Value::ComptimeInt(lhs), Value::ComptimeInt(rhs)])
+                if self.has_same_type_operands() =>
+            {
+                self.exec_comptime_int(lhs, rhs)
+            }
+            (_, [lhs, rhs]) if self.has_same_type_operands() => {
+                match (comptime_float(lhs), comptime_float(rhs)) {
+                    (Some(lhs), Some(rhs)) => self.exec_comptime_float(&lhs, &rhs),
+                    _ => self.exec_equality(lhs, rhs, context),
+                }
+            }
+            (B::Not, [Value::Bool(value)]) => Ok(Value::Bool(!value)),
+            (B::Not, [Value::Type(ok)]) => Ok(Value::Type(Type::ErrorUnion(ErrorUnionType {
+                ok: Box::new(ok.clone()),
+                err: None,
+            }))),
+            (B::Negate | B::BitNot, [Value::Int(int)]) => {
+                let value = match self {
+                    B::Negate => -int.value(),
+                    _ => wrap(&!int.value(), &int.int_type),
+                };
+                new_int(value, &int.int_type)
+            }
+            (B::Negate, [Value::ComptimeInt(int)]) => Ok(Value::ComptimeInt(-int)),
+            (B::BitNot, [Value::ComptimeInt(int)]) => Ok(Value::ComptimeInt(!int)),
+            (B::Negate, [Value::ComptimeFloat(float)]) => Ok(Value::ComptimeFloat(float.negated())),
+            (B::Negate, [Value::Float(float)]) => {
+                let value = float
+                    .to_f64()
+                    .ok_or(EvaluationErrorKind::Unsupported(self.clone()))?;
+                Float::from_f64(-value, float.float_type.clone())
+                    .map(Value::Float)
+                    .ok_or(EvaluationErrorKind::Unsupported(self.clone()))
+            }
+            (B::IsNull, [Value::Option(option)]) => Ok(Value::Bool(option.unwrap().is_none())),
+            (B::UnwrapOptional, [Value::Option(option)]) => {
+                option.unwrap().ok_or(EvaluationErrorKind::UnwrapNull)
+            }
+            (B::OptionalType, [Value::Type(some_type)]) => {
+                Ok(Value::Type(Type::Option(OptionType {
+                    some_type: Box::new(some_type.clone()),
+                })))
+            }
+            (B::ArrayType, [Value::Int(size), Value::Type(indexed_type), sentinel @ ..]) => {
+                let size = usize::try_from(size.value())
+                    .map_err(|_| EvaluationErrorKind::InvalidOperands(self.clone()))?;
+                Ok(Value::Type(Type::Array(ArrayType {
+                    size,
+                    sentinel: self.sentinel(indexed_type, sentinel)?,
+                    indexed_type: Box::new(indexed_type.clone()),
+                })))
+            }
+            (B::PointerType { size, is_const }, [Value::Type(pointed), sentinel @ ..]) => {
+                let sentinel = self.sentinel(pointed, sentinel)?;
+                match (size, sentinel) {
+                    (PointerSize::One, Some(_)) => {
+                        Err(EvaluationErrorKind::InvalidOperands(self.clone()))
+                    }
+                    (PointerSize::Slice, sentinel) => Ok(Value::Type(Type::Slice(SliceType {
+                        indexed_type: Box::new(pointed.clone()),
+                        is_const: *is_const,
+                        sentinel,
+                    }))),
+                    (size, sentinel) => Ok(Value::Type(Type::Pointer(PointerType {
+                        pointed_value: Expression::Value(Box::new(Value::Type(pointed.clone()))),
+                        is_const: *is_const,
+                        is_many: *size == PointerSize::Many,
+                        sentinel,
+                    }))),
+                }
+            }
+            (B::Index, [base, Value::Int(index)]) => {
+                let (elements, _) = base
+                    .elements()
+                    .ok_or_else(|| EvaluationErrorKind::InvalidOperands(self.clone()))?;
+                // The sentinel can be read, but isn't counted in the length
+                usize::try_from(index.value())
+                    .ok()
+                    .and_then(|index| elements.get(index))
+                    .ok_or_else(|| EvaluationErrorKind::IndexOutOfBounds {
+                        index: index.value(),
+                        len: elements.array_type.size,
+                    })
+            }
+            (B::Len, [base]) => match base.counted_elements() {
+                Some((elements, _)) => new_int(
+                    elements.array_type.size.into(),
+                    &context.target().usize_type(),
+                ),
+                None => Err(EvaluationErrorKind::InvalidOperands(self.clone())),
+            },
+            (B::Slice, [base, bounds @ ..]) => self.exec_slice(base, bounds),
+            (B::SizeOf | B::AlignOf, [Value::Type(value_type)]) => {
+                let target = context.target();
+                let layout = target
+                    .layout(value_type)
+                    .map_err(EvaluationErrorKind::Layout)?;
+                let size = match self {
+                    B::SizeOf => layout.stride(),
+                    _ => layout.align,
+                };
+                new_int(size.into(), &target.usize_type())
+            }
+            (
+                B::Import
+                | B::AddressOf
+                | B::Deref
+                | B::IsError
+                | B::ErrorOf
+                | B::UnwrapPayload
+                | B::ErrorUnionType
+                | B::FnType,
+                _,
+            ) => Err(EvaluationErrorKind::Unsupported(self.clone())),
+            _ => Err(EvaluationErrorKind::InvalidOperands(self.clone())),
+        }
+    }
+
+    /// `==` and `!=` on values other than numbers, which were given the same type. Enums are equal
+    /// when they are the same variant, and optionals when both are `null` or both hold equal
+    /// values.
+    fn exec_equality(
+        &self,
+        lhs: &Value,
+        rhs: &Value,
+        context: &EvalExecScope,
+    ) -> Result<Value, EvaluationErrorKind> {
+        use BuiltinFunction as B;
+        let invalid = || EvaluationErrorKind::InvalidOperands(self.clone());
+        if !matches!(self, B::Equal | B::NotEqual) {
+            return Err(invalid());
+        }
+        let equal = match (lhs, rhs) {
+            (Value::Bool(lhs), Value::Bool(rhs)) => lhs == rhs,
+            (Value::Type(lhs), Value::Type(rhs)) => lhs == rhs,
+            (Value::Container(lhs), Value::Container(rhs))
+                if matches!(lhs.container_type.variant, ContainerVariant::Enum { .. })
+                    && lhs.container_type == rhs.container_type =>
+            {
+                lhs.bytes == rhs.bytes
+            }
+            (Value::Option(lhs), Value::Option(rhs)) => match (lhs.unwrap(), rhs.unwrap()) {
+                (Some(lhs), Some(rhs)) => return self.try_exec(&[&lhs, &rhs], context),
+                (lhs, rhs) => lhs.is_none() && rhs.is_none(),
+            },
+            _ => return Err(invalid()),
+        };
+        Ok(Value::Bool(equal == matches!(self, B::Equal)))
+    }
+
+    /// The sentinel of `[len:sentinel]T` or `[:sentinel]T`, which must have a value of
+    /// `indexed_type` at runtime
+    fn sentinel(
+        &self,
+        indexed_type: &Type,
+        sentinel: &[&Value],
+    ) -> Result<Option<Box<Value>>, EvaluationErrorKind> {
+        let sentinel = match sentinel {
+            [] => return Ok(None),
+            [sentinel] => coerce((*sentinel).clone(), Some(indexed_type))?,
+            _ => return Err(EvaluationErrorKind::InvalidOperands(self.clone())),
+        };
+        match sentinel
+            .bytes()
+            .and_then(|bytes| Value::from_bytes(indexed_type, bytes))
+        {
+            Some(_) => Ok(Some(Box::new(sentinel))),
+            None => Err(EvaluationErrorKind::InvalidOperands(self.clone())),
+        }
+    }
+
+    /// Slices of arrays are `const`, as they point to a copy of the array. Slicing to the end
+    /// keeps the sentinel, and needs the length many-item pointers don't have.
+    fn exec_slice(&self, base: &Value, bounds: &[&Value]) -> Result<Value, EvaluationErrorKind> {
+        let invalid = || EvaluationErrorKind::InvalidOperands(self.clone());
+        let (elements, is_const) = base.elements().ok_or_else(invalid)?;
+        let len = elements.array_type.size;
+        let bound = |bound: &Value| match bound {
+            Value::Int(int) => usize::try_from(int.value()).ok(),
+            _ => None,
+        };
+        let (start, end, sentinel) = match bounds {
+            [_] if base.counted_elements().is_none() => return Err(invalid()),
+            [start] => (
+                bound(start),
+                Some(len),
+                elements.array_type.sentinel.clone(),
+            ),
+            [start, end] => (bound(start), bound(end), None),
+            _ => return Err(invalid()),
+        };
+        let (Some(start), Some(end)) = (start, end) else {
+            return Err(invalid());
+        };
+        if start > end || end > len {
+            return Err(EvaluationErrorKind::SliceBounds { start, end, len });
+        }
+        let elements = elements
+            .sub_array(start, end, sentinel.clone())
+            .ok_or_else(invalid)?;
+        Ok(Value::Slice(Slice {
+            slice_type: SliceType {
+                indexed_type: elements.array_type.indexed_type.clone(),
+                is_const,
+                sentinel,
+            },
+            elements,
+        }))
+    }
+
+    fn exec_int(&self, lhs: &Int, rhs: &Int) -> Result<Value, EvaluationErrorKind> {
+        use BuiltinFunction as B;
+        let (lhs_value, rhs_value) = (lhs.value(), rhs.value());
+        if self.is_comparison() {
+            return Ok(Value::Bool(self.compare(lhs_value.partial_cmp(&rhs_value))));
+        }
+        let int_type = &lhs.int_type;
+        let value = match self {
+            B::Add => lhs_value + rhs_value,
+            B::Sub => lhs_value - rhs_value,
+            B::Mul => lhs_value * rhs_value,
+            B::Div | B::Mod if rhs_value == BigInt::ZERO => {
+                return Err(EvaluationErrorKind::DivisionByZero)
+            }
+            B::Div => lhs_value / rhs_value,
+            B::Mod => lhs_value % rhs_value,
+            B::ShiftLeft | B::ShiftRight => {
+                let amount = u16::try_from(&rhs_value)
+                    .ok()
+                    .filter(|amount| *amount < int_type.bits)
+                    .ok_or(EvaluationErrorKind::ShiftAmount(rhs_value))?;
+                match self {
+                    // Bits shifted out are discarded
+                    B::ShiftLeft => wrap(&(lhs_value << amount), int_type),
+                    _ => lhs_value >> amount,
                 }
-                return Some(Value::Type(args[0].get_type()));
             }
+            B::BitAnd => lhs_value & rhs_value,
+            B::BitXor => lhs_value ^ rhs_value,
+            B::BitOr => lhs_value | rhs_value,
+            _ => return Err(EvaluationErrorKind::InvalidOperands(self.clone())),
+        };
+        new_int(value, int_type)
+    }
+
+    fn exec_float(&self, lhs: &Float, rhs: &Float) -> Result<Value, EvaluationErrorKind> {
+        use BuiltinFunction as B;
+        let unsupported = || EvaluationErrorKind::Unsupported(self.clone());
+        let (lhs_value, rhs_value) = (
+            lhs.to_f64().ok_or_else(unsupported)?,
+            rhs.to_f64().ok_or_else(unsupported)?,
+        );
+        if self.is_comparison() {
+            return Ok(Value::Bool(self.compare(lhs_value.partial_cmp(&rhs_value))));
+        }
+        let value = match self {
+            B::Add => lhs_value + rhs_value,
+            B::Sub => lhs_value - rhs_value,
+            B::Mul => lhs_value * rhs_value,
+            B::Div => lhs_value / rhs_value,
+            B::Mod => lhs_value % rhs_value,
+            _ => return Err(EvaluationErrorKind::InvalidOperands(self.clone())),
+        };
+        // The operations are exact in `f64` before rounding, so rounding `f32` results only once
+        Float::from_f64(value, lhs.float_type.clone())
+            .map(Value::Float)
+            .ok_or_else(unsupported)
+    }
+
+    /// Comptime integers have no size, so only shifts are bounded, to keep their values in memory
+    fn exec_comptime_int(&self, lhs: &BigInt, rhs: &BigInt) -> Result<Value, EvaluationErrorKind> {
+        use BuiltinFunction as B;
+        if self.is_comparison() {
+            return Ok(Value::Bool(self.compare(lhs.partial_cmp(rhs))));
+        }
+        let value = match self {
+            B::Add => lhs + rhs,
+            B::Sub => lhs - rhs,
+            B::Mul => lhs * rhs,
+            B::Div | B::Mod if *rhs == BigInt::ZERO => {
+                return Err(EvaluationErrorKind::DivisionByZero)
+            }
+            B::Div => lhs / rhs,
+            B::Mod => lhs % rhs,
+            B::ShiftLeft | B::ShiftRight => {
+                let amount = u16::try_from(rhs)
+                    .map_err(|_| EvaluationErrorKind::ShiftAmount(rhs.clone()))?;
+                match self {
+                    B::ShiftLeft => lhs << amount,
+                    _ => lhs >> amount,
+                }
+            }
+            B::BitAnd => lhs & rhs,
+            B::BitXor => lhs ^ rhs,
+            B::BitOr => lhs | rhs,
+            _ => return Err(EvaluationErrorKind::InvalidOperands(self.clone())),
+        };
+        Ok(Value::ComptimeInt(value))
+    }
+
+    fn exec_comptime_float(
+        &self,
+        lhs: &ComptimeFloat,
+        rhs: &ComptimeFloat,
+    ) -> Result<Value, EvaluationErrorKind> {
+        use BuiltinFunction as B;
+        // The exponents are too far apart to align the values
+        let unsupported = || EvaluationErrorKind::Unsupported(self.clone());
+        if self.is_comparison() {
+            let ordering = lhs.compare(rhs).ok_or_else(unsupported)?;
+            return Ok(Value::Bool(self.compare(Some(ordering))));
+        }
+        let value = match self {
+            B::Add => lhs.checked_add(rhs),
+            B::Sub => lhs.checked_sub(rhs),
+            B::Mul => lhs.checked_mul(rhs),
+            B::Div | B::Mod if rhs.is_zero() => return Err(EvaluationErrorKind::DivisionByZero),
+            B::Div => lhs.checked_div(rhs),
+            B::Mod => lhs.checked_rem(rhs),
+            _ => return Err(EvaluationErrorKind::InvalidOperands(self.clone())),
+        };
+        value.map(Value::ComptimeFloat).ok_or_else(unsupported)
+    }
+
+    /// Whether the comparison holds, `ordering` being `None` for unordered values such as NaN
+    fn compare(&self, ordering: Option<Ordering>) -> bool {
+        use BuiltinFunction as B;
+        match self {
+            B::Equal => ordering == Some(Ordering::Equal),
+            B::NotEqual => ordering != Some(Ordering::Equal),
+            B::LessThan => ordering == Some(Ordering::Less),
+            B::LessOrEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            B::GreaterThan => ordering == Some(Ordering::Greater),
+            B::GreaterOrEqual => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            _ => false,
         }
     }
 }
 
+/// Comptime numbers as comptime floats, which mixing them gives
+fn comptime_float(value: &Value) -> Option<ComptimeFloat> {
+    match value {
+        Value::ComptimeInt(int) => Some(ComptimeFloat::from_int(int)),
+        Value::ComptimeFloat(float) => Some(float.clone()),
+        _ => None,
+    }
+}
+
+fn new_int(value: BigInt, int_type: &IntType) -> Result<Value, EvaluationErrorKind> {
+    match Int::new(&value, int_type.clone()) {
+        Some(int) => Ok(Value::Int(int)),
+        None => Err(EvaluationErrorKind::IntOverflow {
+            value,
+            int_type: int_type.clone(),
+        }),
+    }
+}
+
+/// Keeps the lowest bits of `value` which fit in `int_type`, in two's complement
+fn wrap(value: &BigInt, int_type: &IntType) -> BigInt {
+    let modulus = BigInt::from(1) << int_type.bits;
+    let value = ((value % &modulus) + &modulus) % &modulus;
+    match int_type.signed && value > int_type.max() {
+        true => value - modulus,
+        false => value,
+    }
+}
+
+/// The current call frame
+#[derive(Debug)]
+pub struct CallFrame {
+    /// The type returned values are given
+    pub return_type: Type,
+    /// The amount of calls this one is nested in
+    pub depth: usize,
+    /// The file the called function is declared in
+    pub file: FileId,
+}
+
+#[derive(Debug)]
+struct Binding {
+    value: Value,
+    /// The type values assigned to the binding are given
+    value_type: Option<Type>,
+    is_mutable: bool,
+}
+
 /// A context for evaluating expressions, essentially a stack machine, with scoping rules
+#[derive(Debug)]
 pub struct EvalExecScope<'t> {
     pub super_scope: Option<&'t EvalExecScope<'t>>,
+    bindings: RefCell<HashMap<Box<str>, Binding>>,
+    /// Set for the scopes of function bodies
+    frame: Option<CallFrame>,
+    /// The amount of loop iterations and calls which can still be evaluated, only counted in the
+    /// outermost scope
+    branches_left: Cell<usize>,
+    /// What sizes and alignments are computed for
+    target: Target,
+    /// The file declared in the outermost scope
+    file: FileId,
+}
+
+/// How many loop iterations and calls can be evaluated with a single outermost scope
+pub const BRANCH_QUOTA: usize = 100_000;
+
+/// How deeply calls can be nested when evaluating
+pub const MAX_CALL_DEPTH: usize = 64;
+
+impl Default for EvalExecScope<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'t> EvalExecScope<'t> {
+    /// An outermost scope, in which the declarations of a new file are bound
+    pub fn new() -> Self {
+        Self::with_target(Target::default())
+    }
+
+    pub fn with_target(target: Target) -> Self {
+        Self {
+            super_scope: None,
+            bindings: RefCell::default(),
+            frame: None,
+            branches_left: Cell::new(BRANCH_QUOTA),
+            target,
+            file: FileId::fresh(),
+        }
+    }
+
     pub fn new_scope(&'t self) -> EvalExecScope<'t> {
         Self {
             super_scope: Some(self),
+            bindings: RefCell::default(),
+            frame: None,
+            branches_left: Cell::new(0),
+            target: self.target.clone(),
+            file: self.file,
+        }
+    }
+
+    /// The scope of the body of a called function
+    pub fn new_call_scope(&'t self, frame: CallFrame) -> EvalExecScope<'t> {
+        Self {
+            frame: Some(frame),
+            ..self.new_scope()
+        }
+    }
+
+    /// The outermost scope, the only one visible from function bodies
+    pub fn global(&self) -> &EvalExecScope<'t> {
+        match self.super_scope {
+            Some(scope) => scope.global(),
+            None => self,
+        }
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+
+    /// The frame of the innermost function call
+    pub fn frame(&self) -> Option<&CallFrame> {
+        match (&self.frame, self.super_scope) {
+            (Some(frame), _) => Some(frame),
+            (None, Some(scope)) => scope.frame(),
+            (None, None) => None,
+        }
+    }
+
+    /// The file of the code being evaluated: the one the innermost called function is declared
+    /// in, or the one declared in the outermost scope
+    pub fn file(&self) -> FileId {
+        match self.frame() {
+            Some(frame) => frame.file,
+            None => self.global().file,
+        }
+    }
+
+    /// Whether the private declarations of `container_type` are visible, which they are throughout
+    /// the file declaring it
+    pub fn sees_private(&self, container_type: &ContainerType) -> bool {
+        container_type.file.is_none_or(|file| file == self.file())
+    }
+
+    /// Counts a loop iteration or a call against the branch quota
+    pub fn branch(&self) -> Result<(), EvaluationErrorKind> {
+        let global = self.global();
+        match global.branches_left.get().checked_sub(1) {
+            Some(left) => {
+                global.branches_left.set(left);
+                Ok(())
+            }
+            None => Err(EvaluationErrorKind::BranchQuotaExceeded),
+        }
+    }
+
+    /// Binds `name` in this scope, hiding any binding with the same name in enclosing scopes
+    pub fn declare(&self, name: &str, value: Value, value_type: Option<Type>, is_mutable: bool) {
+        self.bindings.borrow_mut().insert(
+            name.into(),
+            Binding {
+                value,
+                value_type,
+                is_mutable,
+            },
+        );
+    }
+
+    /// The value bound to `name`, along with its declared type
+    pub fn get(&self, name: &str) -> Option<(Value, Option<Type>)> {
+        if let Some(binding) = self.bindings.borrow().get(name) {
+            return Some((binding.value.clone(), binding.value_type.clone()));
+        }
+        self.super_scope?.get(name)
+    }
+
+    /// Whether `name` is bound to a `var`, `None` if it isn't bound
+    pub fn is_mutable(&self, name: &str) -> Option<bool> {
+        if let Some(binding) = self.bindings.borrow().get(name) {
+            return Some(binding.is_mutable);
+        }
+        self.super_scope?.is_mutable(name)
+    }
+
+    /// Replaces the value bound to `name`, which must be mutable
+    pub fn assign(&self, name: &str, value: Value) -> Result<(), EvaluationErrorKind> {
+        if let Some(binding) = self.bindings.borrow_mut().get_mut(name) {
+            if !binding.is_mutable {
+                return Err(EvaluationErrorKind::AssignToConstant(name.into()));
+            }
+            binding.value = value;
+            return Ok(());
+        }
+        match self.super_scope {
+            Some(scope) => scope.assign(name, value),
+            None => Err(EvaluationErrorKind::Undefined(name.into())),
         }
     }
 }
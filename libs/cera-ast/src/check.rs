@@ -0,0 +1,1629 @@
+//! Type checking of expressions. Every expression is given a type, and the values of calls,
+//! returns, assignments and declarations must coerce to the type they are given. Types, and the
+//! values they depend on, are evaluated at compile time in the scope being checked, in which
+//! runtime values are bound as `undefined` along with their type.
+
+use std::fmt;
+
+use cera_parse::text_region::TextSpan;
+use cera_src_ast::suggest::NameKind;
+
+use crate::{
+    builtin::{BuiltinFunction, CallFrame, EvalExecScope},
+    eval::{coerce, EvaluationErrorKind},
+    expressions::{Expression, LabelId, Let},
+    intern::{normalize, TypeInterner},
+    types::{
+        sentinel_coerces, AnonField, AnonInitError, ArrayType, ContainerField, ContainerType,
+        ContainerVariant, ErrorSetType, ErrorUnionType, FieldError, FieldKind, OptionType,
+        PointerType, SliceType, Type,
+    },
+    values::{Function, Value},
+};
+
+#[derive(Debug, Clone)]
+pub enum TypeErrorKind {
+    /// A value of the `found` type where one of the `expected` type is needed
+    Mismatch {
+        expected: Box<Type>,
+        found: Box<Type>,
+    },
+    /// Conditions must be `true` or `false`
+    NotACondition(Box<Type>),
+    InvalidOperands {
+        function: BuiltinFunction,
+        found: Box<[Type]>,
+    },
+    NotCallable(Box<Type>),
+    ArgumentCount {
+        expected: usize,
+        found: usize,
+    },
+    /// A field accessed on a value of a type without fields
+    NotAContainer(Box<Type>),
+    Field {
+        name: Box<str>,
+        error: FieldError,
+    },
+    /// An instance field accessed on its container rather than on a value
+    InstanceField(Box<str>),
+    /// `.{ ... }` or `.variant` which can't be a value of the `container` type
+    AnonInit {
+        container: Box<Type>,
+        error: AnonInitError,
+    },
+    AssignToConstant(Box<str>),
+    /// A runtime variable of a comptime number type, which has no size
+    ComptimeVariable {
+        name: Box<str>,
+        found: Box<Type>,
+    },
+    /// Only names, fields and dereferenced pointers can be assigned to
+    InvalidAssignTarget,
+    BreakOutsideLabel(LabelId),
+    ReturnOutsideFunction,
+    /// A type, or a value needed at compile time, which couldn't be evaluated
+    Evaluation(EvaluationErrorKind),
+}
+
+impl fmt::Display for TypeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeErrorKind::Mismatch { expected, found } => {
+                write!(f, "expected `{expected}`, found `{found}`")
+            }
+            TypeErrorKind::NotACondition(found) => {
+                write!(f, "expected `bool`, found `{found}`")?;
+                match condition_hint(found) {
+                    Some(hint) => write!(f, ", compare it with `{hint}`"),
+                    None => Ok(()),
+                }
+            }
+            TypeErrorKind::InvalidOperands { function, found } => {
+                let found: Vec<_> = found.iter().map(|found| format!("`{found}`")).collect();
+                write!(
+                    f,
+                    "`{function}` can't be applied to {}",
+                    found.join(" and ")
+                )
+            }
+            TypeErrorKind::NotCallable(found) => write!(f, "`{found}` isn't a function"),
+            TypeErrorKind::ArgumentCount { expected, found } => {
+                let plural = if *expected == 1 { "" } else { "s" };
+                write!(f, "expected {expected} argument{plural}, found {found}")
+            }
+            TypeErrorKind::NotAContainer(found) => write!(f, "`{found}` has no fields"),
+            TypeErrorKind::Field {
+                name,
+                error: FieldError::NotFound { suggestions },
+            } => match suggestions.first() {
+                Some(suggestion) => {
+                    write!(f, "no field `{name}`, did you mean `{}`", suggestion.name)?;
+                    write_declared_at(f, suggestion.declared_at.as_ref())?;
+                    write!(f, "?")
+                }
+                None => write!(f, "no field `{name}`"),
+            },
+            TypeErrorKind::Field {
+                name,
+                error: FieldError::Private { declared_at },
+            } => {
+                write!(f, "`{name}` is private")?;
+                write_declared_at(f, declared_at.as_ref())
+            }
+            TypeErrorKind::InstanceField(name) => {
+                write!(f, "`{name}` is a field of values, not of their type")
+            }
+            TypeErrorKind::AnonInit { container, error }
+            | TypeErrorKind::Evaluation(EvaluationErrorKind::AnonInit { container, error }) => {
+                write_anon_init_error(f, container, error)
+            }
+            TypeErrorKind::AssignToConstant(name) => write!(f, "`{name}` is constant"),
+            TypeErrorKind::ComptimeVariable { name, found } => {
+                write!(
+                    f,
+                    "`var {name}` of type `{found}` must be `const`, or have a sized type"
+                )
+            }
+            TypeErrorKind::InvalidAssignTarget => write!(f, "this can't be assigned to"),
+            TypeErrorKind::BreakOutsideLabel(_) => write!(f, "`break` outside of its target"),
+            TypeErrorKind::ReturnOutsideFunction => write!(f, "`return` outside of a function"),
+            TypeErrorKind::Evaluation(EvaluationErrorKind::IntOverflow { value, int_type }) => {
+                write!(
+                    f,
+                    "`{value}` doesn't fit in `{}`",
+                    Type::Int(int_type.clone())
+                )
+            }
+            TypeErrorKind::Evaluation(EvaluationErrorKind::LossyCoercion { value, expected }) => {
+                match &**value {
+                    Value::ComptimeInt(value) => write!(f, "`{value}`")?,
+                    Value::ComptimeFloat(value) => write!(f, "`{value}`")?,
+                    value => write!(f, "{value:?}")?,
+                }
+                write!(f, " can't be represented as `{expected}`")
+            }
+            TypeErrorKind::Evaluation(EvaluationErrorKind::Undefined(name)) => {
+                write!(f, "`{name}` isn't declared")
+            }
+            TypeErrorKind::Evaluation(EvaluationErrorKind::IndexOutOfBounds { index, len }) => {
+                write!(f, "index `{index}` is out of bounds of length {len}")
+            }
+            TypeErrorKind::Evaluation(EvaluationErrorKind::SliceBounds { start, end, len }) => {
+                match start > end {
+                    true => write!(f, "`{start}..{end}` ends before it starts"),
+                    false => write!(f, "`{start}..{end}` is out of bounds of length {len}"),
+                }
+            }
+            TypeErrorKind::Evaluation(EvaluationErrorKind::UntypedLiteral) => {
+                write!(f, "the type of this value can't be inferred")
+            }
+            TypeErrorKind::Evaluation(EvaluationErrorKind::DivisionByZero) => {
+                write!(f, "division by zero")
+            }
+            TypeErrorKind::Evaluation(EvaluationErrorKind::Unsupported(function)) => {
+                write!(f, "`{function}` can't be evaluated at compile time yet")
+            }
+            TypeErrorKind::Evaluation(kind) => write!(f, "{kind:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeError {
+    pub kind: TypeErrorKind,
+    /// The innermost `Spanned` expression around the one with the error
+    pub span: Option<TextSpan>,
+}
+
+/// What checking an expression found out about its values
+#[derive(Debug, Clone)]
+enum Typing {
+    Type(Type),
+    /// Expressions such as `return` and `break`, which leave before producing a value
+    NoReturn,
+    /// An expression with errors, which were already reported
+    Invalid,
+}
+
+/// How the value of an expression is used
+#[derive(Debug, Clone, Copy)]
+enum Expected<'t> {
+    /// The value is discarded, like the ones of statements which aren't last in their block
+    Nothing,
+    /// The value is used, whatever its type
+    Any,
+    /// The value must coerce to this type
+    Type(&'t Type),
+}
+
+impl<'t> Expected<'t> {
+    fn get(self) -> Option<&'t Type> {
+        match self {
+            Expected::Type(expected) => Some(expected),
+            Expected::Nothing | Expected::Any => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Label {
+    id: LabelId,
+    /// The type of the values of `break`s, if the value of the `Labeled` expression has one
+    expected: Option<Type>,
+    is_discarded: bool,
+    /// The values of the `break`s found so far
+    found: Typing,
+}
+
+#[derive(Debug, Default)]
+pub struct TypeChecker {
+    interner: TypeInterner,
+    errors: Vec<TypeError>,
+    /// The innermost `Spanned` expression being checked
+    span: Option<TextSpan>,
+    /// The enclosing `Labeled` expressions, innermost last
+    labels: Vec<Label>,
+    /// The type returned by the function being checked
+    return_type: Option<Type>,
+}
+
+fn is_comptime_number(value_type: &Type) -> bool {
+    matches!(value_type, Type::ComptimeInt | Type::ComptimeFloat)
+}
+
+/// Where a field mentioned by a diagnostic is declared, when it is declared in source
+fn write_declared_at(f: &mut fmt::Formatter, span: Option<&TextSpan>) -> fmt::Result {
+    match span {
+        Some(span) => write!(f, " (declared at {}..{})", span.idx, span.end()),
+        None => Ok(()),
+    }
+}
+
+/// The names of fields, as a list of code
+fn field_list(names: &[Box<str>]) -> String {
+    let names: Vec<_> = names.iter().map(|name| format!("`{name}`")).collect();
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {last}", rest.join(", ")),
+        _ => names.concat(),
+    }
+}
+
+fn write_anon_init_error(
+    f: &mut fmt::Formatter,
+    container: &Type,
+    error: &AnonInitError,
+) -> fmt::Result {
+    match error {
+        AnonInitError::NotAContainer => write!(
+            f,
+            "`{container}` can't be initialized with `.{{ ... }}` or `.variant`"
+        ),
+        AnonInitError::UnknownField { name, expected, .. } => {
+            write!(f, "`{container}` has no field `{name}`")?;
+            match expected.is_empty() {
+                true => Ok(()),
+                false => write!(f, ", expected {}", field_list(expected)),
+            }
+        }
+        AnonInitError::DuplicateField { name, .. } => {
+            write!(f, "field `{name}` of `{container}` is given twice")
+        }
+        AnonInitError::MissingFields { names, .. } => {
+            let plural = if names.len() == 1 { "" } else { "s" };
+            write!(
+                f,
+                "missing field{plural} {} of `{container}`",
+                field_list(names)
+            )
+        }
+        AnonInitError::MixedFields { .. } => write!(
+            f,
+            "the fields of `{container}` are given both by name and by position"
+        ),
+        AnonInitError::TooManyElements { expected, .. } => {
+            let plural = if expected.len() == 1 { "" } else { "s" };
+            write!(
+                f,
+                "`{container}` has {} field{plural}, more values are given",
+                expected.len()
+            )
+        }
+        AnonInitError::NotOneUnionField { expected } => write!(
+            f,
+            "union `{container}` must be given exactly one of its fields, {}",
+            field_list(expected)
+        ),
+    }
+}
+
+/// The comparison turning a value of `found` type into a condition
+fn condition_hint(found: &Type) -> Option<&'static str> {
+    match found {
+        Type::Int(_) | Type::Float(_) | Type::ComptimeInt | Type::ComptimeFloat => Some("!= 0"),
+        Type::Option(_) => Some("!= null"),
+        _ => None,
+    }
+}
+
+pub(crate) fn type_expression(value_type: Type) -> Expression {
+    Expression::Value(Box::new(Value::Type(value_type)))
+}
+
+/// The type comptime numbers are converted to when this type is expected, as they are wrapped in
+/// optionals and error unions
+fn literal_target(expected: &Type) -> &Type {
+    match expected {
+        Type::Option(OptionType { some_type }) => literal_target(some_type),
+        Type::ErrorUnion(ErrorUnionType { ok, .. }) => literal_target(ok),
+        expected => expected,
+    }
+}
+
+/// The optional `null` is when this type is expected
+fn null_target(expected: &Type) -> Option<&Type> {
+    match expected {
+        Type::Option(_) => Some(expected),
+        Type::ErrorUnion(ErrorUnionType { ok, .. }) => null_target(ok),
+        _ => None,
+    }
+}
+
+/// Whether the errors of `found` are all in `expected`
+fn is_subset(found: &ErrorSetType, expected: &ErrorSetType) -> bool {
+    found
+        .possible_errors
+        .iter()
+        .all(|error| expected.possible_errors.contains(error))
+}
+
+/// Arrays, and the arrays single pointers point to, the length of which is known at compile time
+fn array_type(value_type: &Type) -> Option<&ArrayType> {
+    match value_type {
+        Type::Array(array_type) => Some(array_type),
+        Type::Pointer(PointerType {
+            pointed_value,
+            is_many: false,
+            ..
+        }) => match normalize(pointed_value)? {
+            Type::Array(array_type) => Some(array_type),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The type of `base[start..end]` for a `base` of type `value_type`, which keeps the sentinel
+/// when slicing `to_end`. Arrays are sliced by value, so their slices are `const`. Many-item
+/// pointers have no end to slice to.
+fn sliced_type(value_type: &Type, to_end: bool) -> Option<SliceType> {
+    let (indexed_type, is_const, sentinel) = match value_type {
+        Type::Pointer(PointerType {
+            pointed_value,
+            is_const,
+            is_many: true,
+            ..
+        }) => {
+            if to_end {
+                return None;
+            }
+            return Some(SliceType {
+                indexed_type: Box::new(normalize(pointed_value)?.clone()),
+                is_const: *is_const,
+                sentinel: None,
+            });
+        }
+        Type::Slice(slice_type) => (
+            &slice_type.indexed_type,
+            slice_type.is_const,
+            &slice_type.sentinel,
+        ),
+        Type::Pointer(PointerType { is_const, .. }) => {
+            let array_type = array_type(value_type)?;
+            (&array_type.indexed_type, *is_const, &array_type.sentinel)
+        }
+        Type::Array(array_type) => (&array_type.indexed_type, true, &array_type.sentinel),
+        _ => return None,
+    };
+    Some(SliceType {
+        indexed_type: indexed_type.clone(),
+        is_const,
+        sentinel: sentinel.clone().filter(|_| to_end),
+    })
+}
+
+/// The type of the values of arrays and slices, or of the arrays pointers point to
+fn indexed_type(value_type: &Type) -> Option<Type> {
+    sliced_type(value_type, false).map(|slice_type| *slice_type.indexed_type)
+}
+
+/// The constant the value at `place` is part of, which makes it unassignable, along with the
+/// type of the value. Values reached through pointers aren't part of the constant storing the
+/// pointer.
+fn constant_place(place: &Expression, scope: &EvalExecScope) -> Option<(Box<str>, Type)> {
+    match place {
+        Expression::Spanned(_, place) => constant_place(place, scope),
+        Expression::Name(name) => match scope.is_mutable(name)? {
+            true => None,
+            false => {
+                let (value, value_type) = scope.get(name)?;
+                Some((name.clone(), value_type.unwrap_or_else(|| value.get_type())))
+            }
+        },
+        Expression::Field { base, name } => {
+            let (constant, Type::Container(container_type)) = constant_place(base, scope)? else {
+                return None;
+            };
+            let field = container_type
+                .field(name)
+                .filter(|field| matches!(field.field_kind, FieldKind::Instance))?;
+            let field_type = field.field_type.as_ref().and_then(normalize)?;
+            Some((constant, field_type.clone()))
+        }
+        Expression::Builtin(BuiltinFunction::Index, args) => {
+            let (constant, Type::Array(array_type)) = constant_place(args.first()?, scope)? else {
+                return None;
+            };
+            Some((constant, *array_type.indexed_type))
+        }
+        _ => None,
+    }
+}
+
+/// The type of a const or static field, given by its type or by its value
+fn declaration_type(field: &ContainerField) -> Option<Type> {
+    match (&field.field_type, &field.val) {
+        (Some(field_type), _) => normalize(field_type).cloned(),
+        (None, Some(Expression::Value(value))) => Some(value.get_type()),
+        (None, _) => None,
+    }
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn errors(&self) -> &[TypeError] {
+        &self.errors
+    }
+
+    pub fn into_errors(self) -> Vec<TypeError> {
+        self.errors
+    }
+
+    /// Checks `expression`, the value of which must coerce to `expected` if it is given. Returns
+    /// its type, or `None` if it has errors or doesn't produce a value.
+    pub fn check_expression(
+        &mut self,
+        expression: &Expression,
+        expected: Option<&Type>,
+        scope: &EvalExecScope,
+    ) -> Option<Type> {
+        let expected = expected.map_or(Expected::Any, Expected::Type);
+        match self.check(expression, expected, scope) {
+            Typing::Type(value_type) => Some(value_type),
+            Typing::NoReturn | Typing::Invalid => None,
+        }
+    }
+
+    /// Checks the body of a function, with its parameters bound in the outermost scope. Only
+    /// functions callable at runtime are checked, as the types of the others can depend on the
+    /// values of their parameters.
+    pub fn check_function(&mut self, function: &Function, scope: &EvalExecScope) {
+        if function.calling_convention.is_none() {
+            return;
+        }
+        let Expression::ExpressionList(param_types) = &function.function_type.parameters else {
+            return;
+        };
+        let params = scope.global().new_scope();
+        for (name, param_type) in function.param_names.iter().zip(param_types) {
+            let param_type = self.eval_type(param_type, &params);
+            params.declare(name, Value::Undefined, param_type, false);
+        }
+        let Some(return_type) = self.eval_type(&function.function_type.result, &params) else {
+            return;
+        };
+        let outer = self.return_type.replace(return_type.clone());
+        let frame = CallFrame {
+            return_type: return_type.clone(),
+            depth: 0,
+            file: function.file,
+        };
+        let body = self.check(
+            &function.expression,
+            Expected::Nothing,
+            &params.new_call_scope(frame),
+        );
+        self.return_type = outer;
+
+        // Reaching the end of the body returns `void`
+        if let Typing::Type(_) = body {
+            if !self.coerces(&Type::Void, &return_type) {
+                let span = match &function.expression {
+                    Expression::Spanned(span, _) => Some(span.clone()),
+                    _ => None,
+                };
+                self.errors.push(TypeError {
+                    kind: TypeErrorKind::Mismatch {
+                        expected: Box::new(return_type),
+                        found: Box::new(Type::Void),
+                    },
+                    span,
+                });
+            }
+        }
+    }
+
+    fn error(&mut self, kind: TypeErrorKind) -> Typing {
+        self.errors.push(TypeError {
+            kind,
+            span: self.span.clone(),
+        });
+        Typing::Invalid
+    }
+
+    fn invalid_operands(&mut self, function: &BuiltinFunction, found: Vec<Type>) -> Typing {
+        self.error(TypeErrorKind::InvalidOperands {
+            function: function.clone(),
+            found: found.into_boxed_slice(),
+        })
+    }
+
+    /// Evaluates an expression which must be a type
+    fn eval_type(&mut self, expression: &Expression, scope: &EvalExecScope) -> Option<Type> {
+        match expression.eval(scope) {
+            Ok(Value::Type(value_type)) => Some(value_type),
+            Ok(_) => {
+                self.error(TypeErrorKind::Evaluation(EvaluationErrorKind::NotAType));
+                None
+            }
+            Err(error) => {
+                self.errors.push(TypeError {
+                    kind: TypeErrorKind::Evaluation(error.kind),
+                    span: error.span.or_else(|| self.span.clone()),
+                });
+                None
+            }
+        }
+    }
+
+    /// Whether the types are the same, by their structure except for containers
+    fn same(&mut self, first: &Type, second: &Type) -> bool {
+        match (self.interner.intern(first), self.interner.intern(second)) {
+            (Ok(first), Ok(second)) => first == second,
+            _ => false,
+        }
+    }
+
+    /// Whether values of type `found` can be used where `expected` is
+    fn coerces(&mut self, found: &Type, expected: &Type) -> bool {
+        if self.same(found, expected) {
+            return true;
+        }
+        match (found, expected) {
+            (Type::Undefined, _) => true,
+            // When their value fits, which `check_comptime` checks
+            (Type::ComptimeInt, Type::Int(_) | Type::Float(_) | Type::ComptimeFloat) => true,
+            (Type::ComptimeFloat, Type::Float(_) | Type::Int(_) | Type::ComptimeInt) => true,
+            (_, Type::Option(OptionType { some_type })) => self.coerces(found, some_type),
+            (Type::ErrorSet(found), Type::ErrorSet(expected)) => is_subset(found, expected),
+            // Inferred error sets hold whichever errors their function returns
+            (Type::ErrorSet(found), Type::ErrorUnion(ErrorUnionType { err, .. })) => {
+                err.as_ref().is_none_or(|err| is_subset(found, err))
+            }
+            (_, Type::ErrorUnion(ErrorUnionType { ok, .. })) => self.coerces(found, ok),
+            (Type::Pointer(found), Type::Pointer(expected)) => {
+                (!found.is_const || expected.is_const) && self.pointer_coerces(found, expected)
+            }
+            (Type::Slice(_) | Type::Pointer(_), Type::Slice(expected)) => {
+                self.slice_coerces(found, expected)
+            }
+            _ => false,
+        }
+    }
+
+    /// Slices and pointers to arrays coerce to slices of the same values, which can be `const`
+    /// or not be ended by a sentinel
+    fn slice_coerces(&mut self, found: &Type, expected: &SliceType) -> bool {
+        let Some(found) = sliced_type(found, true) else {
+            return false;
+        };
+        (!found.is_const || expected.is_const)
+            && sentinel_coerces(found.sentinel.as_deref(), expected.sentinel.as_deref())
+            && self.same(&found.indexed_type, &expected.indexed_type)
+    }
+
+    /// Pointers to containers coerce to pointers to their `ptr_coerce_target` field, and to
+    /// anything that one coerces to. Pointers to arrays coerce to many-item pointers to their
+    /// values, which like slices can drop the sentinel.
+    fn pointer_coerces(&mut self, found: &PointerType, expected: &PointerType) -> bool {
+        if expected.is_many {
+            let found_values = match found.is_many {
+                true => Some((normalize(&found.pointed_value), found.sentinel.as_deref())),
+                false => match normalize(&found.pointed_value) {
+                    Some(Type::Array(array_type)) => Some((
+                        Some(&*array_type.indexed_type),
+                        array_type.sentinel.as_deref(),
+                    )),
+                    _ => None,
+                },
+            };
+            return match (found_values, normalize(&expected.pointed_value)) {
+                (Some((Some(found_value), sentinel)), Some(expected_value)) => {
+                    sentinel_coerces(sentinel, expected.sentinel.as_deref())
+                        && self.same(found_value, expected_value)
+                }
+                _ => false,
+            };
+        }
+        if found.is_many {
+            return false;
+        }
+        let (Some(mut pointed), Some(expected)) = (
+            normalize(&found.pointed_value),
+            normalize(&expected.pointed_value),
+        ) else {
+            return false;
+        };
+        let mut seen = Vec::new();
+        loop {
+            if self.same(pointed, expected) {
+                return true;
+            }
+            let Type::Container(container_type) = pointed else {
+                return false;
+            };
+            let (ContainerVariant::Struct {
+                ptr_coerce_target: Some(target),
+                ..
+            }
+            | ContainerVariant::Trait {
+                ptr_coerce_target: Some(target),
+            }) = &container_type.variant
+            else {
+                return false;
+            };
+            if seen.contains(&container_type.id) {
+                return false;
+            }
+            seen.push(container_type.id);
+            let field_type = container_type
+                .field(target)
+                .and_then(|field| field.field_type.as_ref())
+                .and_then(normalize);
+            match field_type {
+                Some(field_type) => pointed = field_type,
+                None => return false,
+            }
+        }
+    }
+
+    /// The typing of `values` when any of them can be produced, which is the expected type if
+    /// there is one
+    fn join(&mut self, first: Typing, second: Typing, expected: Expected) -> Typing {
+        match (first, second) {
+            (Typing::NoReturn, typing) | (typing, Typing::NoReturn) => typing,
+            (Typing::Invalid, _) | (_, Typing::Invalid) => Typing::Invalid,
+            (Typing::Type(first), Typing::Type(second)) => {
+                if self.same(&first, &second) {
+                    return Typing::Type(first);
+                }
+                // Comptime numbers take the type of the values they are joined with
+                if is_comptime_number(&second) && self.coerces(&second, &first) {
+                    return Typing::Type(first);
+                }
+                if is_comptime_number(&first) && self.coerces(&first, &second) {
+                    return Typing::Type(second);
+                }
+                match expected {
+                    Expected::Nothing => Typing::Type(Type::Void),
+                    _ => self.error(TypeErrorKind::Mismatch {
+                        expected: Box::new(first),
+                        found: Box::new(second),
+                    }),
+                }
+            }
+        }
+    }
+
+    /// Checks an expression along with its coercion to the expected type
+    fn check(
+        &mut self,
+        expression: &Expression,
+        expected: Expected,
+        scope: &EvalExecScope,
+    ) -> Typing {
+        let typing = self.infer(expression, expected, scope);
+        match (expected, typing) {
+            (Expected::Type(expected), Typing::Type(found)) => {
+                match self.coerces(&found, expected) {
+                    true if is_comptime_number(&found) => {
+                        self.check_comptime(expression, expected, scope)
+                    }
+                    true => Typing::Type(expected.clone()),
+                    false => self.error(TypeErrorKind::Mismatch {
+                        expected: Box::new(expected.clone()),
+                        found: Box::new(found),
+                    }),
+                }
+            }
+            (_, typing) => typing,
+        }
+    }
+
+    /// Checks an index, or a bound of a slice, giving its value when it is known at compile time
+    fn check_index(
+        &mut self,
+        index: &Expression,
+        usize_type: &Type,
+        scope: &EvalExecScope,
+    ) -> Option<usize> {
+        match self.check(index, Expected::Any, scope) {
+            Typing::Type(found) if is_comptime_number(&found) => {
+                if let Typing::Type(_) = self.check_comptime(index, usize_type, scope) {
+                    if let Ok(Value::ComptimeInt(value)) = index.eval(scope) {
+                        return usize::try_from(value).ok();
+                    }
+                }
+            }
+            Typing::Type(Type::Int(_)) | Typing::NoReturn | Typing::Invalid => {}
+            Typing::Type(found) => {
+                self.error(TypeErrorKind::Mismatch {
+                    expected: Box::new(usize_type.clone()),
+                    found: Box::new(found),
+                });
+            }
+        }
+        None
+    }
+
+    /// Checks the sentinel of an array or a slice type, which is a value of its indexed type
+    fn check_sentinel(
+        &mut self,
+        indexed_type: &Expression,
+        sentinel: &[Expression],
+        scope: &EvalExecScope,
+    ) {
+        for sentinel in sentinel {
+            match indexed_type.eval(scope) {
+                Ok(Value::Type(indexed_type)) => {
+                    self.check(sentinel, Expected::Type(&indexed_type), scope)
+                }
+                _ => self.check(sentinel, Expected::Any, scope),
+            };
+        }
+    }
+
+    /// Checks that the value of an expression of a comptime number type fits in `expected`
+    fn check_comptime(
+        &mut self,
+        expression: &Expression,
+        expected: &Type,
+        scope: &EvalExecScope,
+    ) -> Typing {
+        match expression.eval(scope) {
+            Ok(value) => match coerce(value, Some(literal_target(expected))) {
+                Ok(_) => Typing::Type(expected.clone()),
+                Err(kind) => self.error(TypeErrorKind::Evaluation(kind)),
+            },
+            Err(error) => self.error(TypeErrorKind::Evaluation(error.kind)),
+        }
+    }
+
+    // Like evaluation, each kind of expression is checked by a function of its own
+    fn infer(
+        &mut self,
+        expression: &Expression,
+        expected: Expected,
+        scope: &EvalExecScope,
+    ) -> Typing {
+        match expression {
+            Expression::Value(value) => Typing::Type(value.get_type()),
+            Expression::Literal(_) => self.infer_literal(expression, scope),
+            Expression::Builtin(function, args) => self.infer_builtin(function, args, scope),
+            Expression::ExpressionList(expressions) => {
+                self.infer_list(expressions, expected, scope)
+            }
+            Expression::Name(name) => self.infer_name(name, expected, scope),
+            Expression::Let(let_) => self.infer_let(let_, scope),
+            Expression::Assign { target, value } => self.infer_assign(target, value, scope),
+            Expression::If {
+                condition,
+                then,
+                otherwise,
+            } => self.infer_if(condition, then, otherwise, expected, scope),
+            Expression::Loop(body) => match self.check(body, Expected::Nothing, scope) {
+                Typing::Invalid => Typing::Invalid,
+                _ => Typing::NoReturn,
+            },
+            Expression::Labeled { label, body } => {
+                self.infer_labeled(*label, body, expected, scope)
+            }
+            Expression::Break { label, value } => self.infer_break(*label, value, scope),
+            Expression::Return(value) => self.infer_return(value, scope),
+            Expression::Call { callee, args } => self.infer_call(callee, args, scope),
+            Expression::Field { base, name } => self.infer_field(base, name, false, scope),
+            Expression::AnonInit(fields) => self.infer_anon_init(fields, expected, scope),
+            Expression::EnumLiteral(name) => self.infer_enum_literal(name, expected),
+            Expression::Spanned(span, expression) => {
+                let outer = self.span.replace(span.clone());
+                let typing = self.check(expression, expected, scope);
+                self.span = outer;
+                typing
+            }
+        }
+    }
+
+    /// Number literals are comptime numbers, until they are coerced
+    fn infer_literal(&mut self, literal: &Expression, scope: &EvalExecScope) -> Typing {
+        match literal.eval(scope) {
+            Ok(value) => Typing::Type(value.get_type()),
+            Err(error) => self.error(TypeErrorKind::Evaluation(error.kind)),
+        }
+    }
+
+    fn infer_list(
+        &mut self,
+        expressions: &[Expression],
+        expected: Expected,
+        scope: &EvalExecScope,
+    ) -> Typing {
+        let scope = scope.new_scope();
+        let mut typing = Typing::Type(Type::Void);
+        let mut diverges = false;
+        for (idx, expression) in expressions.iter().enumerate() {
+            let is_last = idx + 1 == expressions.len();
+            let expected = if is_last { expected } else { Expected::Nothing };
+            typing = self.check(expression, expected, &scope);
+            diverges |= matches!(typing, Typing::NoReturn);
+        }
+        match diverges {
+            true => Typing::NoReturn,
+            false => typing,
+        }
+    }
+
+    fn infer_name(&mut self, name: &str, expected: Expected, scope: &EvalExecScope) -> Typing {
+        match scope.get(name) {
+            // Bindings of which the value had errors
+            Some((Value::Undefined, None)) => Typing::Invalid,
+            Some((value, value_type)) => {
+                Typing::Type(value_type.unwrap_or_else(|| value.get_type()))
+            }
+            None if name == "null" => match expected.get() {
+                Some(expected) => match null_target(expected) {
+                    Some(optional) => Typing::Type(optional.clone()),
+                    None => self.error(TypeErrorKind::Evaluation(
+                        EvaluationErrorKind::InvalidLiteral {
+                            expected: Box::new(expected.clone()),
+                        },
+                    )),
+                },
+                None => self.error(TypeErrorKind::Evaluation(
+                    EvaluationErrorKind::UntypedLiteral,
+                )),
+            },
+            None => match Expression::Name(name.into()).eval(scope) {
+                Ok(value) => Typing::Type(value.get_type()),
+                Err(error) => self.error(TypeErrorKind::Evaluation(error.kind)),
+            },
+        }
+    }
+
+    fn infer_let(&mut self, let_: &Let, scope: &EvalExecScope) -> Typing {
+        let var_type = match &let_.var_type {
+            Some(var_type) => match self.eval_type(var_type, scope) {
+                Some(var_type) => Some(var_type),
+                None => {
+                    self.check(&let_.value, Expected::Any, scope);
+                    scope.declare(&let_.name, Value::Undefined, None, let_.is_mutable);
+                    return Typing::Type(Type::Void);
+                }
+            },
+            None => None,
+        };
+        let expected = var_type.as_ref().map_or(Expected::Any, Expected::Type);
+        let typing = self.check(&let_.value, expected, scope);
+        let value_type = match &typing {
+            Typing::Type(value_type) => Some(value_type.clone()),
+            Typing::NoReturn | Typing::Invalid => None,
+        };
+        if let (true, Some(found)) = (let_.is_mutable, &value_type) {
+            if is_comptime_number(found) {
+                self.error(TypeErrorKind::ComptimeVariable {
+                    name: let_.name.clone(),
+                    found: Box::new(found.clone()),
+                });
+            }
+        }
+        // Types and comptime numbers are bound with their value, so that other bindings can use
+        // them at compile time
+        let value = match value_type {
+            Some(Type::Type | Type::ComptimeInt | Type::ComptimeFloat) => {
+                match let_.value.eval(scope) {
+                    Ok(value) => value,
+                    Err(error) => {
+                        self.error(TypeErrorKind::Evaluation(error.kind));
+                        scope.declare(&let_.name, Value::Undefined, None, let_.is_mutable);
+                        return Typing::Type(Type::Void);
+                    }
+                }
+            }
+            _ => Value::Undefined,
+        };
+        scope.declare(&let_.name, value, value_type, let_.is_mutable);
+        match typing {
+            Typing::NoReturn => Typing::NoReturn,
+            _ => Typing::Type(Type::Void),
+        }
+    }
+
+    fn infer_assign(
+        &mut self,
+        target: &Expression,
+        value: &Expression,
+        scope: &EvalExecScope,
+    ) -> Typing {
+        let target_typing = self.infer_place(target, scope);
+        let expected = match &target_typing {
+            Typing::Type(target_type) => Expected::Type(target_type),
+            Typing::NoReturn | Typing::Invalid => Expected::Any,
+        };
+        match self.check(value, expected, scope) {
+            Typing::NoReturn => Typing::NoReturn,
+            _ => Typing::Type(Type::Void),
+        }
+    }
+
+    /// The type of the values which can be assigned to `target`
+    fn infer_place(&mut self, target: &Expression, scope: &EvalExecScope) -> Typing {
+        match target {
+            Expression::Spanned(span, target) => {
+                let outer = self.span.replace(span.clone());
+                let typing = self.infer_place(target, scope);
+                self.span = outer;
+                typing
+            }
+            Expression::Name(name) => match scope.is_mutable(name) {
+                Some(true) => self.infer_name(name, Expected::Any, scope),
+                Some(false) => self.error(TypeErrorKind::AssignToConstant(name.clone())),
+                None => self.error(TypeErrorKind::Evaluation(EvaluationErrorKind::Undefined(
+                    name.clone(),
+                ))),
+            },
+            Expression::Field { base, name } => {
+                let typing = self.infer_field(base, name, true, scope);
+                self.assignable(typing, target, scope)
+            }
+            Expression::Builtin(BuiltinFunction::Index, _) => {
+                let typing = self.infer(target, Expected::Any, scope);
+                self.assignable(typing, target, scope)
+            }
+            Expression::Builtin(BuiltinFunction::Deref, _) => {
+                self.infer(target, Expected::Any, scope)
+            }
+            _ => self.error(TypeErrorKind::InvalidAssignTarget),
+        }
+    }
+
+    /// The typing of the place `target`, unless it is part of a constant
+    fn assignable(&mut self, typing: Typing, target: &Expression, scope: &EvalExecScope) -> Typing {
+        match (&typing, constant_place(target, scope)) {
+            (Typing::Type(_), Some((constant, _))) => {
+                self.error(TypeErrorKind::AssignToConstant(constant))
+            }
+            _ => typing,
+        }
+    }
+
+    fn infer_if(
+        &mut self,
+        condition: &Expression,
+        then: &Expression,
+        otherwise: &Expression,
+        expected: Expected,
+        scope: &EvalExecScope,
+    ) -> Typing {
+        let condition = match self.check(condition, Expected::Any, scope) {
+            Typing::Type(found) if !matches!(found, Type::Bool) => {
+                self.error(TypeErrorKind::NotACondition(Box::new(found)))
+            }
+            typing => typing,
+        };
+        let then = self.check(then, expected, scope);
+        let otherwise = self.check(otherwise, expected, scope);
+        match condition {
+            Typing::NoReturn => Typing::NoReturn,
+            _ => self.join(then, otherwise, expected),
+        }
+    }
+
+    fn infer_labeled(
+        &mut self,
+        label: LabelId,
+        body: &Expression,
+        expected: Expected,
+        scope: &EvalExecScope,
+    ) -> Typing {
+        self.labels.push(Label {
+            id: label,
+            expected: expected.get().cloned(),
+            is_discarded: matches!(expected, Expected::Nothing),
+            found: Typing::NoReturn,
+        });
+        let body = self.check(body, expected, scope);
+        let label = self
+            .labels
+            .pop()
+            .expect("labels are pushed and popped in pairs");
+        self.join(body, label.found, expected)
+    }
+
+    fn infer_break(&mut self, label: LabelId, value: &Expression, scope: &EvalExecScope) -> Typing {
+        let Some(idx) = self.labels.iter().rposition(|other| other.id == label) else {
+            self.check(value, Expected::Any, scope);
+            return self.error(TypeErrorKind::BreakOutsideLabel(label));
+        };
+        let expected_type = self.labels[idx].expected.clone();
+        let expected = match (&expected_type, self.labels[idx].is_discarded) {
+            (Some(expected_type), _) => Expected::Type(expected_type),
+            (None, true) => Expected::Nothing,
+            (None, false) => Expected::Any,
+        };
+        let typing = self.check(value, expected, scope);
+        let found = std::mem::replace(&mut self.labels[idx].found, Typing::NoReturn);
+        self.labels[idx].found = self.join(found, typing, expected);
+        Typing::NoReturn
+    }
+
+    fn infer_return(&mut self, value: &Expression, scope: &EvalExecScope) -> Typing {
+        let Some(return_type) = self.return_type.clone() else {
+            self.check(value, Expected::Any, scope);
+            return self.error(TypeErrorKind::ReturnOutsideFunction);
+        };
+        self.check(value, Expected::Type(&return_type), scope);
+        Typing::NoReturn
+    }
+
+    fn infer_call(
+        &mut self,
+        callee: &Expression,
+        args: &[Expression],
+        scope: &EvalExecScope,
+    ) -> Typing {
+        let signature = match self.check(callee, Expected::Any, scope) {
+            Typing::Type(Type::ComptimeFunction(signature)) => signature,
+            Typing::Type(Type::RuntimeFunction(function_type)) => function_type.base_signature,
+            typing => {
+                if let Typing::Type(found) = typing {
+                    self.error(TypeErrorKind::NotCallable(Box::new(found)));
+                }
+                for arg in args {
+                    self.check(arg, Expected::Any, scope);
+                }
+                return Typing::Invalid;
+            }
+        };
+        let Expression::ExpressionList(param_types) = &signature.parameters else {
+            return Typing::Invalid;
+        };
+        let counts_match = param_types.len() == args.len();
+        if !counts_match {
+            self.error(TypeErrorKind::ArgumentCount {
+                expected: param_types.len(),
+                found: args.len(),
+            });
+        }
+        // The types of parameters and of the result can depend on the values of parameters,
+        // which are known at compile time when they are types
+        let function = match callee.eval(scope) {
+            Ok(Value::Function(function)) => Some(function),
+            _ => None,
+        };
+        let params = scope.global().new_scope();
+        for (idx, (param_type, arg)) in param_types.iter().zip(args).enumerate() {
+            let param_type = self.eval_type(param_type, &params);
+            let expected = param_type.as_ref().map_or(Expected::Any, Expected::Type);
+            self.check(arg, expected, scope);
+            if let Some(name) = function
+                .as_ref()
+                .and_then(|function| function.param_names.get(idx))
+            {
+                let value = match param_type {
+                    Some(Type::Type) => arg.eval(scope).unwrap_or(Value::Undefined),
+                    _ => Value::Undefined,
+                };
+                params.declare(name, value, param_type, false);
+            }
+        }
+        // Arguments without a parameter are still checked, though they have nothing to match
+        for arg in args.iter().skip(param_types.len()) {
+            self.check(arg, Expected::Any, scope);
+        }
+        if !counts_match {
+            return Typing::Invalid;
+        }
+        match self.eval_type(&signature.result, &params) {
+            Some(result) => Typing::Type(result),
+            None => Typing::Invalid,
+        }
+    }
+
+    /// `base.name`, which is assigned to if `is_place` is set
+    fn infer_field(
+        &mut self,
+        base: &Expression,
+        name: &str,
+        is_place: bool,
+        scope: &EvalExecScope,
+    ) -> Typing {
+        let base_type = match self.check(base, Expected::Any, scope) {
+            Typing::Type(base_type) => base_type,
+            typing => return typing,
+        };
+        let field_error = |error| TypeErrorKind::Field {
+            name: name.into(),
+            error,
+        };
+        // The length is the only field of arrays and slices
+        if name == "len" && sliced_type(&base_type, true).is_some() {
+            return match is_place {
+                true => self.error(TypeErrorKind::AssignToConstant(name.into())),
+                false => Typing::Type(Type::Int(scope.target().usize_type())),
+            };
+        }
+        let container_type = match &base_type {
+            Type::Type => match base.eval(scope) {
+                Ok(Value::Type(Type::Container(container_type))) => {
+                    let field = match container_type.lookup_field(
+                        name,
+                        NameKind::Value,
+                        !scope.sees_private(&container_type),
+                    ) {
+                        Ok(field) => field,
+                        Err(error) => return self.error(field_error(error)),
+                    };
+                    return match (&field.field_kind, &container_type.variant) {
+                        (FieldKind::Instance, ContainerVariant::Enum { .. }) if !is_place => {
+                            Typing::Type(Type::Container(container_type.clone()))
+                        }
+                        (FieldKind::Instance, _) => {
+                            self.error(TypeErrorKind::InstanceField(name.into()))
+                        }
+                        (FieldKind::Const, _) if is_place => {
+                            self.error(TypeErrorKind::AssignToConstant(name.into()))
+                        }
+                        (FieldKind::Const | FieldKind::Static, _) => {
+                            declaration_type(field).map_or(Typing::Invalid, Typing::Type)
+                        }
+                    };
+                }
+                _ => return self.error(TypeErrorKind::NotAContainer(Box::new(base_type))),
+            },
+            Type::Container(container_type) => container_type,
+            // Fields are accessed through single pointers
+            Type::Pointer(PointerType {
+                pointed_value,
+                is_many: false,
+                ..
+            }) => match normalize(pointed_value) {
+                Some(Type::Container(container_type)) => container_type,
+                _ => return self.error(TypeErrorKind::NotAContainer(Box::new(base_type))),
+            },
+            _ => return self.error(TypeErrorKind::NotAContainer(Box::new(base_type))),
+        };
+        let field = match container_type.lookup_field(
+            name,
+            NameKind::Field,
+            !scope.sees_private(container_type),
+        ) {
+            Ok(field) => field,
+            Err(error) => return self.error(field_error(error)),
+        };
+        match field.field_kind {
+            FieldKind::Instance => match field.field_type.as_ref().and_then(normalize) {
+                Some(field_type) => Typing::Type(field_type.clone()),
+                None => self.error(TypeErrorKind::NotAContainer(Box::new(base_type.clone()))),
+            },
+            FieldKind::Const if is_place => {
+                self.error(TypeErrorKind::AssignToConstant(name.into()))
+            }
+            FieldKind::Const | FieldKind::Static => {
+                declaration_type(field).map_or(Typing::Invalid, Typing::Type)
+            }
+        }
+    }
+
+    fn infer_anon_init(
+        &mut self,
+        fields: &[AnonField<Expression>],
+        expected: Expected,
+        scope: &EvalExecScope,
+    ) -> Typing {
+        let Some(expected) = expected.get() else {
+            return self.error(TypeErrorKind::Evaluation(
+                EvaluationErrorKind::UntypedLiteral,
+            ));
+        };
+        let Some(container_type) = expected.anon_init_target() else {
+            return self.error(TypeErrorKind::AnonInit {
+                container: Box::new(expected.clone()),
+                error: AnonInitError::NotAContainer,
+            });
+        };
+        let init = fields
+            .iter()
+            .map(|field| match field {
+                AnonField::Named(name, value) => AnonField::Named(name.clone(), value),
+                AnonField::Ident(name, value) => AnonField::Ident(name.clone(), value),
+                AnonField::Positional(value) => AnonField::Positional(value),
+            })
+            .collect();
+        let resolved = match container_type.resolve_anon_init(init) {
+            Ok(resolved) => resolved,
+            Err(error) => {
+                return self.error(TypeErrorKind::AnonInit {
+                    container: Box::new(Type::Container(container_type.clone())),
+                    error,
+                })
+            }
+        };
+        for (field, value) in resolved {
+            let Some(value) = value else {
+                continue;
+            };
+            match field.field_type.as_ref().and_then(normalize) {
+                Some(field_type) => self.check(value, Expected::Type(field_type), scope),
+                None => self.check(value, Expected::Any, scope),
+            };
+        }
+        Typing::Type(Type::Container(container_type.clone()))
+    }
+
+    fn infer_enum_literal(&mut self, name: &str, expected: Expected) -> Typing {
+        let Some(container_type) = expected.get().and_then(Type::anon_init_target) else {
+            return self.error(TypeErrorKind::Evaluation(
+                EvaluationErrorKind::UntypedLiteral,
+            ));
+        };
+        match container_type.resolve_enum_literal(name) {
+            Ok(_) => Typing::Type(Type::Container(container_type.clone())),
+            Err(error) => self.error(TypeErrorKind::AnonInit {
+                container: Box::new(Type::Container(container_type.clone())),
+                error,
+            }),
+        }
+    }
+
+    /// Checks the operand of a builtin, which gives the type of the result
+    fn infer_unary(
+        &mut self,
+        function: &BuiltinFunction,
+        operand: &Expression,
+        scope: &EvalExecScope,
+        result: impl FnOnce(&Type) -> Option<Type>,
+    ) -> Typing {
+        match self.check(operand, Expected::Any, scope) {
+            Typing::Type(operand_type) => match result(&operand_type) {
+                Some(result) => Typing::Type(result),
+                None => self.invalid_operands(function, vec![operand_type]),
+            },
+            typing => typing,
+        }
+    }
+
+    fn infer_builtin(
+        &mut self,
+        function: &BuiltinFunction,
+        args: &[Expression],
+        scope: &EvalExecScope,
+    ) -> Typing {
+        use BuiltinFunction as B;
+        if function.is_arithmetic() || function.is_comparison() {
+            return self.infer_operation(function, args, scope);
+        }
+        let usize_type = Type::Int(scope.target().usize_type());
+        match (function, args) {
+            (B::TypeOf, [value]) => {
+                self.check(value, Expected::Any, scope);
+                Typing::Type(Type::Type)
+            }
+            (B::Import, _) => Typing::Type(Type::Type),
+            (B::SizeOf | B::AlignOf, [value_type]) => {
+                self.check(value_type, Expected::Type(&Type::Type), scope);
+                Typing::Type(usize_type)
+            }
+            (B::ArrayType, [len, indexed_type, sentinel @ ..]) => {
+                self.check(len, Expected::Type(&usize_type), scope);
+                self.check(indexed_type, Expected::Type(&Type::Type), scope);
+                self.check_sentinel(indexed_type, sentinel, scope);
+                Typing::Type(Type::Type)
+            }
+            (B::PointerType { .. }, [pointed, sentinel @ ..]) => {
+                self.check(pointed, Expected::Type(&Type::Type), scope);
+                self.check_sentinel(pointed, sentinel, scope);
+                Typing::Type(Type::Type)
+            }
+            (B::OptionalType | B::ErrorUnionType | B::FnType, _) => {
+                for arg in args {
+                    self.check(arg, Expected::Type(&Type::Type), scope);
+                }
+                Typing::Type(Type::Type)
+            }
+            (B::Not, [operand]) => self.infer_unary(function, operand, scope, |operand| {
+                match operand {
+                    // `!T` is an error union
+                    Type::Type => Some(Type::Type),
+                    Type::Bool => Some(Type::Bool),
+                    _ => None,
+                }
+            }),
+            (B::AddressOf, [operand]) => {
+                let is_const = constant_place(operand, scope).is_some();
+                self.infer_unary(function, operand, scope, |operand| {
+                    Some(Type::Pointer(PointerType {
+                        pointed_value: type_expression(operand.clone()),
+                        is_const,
+                        is_many: false,
+                        sentinel: None,
+                    }))
+                })
+            }
+            (B::Deref, [operand]) => {
+                self.infer_unary(function, operand, scope, |operand| match operand {
+                    Type::Pointer(PointerType {
+                        pointed_value,
+                        is_many: false,
+                        ..
+                    }) => normalize(pointed_value).cloned(),
+                    _ => None,
+                })
+            }
+            (B::Index, [base, index]) => {
+                let index = self.check_index(index, &usize_type, scope);
+                let base_type = match self.check(base, Expected::Any, scope) {
+                    Typing::Type(base_type) => base_type,
+                    typing => return typing,
+                };
+                let Some(result) = indexed_type(&base_type) else {
+                    return self.invalid_operands(function, vec![base_type]);
+                };
+                // The sentinel can be read, but isn't counted in the length
+                match (index, array_type(&base_type)) {
+                    (Some(index), Some(array_type)) if index >= array_type.stored_len() => self
+                        .error(TypeErrorKind::Evaluation(
+                            EvaluationErrorKind::IndexOutOfBounds {
+                                index: index.into(),
+                                len: array_type.size,
+                            },
+                        )),
+                    _ => Typing::Type(result),
+                }
+            }
+            (B::Slice, [base, bounds @ ..]) if bounds.len() <= 2 => {
+                let bounds: Vec<_> = bounds
+                    .iter()
+                    .map(|bound| self.check_index(bound, &usize_type, scope))
+                    .collect();
+                let base_type = match self.check(base, Expected::Any, scope) {
+                    Typing::Type(base_type) => base_type,
+                    typing => return typing,
+                };
+                let Some(slice_type) = sliced_type(&base_type, bounds.len() == 1) else {
+                    return self.invalid_operands(function, vec![base_type]);
+                };
+                // Bounds known at compile time are checked against lengths known at compile time
+                if let Some(len) = array_type(&base_type).map(|array_type| array_type.size) {
+                    if let (Some(start), Some(end)) =
+                        (bounds[0], bounds.get(1).unwrap_or(&Some(len)))
+                    {
+                        if start > *end || *end > len {
+                            return self.error(TypeErrorKind::Evaluation(
+                                EvaluationErrorKind::SliceBounds {
+                                    start,
+                                    end: *end,
+                                    len,
+                                },
+                            ));
+                        }
+                    }
+                }
+                Typing::Type(Type::Slice(slice_type))
+            }
+            // Many-item pointers can be indexed, but don't know their length
+            (B::Len, [operand]) => self.infer_unary(function, operand, scope, |operand| {
+                sliced_type(operand, true).map(|_| usize_type)
+            }),
+            (B::IsNull | B::UnwrapOptional, [operand]) => {
+                self.infer_unary(function, operand, scope, |operand| match operand {
+                    Type::Option(_) if matches!(function, B::IsNull) => Some(Type::Bool),
+                    Type::Option(OptionType { some_type }) => Some((**some_type).clone()),
+                    _ => None,
+                })
+            }
+            (B::IsError | B::ErrorOf | B::UnwrapPayload, [operand]) => {
+                self.infer_unary(function, operand, scope, |operand| {
+                    match (function, operand) {
+                        (B::IsError, Type::ErrorUnion(_) | Type::ErrorSet(_)) => Some(Type::Bool),
+                        // The errors of inferred sets aren't known while checking their function
+                        (B::ErrorOf, Type::ErrorUnion(ErrorUnionType { err, .. })) => {
+                            Some(Type::ErrorSet(err.clone().unwrap_or(ErrorSetType {
+                                possible_errors: Box::new([]),
+                            })))
+                        }
+                        (B::ErrorOf, Type::ErrorSet(_)) => Some(operand.clone()),
+                        (B::UnwrapPayload, Type::ErrorUnion(ErrorUnionType { ok, .. })) => {
+                            Some((**ok).clone())
+                        }
+                        _ => None,
+                    }
+                })
+            }
+            _ => {
+                let mut found = Vec::with_capacity(args.len());
+                for arg in args {
+                    if let Typing::Type(arg_type) = self.check(arg, Expected::Any, scope) {
+                        found.push(arg_type);
+                    }
+                }
+                self.invalid_operands(function, found)
+            }
+        }
+    }
+
+    /// Arithmetic and comparisons, the operands of which have the same type. Comptime numbers
+    /// take the type of the other operands, and stay comptime when all of them are.
+    fn infer_operation(
+        &mut self,
+        function: &BuiltinFunction,
+        args: &[Expression],
+        scope: &EvalExecScope,
+    ) -> Typing {
+        let mut operand_type: Option<Type> = None;
+        let mut comptime_type: Option<Type> = None;
+        let mut comptime_args = Vec::new();
+        let mut typing = Typing::NoReturn;
+        for arg in args {
+            let expected = operand_type.as_ref().map_or(Expected::Any, Expected::Type);
+            match self.check(arg, expected, scope) {
+                Typing::Type(found) if is_comptime_number(&found) => {
+                    comptime_args.push(arg);
+                    // Mixing comptime integers and floats gives floats
+                    if !matches!(comptime_type, Some(Type::ComptimeFloat)) {
+                        comptime_type = Some(found);
+                    }
+                }
+                Typing::Type(found) => {
+                    operand_type.get_or_insert(found);
+                }
+                Typing::Invalid => typing = Typing::Invalid,
+                Typing::NoReturn => {}
+            }
+        }
+        let operand_type = match (operand_type, comptime_type) {
+            (Some(operand_type), _) => {
+                for arg in comptime_args {
+                    self.check(arg, Expected::Type(&operand_type), scope);
+                }
+                operand_type
+            }
+            (None, Some(comptime_type)) => comptime_type,
+            (None, None) => return typing,
+        };
+        if let Typing::Invalid = typing {
+            return typing;
+        }
+        if !accepts(function, &operand_type) {
+            return self.invalid_operands(function, vec![operand_type]);
+        }
+        match function.is_comparison() {
+            true => Typing::Type(Type::Bool),
+            false => Typing::Type(operand_type),
+        }
+    }
+}
+
+/// Whether an arithmetic builtin or a comparison can be applied to values of `operand_type`
+fn accepts(function: &BuiltinFunction, operand_type: &Type) -> bool {
+    use BuiltinFunction as B;
+    match (function, operand_type) {
+        (_, Type::Int(_) | Type::ComptimeInt) => true,
+        (B::ShiftLeft | B::ShiftRight | B::BitAnd | B::BitXor | B::BitOr | B::BitNot, _) => false,
+        (_, Type::Float(_) | Type::ComptimeFloat) => true,
+        (B::Equal | B::NotEqual, Type::Container(ContainerType { variant, .. })) => {
+            matches!(variant, ContainerVariant::Enum { .. })
+        }
+        (B::Equal | B::NotEqual, operand_type) => matches!(
+            operand_type,
+            Type::Bool | Type::Pointer(_) | Type::Option(_) | Type::ErrorSet(_) | Type::Type
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::{field, many_pointer, pointer, struct_type, u8_type, u8_value},
+        types::IntType,
+        values::Int,
+    };
+
+    fn errors(possible_errors: &[u32]) -> ErrorSetType {
+        ErrorSetType {
+            possible_errors: possible_errors.into(),
+        }
+    }
+
+    /// A struct with a single field, to which pointers to it coerce
+    fn wrapper(inner: Type) -> Type {
+        Type::Container(ContainerType {
+            variant: ContainerVariant::Struct {
+                ptr_coerce_target: Some("inner".into()),
+                droppers: Box::new([]),
+            },
+            ..struct_type(vec![field("inner", inner)])
+        })
+    }
+
+    #[test]
+    fn pointer_coercion() {
+        let mut checker = TypeChecker::new();
+        let byte = u8_type();
+        let inner = wrapper(byte.clone());
+        let outer = wrapper(inner.clone());
+        assert!(checker.coerces(&pointer(outer.clone()), &pointer(inner.clone())));
+        assert!(checker.coerces(&pointer(outer.clone()), &pointer(byte.clone())));
+        assert!(!checker.coerces(&pointer(inner), &pointer(outer)));
+        assert!(!checker.coerces(&pointer(byte), &pointer(Type::Void)));
+    }
+
+    #[test]
+    fn slice_coercion() {
+        let mut checker = TypeChecker::new();
+        let u8_type = IntType {
+            signed: false,
+            bits: 8,
+        };
+        let byte = Type::Int(u8_type.clone());
+        let zero = || {
+            Some(Box::new(Value::Int(
+                Int::new(&0.into(), u8_type.clone()).unwrap(),
+            )))
+        };
+        let slice = |is_const, sentinel| {
+            Type::Slice(SliceType {
+                indexed_type: Box::new(byte.clone()),
+                is_const,
+                sentinel,
+            })
+        };
+        let array = Type::Array(ArrayType {
+            size: 4,
+            indexed_type: Box::new(byte.clone()),
+            sentinel: zero(),
+        });
+        assert!(checker.coerces(&slice(false, zero()), &slice(true, None)));
+        assert!(!checker.coerces(&slice(true, zero()), &slice(false, zero())));
+        assert!(!checker.coerces(&slice(false, None), &slice(false, zero())));
+        assert!(checker.coerces(&pointer(array.clone()), &slice(false, zero())));
+        // Arrays are sliced or pointed to to be used as slices
+        assert!(!checker.coerces(&array, &slice(true, zero())));
+    }
+
+    #[test]
+    fn many_pointer_coercion() {
+        let mut checker = TypeChecker::new();
+        let terminated = || many_pointer(u8_type(), Some(u8_value(0)));
+        let array = |sentinel| {
+            Type::Array(ArrayType {
+                size: 4,
+                indexed_type: Box::new(u8_type()),
+                sentinel,
+            })
+        };
+        assert!(checker.coerces(&terminated(), &many_pointer(u8_type(), None)));
+        assert!(!checker.coerces(&many_pointer(u8_type(), None), &terminated()));
+        assert!(!checker.coerces(&terminated(), &many_pointer(Type::Void, None)));
+        let terminated_array = pointer(array(Some(Box::new(u8_value(0)))));
+        assert!(checker.coerces(&terminated_array, &terminated()));
+        assert!(checker.coerces(&pointer(array(None)), &many_pointer(u8_type(), None)));
+        assert!(!checker.coerces(&pointer(array(None)), &terminated()));
+        // Single and many-item pointers to the same type don't coerce to one another
+        assert!(!checker.coerces(&pointer(u8_type()), &many_pointer(u8_type(), None)));
+        assert!(!checker.coerces(&many_pointer(u8_type(), None), &pointer(u8_type())));
+    }
+
+    #[test]
+    fn unsupported_evaluation() {
+        let error =
+            TypeErrorKind::Evaluation(EvaluationErrorKind::Unsupported(BuiltinFunction::Deref));
+        assert_eq!(
+            error.to_string(),
+            "`.*` can't be evaluated at compile time yet"
+        );
+    }
+
+    #[test]
+    fn error_sets() {
+        let mut checker = TypeChecker::new();
+        let union = |err| {
+            Type::ErrorUnion(ErrorUnionType {
+                ok: Box::new(Type::Void),
+                err,
+            })
+        };
+        let set = |possible_errors: &[u32]| Type::ErrorSet(errors(possible_errors));
+        assert!(checker.coerces(&set(&[1]), &union(Some(errors(&[1, 2])))));
+        assert!(!checker.coerces(&set(&[3]), &union(Some(errors(&[1, 2])))));
+        // Empty sets accept no errors, unlike inferred ones
+        assert!(!checker.coerces(&set(&[3]), &union(Some(errors(&[])))));
+        assert!(!checker.coerces(&set(&[3]), &set(&[])));
+        assert!(checker.coerces(&set(&[3]), &union(None)));
+        assert!(checker.coerces(&set(&[]), &set(&[1])));
+        assert!(checker.coerces(&Type::Void, &union(Some(errors(&[1])))));
+    }
+}
@@ -0,0 +1,1044 @@
+//! Evaluation of expressions at compile time. Bindings live in `EvalExecScope`s, and function
+//! calls are evaluated in a scope of their own, enclosed by the outermost one.
+
+use cera_parse::{src_token::Literal, text_region::TextSpan};
+use cera_src_ast::suggest::NameKind;
+use num_bigint::BigInt;
+
+use crate::{
+    builtin::{BuiltinFunction, CallFrame, EvalExecScope, MAX_CALL_DEPTH},
+    expressions::{Expression, LabelId, Let},
+    intern::normalize,
+    layout::{LayoutError, Target},
+    types::{
+        sentinel_coerces, AnonField, AnonInitError, ContainerError, ContainerType,
+        ContainerVariant, FieldError, FieldKind, FloatType, IntType, PointerType, SliceType, Type,
+    },
+    values::{Array, ComptimeFloat, Container, Float, Int, OptionValue, Pointer, Slice, Value},
+};
+
+#[derive(Debug, Clone)]
+pub enum EvaluationErrorKind {
+    Undefined(Box<str>),
+    AssignToConstant(Box<str>),
+    /// Only names and fields can be assigned to
+    InvalidAssignTarget,
+    /// A literal, `null`, `.{ ... }` or `.variant` without an expected type to give it
+    UntypedLiteral,
+    /// A literal, or a comptime number, which can't have the expected type
+    InvalidLiteral {
+        expected: Box<Type>,
+    },
+    IntOverflow {
+        value: BigInt,
+        int_type: IntType,
+    },
+    /// A comptime number which values of the `expected` type can't hold, such as `0.5` as an
+    /// integer or `1.0e39` as an `f32`
+    LossyCoercion {
+        value: Box<Value>,
+        expected: Box<Type>,
+    },
+    /// Shifting by at least the amount of bits of the shifted value
+    ShiftAmount(BigInt),
+    DivisionByZero,
+    /// Operands of types the builtin doesn't accept
+    InvalidOperands(BuiltinFunction),
+    /// A builtin which can't be evaluated at compile time yet
+    Unsupported(BuiltinFunction),
+    /// A value of another type than the `expected` one
+    Mismatch {
+        expected: Box<Type>,
+    },
+    NotAType,
+    NotCallable,
+    NotAContainer,
+    /// Conditions must be `bool`s, this is the type found instead
+    NotACondition(Box<Type>),
+    ArgumentCount {
+        expected: usize,
+        found: usize,
+    },
+    Field {
+        name: Box<str>,
+        error: FieldError,
+    },
+    /// An instance field accessed on its container rather than on a value
+    InstanceField(Box<str>),
+    /// `.{ ... }` or `.variant` which can't be a value of the `container` type
+    AnonInit {
+        container: Box<Type>,
+        error: AnonInitError,
+    },
+    Container(ContainerError),
+    Layout(LayoutError),
+    UnwrapNull,
+    /// An index of an array or a slice which isn't below its length, or at its sentinel
+    IndexOutOfBounds {
+        index: BigInt,
+        len: usize,
+    },
+    /// `base[start..end]` where `end` is before `start`, or after the length of `base`
+    SliceBounds {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    BreakOutsideLabel(LabelId),
+    ReturnOutsideFunction,
+    CallDepthExceeded,
+    BranchQuotaExceeded,
+    /// Declarations using each other's values, each one using the next and the last one the
+    /// first
+    DeclarationCycle(Box<[Box<str>]>),
+}
+
+/// Why an expression couldn't be evaluated
+#[derive(Debug, Clone)]
+pub struct EvaluationError {
+    pub kind: EvaluationErrorKind,
+    /// The innermost `Spanned` expression around the one which failed
+    pub span: Option<TextSpan>,
+}
+
+impl From<EvaluationErrorKind> for EvaluationError {
+    fn from(kind: EvaluationErrorKind) -> Self {
+        Self { kind, span: None }
+    }
+}
+
+/// Why evaluating an expression stopped before it produced a value
+enum Exit {
+    Break(LabelId, Box<Value>),
+    Return(Box<Value>),
+    Error(EvaluationError),
+}
+
+impl From<EvaluationErrorKind> for Exit {
+    fn from(kind: EvaluationErrorKind) -> Self {
+        Exit::Error(kind.into())
+    }
+}
+
+type Flow = Result<Value, Exit>;
+
+impl Expression {
+    /// Evaluates the expression at compile time
+    pub fn eval(&self, scope: &EvalExecScope) -> Result<Value, EvaluationError> {
+        self.eval_expecting(scope, None)
+    }
+
+    /// Evaluates the expression to a value of the `expected` type, which literals without a
+    /// type of their own are given
+    pub fn eval_expecting(
+        &self,
+        scope: &EvalExecScope,
+        expected: Option<&Type>,
+    ) -> Result<Value, EvaluationError> {
+        match eval_coerced(self, scope, expected) {
+            Ok(value) => Ok(value),
+            Err(Exit::Error(error)) => Err(error),
+            Err(Exit::Break(label, _)) => Err(EvaluationErrorKind::BreakOutsideLabel(label).into()),
+            Err(Exit::Return(_)) => Err(EvaluationErrorKind::ReturnOutsideFunction.into()),
+        }
+    }
+}
+
+// Each kind of expression is evaluated by a function of its own, which keeps the frames of the
+// recursion small when nothing is inlined
+fn eval(expression: &Expression, scope: &EvalExecScope, expected: Option<&Type>) -> Flow {
+    match expression {
+        Expression::Value(value) => Ok((**value).clone()),
+        Expression::Literal(literal) => Ok(eval_literal(literal)),
+        Expression::Builtin(function, args) => eval_builtin(function, args, scope),
+        Expression::ExpressionList(expressions) => eval_list(expressions, scope, expected),
+        Expression::Name(name) => eval_name(name, scope, expected),
+        Expression::Let(let_) => eval_let(let_, scope),
+        Expression::Assign { target, value } => eval_assign(target, value, scope),
+        Expression::If {
+            condition,
+            then,
+            otherwise,
+        } => eval_if(condition, then, otherwise, scope, expected),
+        Expression::Loop(body) => eval_loop(body, scope),
+        Expression::Labeled { label, body } => eval_labeled(*label, body, scope, expected),
+        Expression::Break { label, value } => eval_break(*label, value, scope),
+        Expression::Return(value) => eval_return(value, scope),
+        Expression::Call { callee, args } => eval_call(callee, args, scope),
+        Expression::Field { base, name } => eval_field(base, name, scope),
+        Expression::AnonInit(fields) => eval_anon_init(fields, scope, expected),
+        Expression::EnumLiteral(name) => Ok(eval_enum_literal(name, expected)?),
+        Expression::Spanned(span, expression) => eval_spanned(span, expression, scope, expected),
+    }
+}
+
+fn eval_list(expressions: &[Expression], scope: &EvalExecScope, expected: Option<&Type>) -> Flow {
+    let scope = scope.new_scope();
+    let mut value = Value::Void;
+    for (idx, expression) in expressions.iter().enumerate() {
+        let is_last = idx + 1 == expressions.len();
+        value = eval(expression, &scope, expected.filter(|_| is_last))?;
+    }
+    Ok(value)
+}
+
+fn eval_name(name: &str, scope: &EvalExecScope, expected: Option<&Type>) -> Flow {
+    match scope.get(name) {
+        Some((value, _)) => Ok(value),
+        None => Ok(primitive(name, expected, scope.target())?),
+    }
+}
+
+fn eval_let(let_: &Let, scope: &EvalExecScope) -> Flow {
+    let var_type = match &let_.var_type {
+        Some(var_type) => Some(eval_type(var_type, scope)?),
+        None => None,
+    };
+    let value = eval_coerced(&let_.value, scope, var_type.as_ref())?;
+    let value_type = var_type.or_else(|| stored_type(&value));
+    scope.declare(&let_.name, value, value_type, let_.is_mutable);
+    Ok(Value::Void)
+}
+
+fn eval_assign(target: &Expression, value: &Expression, scope: &EvalExecScope) -> Flow {
+    let target_type = place_type(target, scope)?;
+    let value = eval_coerced(value, scope, target_type.as_ref())?;
+    store(target, value, scope)?;
+    Ok(Value::Void)
+}
+
+fn eval_if(
+    condition: &Expression,
+    then: &Expression,
+    otherwise: &Expression,
+    scope: &EvalExecScope,
+    expected: Option<&Type>,
+) -> Flow {
+    match eval(condition, scope, None)? {
+        Value::Bool(true) => eval(then, scope, expected),
+        Value::Bool(false) => eval(otherwise, scope, expected),
+        value => Err(EvaluationErrorKind::NotACondition(Box::new(value.get_type())).into()),
+    }
+}
+
+fn eval_loop(body: &Expression, scope: &EvalExecScope) -> Flow {
+    loop {
+        scope.branch()?;
+        eval(body, scope, None)?;
+    }
+}
+
+fn eval_labeled(
+    label: LabelId,
+    body: &Expression,
+    scope: &EvalExecScope,
+    expected: Option<&Type>,
+) -> Flow {
+    match eval(body, scope, expected) {
+        Err(Exit::Break(target, value)) if target == label => Ok(*value),
+        result => result,
+    }
+}
+
+fn eval_break(label: LabelId, value: &Expression, scope: &EvalExecScope) -> Flow {
+    Err(Exit::Break(label, Box::new(eval(value, scope, None)?)))
+}
+
+fn eval_return(value: &Expression, scope: &EvalExecScope) -> Flow {
+    let Some(frame) = scope.frame() else {
+        return Err(EvaluationErrorKind::ReturnOutsideFunction.into());
+    };
+    let value = eval_coerced(value, scope, Some(&frame.return_type))?;
+    Err(Exit::Return(Box::new(value)))
+}
+
+fn eval_enum_literal(name: &str, expected: Option<&Type>) -> Result<Value, EvaluationErrorKind> {
+    let container_type = expected
+        .and_then(Type::anon_init_target)
+        .ok_or(EvaluationErrorKind::UntypedLiteral)?;
+    let variant = container_type.resolve_enum_literal(name).map_err(|error| {
+        EvaluationErrorKind::AnonInit {
+            container: Box::new(Type::Container(container_type.clone())),
+            error,
+        }
+    })?;
+    variant_value(container_type, variant.val.as_ref())
+}
+
+fn eval_spanned(
+    span: &TextSpan,
+    expression: &Expression,
+    scope: &EvalExecScope,
+    expected: Option<&Type>,
+) -> Flow {
+    eval(expression, scope, expected).map_err(|exit| match exit {
+        Exit::Error(EvaluationError { kind, span: None }) => Exit::Error(EvaluationError {
+            kind,
+            span: Some(span.clone()),
+        }),
+        exit => exit,
+    })
+}
+
+/// Evaluates `expression` to a value of the `expected` type, which is blamed on the whole
+/// expression when it doesn't have that type
+fn eval_coerced(expression: &Expression, scope: &EvalExecScope, expected: Option<&Type>) -> Flow {
+    let value = eval(expression, scope, expected)?;
+    coerce(value, expected).map_err(|kind| {
+        Exit::Error(EvaluationError {
+            kind,
+            span: expression.span().cloned(),
+        })
+    })
+}
+
+/// Evaluates an expression which must be a type
+fn eval_type(expression: &Expression, scope: &EvalExecScope) -> Result<Type, Exit> {
+    match eval(expression, scope, None)? {
+        Value::Type(value_type) => Ok(value_type),
+        _ => Err(EvaluationErrorKind::NotAType.into()),
+    }
+}
+
+/// The type of values which store it, which is the type given to values assigned after them
+fn stored_type(value: &Value) -> Option<Type> {
+    value.bytes().map(|_| value.get_type())
+}
+
+/// Converts `value` to the `expected` type: comptime numbers to the types which hold their
+/// value exactly, values to optionals of their type, and arrays to the slices and many-item
+/// pointers of their values
+pub fn coerce(value: Value, expected: Option<&Type>) -> Result<Value, EvaluationErrorKind> {
+    match (expected, value) {
+        (Some(Type::Option(_)), value @ (Value::Option(_) | Value::Undefined)) => Ok(value),
+        (Some(expected @ Type::Option(option_type)), value) => {
+            let value = coerce(value, Some(&option_type.some_type))?;
+            OptionValue::some(&value, option_type.clone())
+                .map(Value::Option)
+                .ok_or_else(|| EvaluationErrorKind::Mismatch {
+                    expected: Box::new(expected.clone()),
+                })
+        }
+        (Some(expected), Value::ComptimeInt(value)) => coerce_comptime_int(value, expected),
+        (Some(expected), Value::ComptimeFloat(value)) => coerce_comptime_float(value, expected),
+        (Some(Type::Slice(slice_type)), value @ (Value::Slice(_) | Value::Pointer(_))) => {
+            coerce_slice(value, slice_type)
+        }
+        (Some(Type::Pointer(pointer_type)), Value::Pointer(pointer)) if pointer_type.is_many => {
+            coerce_many_pointer(pointer, pointer_type)
+        }
+        (_, value) => Ok(value),
+    }
+}
+
+/// Slices and pointers to arrays as slices of the same values, which can be `const` or not be
+/// ended by a sentinel
+fn coerce_slice(value: Value, slice_type: &SliceType) -> Result<Value, EvaluationErrorKind> {
+    let Some((elements, is_const)) = value.counted_elements() else {
+        return Ok(value);
+    };
+    let mismatch = || EvaluationErrorKind::Mismatch {
+        expected: Box::new(Type::Slice(slice_type.clone())),
+    };
+    let sentinel = elements.array_type.sentinel.as_deref();
+    if elements.array_type.indexed_type != slice_type.indexed_type
+        || is_const && !slice_type.is_const
+        || !sentinel_coerces(sentinel, slice_type.sentinel.as_deref())
+    {
+        return Err(mismatch());
+    }
+    let elements = elements
+        .sub_array(0, elements.array_type.size, slice_type.sentinel.clone())
+        .ok_or_else(mismatch)?;
+    Ok(Value::Slice(Slice {
+        elements,
+        slice_type: slice_type.clone(),
+    }))
+}
+
+/// Pointers to arrays as many-item pointers to the same values, which can be `const` or not be
+/// ended by a sentinel
+fn coerce_many_pointer(
+    pointer: Pointer,
+    pointer_type: &PointerType,
+) -> Result<Value, EvaluationErrorKind> {
+    if let (false, Some(Value::Array(array))) =
+        (pointer.pointer_type.is_many, pointer.pointee.as_deref())
+    {
+        let sentinel = array.array_type.sentinel.as_deref();
+        if normalize(&pointer_type.pointed_value) != Some(&*array.array_type.indexed_type)
+            || pointer.pointer_type.is_const && !pointer_type.is_const
+            || !sentinel_coerces(sentinel, pointer_type.sentinel.as_deref())
+        {
+            return Err(EvaluationErrorKind::Mismatch {
+                expected: Box::new(Type::Pointer(pointer_type.clone())),
+            });
+        }
+    }
+    Ok(Value::Pointer(Pointer {
+        pointer_type: pointer_type.clone(),
+        ..pointer
+    }))
+}
+
+fn coerce_comptime_int(value: BigInt, expected: &Type) -> Result<Value, EvaluationErrorKind> {
+    let lossy = |value| EvaluationErrorKind::LossyCoercion {
+        value: Box::new(Value::ComptimeInt(value)),
+        expected: Box::new(expected.clone()),
+    };
+    match expected {
+        Type::Int(int_type) => match Int::new(&value, int_type.clone()) {
+            Some(int) => Ok(Value::Int(int)),
+            None => Err(EvaluationErrorKind::IntOverflow {
+                value,
+                int_type: int_type.clone(),
+            }),
+        },
+        Type::Float(float_type) => {
+            let float = float_from_decimal(&value.to_string(), float_type).ok_or_else(|| {
+                EvaluationErrorKind::InvalidLiteral {
+                    expected: Box::new(expected.clone()),
+                }
+            })?;
+            // Integers are exact when their significant bits fit in the significand
+            let magnitude = value.magnitude();
+            let bits = magnitude.bits() - magnitude.trailing_zeros().unwrap_or(0);
+            match bits <= float_type.significand_bits() && is_finite(&float) {
+                true => Ok(Value::Float(float)),
+                false => Err(lossy(value)),
+            }
+        }
+        Type::ComptimeInt => Ok(Value::ComptimeInt(value)),
+        Type::ComptimeFloat => Ok(Value::ComptimeFloat(ComptimeFloat::from_int(&value))),
+        _ => Err(EvaluationErrorKind::InvalidLiteral {
+            expected: Box::new(expected.clone()),
+        }),
+    }
+}
+
+/// Comptime floats are rounded to the nearest value of float types, but must be in their range
+fn coerce_comptime_float(
+    value: ComptimeFloat,
+    expected: &Type,
+) -> Result<Value, EvaluationErrorKind> {
+    let lossy = |value| EvaluationErrorKind::LossyCoercion {
+        value: Box::new(Value::ComptimeFloat(value)),
+        expected: Box::new(expected.clone()),
+    };
+    match expected {
+        Type::Float(float_type) => match float_from_decimal(&value.to_scientific(), float_type) {
+            Some(float) if is_finite(&float) => Ok(Value::Float(float)),
+            Some(_) => Err(lossy(value)),
+            None => Err(EvaluationErrorKind::InvalidLiteral {
+                expected: Box::new(expected.clone()),
+            }),
+        },
+        Type::Int(_) | Type::ComptimeInt => match value.to_int() {
+            Some(int) => coerce_comptime_int(int, expected),
+            None => Err(lossy(value)),
+        },
+        Type::ComptimeFloat => Ok(Value::ComptimeFloat(value)),
+        _ => Err(EvaluationErrorKind::InvalidLiteral {
+            expected: Box::new(expected.clone()),
+        }),
+    }
+}
+
+fn is_finite(float: &Float) -> bool {
+    float.to_f64().is_some_and(f64::is_finite)
+}
+
+fn primitive(
+    name: &str,
+    expected: Option<&Type>,
+    target: &Target,
+) -> Result<Value, EvaluationErrorKind> {
+    let float = |float_type| Ok(Value::Type(Type::Float(float_type)));
+    match name {
+        "true" => Ok(Value::Bool(true)),
+        "false" => Ok(Value::Bool(false)),
+        "undefined" => Ok(Value::Undefined),
+        "null" => match expected {
+            Some(Type::Option(option_type)) => OptionValue::null(option_type.clone())
+                .map(Value::Option)
+                .ok_or(EvaluationErrorKind::InvalidLiteral {
+                    expected: Box::new(Type::Option(option_type.clone())),
+                }),
+            Some(expected) => Err(EvaluationErrorKind::InvalidLiteral {
+                expected: Box::new(expected.clone()),
+            }),
+            None => Err(EvaluationErrorKind::UntypedLiteral),
+        },
+        "bool" => Ok(Value::Type(Type::Bool)),
+        "void" => Ok(Value::Type(Type::Void)),
+        "type" => Ok(Value::Type(Type::Type)),
+        "comptime_int" => Ok(Value::Type(Type::ComptimeInt)),
+        "comptime_float" => Ok(Value::Type(Type::ComptimeFloat)),
+        "usize" => Ok(Value::Type(Type::Int(target.usize_type()))),
+        "isize" => Ok(Value::Type(Type::Int(IntType {
+            signed: true,
+            ..target.usize_type()
+        }))),
+        "f16" => float(FloatType::F16),
+        "f32" => float(FloatType::F32),
+        "f64" => float(FloatType::F64),
+        "f80" => float(FloatType::F80),
+        "f128" => float(FloatType::F128),
+        _ => {
+            let int_type = name
+                .strip_prefix(['i', 'u'])
+                .filter(|bits| !bits.starts_with('0'))
+                .and_then(|bits| bits.parse().ok())
+                .map(|bits| IntType {
+                    signed: name.starts_with('i'),
+                    bits,
+                });
+            match int_type {
+                Some(int_type) => Ok(Value::Type(Type::Int(int_type))),
+                None => Err(EvaluationErrorKind::Undefined(name.into())),
+            }
+        }
+    }
+}
+
+/// Numbers are comptime ones until they are coerced to the type they are used as
+fn eval_literal(literal: &Literal) -> Value {
+    match literal {
+        Literal::Int(value) => Value::ComptimeInt(value.clone().into()),
+        Literal::Float(value) => Value::ComptimeFloat(value.into()),
+        // A constant array of the bytes of the string, ended by a `0` like in C
+        Literal::String(text) => {
+            Value::Pointer(Pointer::comptime(Value::Array(Array::string(text)), true))
+        }
+    }
+}
+
+/// Rounds a number written in decimal to the nearest value of `float_type`
+fn float_from_decimal(decimal: &str, float_type: &FloatType) -> Option<Float> {
+    let value = match float_type {
+        // Parsed as `f32` directly, as rounding to `f64` first could round differently
+        FloatType::F32 => decimal.parse::<f32>().ok()? as f64,
+        _ => decimal.parse::<f64>().ok()?,
+    };
+    Float::from_f64(value, float_type.clone())
+}
+
+/// Whether the expression is a literal, which takes its type from the other operands
+fn is_literal(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal(_) => true,
+        Expression::Spanned(_, expression) => is_literal(expression),
+        _ => false,
+    }
+}
+
+fn eval_builtin(function: &BuiltinFunction, args: &[Expression], scope: &EvalExecScope) -> Flow {
+    let mut hint = match function {
+        BuiltinFunction::ArrayType => Some(Type::Int(IntType {
+            signed: false,
+            bits: 64,
+        })),
+        BuiltinFunction::Index | BuiltinFunction::Slice => {
+            Some(Type::Int(scope.target().usize_type()))
+        }
+        _ => None,
+    };
+    let (literals, others): (Vec<_>, Vec<_>) =
+        (0..args.len()).partition(|idx| is_literal(&args[*idx]));
+    let mut values: Vec<Value> = vec![Value::Void; args.len()];
+    // Literals are evaluated last, so that `null` and `.variant` among the others can take the
+    // type of the ones before them
+    for idx in others.into_iter().chain(literals) {
+        let value = eval(&args[idx], scope, hint.as_ref())?;
+        if function.has_same_type_operands() && hint.is_none() {
+            hint = stored_type(&value);
+        }
+        values[idx] = value;
+    }
+    // Comptime numbers take the type of the other operands, and stay comptime otherwise. The
+    // sentinel of `[len:sentinel]T` takes the type of the values instead.
+    let hinted = match function {
+        BuiltinFunction::ArrayType => 1,
+        _ => values.len(),
+    };
+    if hint.is_some() {
+        for value in values.iter_mut().take(hinted) {
+            if matches!(value, Value::ComptimeInt(_) | Value::ComptimeFloat(_)) {
+                *value = coerce(std::mem::replace(value, Value::Void), hint.as_ref())?;
+            }
+        }
+    }
+    let args: Vec<&Value> = values.iter().collect();
+    Ok(function.try_exec(&args, scope)?)
+}
+
+fn eval_call(callee: &Expression, args: &[Expression], scope: &EvalExecScope) -> Flow {
+    let Value::Function(function) = eval(callee, scope, None)? else {
+        return Err(EvaluationErrorKind::NotCallable.into());
+    };
+    let param_types = match &function.function_type.parameters {
+        Expression::ExpressionList(param_types) => param_types,
+        _ => return Err(EvaluationErrorKind::NotCallable.into()),
+    };
+    if param_types.len() != args.len() || function.param_names.len() != args.len() {
+        return Err(EvaluationErrorKind::ArgumentCount {
+            expected: param_types.len(),
+            found: args.len(),
+        }
+        .into());
+    }
+    let depth = scope.frame().map_or(0, |frame| frame.depth) + 1;
+    if depth > MAX_CALL_DEPTH {
+        return Err(EvaluationErrorKind::CallDepthExceeded.into());
+    }
+    scope.branch()?;
+
+    // Functions only see the outermost scope, along with their parameters
+    let params = scope.global().new_scope();
+    for ((name, param_type), arg) in function.param_names.iter().zip(param_types).zip(args) {
+        let param_type = eval_type(param_type, &params)?;
+        let value = eval_coerced(arg, scope, Some(&param_type))?;
+        params.declare(name, value, Some(param_type), false);
+    }
+    let return_type = eval_type(&function.function_type.result, &params)?;
+    let body = params.new_call_scope(CallFrame {
+        return_type,
+        depth,
+        file: function.file,
+    });
+    match eval(&function.expression, &body, None) {
+        Ok(_) => Ok(Value::Void),
+        Err(Exit::Return(value)) => Ok(*value),
+        Err(Exit::Break(label, _)) => Err(EvaluationErrorKind::BreakOutsideLabel(label).into()),
+        Err(error) => Err(error),
+    }
+}
+
+/// The value of an enum variant, from the value of its field
+fn variant_value(
+    container_type: &ContainerType,
+    value: Option<&Expression>,
+) -> Result<Value, EvaluationErrorKind> {
+    match value {
+        Some(Expression::Value(value)) => match &**value {
+            Value::Int(int) => Ok(Value::Container(Container {
+                bytes: int.bytes.clone(),
+                container_type: container_type.clone(),
+            })),
+            _ => Err(EvaluationErrorKind::NotAContainer),
+        },
+        _ => Err(EvaluationErrorKind::NotAContainer),
+    }
+}
+
+/// The value of const and static fields, which are evaluated when the container is built
+fn declaration_value(name: &str, value: Option<&Expression>, scope: &EvalExecScope) -> Flow {
+    match value {
+        Some(value) => eval(value, scope.global(), None),
+        None => Err(EvaluationErrorKind::Undefined(name.into()).into()),
+    }
+}
+
+fn field_error(name: &str, error: FieldError) -> EvaluationErrorKind {
+    EvaluationErrorKind::Field {
+        name: name.into(),
+        error,
+    }
+}
+
+fn eval_field(base: &Expression, name: &str, scope: &EvalExecScope) -> Flow {
+    match eval(base, scope, None)? {
+        Value::Type(Type::Container(container_type)) => {
+            let field = container_type
+                .lookup_field(name, NameKind::Value, !scope.sees_private(&container_type))
+                .map_err(|error| field_error(name, error))?;
+            match (&field.field_kind, &container_type.variant) {
+                (FieldKind::Instance, ContainerVariant::Enum { .. }) => {
+                    Ok(variant_value(&container_type, field.val.as_ref())?)
+                }
+                (FieldKind::Instance, _) => {
+                    Err(EvaluationErrorKind::InstanceField(name.into()).into())
+                }
+                (FieldKind::Const | FieldKind::Static, _) => {
+                    declaration_value(name, field.val.as_ref(), scope)
+                }
+            }
+        }
+        Value::Container(container) => {
+            let container_type = &container.container_type;
+            let field = container_type
+                .lookup_field(name, NameKind::Field, !scope.sees_private(container_type))
+                .map_err(|error| field_error(name, error))?;
+            match field.field_kind {
+                FieldKind::Instance => Ok(container
+                    .get(name)
+                    .ok_or(EvaluationErrorKind::NotAContainer)?),
+                FieldKind::Const | FieldKind::Static => {
+                    declaration_value(name, field.val.as_ref(), scope)
+                }
+            }
+        }
+        // The length is the only field of arrays and slices
+        value if name == "len" && value.counted_elements().is_some() => {
+            Ok(BuiltinFunction::Len.try_exec(&[&value], scope)?)
+        }
+        _ => Err(EvaluationErrorKind::NotAContainer.into()),
+    }
+}
+
+/// The type of the field of a container, which must have been evaluated
+fn field_type(field_type: Option<&Expression>) -> Result<Type, EvaluationErrorKind> {
+    match field_type {
+        Some(Expression::Value(value)) => match &**value {
+            Value::Type(field_type) => Ok(field_type.clone()),
+            _ => Err(EvaluationErrorKind::NotAType),
+        },
+        _ => Err(EvaluationErrorKind::NotAType),
+    }
+}
+
+fn eval_anon_init(
+    fields: &[AnonField<Expression>],
+    scope: &EvalExecScope,
+    expected: Option<&Type>,
+) -> Flow {
+    let container_type = expected
+        .and_then(Type::anon_init_target)
+        .ok_or(EvaluationErrorKind::UntypedLiteral)?;
+    if !matches!(container_type.variant, ContainerVariant::Struct { .. }) {
+        return Err(EvaluationErrorKind::InvalidLiteral {
+            expected: Box::new(Type::Container(container_type.clone())),
+        }
+        .into());
+    }
+    let init = fields
+        .iter()
+        .map(|field| match field {
+            AnonField::Named(name, value) => AnonField::Named(name.clone(), value),
+            AnonField::Ident(name, value) => AnonField::Ident(name.clone(), value),
+            AnonField::Positional(value) => AnonField::Positional(value),
+        })
+        .collect();
+    let resolved =
+        container_type
+            .resolve_anon_init(init)
+            .map_err(|error| EvaluationErrorKind::AnonInit {
+                container: Box::new(Type::Container(container_type.clone())),
+                error,
+            })?;
+    let mut values = Vec::with_capacity(resolved.len());
+    for (field, value) in resolved {
+        let field_type = field_type(field.field_type.as_ref())?;
+        let value = match (value, &field.val) {
+            (Some(value), _) => eval(value, scope, Some(&field_type))?,
+            (None, Some(default)) => eval(default, scope.global(), Some(&field_type))?,
+            (None, None) => unreachable!("missing fields are reported when resolving"),
+        };
+        values.push(coerce(value, Some(&field_type))?);
+    }
+    match Container::new(container_type.clone(), &values) {
+        Some(container) => Ok(Value::Container(container)),
+        None => Err(EvaluationErrorKind::Mismatch {
+            expected: Box::new(Type::Container(container_type.clone())),
+        }
+        .into()),
+    }
+}
+
+/// The type given to values assigned to `target`
+fn place_type(target: &Expression, scope: &EvalExecScope) -> Result<Option<Type>, Exit> {
+    match target {
+        Expression::Spanned(_, target) => place_type(target, scope),
+        Expression::Name(name) => match scope.get(name) {
+            Some((value, value_type)) => Ok(value_type.or_else(|| stored_type(&value))),
+            None => Err(EvaluationErrorKind::Undefined(name.clone()).into()),
+        },
+        Expression::Field { base, name } => match eval(base, scope, None)? {
+            Value::Container(container) => match container.container_type.field(name) {
+                Some(field) => Ok(field_type(field.field_type.as_ref()).ok()),
+                None => Ok(None),
+            },
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Assigns `value` to the name or field `target`
+fn store(target: &Expression, value: Value, scope: &EvalExecScope) -> Result<(), Exit> {
+    match target {
+        Expression::Spanned(_, target) => store(target, value, scope),
+        Expression::Name(name) => Ok(scope.assign(name, value)?),
+        Expression::Field { base, name } => {
+            let Value::Container(mut container) = eval(base, scope, None)? else {
+                return Err(EvaluationErrorKind::NotAContainer.into());
+            };
+            let container_type = &container.container_type;
+            let field = container_type
+                .lookup_field(name, NameKind::Field, !scope.sees_private(container_type))
+                .map_err(|error| field_error(name, error))?;
+            if !matches!(field.field_kind, FieldKind::Instance) {
+                return Err(EvaluationErrorKind::InvalidAssignTarget.into());
+            }
+            let field_type = field_type(field.field_type.as_ref())?;
+            if container.set(name, &value).is_none() {
+                return Err(EvaluationErrorKind::Mismatch {
+                    expected: Box::new(field_type),
+                }
+                .into());
+            }
+            store(base, Value::Container(container), scope)
+        }
+        Expression::Builtin(function @ BuiltinFunction::Deref, _) => {
+            Err(EvaluationErrorKind::Unsupported(function.clone()).into())
+        }
+        _ => Err(EvaluationErrorKind::InvalidAssignTarget.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cera_parse::{literals::FloatValue, text_region::TextSpan};
+    use num_bigint::BigUint;
+
+    use super::*;
+    use crate::fixtures::u8_type;
+
+    fn int_literal(value: u32) -> Expression {
+        Expression::Literal(Literal::Int(BigUint::from(value)))
+    }
+
+    fn builtin(function: BuiltinFunction, args: Vec<Expression>) -> Expression {
+        Expression::Builtin(function, args.into_boxed_slice())
+    }
+
+    fn let_(name: &str, is_mutable: bool, var_type: Option<&str>, value: Expression) -> Expression {
+        Expression::Let(Box::new(Let {
+            name: name.into(),
+            is_mutable,
+            var_type: var_type.map(|var_type| Expression::Name(var_type.into())),
+            value,
+        }))
+    }
+
+    fn int_value(value: Result<Value, EvaluationError>) -> BigInt {
+        match value {
+            Ok(Value::Int(int)) => int.value(),
+            value => panic!("{value:?}"),
+        }
+    }
+
+    #[test]
+    fn literals_take_the_expected_type() {
+        let scope = EvalExecScope::new();
+        let sum = builtin(
+            BuiltinFunction::Add,
+            vec![int_literal(200), int_literal(55)],
+        );
+        assert_eq!(
+            int_value(sum.eval_expecting(&scope, Some(&u8_type()))),
+            255.into()
+        );
+        let sum = builtin(
+            BuiltinFunction::Add,
+            vec![int_literal(200), int_literal(56)],
+        );
+        assert!(matches!(
+            sum.eval_expecting(&scope, Some(&u8_type())).unwrap_err().kind,
+            EvaluationErrorKind::IntOverflow { value, .. } if value == 256.into()
+        ));
+        assert!(matches!(
+            int_literal(1).eval(&scope),
+            Ok(Value::ComptimeInt(value)) if value == 1.into()
+        ));
+
+        // The literal takes the type of the other operand, and shifted out bits are discarded
+        let int_type = IntType {
+            signed: true,
+            bits: 8,
+        };
+        let x = Int::new(&BigInt::from(-100), int_type).unwrap();
+        scope.declare("x", Value::Int(x), None, false);
+        let sum = builtin(
+            BuiltinFunction::ShiftLeft,
+            vec![Expression::Name("x".into()), int_literal(1)],
+        );
+        assert_eq!(int_value(sum.eval(&scope)), 56.into());
+    }
+
+    fn float_literal(value: u32, exponent: i32) -> Expression {
+        Expression::Literal(Literal::Float(FloatValue {
+            value: value.into(),
+            exponent: exponent.into(),
+        }))
+    }
+
+    fn comptime_float(value: Result<Value, EvaluationError>) -> String {
+        match value {
+            Ok(Value::ComptimeFloat(float)) => float.to_string(),
+            value => panic!("{value:?}"),
+        }
+    }
+
+    #[test]
+    fn comptime_numbers() {
+        let scope = EvalExecScope::new();
+        let big = builtin(
+            BuiltinFunction::ShiftLeft,
+            vec![int_literal(1), int_literal(100)],
+        );
+        let big = builtin(BuiltinFunction::ShiftRight, vec![big, int_literal(98)]);
+        assert!(matches!(big.eval(&scope), Ok(Value::ComptimeInt(value)) if value == 4.into()));
+
+        // Decimals are exact, so `0.1 + 0.2` is `0.3`
+        let sum = builtin(
+            BuiltinFunction::Add,
+            vec![float_literal(1, -1), float_literal(2, -1)],
+        );
+        let equal = builtin(BuiltinFunction::Equal, vec![sum, float_literal(3, -1)]);
+        assert!(matches!(equal.eval(&scope), Ok(Value::Bool(true))));
+        let product = builtin(
+            BuiltinFunction::Mul,
+            vec![float_literal(15, -1), int_literal(2)],
+        );
+        assert_eq!(comptime_float(product.eval(&scope)), "3.0");
+        let quotient = builtin(
+            BuiltinFunction::Div,
+            vec![int_literal(1), float_literal(8, 0)],
+        );
+        assert_eq!(comptime_float(quotient.eval(&scope)), "0.125");
+        assert_eq!(
+            comptime_float(float_literal(29, 8).eval(&scope)),
+            "2900000000.0"
+        );
+
+        // Coercions must keep the value
+        let f32_type = Type::Float(FloatType::F32);
+        let lossy = |expression: Expression, expected: &Type| {
+            matches!(
+                expression
+                    .eval_expecting(&scope, Some(expected))
+                    .unwrap_err()
+                    .kind,
+                EvaluationErrorKind::LossyCoercion { .. }
+            )
+        };
+        assert!(lossy(float_literal(5, -1), &u8_type()));
+        assert!(lossy(int_literal(16_777_217), &f32_type));
+        assert!(lossy(float_literal(1, 39), &f32_type));
+        assert_eq!(
+            int_value(float_literal(20, -1).eval_expecting(&scope, Some(&u8_type()))),
+            2.into()
+        );
+        assert!(float_literal(1, -1)
+            .eval_expecting(&scope, Some(&f32_type))
+            .is_ok());
+    }
+
+    fn if_(condition: Expression, then: Expression, otherwise: Expression) -> Expression {
+        Expression::If {
+            condition: Box::new(condition),
+            then: Box::new(then),
+            otherwise: Box::new(otherwise),
+        }
+    }
+
+    #[test]
+    fn conditions() {
+        let scope = EvalExecScope::new();
+        let name = |name: &str| Expression::Name(name.into());
+        // `false and missing` and `true or missing` don't evaluate `missing`
+        let and = if_(name("false"), name("missing"), name("false"));
+        assert!(matches!(and.eval(&scope), Ok(Value::Bool(false))));
+        let or = if_(name("true"), name("true"), name("missing"));
+        assert!(matches!(or.eval(&scope), Ok(Value::Bool(true))));
+        let not = builtin(BuiltinFunction::Not, vec![name("false")]);
+        assert!(matches!(not.eval(&scope), Ok(Value::Bool(true))));
+        let equal = builtin(BuiltinFunction::Equal, vec![name("true"), name("false")]);
+        assert!(matches!(equal.eval(&scope), Ok(Value::Bool(false))));
+
+        let int_type = IntType {
+            signed: false,
+            bits: 8,
+        };
+        let x = Int::new(&BigInt::from(1), int_type).unwrap();
+        scope.declare("x", Value::Int(x), None, false);
+        let int_condition = if_(name("x"), int_literal(1), int_literal(2));
+        assert!(matches!(
+            int_condition.eval(&scope).unwrap_err().kind,
+            EvaluationErrorKind::NotACondition(found) if matches!(*found, Type::Int(_))
+        ));
+    }
+
+    #[test]
+    fn blocks_and_labels() {
+        let scope = EvalExecScope::new();
+        let body = Expression::ExpressionList(Box::new([
+            let_("x", true, Some("u8"), int_literal(1)),
+            Expression::Labeled {
+                label: LabelId(0),
+                body: Box::new(Expression::Loop(Box::new(Expression::ExpressionList(
+                    Box::new([
+                        Expression::Assign {
+                            target: Box::new(Expression::Name("x".into())),
+                            value: Box::new(builtin(
+                                BuiltinFunction::Mul,
+                                vec![Expression::Name("x".into()), int_literal(3)],
+                            )),
+                        },
+                        Expression::If {
+                            condition: Box::new(builtin(
+                                BuiltinFunction::GreaterThan,
+                                vec![Expression::Name("x".into()), int_literal(50)],
+                            )),
+                            then: Box::new(Expression::Break {
+                                label: LabelId(0),
+                                value: Box::new(Expression::Name("x".into())),
+                            }),
+                            otherwise: Box::new(Expression::Value(Box::new(Value::Void))),
+                        },
+                    ]),
+                )))),
+            },
+        ]));
+        assert_eq!(int_value(body.eval(&scope)), 81.into());
+        // Bindings don't outlive their list
+        assert!(scope.get("x").is_none());
+    }
+
+    #[test]
+    fn errors_have_the_innermost_span() {
+        let scope = EvalExecScope::new();
+        let span = |idx| TextSpan { idx, len: 1 };
+        let assign = Expression::Spanned(
+            span(0),
+            Box::new(Expression::ExpressionList(Box::new([
+                let_("x", false, Some("u8"), int_literal(1)),
+                Expression::Spanned(
+                    span(1),
+                    Box::new(Expression::Assign {
+                        target: Box::new(Expression::Name("x".into())),
+                        value: Box::new(int_literal(2)),
+                    }),
+                ),
+            ]))),
+        );
+        let error = assign.eval(&scope).unwrap_err();
+        assert!(matches!(error.kind, EvaluationErrorKind::AssignToConstant(name) if &*name == "x"));
+        assert_eq!(error.span, Some(span(1)));
+
+        let escaping = Expression::Break {
+            label: LabelId(3),
+            value: Box::new(Expression::Value(Box::new(Value::Void))),
+        };
+        assert!(matches!(
+            escaping.eval(&scope).unwrap_err().kind,
+            EvaluationErrorKind::BreakOutsideLabel(LabelId(3))
+        ));
+        let endless = Expression::Loop(Box::new(Expression::Value(Box::new(Value::Void))));
+        assert!(matches!(
+            endless.eval(&scope).unwrap_err().kind,
+            EvaluationErrorKind::BranchQuotaExceeded
+        ));
+    }
+}
@@ -1,88 +1,89 @@
+use cera_parse::{src_token::Literal, text_region::TextSpan};
+use serde::{Deserialize, Serialize};
+
 use crate::{
     builtin::{BuiltinFunction, EvalExecScope},
+    eval::EvaluationError,
+    types::AnonField,
     values::Value,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Expression {
     Value(Box<Value>),
+    /// A literal, the type of which depends on where it is used
+    Literal(Literal),
     Builtin(BuiltinFunction, Box<[Expression]>),
+    /// A block, evaluating to its last expression
     ExpressionList(Box<[Expression]>),
+    /// Refers to a `Let` or a declaration
+    Name(Box<str>),
+    /// Declares a name for the rest of the enclosing list
+    Let(Box<Let>),
+    Assign {
+        target: Box<Expression>,
+        value: Box<Expression>,
+    },
+    If {
+        condition: Box<Expression>,
+        then: Box<Expression>,
+        otherwise: Box<Expression>,
+    },
+    /// Evaluates its body until a `Break` exits it
+    Loop(Box<Expression>),
+    /// Evaluates to its body, or to the value of a `Break` with the same label
+    Labeled {
+        label: LabelId,
+        body: Box<Expression>,
+    },
+    Break {
+        label: LabelId,
+        value: Box<Expression>,
+    },
+    Return(Box<Expression>),
+    Call {
+        callee: Box<Expression>,
+        args: Box<[Expression]>,
+    },
+    Field {
+        base: Box<Expression>,
+        name: Box<str>,
+    },
+    /// `.{ ... }`, resolved against the expected type
+    AnonInit(Box<[AnonField<Expression>]>),
+    /// `.variant`, resolved against the expected type
+    EnumLiteral(Box<str>),
+    /// The source code the expression comes from
+    Spanned(TextSpan, Box<Expression>),
 }
 
-pub struct EvaluationError;
-
-impl Expression {
-    pub fn recursive_eval(&mut self, context: &mut EvalExecScope) -> Result<(), EvaluationError> {
-        match self {
-            Expression::Value(_) => Ok(()),
-            Expression::Builtin(builtin, args) => {
-                let mut errored = false;
-                args.iter_mut().for_each(|arg| {
-                    if arg.recursive_eval(context).is_err() {
-                        errored = true;
-                    }
-                });
-                if errored {
-                    return Err(EvaluationError);
-                };
-
-                let args = args.iter().map(|elem| elem.get_val()).try_fold(
-                    Vec::with_capacity(args.len()),
-                    |mut prev, arg| {
-                        if let Some(val) = arg {
-                            prev.push(val);
-                            Ok(prev)
-                        } else {
-                            Err(EvaluationError)
-                        }
-                    },
-                )?;
+/// Identifies a `Labeled` expression, unique within a function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LabelId(pub u32);
 
-                if let Some(val) = builtin.try_exec(args.as_slice(), context) {
-                    *self = Self::Value(Box::new(val));
-                    Ok(())
-                } else {
-                    Err(EvaluationError)
-                }
-            }
-            Expression::ExpressionList(expressions) => {
-                let mut context = context.new_scope();
-                let mut errored = false;
-                expressions.iter_mut().for_each(|elem| {
-                    if elem.recursive_eval(&mut context).is_err() {
-                        errored = true;
-                    }
-                });
-                if errored {
-                    return Err(EvaluationError);
-                }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Let {
+    pub name: Box<str>,
+    pub is_mutable: bool,
+    pub var_type: Option<Expression>,
+    pub value: Expression,
+}
 
-                let expressions_old = expressions;
-                let mut expressions: Box<[Expression]> = Box::new([]);
-                std::mem::swap(expressions_old, &mut expressions);
-                let mut expressions = expressions.into_vec();
+impl Expression {
+    /// Replaces the expression by its value
+    pub fn recursive_eval(&mut self, context: &EvalExecScope) -> Result<(), EvaluationError> {
+        *self = Expression::Value(Box::new(self.eval(context)?));
+        Ok(())
+    }
 
-                match expressions.pop() {
-                    Some(elem) => match elem.get_val_move() {
-                        Ok(val) => {
-                            *self = Expression::Value(val);
-                            Ok(())
-                        }
-                        Err(val) => {
-                            expressions.push(val);
-                            *self = Expression::ExpressionList(expressions.into_boxed_slice());
-                            Err(EvaluationError)
-                        }
-                    },
-                    None => {
-                        *self = Expression::Value(Box::new(Value::Void));
-                        Ok(())
-                    }
-                }
-            }
+    /// The span of the source the expression was lowered from, if it was
+    pub fn span(&self) -> Option<&TextSpan> {
+        match self {
+            Self::Spanned(span, _) => Some(span),
+            _ => None,
         }
     }
+
     pub fn get_val(&self) -> Option<&Value> {
         if let Self::Value(val) = self {
             Some(val.as_ref())
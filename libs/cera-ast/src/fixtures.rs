@@ -0,0 +1,87 @@
+//! Types and containers which the tests of several modules are built from.
+
+use cera_parse::text_region::TextSpan;
+
+use crate::{
+    check::type_expression,
+    expressions::Expression,
+    types::{
+        ContainerField, ContainerId, ContainerType, ContainerVariant, FieldKind, IntType,
+        PointerType, Type, Visibility,
+    },
+    values::{Int, Value},
+};
+
+pub fn u8_type() -> Type {
+    Type::Int(IntType {
+        signed: false,
+        bits: 8,
+    })
+}
+
+/// A mutable pointer to `pointed`
+pub fn pointer(pointed: Type) -> Type {
+    Type::Pointer(PointerType {
+        pointed_value: type_expression(pointed),
+        is_const: false,
+        is_many: false,
+        sentinel: None,
+    })
+}
+
+/// A `u8` holding `value`
+pub fn u8_value(value: u8) -> Value {
+    let Type::Int(int_type) = u8_type() else {
+        unreachable!()
+    };
+    Value::Int(Int::new(&value.into(), int_type).unwrap())
+}
+
+/// A mutable many-item pointer to `pointed` values, which are ended by `sentinel` if there is one
+pub fn many_pointer(pointed: Type, sentinel: Option<Value>) -> Type {
+    Type::Pointer(PointerType {
+        pointed_value: type_expression(pointed),
+        is_const: false,
+        is_many: true,
+        sentinel: sentinel.map(Box::new),
+    })
+}
+
+/// A pointer whose pointed type is spanned, as it is when parsed
+pub fn spanned_pointer(pointed: Type) -> Type {
+    Type::Pointer(PointerType {
+        pointed_value: Expression::Spanned(
+            TextSpan { idx: 0, len: 1 },
+            Box::new(type_expression(pointed)),
+        ),
+        is_const: false,
+        is_many: false,
+        sentinel: None,
+    })
+}
+
+/// A public instance field without a default value
+pub fn field(name: &str, field_type: Type) -> ContainerField {
+    ContainerField {
+        name: name.into(),
+        visibility: Visibility::Public,
+        field_kind: FieldKind::Instance,
+        field_type: Some(type_expression(field_type)),
+        val: None,
+        span: None,
+    }
+}
+
+/// An anonymous struct, which is only equal to itself
+pub fn struct_type(fields: Vec<ContainerField>) -> ContainerType {
+    ContainerType {
+        id: ContainerId::fresh(),
+        name: None,
+        file: None,
+        fields: fields.into_boxed_slice(),
+        variant: ContainerVariant::Struct {
+            ptr_coerce_target: None,
+            droppers: Box::new([]),
+        },
+    }
+}
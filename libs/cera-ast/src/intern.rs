@@ -0,0 +1,429 @@
+//! Interning of types, so that they can be compared and hashed through a `TypeId`. Types are
+//! equal when they have the same structure, except containers which are only equal to themselves.
+//! `Type` implements `PartialEq` and `Hash` the same way in `types`, for when there is no interner
+//! at hand.
+
+use std::hash::Hash;
+
+use hashbrown::HashMap;
+
+use crate::{
+    expressions::Expression,
+    types::{
+        ArrayType, CallingConvetion, ComptimeFunctionType, ContainerId, ContainerType, ErrorID,
+        ErrorSetType, ErrorUnionType, FloatType, IntType, OptionType, PointerType,
+        RuntimeFunctionType, SliceType, Type,
+    },
+    values::Value,
+};
+
+/// A type interned by a `TypeInterner`, only meaningful for that interner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TypeId(pub u32);
+
+/// A type of which the parts are interned
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InternedType {
+    Container(ContainerId),
+    Bool,
+    Int(IntType),
+    Float(FloatType),
+    /// Sentinels are interned by their bytes, as values of the pointed type
+    Pointer {
+        pointed: TypeId,
+        is_const: bool,
+        is_many: bool,
+        sentinel: Option<Box<[u8]>>,
+    },
+    /// Sentinels are interned by their bytes, as values of the indexed type
+    Slice {
+        indexed_type: TypeId,
+        is_const: bool,
+        sentinel: Option<Box<[u8]>>,
+    },
+    /// The possible errors, sorted and without duplicates
+    ErrorSet(Box<[ErrorID]>),
+    /// The error set is `None` when it is inferred
+    ErrorUnion {
+        ok: TypeId,
+        err: Option<TypeId>,
+    },
+    Option(TypeId),
+    Array {
+        size: usize,
+        indexed_type: TypeId,
+        sentinel: Option<Box<[u8]>>,
+    },
+    Type,
+    ComptimeInt,
+    ComptimeFloat,
+    ComptimeFunction {
+        parameters: Box<[TypeId]>,
+        result: TypeId,
+    },
+    RuntimeFunction {
+        parameters: Box<[TypeId]>,
+        result: TypeId,
+        calling_convention: CallingConvetion,
+    },
+    Void,
+    Undefined,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InternError {
+    /// A type given by an expression which hasn't been evaluated to a type, such as the parameter
+    /// types of a generic function
+    Unevaluated,
+    /// A sentinel which doesn't exist at runtime, and so can't end values in memory
+    Sentinel,
+}
+
+#[derive(Debug, Default)]
+pub struct TypeInterner {
+    types: Vec<InternedType>,
+    ids: HashMap<InternedType, TypeId>,
+    /// The last version of each container, which its fields are read from
+    containers: HashMap<ContainerId, ContainerType>,
+}
+
+impl TypeInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The id of `value_type`, which is the id of any equal type
+    pub fn intern(&mut self, value_type: &Type) -> Result<TypeId, InternError> {
+        let interned = match value_type {
+            Type::Container(container_type) => {
+                self.containers
+                    .insert(container_type.id, container_type.clone());
+                InternedType::Container(container_type.id)
+            }
+            Type::Bool => InternedType::Bool,
+            Type::Int(int_type) => InternedType::Int(int_type.clone()),
+            Type::Float(float_type) => InternedType::Float(float_type.clone()),
+            Type::Pointer(PointerType {
+                pointed_value,
+                is_const,
+                is_many,
+                sentinel,
+            }) => InternedType::Pointer {
+                pointed: self.intern_expression(pointed_value)?,
+                is_const: *is_const,
+                is_many: *is_many,
+                sentinel: sentinel_bytes(sentinel.as_deref())?,
+            },
+            Type::Slice(SliceType {
+                indexed_type,
+                is_const,
+                sentinel,
+            }) => InternedType::Slice {
+                indexed_type: self.intern(indexed_type)?,
+                is_const: *is_const,
+                sentinel: sentinel_bytes(sentinel.as_deref())?,
+            },
+            Type::ErrorSet(ErrorSetType { possible_errors }) => {
+                let mut possible_errors = possible_errors.to_vec();
+                possible_errors.sort_unstable();
+                possible_errors.dedup();
+                InternedType::ErrorSet(possible_errors.into_boxed_slice())
+            }
+            Type::ErrorUnion(ErrorUnionType { ok, err }) => InternedType::ErrorUnion {
+                ok: self.intern(ok)?,
+                err: match err {
+                    Some(err) => Some(self.intern(&Type::ErrorSet(err.clone()))?),
+                    None => None,
+                },
+            },
+            Type::Option(OptionType { some_type }) => InternedType::Option(self.intern(some_type)?),
+            Type::Array(ArrayType {
+                size,
+                indexed_type,
+                sentinel,
+            }) => InternedType::Array {
+                size: *size,
+                indexed_type: self.intern(indexed_type)?,
+                sentinel: sentinel_bytes(sentinel.as_deref())?,
+            },
+            Type::Type => InternedType::Type,
+            Type::ComptimeInt => InternedType::ComptimeInt,
+            Type::ComptimeFloat => InternedType::ComptimeFloat,
+            Type::ComptimeFunction(function_type) => {
+                let (parameters, result) = self.intern_signature(function_type)?;
+                InternedType::ComptimeFunction { parameters, result }
+            }
+            Type::RuntimeFunction(RuntimeFunctionType {
+                base_signature,
+                calling_convention,
+            }) => {
+                let (parameters, result) = self.intern_signature(base_signature)?;
+                InternedType::RuntimeFunction {
+                    parameters,
+                    result,
+                    calling_convention: calling_convention.clone(),
+                }
+            }
+            Type::Void => InternedType::Void,
+            Type::Undefined => InternedType::Undefined,
+        };
+        Ok(self.insert(interned))
+    }
+
+    fn insert(&mut self, interned: InternedType) -> TypeId {
+        if let Some(id) = self.ids.get(&interned) {
+            return *id;
+        }
+        let id = TypeId(self.types.len() as u32);
+        self.types.push(interned.clone());
+        self.ids.insert(interned, id);
+        id
+    }
+
+    /// Interns the type given by an expression, looking through its spans
+    fn intern_expression(&mut self, expression: &Expression) -> Result<TypeId, InternError> {
+        match normalize(expression) {
+            Some(value_type) => self.intern(value_type),
+            None => Err(InternError::Unevaluated),
+        }
+    }
+
+    fn intern_signature(
+        &mut self,
+        function_type: &ComptimeFunctionType,
+    ) -> Result<(Box<[TypeId]>, TypeId), InternError> {
+        let Expression::ExpressionList(parameters) = strip_spans(&function_type.parameters) else {
+            return Err(InternError::Unevaluated);
+        };
+        let parameters = parameters
+            .iter()
+            .map(|parameter| self.intern_expression(parameter))
+            .collect::<Result<_, _>>()?;
+        Ok((parameters, self.intern_expression(&function_type.result)?))
+    }
+
+    pub fn get(&self, id: TypeId) -> &InternedType {
+        &self.types[id.0 as usize]
+    }
+
+    /// The container interned as `id`
+    pub fn container(&self, id: ContainerId) -> Option<&ContainerType> {
+        self.containers.get(&id)
+    }
+
+    /// Builds the type interned as `id` back
+    pub fn to_type(&self, id: TypeId) -> Type {
+        let type_expression = |id| Expression::Value(Box::new(Value::Type(self.to_type(id))));
+        let signature = |parameters: &[TypeId], result| ComptimeFunctionType {
+            parameters: Expression::ExpressionList(
+                parameters.iter().map(|id| type_expression(*id)).collect(),
+            ),
+            result: type_expression(result),
+        };
+        match self.get(id) {
+            InternedType::Container(id) => Type::Container(self.containers[id].clone()),
+            InternedType::Bool => Type::Bool,
+            InternedType::Int(int_type) => Type::Int(int_type.clone()),
+            InternedType::Float(float_type) => Type::Float(float_type.clone()),
+            InternedType::Pointer {
+                pointed,
+                is_const,
+                is_many,
+                sentinel,
+            } => Type::Pointer(PointerType {
+                pointed_value: type_expression(*pointed),
+                is_const: *is_const,
+                is_many: *is_many,
+                sentinel: sentinel_value(&self.to_type(*pointed), sentinel.as_deref()),
+            }),
+            InternedType::Slice {
+                indexed_type,
+                is_const,
+                sentinel,
+            } => {
+                let indexed_type = self.to_type(*indexed_type);
+                Type::Slice(SliceType {
+                    sentinel: sentinel_value(&indexed_type, sentinel.as_deref()),
+                    indexed_type: Box::new(indexed_type),
+                    is_const: *is_const,
+                })
+            }
+            InternedType::ErrorSet(possible_errors) => Type::ErrorSet(ErrorSetType {
+                possible_errors: possible_errors.clone(),
+            }),
+            InternedType::ErrorUnion { ok, err } => Type::ErrorUnion(ErrorUnionType {
+                ok: Box::new(self.to_type(*ok)),
+                err: err.map(|err| match self.to_type(err) {
+                    Type::ErrorSet(err) => err,
+                    _ => unreachable!("error unions are interned with error sets"),
+                }),
+            }),
+            InternedType::Option(some_type) => Type::Option(OptionType {
+                some_type: Box::new(self.to_type(*some_type)),
+            }),
+            InternedType::Array {
+                size,
+                indexed_type,
+                sentinel,
+            } => {
+                let indexed_type = self.to_type(*indexed_type);
+                Type::Array(ArrayType {
+                    size: *size,
+                    sentinel: sentinel_value(&indexed_type, sentinel.as_deref()),
+                    indexed_type: Box::new(indexed_type),
+                })
+            }
+            InternedType::Type => Type::Type,
+            InternedType::ComptimeInt => Type::ComptimeInt,
+            InternedType::ComptimeFloat => Type::ComptimeFloat,
+            InternedType::ComptimeFunction { parameters, result } => {
+                Type::ComptimeFunction(signature(parameters, *result))
+            }
+            InternedType::RuntimeFunction {
+                parameters,
+                result,
+                calling_convention,
+            } => Type::RuntimeFunction(RuntimeFunctionType {
+                base_signature: signature(parameters, *result),
+                calling_convention: calling_convention.clone(),
+            }),
+            InternedType::Void => Type::Void,
+            InternedType::Undefined => Type::Undefined,
+        }
+    }
+}
+
+fn sentinel_bytes(sentinel: Option<&Value>) -> Result<Option<Box<[u8]>>, InternError> {
+    match sentinel {
+        Some(sentinel) => match sentinel.bytes() {
+            Some(bytes) => Ok(Some(bytes.into())),
+            None => Err(InternError::Sentinel),
+        },
+        None => Ok(None),
+    }
+}
+
+fn sentinel_value(indexed_type: &Type, bytes: Option<&[u8]>) -> Option<Box<Value>> {
+    Some(Box::new(Value::from_bytes(indexed_type, bytes?)?))
+}
+
+pub(crate) fn strip_spans(expression: &Expression) -> &Expression {
+    match expression {
+        Expression::Spanned(_, expression) => strip_spans(expression),
+        expression => expression,
+    }
+}
+
+/// The type an expression evaluated to, `None` if it hasn't been evaluated
+pub fn normalize(expression: &Expression) -> Option<&Type> {
+    match strip_spans(expression) {
+        Expression::Value(value) => match &**value {
+            Value::Type(value_type) => Some(value_type),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        check::type_expression,
+        fixtures::{many_pointer, pointer, spanned_pointer, struct_type, u8_value},
+    };
+
+    fn int(signed: bool, bits: u16) -> Type {
+        Type::Int(IntType { signed, bits })
+    }
+
+    #[test]
+    fn structural_identity() {
+        let mut interner = TypeInterner::new();
+        let mut intern = |value_type| interner.intern(&value_type).unwrap();
+        assert_eq!(intern(int(false, 8)), intern(int(false, 8)));
+        assert_ne!(intern(int(false, 8)), intern(int(true, 8)));
+        assert_eq!(
+            intern(spanned_pointer(int(true, 32))),
+            intern(pointer(int(true, 32)))
+        );
+        assert_ne!(
+            intern(pointer(int(true, 32))),
+            intern(many_pointer(int(true, 32), None))
+        );
+        let terminated = |sentinel| many_pointer(int(false, 8), Some(u8_value(sentinel)));
+        assert_eq!(intern(terminated(0)), intern(terminated(0)));
+        assert_ne!(intern(terminated(0)), intern(terminated(1)));
+        let array = |size| {
+            Type::Array(ArrayType {
+                size,
+                indexed_type: Box::new(Type::Float(FloatType::F32)),
+                sentinel: None,
+            })
+        };
+        assert_eq!(intern(array(3)), intern(array(3)));
+        assert_ne!(intern(array(3)), intern(array(4)));
+        let errors = |possible_errors: &[ErrorID]| {
+            Type::ErrorSet(ErrorSetType {
+                possible_errors: possible_errors.into(),
+            })
+        };
+        assert_eq!(intern(errors(&[2, 1, 2])), intern(errors(&[1, 2])));
+    }
+
+    #[test]
+    fn nominal_containers() {
+        let mut interner = TypeInterner::new();
+        let container = struct_type(Vec::new());
+        let same = interner
+            .intern(&Type::Container(container.clone()))
+            .unwrap();
+        let optional = |container: &ContainerType| {
+            Type::Option(OptionType {
+                some_type: Box::new(Type::Container(container.clone())),
+            })
+        };
+        assert_eq!(
+            interner.intern(&Type::Container(container.clone())),
+            Ok(same)
+        );
+        assert_eq!(
+            interner.intern(&optional(&container)),
+            interner.intern(&optional(&container))
+        );
+        // Another container with the same fields is another type
+        assert_ne!(
+            interner.intern(&Type::Container(struct_type(Vec::new()))),
+            Ok(same)
+        );
+        assert_ne!(
+            interner.intern(&optional(&container)),
+            interner.intern(&optional(&struct_type(Vec::new())))
+        );
+    }
+
+    #[test]
+    fn round_trip_and_errors() {
+        let mut interner = TypeInterner::new();
+        let function = Type::RuntimeFunction(RuntimeFunctionType {
+            base_signature: ComptimeFunctionType {
+                parameters: Expression::ExpressionList(Box::new([type_expression(pointer(int(
+                    false, 8,
+                )))])),
+                result: type_expression(Type::Option(OptionType {
+                    some_type: Box::new(Type::Void),
+                })),
+            },
+            calling_convention: CallingConvetion::Cera,
+        });
+        let id = interner.intern(&function).unwrap();
+        assert_eq!(interner.intern(&interner.to_type(id)), Ok(id));
+        assert_eq!(interner.to_type(id), function);
+
+        let generic = Type::ComptimeFunction(ComptimeFunctionType {
+            parameters: Expression::ExpressionList(Box::new([Expression::Name("T".into())])),
+            result: Expression::Name("T".into()),
+        });
+        assert_eq!(interner.intern(&generic), Err(InternError::Unevaluated));
+    }
+}
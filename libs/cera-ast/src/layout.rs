@@ -0,0 +1,484 @@
+//! Sizes, alignments and field offsets of runtime values, which depend on the target.
+
+use crate::{
+    intern::normalize,
+    types::{
+        ContainerField, ContainerType, ContainerVariant, ErrorUnionType, FloatType, IntType,
+        OptionType, Type,
+    },
+};
+
+/// What the layout of types depends on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub pointer_bits: u16,
+    /// The alignment of the largest integers and floats
+    pub max_align: u64,
+}
+
+impl Target {
+    pub const X86_64: Target = Target {
+        pointer_bits: 64,
+        max_align: 16,
+    };
+    pub const WASM32: Target = Target {
+        pointer_bits: 32,
+        max_align: 8,
+    };
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Target::X86_64
+    }
+}
+
+/// Errors are stored as `u16`
+pub const ERROR_TYPE: IntType = IntType {
+    signed: false,
+    bits: 16,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub size: u64,
+    pub align: u64,
+    /// The instance fields of containers, in declaration order
+    pub fields: Box<[FieldLayout]>,
+    /// Where the tag of tagged unions, the error of error unions or whether an optional isn't
+    /// `null` is stored
+    pub tag_offset: Option<u64>,
+    /// Whether bytes which are all zero aren't a value of the type, so that optionals of it can
+    /// be `null` without storing anything else
+    pub has_null_niche: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldLayout {
+    pub name: Box<str>,
+    pub offset: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    /// Types such as `type` which don't exist at runtime
+    ComptimeOnly,
+    /// Traits and functions, which can only be used through pointers
+    Unsized,
+    /// A field type which hasn't been evaluated to a type
+    Unevaluated { field: Box<str> },
+    /// Types with more bytes than a `u64` counts
+    TooLarge,
+}
+
+fn align_forward(offset: u64, align: u64) -> u64 {
+    offset.div_ceil(align) * align
+}
+
+/// Aligns offsets and sizes which depend on the sizes of other types, and can overflow
+fn checked_align_forward(offset: u64, align: u64) -> Result<u64, LayoutError> {
+    offset
+        .checked_next_multiple_of(align)
+        .ok_or(LayoutError::TooLarge)
+}
+
+fn checked_add(lhs: u64, rhs: u64) -> Result<u64, LayoutError> {
+    lhs.checked_add(rhs).ok_or(LayoutError::TooLarge)
+}
+
+impl Layout {
+    /// The layout values known at compile time are stored with, which is the one of the default
+    /// target
+    pub fn of(value_type: &Type) -> Result<Layout, LayoutError> {
+        Target::default().layout(value_type)
+    }
+
+    fn scalar(size: u64, align: u64) -> Self {
+        Layout {
+            size,
+            align,
+            fields: Box::new([]),
+            tag_offset: None,
+            has_null_niche: false,
+        }
+    }
+
+    /// The distance between values of this type in arrays
+    pub fn stride(&self) -> u64 {
+        align_forward(self.size, self.align)
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldLayout> {
+        self.fields.iter().find(|field| &*field.name == name)
+    }
+
+    /// A value of `payload` along with a tag of type `tag`, the one with the largest alignment
+    /// first
+    fn tagged(payload: &Layout, tag: &Layout) -> Result<(Self, u64, u64), LayoutError> {
+        let align = payload.align.max(tag.align);
+        let (payload_offset, tag_offset) = match payload.align >= tag.align {
+            true => (0, checked_align_forward(payload.size, tag.align)?),
+            false => (checked_align_forward(tag.size, payload.align)?, 0),
+        };
+        let end =
+            checked_add(payload_offset, payload.size)?.max(checked_add(tag_offset, tag.size)?);
+        let layout = Layout {
+            tag_offset: Some(tag_offset),
+            ..Layout::scalar(checked_align_forward(end, align)?, align)
+        };
+        Ok((layout, payload_offset, tag_offset))
+    }
+}
+
+impl Target {
+    /// The unsigned integer as large as pointers
+    pub fn usize_type(&self) -> IntType {
+        IntType {
+            signed: false,
+            bits: self.pointer_bits,
+        }
+    }
+
+    pub fn pointer_layout(&self) -> Layout {
+        let bytes = u64::from(self.pointer_bits).div_ceil(8);
+        Layout {
+            has_null_niche: true,
+            ..Layout::scalar(bytes, bytes)
+        }
+    }
+
+    /// Slices are a pointer followed by a `usize` length, the pointer being `null` for `null`
+    /// optional slices
+    pub fn slice_layout(&self) -> Layout {
+        let pointer = self.pointer_layout();
+        let field = |name: &str, offset| FieldLayout {
+            name: name.into(),
+            offset,
+            size: pointer.size,
+        };
+        Layout {
+            fields: Box::new([field("ptr", 0), field("len", pointer.size)]),
+            has_null_niche: true,
+            ..Layout::scalar(pointer.size * 2, pointer.align)
+        }
+    }
+
+    /// Integers take the smallest power of two of bytes holding their bits, up to the largest
+    /// alignment, after which their size is a multiple of it
+    pub fn int_layout(&self, int_type: &IntType) -> Layout {
+        let bytes = u64::from(int_type.bits).div_ceil(8);
+        let align = bytes.next_power_of_two().clamp(1, self.max_align);
+        Layout::scalar(align_forward(bytes, align), align)
+    }
+
+    pub fn float_layout(&self, float_type: &FloatType) -> Layout {
+        let bytes = match float_type {
+            FloatType::F16 => 2,
+            FloatType::F32 => 4,
+            FloatType::F64 => 8,
+            // Padded like a 128 bits float
+            FloatType::F80 | FloatType::F128 => 16,
+        };
+        let align = u64::min(bytes, self.max_align);
+        Layout::scalar(align_forward(bytes, align), align)
+    }
+
+    pub fn layout(&self, value_type: &Type) -> Result<Layout, LayoutError> {
+        match value_type {
+            Type::Container(container_type) => self.container_layout(container_type),
+            Type::Bool => Ok(Layout::scalar(1, 1)),
+            Type::Int(int_type) => Ok(self.int_layout(int_type)),
+            Type::Float(float_type) => Ok(self.float_layout(float_type)),
+            Type::Pointer(_) => Ok(self.pointer_layout()),
+            Type::Slice(_) => Ok(self.slice_layout()),
+            Type::ErrorSet(_) => Ok(self.int_layout(&ERROR_TYPE)),
+            Type::ErrorUnion(ErrorUnionType { ok, .. }) => {
+                let payload = self.layout(ok)?;
+                Ok(Layout::tagged(&payload, &self.int_layout(&ERROR_TYPE))?.0)
+            }
+            Type::Option(OptionType { some_type }) => {
+                let payload = self.layout(some_type)?;
+                if payload.has_null_niche {
+                    return Ok(Layout {
+                        has_null_niche: false,
+                        ..payload
+                    });
+                }
+                Ok(Layout::tagged(&payload, &Layout::scalar(1, 1))?.0)
+            }
+            Type::Array(array_type) => {
+                let element = self.layout(&array_type.indexed_type)?;
+                let size = u64::try_from(array_type.stored_len())
+                    .ok()
+                    .and_then(|len| element.stride().checked_mul(len))
+                    .ok_or(LayoutError::TooLarge)?;
+                Ok(Layout::scalar(size, element.align))
+            }
+            Type::Void => Ok(Layout::scalar(0, 1)),
+            Type::Type
+            | Type::ComptimeInt
+            | Type::ComptimeFloat
+            | Type::ComptimeFunction(_)
+            | Type::Undefined => Err(LayoutError::ComptimeOnly),
+            Type::RuntimeFunction(_) => Err(LayoutError::Unsized),
+        }
+    }
+
+    fn field_layout(&self, field: &ContainerField) -> Result<Layout, LayoutError> {
+        match field.field_type.as_ref().and_then(normalize) {
+            Some(field_type) => self.layout(field_type),
+            None => Err(LayoutError::Unevaluated {
+                field: field.name.clone(),
+            }),
+        }
+    }
+
+    pub fn container_layout(&self, container_type: &ContainerType) -> Result<Layout, LayoutError> {
+        let fields: Result<Vec<_>, _> = container_type
+            .instance_fields()
+            .map(|field| Ok((field.name.clone(), self.field_layout(field)?)))
+            .collect();
+        match &container_type.variant {
+            ContainerVariant::Struct { .. } => self.struct_layout(fields?),
+            ContainerVariant::Union { tag } => {
+                let fields = fields?;
+                let payload = Layout::scalar(
+                    fields
+                        .iter()
+                        .map(|(_, field)| field.size)
+                        .max()
+                        .unwrap_or(0),
+                    fields
+                        .iter()
+                        .map(|(_, field)| field.align)
+                        .max()
+                        .unwrap_or(1),
+                );
+                let payload = Layout::scalar(
+                    checked_align_forward(payload.size, payload.align)?,
+                    payload.align,
+                );
+                let (layout, payload_offset) = match tag {
+                    Some(tag) => {
+                        let (layout, payload_offset, _) =
+                            Layout::tagged(&payload, &self.container_layout(tag)?)?;
+                        (layout, payload_offset)
+                    }
+                    None => (payload, 0),
+                };
+                let fields = fields
+                    .into_iter()
+                    .map(|(name, field)| FieldLayout {
+                        name,
+                        offset: payload_offset,
+                        size: field.size,
+                    })
+                    .collect();
+                Ok(Layout { fields, ..layout })
+            }
+            ContainerVariant::Enum { backing_type, .. } => Ok(self.int_layout(backing_type)),
+            ContainerVariant::Trait { .. } => Err(LayoutError::Unsized),
+        }
+    }
+
+    /// Fields are placed by decreasing alignment, which avoids padding between them
+    fn struct_layout(&self, fields: Vec<(Box<str>, Layout)>) -> Result<Layout, LayoutError> {
+        let mut order: Vec<usize> = (0..fields.len()).collect();
+        order.sort_by_key(|idx| std::cmp::Reverse(fields[*idx].1.align));
+        let mut offsets = vec![0; fields.len()];
+        let mut end = 0;
+        for idx in order {
+            let field = &fields[idx].1;
+            offsets[idx] = checked_align_forward(end, field.align)?;
+            end = checked_add(offsets[idx], field.size)?;
+        }
+        let align = fields
+            .iter()
+            .map(|(_, field)| field.align)
+            .max()
+            .unwrap_or(1);
+        let fields = fields
+            .into_iter()
+            .zip(offsets)
+            .map(|((name, field), offset)| FieldLayout {
+                name,
+                offset,
+                size: field.size,
+            })
+            .collect();
+        Ok(Layout {
+            fields,
+            ..Layout::scalar(checked_align_forward(end, align)?, align)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        expressions::Expression,
+        fixtures::{field, many_pointer, pointer, struct_type, u8_type, u8_value},
+        types::{ArrayType, SliceType, UnionTagging},
+        values::{Int, Value},
+    };
+
+    fn int(bits: u16) -> Type {
+        Type::Int(IntType {
+            signed: false,
+            bits,
+        })
+    }
+
+    fn optional(some_type: Type) -> Type {
+        Type::Option(OptionType {
+            some_type: Box::new(some_type),
+        })
+    }
+
+    fn size_align(target: &Target, value_type: &Type) -> (u64, u64) {
+        let layout = target.layout(value_type).unwrap();
+        (layout.size, layout.align)
+    }
+
+    #[test]
+    fn scalars() {
+        let target = Target::X86_64;
+        assert_eq!(size_align(&target, &int(0)), (0, 1));
+        assert_eq!(size_align(&target, &int(1)), (1, 1));
+        assert_eq!(size_align(&target, &int(24)), (4, 4));
+        assert_eq!(size_align(&target, &int(65)), (16, 16));
+        assert_eq!(size_align(&target, &int(129)), (32, 16));
+        assert_eq!(size_align(&Target::WASM32, &int(128)), (16, 8));
+        assert_eq!(size_align(&target, &Type::Float(FloatType::F16)), (2, 2));
+        assert_eq!(size_align(&target, &Type::Float(FloatType::F80)), (16, 16));
+        assert_eq!(size_align(&target, &pointer(u8_type())), (8, 8));
+        assert_eq!(size_align(&Target::WASM32, &pointer(u8_type())), (4, 4));
+        let terminated = many_pointer(u8_type(), Some(u8_value(0)));
+        assert_eq!(size_align(&target, &many_pointer(u8_type(), None)), (8, 8));
+        assert_eq!(size_align(&Target::WASM32, &terminated), (4, 4));
+        assert_eq!(size_align(&target, &Type::Bool), (1, 1));
+        assert_eq!(size_align(&target, &Type::Void), (0, 1));
+        assert_eq!(target.layout(&Type::Type), Err(LayoutError::ComptimeOnly));
+    }
+
+    #[test]
+    fn wrappers() {
+        let target = Target::X86_64;
+        let array = Type::Array(ArrayType {
+            size: 3,
+            indexed_type: Box::new(int(24)),
+            sentinel: None,
+        });
+        assert_eq!(size_align(&target, &array), (12, 4));
+        let terminated = Type::Array(ArrayType {
+            size: 3,
+            indexed_type: Box::new(int(8)),
+            sentinel: Some(Box::new(Value::Int(
+                Int::new(
+                    &0.into(),
+                    IntType {
+                        signed: false,
+                        bits: 8,
+                    },
+                )
+                .unwrap(),
+            ))),
+        });
+        assert_eq!(size_align(&target, &terminated), (4, 1));
+        let slice = Type::Slice(SliceType {
+            indexed_type: Box::new(int(8)),
+            is_const: true,
+            sentinel: None,
+        });
+        assert_eq!(size_align(&target, &slice), (16, 8));
+        assert_eq!(size_align(&Target::WASM32, &slice), (8, 4));
+        // The null pointer of a slice is the null of its optional
+        assert_eq!(size_align(&target, &optional(slice)), (16, 8));
+        // Null pointers are the null of their optionals
+        assert_eq!(size_align(&target, &optional(pointer(u8_type()))), (8, 8));
+        assert_eq!(
+            size_align(&target, &optional(many_pointer(u8_type(), None))),
+            (8, 8)
+        );
+        assert_eq!(
+            size_align(&target, &optional(optional(pointer(u8_type())))),
+            (16, 8)
+        );
+        let optional_int = target.layout(&optional(int(32))).unwrap();
+        assert_eq!((optional_int.size, optional_int.tag_offset), (8, Some(4)));
+        let error_union = |ok| {
+            Type::ErrorUnion(ErrorUnionType {
+                ok: Box::new(ok),
+                err: None,
+            })
+        };
+        let error_u64 = target.layout(&error_union(int(64))).unwrap();
+        assert_eq!((error_u64.size, error_u64.tag_offset), (16, Some(8)));
+        let error_u8 = target.layout(&error_union(int(8))).unwrap();
+        assert_eq!((error_u8.size, error_u8.tag_offset), (4, Some(0)));
+    }
+
+    #[test]
+    fn too_large() {
+        let target = Target::X86_64;
+        let array = |size, indexed_type| {
+            Type::Array(ArrayType {
+                size,
+                indexed_type: Box::new(indexed_type),
+                sentinel: None,
+            })
+        };
+        assert_eq!(
+            target.layout(&array(1 << 62, int(64))),
+            Err(LayoutError::TooLarge)
+        );
+        assert_eq!(
+            target.layout(&array(1 << 40, array(1 << 40, int(64)))),
+            Err(LayoutError::TooLarge)
+        );
+        assert_eq!(array(1 << 62, int(64)).byte_len(), None);
+        // The largest arrays fit, but not along with a tag or another field
+        let largest = array(usize::MAX, int(8));
+        assert_eq!(size_align(&target, &largest), (u64::MAX, 1));
+        assert_eq!(
+            target.layout(&optional(largest.clone())),
+            Err(LayoutError::TooLarge)
+        );
+        let pair = struct_type(vec![field("a", largest.clone()), field("b", largest)]);
+        assert_eq!(target.container_layout(&pair), Err(LayoutError::TooLarge));
+    }
+
+    #[test]
+    fn containers() {
+        let target = Target::X86_64;
+        let fields = vec![field("a", int(8)), field("b", int(64)), field("c", int(16))];
+        let point = struct_type(fields.clone());
+        let layout = target.container_layout(&point).unwrap();
+        assert_eq!((layout.size, layout.align), (16, 8));
+        let offsets: Vec<_> = layout.fields.iter().map(|field| field.offset).collect();
+        assert_eq!(offsets, [10, 0, 8]);
+        assert_eq!(layout.field("c").map(|field| field.size), Some(2));
+
+        let union = ContainerType::new_union(fields, UnionTagging::Inferred).unwrap();
+        let layout = target.container_layout(&union).unwrap();
+        assert_eq!(
+            (layout.size, layout.align, layout.tag_offset),
+            (16, 8, Some(8))
+        );
+        assert!(layout.fields.iter().all(|field| field.offset == 0));
+
+        let untyped = ContainerType {
+            fields: Box::new([ContainerField {
+                field_type: Some(Expression::Name("T".into())),
+                ..field("x", Type::Void)
+            }]),
+            ..point
+        };
+        assert_eq!(
+            target.container_layout(&untyped),
+            Err(LayoutError::Unevaluated { field: "x".into() })
+        );
+    }
+}
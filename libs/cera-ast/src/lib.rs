@@ -1,4 +1,12 @@
 pub mod builtin;
+pub mod check;
+pub mod eval;
 pub mod expressions;
+#[cfg(test)]
+mod fixtures;
+pub mod intern;
+pub mod layout;
 pub mod types;
 pub mod values;
+pub mod visit;
+pub mod visit_mut;
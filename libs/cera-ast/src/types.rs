@@ -1,57 +1,555 @@
-use hashbrown::HashMap;
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::atomic::{self, AtomicU32},
+};
 
-use crate::expressions::Expression;
+use cera_parse::text_region::TextSpan;
+use cera_src_ast::suggest::{suggest, NameKind};
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+use crate::{
+    expressions::Expression,
+    intern::{normalize, strip_spans},
+    layout::Layout,
+    values::{Int, Value},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Visibility {
     Private,
     Public,
 }
 
-#[derive(Debug, Clone)]
+/// Containers are nominal types: they are only equal to themselves, whatever their fields
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContainerType {
-    pub fields: HashMap<Box<str>, ContainerField>,
+    pub id: ContainerId,
+    /// The name of the declaration, which diagnostics refer to the container by
+    pub name: Option<Box<str>>,
+    /// The file the container is declared in, throughout which its private declarations are
+    /// visible. `None` for containers which aren't declared in source.
+    pub file: Option<FileId>,
+    /// In declaration order, which is also the order of tuple-style initializers
+    pub fields: Box<[ContainerField]>,
     pub variant: ContainerVariant,
 }
 
-#[derive(Debug, Clone)]
+impl PartialEq for ContainerType {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for ContainerType {}
+
+impl Hash for ContainerType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+/// Identifies a container declaration, which is a different type than any other container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ContainerId(pub u32);
+
+impl ContainerId {
+    /// An id which no other container built in this process has
+    pub fn fresh() -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        ContainerId(NEXT.fetch_add(1, atomic::Ordering::Relaxed))
+    }
+}
+
+/// Identifies a source file, the outermost scope of which its declarations are bound in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FileId(pub u32);
+
+impl FileId {
+    /// An id which no other file evaluated in this process has
+    pub fn fresh() -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        FileId(NEXT.fetch_add(1, atomic::Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ContainerVariant {
     Struct {
+        /// The field which pointers to the container coerce to pointers to
         ptr_coerce_target: Option<Box<str>>,
         droppers: Box<[Box<str>]>,
     },
     Trait {
+        /// The field which pointers to the container coerce to pointers to
         ptr_coerce_target: Option<Box<str>>,
     },
-    Union,
+    Union {
+        /// The enum storing which field is active, if the union is tagged
+        tag: Option<Box<ContainerType>>,
+    },
     Enum {
         backing_type: IntType,
         is_exhaustive: bool,
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContainerField {
+    pub name: Box<str>,
     pub visibility: Visibility,
     pub field_kind: FieldKind,
     pub field_type: Option<Expression>,
-    pub val: Expression,
+    /// The value of const and static fields, or the default value of instance fields
+    pub val: Option<Expression>,
+    /// Where the field is declared, for diagnostics
+    pub span: Option<TextSpan>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FieldKind {
     Instance,
     Const,
     Static,
 }
 
+impl ContainerField {
+    /// Instance fields are fields, while the others are types or values depending on their value
+    pub fn name_kind(&self) -> NameKind {
+        match (&self.field_kind, &self.val) {
+            (FieldKind::Instance, _) => NameKind::Field,
+            (_, Some(Expression::Value(value))) if matches!(**value, Value::Type(_)) => {
+                NameKind::Type
+            }
+            _ => NameKind::Value,
+        }
+    }
+}
+
+/// Why `container.name` couldn't be accessed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldError {
+    NotFound {
+        /// The closest fields which could have been accessed
+        suggestions: Box<[FieldSuggestion]>,
+    },
+    /// The field exists, but isn't `pub` and is accessed from another file than the container's
+    Private { declared_at: Option<TextSpan> },
+}
+
+/// A field which could have been meant by a field access
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSuggestion {
+    pub name: Box<str>,
+    pub declared_at: Option<TextSpan>,
+}
+
+/// A field of an anonymous initializer `.{ ... }`, where `T` is the initial value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AnonField<T> {
+    /// `.name = value`
+    Named(Box<str>, T),
+    /// `name`, either short for `.name = name` or a tuple element
+    Ident(Box<str>, T),
+    /// Any other tuple element
+    Positional(T),
+}
+
+/// The fields of initializers are referred to by their index in the initializer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnonInitError {
+    /// The expected type can't be initialized with `.{ ... }` or `.variant`
+    NotAContainer,
+    UnknownField {
+        field: usize,
+        name: Box<str>,
+        expected: Box<[Box<str>]>,
+    },
+    DuplicateField {
+        first: usize,
+        second: usize,
+        name: Box<str>,
+    },
+    /// Fields without a default value which aren't given
+    MissingFields {
+        names: Box<[Box<str>]>,
+        expected: Box<[Box<str>]>,
+    },
+    /// A tuple element given among named fields
+    MixedFields { field: usize },
+    TooManyElements {
+        field: usize,
+        expected: Box<[Box<str>]>,
+    },
+    /// A union initialized with more or less than one field
+    NotOneUnionField { expected: Box<[Box<str>]> },
+}
+
+impl ContainerType {
+    pub fn field(&self, name: &str) -> Option<&ContainerField> {
+        self.fields.iter().find(|field| &*field.name == name)
+    }
+
+    /// Looks up the field accessed by `container.name`, where `expected` tells which fields are
+    /// the most likely to be meant if there isn't any with that name. Only `pub` fields can be
+    /// accessed when `from_outside` is true, from another file than the container's.
+    pub fn lookup_field(
+        &self,
+        name: &str,
+        expected: NameKind,
+        from_outside: bool,
+    ) -> Result<&ContainerField, FieldError> {
+        let accessible = |field: &ContainerField| {
+            !from_outside || matches!(field.visibility, Visibility::Public)
+        };
+        match self.field(name) {
+            Some(field) if accessible(field) => Ok(field),
+            Some(field) => Err(FieldError::Private {
+                declared_at: field.span.clone(),
+            }),
+            None => {
+                let candidates =
+                    self.fields
+                        .iter()
+                        .filter(|field| accessible(field))
+                        .map(|field| {
+                            let suggestion = FieldSuggestion {
+                                name: field.name.clone(),
+                                declared_at: field.span.clone(),
+                            };
+                            (suggestion, &*field.name, field.name_kind())
+                        });
+                Err(FieldError::NotFound {
+                    suggestions: suggest(name, expected, candidates).into(),
+                })
+            }
+        }
+    }
+
+    /// The fields given a value by initializers, or the variants of an enum
+    pub fn instance_fields(&self) -> impl Iterator<Item = &ContainerField> {
+        self.fields
+            .iter()
+            .filter(|field| matches!(field.field_kind, FieldKind::Instance))
+    }
+
+    fn instance_field_names(&self) -> Box<[Box<str>]> {
+        self.instance_fields()
+            .map(|field| field.name.clone())
+            .collect()
+    }
+
+    /// Matches the fields of `.{ ... }` with the instance fields, in declaration order.
+    /// Fields left to their default value are paired with `None`.
+    pub fn resolve_anon_init<T>(
+        &self,
+        init: Vec<AnonField<T>>,
+    ) -> Result<Vec<(&ContainerField, Option<T>)>, AnonInitError> {
+        let is_union = match self.variant {
+            ContainerVariant::Struct { .. } => false,
+            ContainerVariant::Union { .. } => true,
+            ContainerVariant::Trait { .. } | ContainerVariant::Enum { .. } => {
+                return Err(AnonInitError::NotAContainer)
+            }
+        };
+        let fields: Vec<_> = self.instance_fields().collect();
+        let is_named = init
+            .iter()
+            .any(|field| matches!(field, AnonField::Named(..)))
+            || init.iter().all(|field| match field {
+                AnonField::Ident(name, _) => self.field(name).is_some(),
+                AnonField::Named(..) => true,
+                AnonField::Positional(_) => false,
+            }) && !init.is_empty();
+
+        let mut values: Vec<Option<(usize, T)>> = fields.iter().map(|_| None).collect();
+        for (idx, field) in init.into_iter().enumerate() {
+            let (position, value) = match field {
+                AnonField::Named(name, value) | AnonField::Ident(name, value) if is_named => {
+                    match fields.iter().position(|field| field.name == name) {
+                        Some(position) => (position, value),
+                        None => {
+                            return Err(AnonInitError::UnknownField {
+                                field: idx,
+                                name,
+                                expected: self.instance_field_names(),
+                            })
+                        }
+                    }
+                }
+                AnonField::Positional(_) if is_named => {
+                    return Err(AnonInitError::MixedFields { field: idx })
+                }
+                AnonField::Named(..) => unreachable!("named fields make the initializer named"),
+                AnonField::Ident(_, value) | AnonField::Positional(value) => {
+                    if idx >= fields.len() {
+                        return Err(AnonInitError::TooManyElements {
+                            field: idx,
+                            expected: self.instance_field_names(),
+                        });
+                    }
+                    (idx, value)
+                }
+            };
+            if let Some((first, _)) = &values[position] {
+                return Err(AnonInitError::DuplicateField {
+                    first: *first,
+                    second: idx,
+                    name: fields[position].name.clone(),
+                });
+            }
+            values[position] = Some((idx, value));
+        }
+
+        if is_union {
+            if values.iter().filter(|value| value.is_some()).count() != 1 {
+                return Err(AnonInitError::NotOneUnionField {
+                    expected: self.instance_field_names(),
+                });
+            }
+        } else {
+            let missing: Box<[Box<str>]> = fields
+                .iter()
+                .zip(&values)
+                .filter(|(field, value)| value.is_none() && field.val.is_none())
+                .map(|(field, _)| field.name.clone())
+                .collect();
+            if !missing.is_empty() {
+                return Err(AnonInitError::MissingFields {
+                    names: missing,
+                    expected: self.instance_field_names(),
+                });
+            }
+        }
+        Ok(fields
+            .into_iter()
+            .zip(values)
+            .map(|(field, value)| (field, value.map(|(_, value)| value)))
+            .collect())
+    }
+
+    /// Finds the variant named by `.variant`
+    pub fn resolve_enum_literal(&self, name: &str) -> Result<&ContainerField, AnonInitError> {
+        if !matches!(self.variant, ContainerVariant::Enum { .. }) {
+            return Err(AnonInitError::NotAContainer);
+        }
+        self.instance_fields()
+            .find(|field| &*field.name == name)
+            .ok_or_else(|| AnonInitError::UnknownField {
+                field: 0,
+                name: name.into(),
+                expected: self.instance_field_names(),
+            })
+    }
+}
+
+/// Errors found while building a container from its declaration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContainerError {
+    DuplicateName {
+        name: Box<str>,
+    },
+    /// A variant value not fitting in the backing type of the enum
+    VariantOverflow {
+        variant: Box<str>,
+        value: BigInt,
+        backing_type: IntType,
+    },
+    DuplicateVariantValue {
+        first: Box<str>,
+        second: Box<str>,
+        value: BigInt,
+    },
+    /// `_` requires the backing type to be given, as it determines the possible values
+    NonExhaustiveWithoutBackingType,
+    /// A field of a union without a variant with the same name in the tag
+    UnknownTagVariant {
+        field: Box<str>,
+        expected: Box<[Box<str>]>,
+    },
+    /// A variant of the tag without a field with the same name in the union
+    MissingUnionField {
+        variant: Box<str>,
+        fields: Box<[Box<str>]>,
+    },
+    /// Unions can only be tagged by enums
+    InvalidTag,
+}
+
 #[derive(Debug, Clone)]
+pub enum UnionTagging {
+    Untagged,
+    /// `union(enum)`
+    Inferred,
+    Explicit(ContainerType),
+}
+
+impl ContainerType {
+    /// Builds an enum from its variants, with the values they were explicitly given. The
+    /// backing type is inferred from the values if it isn't given.
+    pub fn new_enum(
+        backing_type: Option<IntType>,
+        variants: Vec<(Box<str>, Option<BigInt>)>,
+        is_exhaustive: bool,
+        declarations: Vec<ContainerField>,
+    ) -> Result<Self, ContainerError> {
+        if !is_exhaustive && backing_type.is_none() {
+            return Err(ContainerError::NonExhaustiveWithoutBackingType);
+        }
+        let mut values: Vec<(Box<str>, BigInt)> = Vec::with_capacity(variants.len());
+        for (name, value) in variants {
+            let value = match (value, values.last()) {
+                (Some(value), _) => value,
+                (None, Some((_, prev))) => prev + 1,
+                (None, None) => BigInt::ZERO,
+            };
+            if let Some((first, _)) = values.iter().find(|(_, other)| *other == value) {
+                return Err(ContainerError::DuplicateVariantValue {
+                    first: first.clone(),
+                    second: name,
+                    value,
+                });
+            }
+            values.push((name, value));
+        }
+        let backing_type = backing_type.unwrap_or_else(|| IntType {
+            signed: false,
+            bits: values
+                .iter()
+                .map(|(_, value)| value.bits() as u16)
+                .max()
+                .unwrap_or(0),
+        });
+
+        let mut fields = Vec::with_capacity(values.len() + declarations.len());
+        for (name, value) in values {
+            let Some(int) = Int::new(&value, backing_type.clone()) else {
+                return Err(ContainerError::VariantOverflow {
+                    variant: name,
+                    value,
+                    backing_type,
+                });
+            };
+            fields.push(ContainerField {
+                name,
+                visibility: Visibility::Public,
+                field_kind: FieldKind::Instance,
+                field_type: None,
+                val: Some(Expression::Value(Box::new(Value::Int(int)))),
+                span: None,
+            });
+        }
+        fields.extend(declarations);
+        Self::with_unique_names(
+            fields,
+            ContainerVariant::Enum {
+                backing_type,
+                is_exhaustive,
+            },
+        )
+    }
+
+    /// Builds a union from its fields and declarations
+    pub fn new_union(
+        fields: Vec<ContainerField>,
+        tagging: UnionTagging,
+    ) -> Result<Self, ContainerError> {
+        let union = Self::with_unique_names(fields, ContainerVariant::Union { tag: None })?;
+        let tag = match tagging {
+            UnionTagging::Untagged => return Ok(union),
+            UnionTagging::Inferred => Self::new_enum(
+                None,
+                union
+                    .instance_fields()
+                    .map(|field| (field.name.clone(), None))
+                    .collect(),
+                true,
+                Vec::new(),
+            )?,
+            UnionTagging::Explicit(tag) => {
+                if !matches!(tag.variant, ContainerVariant::Enum { .. }) {
+                    return Err(ContainerError::InvalidTag);
+                }
+                if let Some(field) = union
+                    .instance_fields()
+                    .find(|field| tag.resolve_enum_literal(&field.name).is_err())
+                {
+                    return Err(ContainerError::UnknownTagVariant {
+                        field: field.name.clone(),
+                        expected: tag.instance_field_names(),
+                    });
+                }
+                if let Some(variant) = tag
+                    .instance_fields()
+                    .find(|variant| union.field(&variant.name).is_none())
+                {
+                    return Err(ContainerError::MissingUnionField {
+                        variant: variant.name.clone(),
+                        fields: union.instance_field_names(),
+                    });
+                }
+                tag
+            }
+        };
+        Ok(ContainerType {
+            variant: ContainerVariant::Union {
+                tag: Some(Box::new(tag)),
+            },
+            ..union
+        })
+    }
+
+    fn with_unique_names(
+        fields: Vec<ContainerField>,
+        variant: ContainerVariant,
+    ) -> Result<Self, ContainerError> {
+        for (idx, field) in fields.iter().enumerate() {
+            if fields[..idx].iter().any(|other| other.name == field.name) {
+                return Err(ContainerError::DuplicateName {
+                    name: field.name.clone(),
+                });
+            }
+        }
+        Ok(ContainerType {
+            id: ContainerId::fresh(),
+            name: None,
+            file: None,
+            fields: fields.into_boxed_slice(),
+            variant,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct IntType {
     pub signed: bool,
     pub bits: u16,
 }
 
-#[derive(Debug, Clone)]
+impl IntType {
+    pub fn min(&self) -> BigInt {
+        match self.signed {
+            true if self.bits > 0 => -(BigInt::from(1) << (self.bits - 1)),
+            _ => BigInt::ZERO,
+        }
+    }
+
+    pub fn max(&self) -> BigInt {
+        match self.signed {
+            true if self.bits > 0 => (BigInt::from(1) << (self.bits - 1)) - 1,
+            true => BigInt::ZERO,
+            false => (BigInt::from(1) << self.bits) - 1,
+        }
+    }
+
+    pub fn contains(&self, value: &BigInt) -> bool {
+        self.min() <= *value && *value <= self.max()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FloatType {
     F16,
     F32,
@@ -60,65 +558,954 @@ pub enum FloatType {
     F128,
 }
 
-#[derive(Debug, Clone)]
+impl FloatType {
+    pub fn bits(&self) -> u16 {
+        match self {
+            FloatType::F16 => 16,
+            FloatType::F32 => 32,
+            FloatType::F64 => 64,
+            FloatType::F80 => 80,
+            FloatType::F128 => 128,
+        }
+    }
+
+    /// The amount of significant bits, including the implicit leading one of normal values
+    pub fn significand_bits(&self) -> u64 {
+        match self {
+            FloatType::F16 => 11,
+            FloatType::F32 => 24,
+            FloatType::F64 => 53,
+            FloatType::F80 => 64,
+            FloatType::F128 => 113,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComptimeFunctionType {
+    /// An `ExpressionList` of the types of the parameters
     pub parameters: Expression,
     pub result: Expression,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CallingConvetion {
     Cera,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RuntimeFunctionType {
     pub base_signature: ComptimeFunctionType,
     pub calling_convention: CallingConvetion,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PointerType {
     pub pointed_value: Expression,
+    /// Whether the pointed value can't be assigned through the pointer
+    pub is_const: bool,
+    /// `[*]T`, which points to an unknown amount of values rather than to a single one
+    pub is_many: bool,
+    /// The value following the last one pointed to by `[*:sentinel]T`, like the `0` ending C
+    /// strings
+    pub sentinel: Option<Box<Value>>,
 }
 
-type ErrorID = u32;
+/// `[]T`, a pointer along with the amount of values it points to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SliceType {
+    pub indexed_type: Box<Type>,
+    /// Whether the values can't be assigned through the slice
+    pub is_const: bool,
+    /// The value following the last one, like the `0` ending C strings
+    pub sentinel: Option<Box<Value>>,
+}
 
-#[derive(Debug, Clone)]
+pub type ErrorID = u32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorSetType {
     pub possible_errors: Box<[ErrorID]>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorUnionType {
     pub ok: Box<Type>,
-    pub err: ErrorSetType,
+    /// `None` for `!T`, the errors of which are inferred from the function returning it
+    pub err: Option<ErrorSetType>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptionType {
     pub some_type: Box<Type>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArrayType {
     pub size: usize,
     pub indexed_type: Box<Type>,
+    /// The value stored after the last one, which `size` doesn't count
+    pub sentinel: Option<Box<Value>>,
 }
 
-#[derive(Debug, Clone)]
+impl ArrayType {
+    /// The amount of values stored, including the sentinel
+    pub fn stored_len(&self) -> usize {
+        self.size + usize::from(self.sentinel.is_some())
+    }
+}
+
+/// Whether values ended by `found` can be used where values ended by `expected` are, which
+/// holds when nothing is expected, or when both sentinels are the same value
+pub fn sentinel_coerces(found: Option<&Value>, expected: Option<&Value>) -> bool {
+    match (found.and_then(Value::bytes), expected) {
+        (_, None) => true,
+        (Some(found), Some(expected)) => expected.bytes() == Some(found),
+        (None, Some(_)) => false,
+    }
+}
+
+impl Type {
+    /// The amount of bytes storing values of this type at compile time, which is their size in
+    /// `Layout::of`. `None` for types which only exist at compile time, which can't be stored yet,
+    /// or which have more bytes than a `usize` counts.
+    pub fn byte_len(&self) -> Option<usize> {
+        if !self.is_stored_as_bytes() {
+            return None;
+        }
+        usize::try_from(Layout::of(self).ok()?.size).ok()
+    }
+
+    fn is_stored_as_bytes(&self) -> bool {
+        match self {
+            Type::Container(container) => match &container.variant {
+                ContainerVariant::Struct { .. } => container.instance_fields().all(|field| {
+                    field
+                        .field_type
+                        .as_ref()
+                        .and_then(normalize)
+                        .is_some_and(Type::is_stored_as_bytes)
+                }),
+                ContainerVariant::Enum { .. } => true,
+                ContainerVariant::Trait { .. } | ContainerVariant::Union { .. } => false,
+            },
+            Type::Bool | Type::Int(_) | Type::Float(_) | Type::Void => true,
+            Type::Option(OptionType { some_type }) => some_type.is_stored_as_bytes(),
+            Type::Array(array_type) => array_type.indexed_type.is_stored_as_bytes(),
+            _ => false,
+        }
+    }
+
+    /// The container `.{ ... }` and `.variant` are resolved against when this type is expected,
+    /// looking through optionals and error unions
+    pub fn anon_init_target(&self) -> Option<&ContainerType> {
+        match self {
+            Type::Container(container) => Some(container),
+            Type::Option(OptionType { some_type }) => some_type.anon_init_target(),
+            Type::ErrorUnion(ErrorUnionType { ok, .. }) => ok.anon_init_target(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Type {
     Container(ContainerType),
+    Bool,
     Int(IntType),
     Float(FloatType),
     Pointer(PointerType),
+    Slice(SliceType),
     ErrorSet(ErrorSetType),
     ErrorUnion(ErrorUnionType),
     Option(OptionType),
     Array(ArrayType),
     Type,
+    /// Integers of any size, which only exist at compile time, like the values of literals
+    ComptimeInt,
+    /// Exact decimals, which only exist at compile time, like the values of float literals
+    ComptimeFloat,
     ComptimeFunction(ComptimeFunctionType),
     RuntimeFunction(RuntimeFunctionType),
     Void,
     Undefined,
 }
+
+/// Whether two expressions give the same type. Expressions which haven't been evaluated, like
+/// the parameter types of generic functions, are the same when they are written the same.
+fn same_type_expression(first: &Expression, second: &Expression) -> bool {
+    match (normalize(first), normalize(second)) {
+        (Some(first), Some(second)) => first == second,
+        (None, None) => same_expression(first, second),
+        _ => false,
+    }
+}
+
+/// Whether two expressions are written the same, whatever their spans
+fn same_expression(first: &Expression, second: &Expression) -> bool {
+    use Expression as E;
+    let same_lists = |first: &[Expression], second: &[Expression]| {
+        first.len() == second.len()
+            && first
+                .iter()
+                .zip(second)
+                .all(|(first, second)| same_expression(first, second))
+    };
+    match (strip_spans(first), strip_spans(second)) {
+        (E::Value(first), E::Value(second)) => same_value(first, second),
+        (E::Literal(first), E::Literal(second)) => first == second,
+        (E::Builtin(first, first_args), E::Builtin(second, second_args)) => {
+            first == second && same_lists(first_args, second_args)
+        }
+        (E::ExpressionList(first), E::ExpressionList(second)) => same_lists(first, second),
+        (E::Name(first), E::Name(second)) | (E::EnumLiteral(first), E::EnumLiteral(second)) => {
+            first == second
+        }
+        (E::Let(first), E::Let(second)) => {
+            let same_types = match (&first.var_type, &second.var_type) {
+                (Some(first), Some(second)) => same_expression(first, second),
+                (first, second) => first.is_none() && second.is_none(),
+            };
+            first.name == second.name
+                && first.is_mutable == second.is_mutable
+                && same_types
+                && same_expression(&first.value, &second.value)
+        }
+        (
+            E::Assign { target, value },
+            E::Assign {
+                target: second_target,
+                value: second_value,
+            },
+        ) => same_expression(target, second_target) && same_expression(value, second_value),
+        (
+            E::If {
+                condition,
+                then,
+                otherwise,
+            },
+            E::If {
+                condition: second_condition,
+                then: second_then,
+                otherwise: second_otherwise,
+            },
+        ) => {
+            same_expression(condition, second_condition)
+                && same_expression(then, second_then)
+                && same_expression(otherwise, second_otherwise)
+        }
+        (E::Loop(first), E::Loop(second)) | (E::Return(first), E::Return(second)) => {
+            same_expression(first, second)
+        }
+        (
+            E::Labeled { label, body },
+            E::Labeled {
+                label: second_label,
+                body: second_body,
+            },
+        )
+        | (
+            E::Break { label, value: body },
+            E::Break {
+                label: second_label,
+                value: second_body,
+            },
+        ) => label == second_label && same_expression(body, second_body),
+        (
+            E::Call { callee, args },
+            E::Call {
+                callee: second_callee,
+                args: second_args,
+            },
+        ) => same_expression(callee, second_callee) && same_lists(args, second_args),
+        (
+            E::Field { base, name },
+            E::Field {
+                base: second_base,
+                name: second_name,
+            },
+        ) => name == second_name && same_expression(base, second_base),
+        (E::AnonInit(first), E::AnonInit(second)) => {
+            first.len() == second.len()
+                && first.iter().zip(second).all(|fields| match fields {
+                    (
+                        AnonField::Named(first_name, first),
+                        AnonField::Named(second_name, second),
+                    )
+                    | (
+                        AnonField::Ident(first_name, first),
+                        AnonField::Ident(second_name, second),
+                    ) => first_name == second_name && same_expression(first, second),
+                    (AnonField::Positional(first), AnonField::Positional(second)) => {
+                        same_expression(first, second)
+                    }
+                    _ => false,
+                })
+        }
+        _ => false,
+    }
+}
+
+/// Whether two values in expressions are the same, like the lengths of array types. Runtime
+/// values are compared by their type and their bytes, and functions by how they are written.
+fn same_value(first: &Value, second: &Value) -> bool {
+    match (first, second) {
+        (Value::Type(first), Value::Type(second)) => first == second,
+        (Value::Bool(first), Value::Bool(second)) => first == second,
+        (Value::ComptimeInt(first), Value::ComptimeInt(second)) => first == second,
+        (Value::ComptimeFloat(first), Value::ComptimeFloat(second)) => {
+            first.compare(second) == Some(Ordering::Equal)
+        }
+        (Value::Void, Value::Void) | (Value::Undefined, Value::Undefined) => true,
+        (Value::Function(first), Value::Function(second)) => {
+            first.param_names == second.param_names
+                && first.calling_convention == second.calling_convention
+                && first.file == second.file
+                && same_signature(&first.function_type, &second.function_type)
+                && same_expression(&first.expression, &second.expression)
+        }
+        (first, second) => match (first.bytes(), second.bytes()) {
+            (Some(first_bytes), Some(second_bytes)) => {
+                first_bytes == second_bytes && first.get_type() == second.get_type()
+            }
+            _ => false,
+        },
+    }
+}
+
+fn hash_type_expression<H: Hasher>(expression: &Expression, state: &mut H) {
+    normalize(expression).hash(state)
+}
+
+fn parameter_types(function_type: &ComptimeFunctionType) -> Option<&[Expression]> {
+    match strip_spans(&function_type.parameters) {
+        Expression::ExpressionList(parameters) => Some(parameters),
+        _ => None,
+    }
+}
+
+fn same_signature(first: &ComptimeFunctionType, second: &ComptimeFunctionType) -> bool {
+    let same_parameters = match (parameter_types(first), parameter_types(second)) {
+        (Some(first), Some(second)) => {
+            first.len() == second.len()
+                && first
+                    .iter()
+                    .zip(second)
+                    .all(|(first, second)| same_type_expression(first, second))
+        }
+        _ => same_type_expression(&first.parameters, &second.parameters),
+    };
+    same_parameters && same_type_expression(&first.result, &second.result)
+}
+
+fn hash_signature<H: Hasher>(function_type: &ComptimeFunctionType, state: &mut H) {
+    for parameter in parameter_types(function_type).unwrap_or_default() {
+        hash_type_expression(parameter, state);
+    }
+    hash_type_expression(&function_type.result, state);
+}
+
+fn sorted_errors(error_set: &ErrorSetType) -> Vec<ErrorID> {
+    let mut possible_errors = error_set.possible_errors.to_vec();
+    possible_errors.sort_unstable();
+    possible_errors.dedup();
+    possible_errors
+}
+
+fn sentinel_key(sentinel: &Option<Box<Value>>) -> Option<Option<&[u8]>> {
+    sentinel.as_deref().map(Value::bytes)
+}
+
+impl PartialEq for Type {
+    fn eq(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Container(first), Type::Container(second)) => first.id == second.id,
+            (Type::Int(first), Type::Int(second)) => first == second,
+            (Type::Float(first), Type::Float(second)) => first == second,
+            (Type::Pointer(first), Type::Pointer(second)) => {
+                first.is_const == second.is_const
+                    && first.is_many == second.is_many
+                    && sentinel_key(&first.sentinel) == sentinel_key(&second.sentinel)
+                    && same_type_expression(&first.pointed_value, &second.pointed_value)
+            }
+            (Type::Slice(first), Type::Slice(second)) => {
+                first.is_const == second.is_const
+                    && first.indexed_type == second.indexed_type
+                    && sentinel_key(&first.sentinel) == sentinel_key(&second.sentinel)
+            }
+            (Type::ErrorSet(first), Type::ErrorSet(second)) => {
+                sorted_errors(first) == sorted_errors(second)
+            }
+            (Type::ErrorUnion(first), Type::ErrorUnion(second)) => {
+                first.ok == second.ok
+                    && first.err.as_ref().map(sorted_errors)
+                        == second.err.as_ref().map(sorted_errors)
+            }
+            (Type::Option(first), Type::Option(second)) => first.some_type == second.some_type,
+            (Type::Array(first), Type::Array(second)) => {
+                first.size == second.size
+                    && first.indexed_type == second.indexed_type
+                    && sentinel_key(&first.sentinel) == sentinel_key(&second.sentinel)
+            }
+            (Type::ComptimeFunction(first), Type::ComptimeFunction(second)) => {
+                same_signature(first, second)
+            }
+            (Type::RuntimeFunction(first), Type::RuntimeFunction(second)) => {
+                first.calling_convention == second.calling_convention
+                    && same_signature(&first.base_signature, &second.base_signature)
+            }
+            (
+                Type::Bool
+                | Type::Type
+                | Type::ComptimeInt
+                | Type::ComptimeFloat
+                | Type::Void
+                | Type::Undefined,
+                _,
+            ) => std::mem::discriminant(self) == std::mem::discriminant(other),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Type {}
+
+impl Hash for Type {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Type::Container(container_type) => container_type.id.hash(state),
+            Type::Int(int_type) => int_type.hash(state),
+            Type::Float(float_type) => float_type.hash(state),
+            Type::Pointer(pointer_type) => {
+                pointer_type.is_const.hash(state);
+                pointer_type.is_many.hash(state);
+                sentinel_key(&pointer_type.sentinel).hash(state);
+                hash_type_expression(&pointer_type.pointed_value, state);
+            }
+            Type::Slice(slice_type) => {
+                slice_type.is_const.hash(state);
+                slice_type.indexed_type.hash(state);
+                sentinel_key(&slice_type.sentinel).hash(state);
+            }
+            Type::ErrorSet(error_set) => sorted_errors(error_set).hash(state),
+            Type::ErrorUnion(error_union) => {
+                error_union.ok.hash(state);
+                error_union.err.as_ref().map(sorted_errors).hash(state);
+            }
+            Type::Option(option_type) => option_type.some_type.hash(state),
+            Type::Array(array_type) => {
+                array_type.size.hash(state);
+                array_type.indexed_type.hash(state);
+                sentinel_key(&array_type.sentinel).hash(state);
+            }
+            Type::ComptimeFunction(function_type) => hash_signature(function_type, state),
+            Type::RuntimeFunction(function_type) => {
+                function_type.calling_convention.hash(state);
+                hash_signature(&function_type.base_signature, state);
+            }
+            Type::Bool
+            | Type::Type
+            | Type::ComptimeInt
+            | Type::ComptimeFloat
+            | Type::Void
+            | Type::Undefined => {}
+        }
+    }
+}
+
+/// Writes the type given by an expression, or `_` if it hasn't been evaluated
+fn write_type_expression(f: &mut fmt::Formatter, expression: &Expression) -> fmt::Result {
+    match normalize(expression) {
+        Some(value_type) => write!(f, "{value_type}"),
+        None => write!(f, "_"),
+    }
+}
+
+impl fmt::Display for ComptimeFunctionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "fn (")?;
+        match &self.parameters {
+            Expression::ExpressionList(parameters) => {
+                for (idx, parameter) in parameters.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_type_expression(f, parameter)?;
+                }
+            }
+            parameters => write_type_expression(f, parameters)?,
+        }
+        write!(f, ") -> ")?;
+        write_type_expression(f, &self.result)
+    }
+}
+
+/// Writes the `:sentinel` of arrays and slices
+fn write_sentinel(f: &mut fmt::Formatter, sentinel: Option<&Value>) -> fmt::Result {
+    match sentinel {
+        None => Ok(()),
+        Some(Value::Int(int)) => write!(f, ":{}", int.value()),
+        Some(Value::Bool(value)) => write!(f, ":{value}"),
+        Some(_) => write!(f, ":_"),
+    }
+}
+
+/// Types are written as they are in source, containers by the name they were declared with
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Container(ContainerType {
+                name: Some(name), ..
+            }) => write!(f, "{name}"),
+            Type::Container(container_type) => match container_type.variant {
+                ContainerVariant::Struct { .. } => write!(f, "struct"),
+                ContainerVariant::Trait { .. } => write!(f, "trait"),
+                ContainerVariant::Union { .. } => write!(f, "union"),
+                ContainerVariant::Enum { .. } => write!(f, "enum"),
+            },
+            Type::Int(IntType { signed, bits }) => {
+                write!(f, "{}{bits}", if *signed { 'i' } else { 'u' })
+            }
+            Type::Float(float_type) => write!(f, "f{}", float_type.bits()),
+            Type::Pointer(PointerType {
+                pointed_value,
+                is_const,
+                is_many,
+                sentinel,
+            }) => {
+                match is_many {
+                    true => {
+                        write!(f, "[*")?;
+                        write_sentinel(f, sentinel.as_deref())?;
+                        write!(f, "]")?;
+                    }
+                    false => write!(f, "*")?,
+                }
+                write!(f, "{}", if *is_const { "const " } else { "" })?;
+                write_type_expression(f, pointed_value)
+            }
+            Type::Slice(SliceType {
+                indexed_type,
+                is_const,
+                sentinel,
+            }) => {
+                write!(f, "[")?;
+                write_sentinel(f, sentinel.as_deref())?;
+                write!(
+                    f,
+                    "]{}{indexed_type}",
+                    if *is_const { "const " } else { "" }
+                )
+            }
+            Type::ErrorSet(ErrorSetType { possible_errors }) => {
+                let errors: Vec<_> = possible_errors.iter().map(ToString::to_string).collect();
+                write!(f, "error{{{}}}", errors.join(", "))
+            }
+            Type::ErrorUnion(ErrorUnionType { ok, err: None }) => write!(f, "!{ok}"),
+            Type::ErrorUnion(ErrorUnionType { ok, err: Some(err) }) => {
+                write!(f, "{}!{ok}", Type::ErrorSet(err.clone()))
+            }
+            Type::Option(OptionType { some_type }) => write!(f, "?{some_type}"),
+            Type::Array(ArrayType {
+                size,
+                indexed_type,
+                sentinel,
+            }) => {
+                write!(f, "[{size}")?;
+                write_sentinel(f, sentinel.as_deref())?;
+                write!(f, "]{indexed_type}")
+            }
+            Type::Bool => write!(f, "bool"),
+            Type::Type => write!(f, "type"),
+            Type::ComptimeInt => write!(f, "comptime_int"),
+            Type::ComptimeFloat => write!(f, "comptime_float"),
+            Type::ComptimeFunction(function_type) => write!(f, "{function_type}"),
+            Type::RuntimeFunction(RuntimeFunctionType { base_signature, .. }) => {
+                write!(f, "{base_signature}")
+            }
+            Type::Void => write!(f, "void"),
+            Type::Undefined => write!(f, "@TypeOf(undefined)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{
+        field, many_pointer, pointer, spanned_pointer, struct_type, u8_type, u8_value,
+    };
+
+    fn int(signed: bool, bits: u16) -> Type {
+        Type::Int(IntType { signed, bits })
+    }
+
+    fn spaceship() -> ContainerType {
+        let fuel = ContainerField {
+            val: Some(Expression::Value(Box::new(Value::Void))),
+            ..field("fuel", Type::Void)
+        };
+        struct_type(vec![field("speed", Type::Void), fuel])
+    }
+
+    #[test]
+    fn equality_and_hashing() {
+        let hash = |value_type: &Type| {
+            let mut hasher = std::hash::DefaultHasher::new();
+            value_type.hash(&mut hasher);
+            hasher.finish()
+        };
+        let spanned = spanned_pointer(int(true, 32));
+        let unspanned = pointer(int(true, 32));
+        assert_eq!(spanned, unspanned);
+        assert_eq!(hash(&spanned), hash(&unspanned));
+        assert_ne!(spanned, pointer(int(false, 32)));
+        // Many-item pointers are other types than single ones, and so are their sentinels
+        let terminated = many_pointer(u8_type(), Some(u8_value(0)));
+        assert_ne!(pointer(u8_type()), many_pointer(u8_type(), None));
+        assert_ne!(many_pointer(u8_type(), None), terminated);
+        assert_ne!(many_pointer(u8_type(), Some(u8_value(1))), terminated);
+        assert_eq!(many_pointer(u8_type(), Some(u8_value(0))), terminated);
+        assert_eq!(
+            hash(&many_pointer(u8_type(), Some(u8_value(0)))),
+            hash(&terminated)
+        );
+        let errors = |possible_errors: &[ErrorID]| {
+            Type::ErrorSet(ErrorSetType {
+                possible_errors: possible_errors.into(),
+            })
+        };
+        assert_eq!(errors(&[2, 1, 2]), errors(&[1, 2]));
+        assert_eq!(hash(&errors(&[2, 1, 2])), hash(&errors(&[1, 2])));
+        let container = struct_type(Vec::new());
+        assert_eq!(
+            Type::Container(container.clone()),
+            Type::Container(container)
+        );
+        assert_ne!(
+            Type::Container(struct_type(Vec::new())),
+            Type::Container(struct_type(Vec::new()))
+        );
+        // Types which haven't been evaluated are compared by how they are written
+        let generic = |name: &str| {
+            let parameter = Expression::Spanned(
+                TextSpan { idx: 0, len: 1 },
+                Box::new(Expression::Name(name.into())),
+            );
+            Type::ComptimeFunction(ComptimeFunctionType {
+                parameters: Expression::ExpressionList(Box::new([parameter])),
+                result: Expression::Name(name.into()),
+            })
+        };
+        let identity = generic("T");
+        assert_eq!(identity.clone(), identity);
+        assert_eq!(generic("T"), identity);
+        assert_eq!(hash(&generic("T")), hash(&identity));
+        assert_ne!(generic("U"), identity);
+    }
+
+    type Resolved = Vec<(Box<str>, Option<u8>)>;
+
+    fn resolved(init: Vec<AnonField<u8>>) -> Result<Resolved, AnonInitError> {
+        Ok(spaceship()
+            .resolve_anon_init(init)?
+            .into_iter()
+            .map(|(field, value)| (field.name.clone(), value))
+            .collect())
+    }
+
+    #[test]
+    fn named_and_shorthand_fields() {
+        assert_eq!(
+            resolved(vec![AnonField::Ident("speed".into(), 1)]),
+            Ok(vec![("speed".into(), Some(1)), ("fuel".into(), None)])
+        );
+        assert_eq!(
+            resolved(vec![
+                AnonField::Named("fuel".into(), 2),
+                AnonField::Ident("speed".into(), 1)
+            ]),
+            Ok(vec![("speed".into(), Some(1)), ("fuel".into(), Some(2))])
+        );
+    }
+
+    #[test]
+    fn tuple_fields() {
+        assert_eq!(
+            resolved(vec![
+                AnonField::Ident("a".into(), 1),
+                AnonField::Positional(2)
+            ]),
+            Ok(vec![("speed".into(), Some(1)), ("fuel".into(), Some(2))])
+        );
+        assert_eq!(
+            resolved(vec![
+                AnonField::Positional(1),
+                AnonField::Positional(2),
+                AnonField::Positional(3)
+            ]),
+            Err(AnonInitError::TooManyElements {
+                field: 2,
+                expected: Box::new(["speed".into(), "fuel".into()]),
+            })
+        );
+    }
+
+    #[test]
+    fn field_errors() {
+        let expected: Box<[Box<str>]> = Box::new(["speed".into(), "fuel".into()]);
+        assert_eq!(
+            resolved(vec![AnonField::Named("fuel".into(), 1)]),
+            Err(AnonInitError::MissingFields {
+                names: Box::new(["speed".into()]),
+                expected: expected.clone(),
+            })
+        );
+
+        assert_eq!(
+            resolved(vec![
+                AnonField::Named("speed".into(), 1),
+                AnonField::Named("sped".into(), 2)
+            ]),
+            Err(AnonInitError::UnknownField {
+                field: 1,
+                name: "sped".into(),
+                expected,
+            })
+        );
+        assert_eq!(
+            resolved(vec![
+                AnonField::Named("speed".into(), 1),
+                AnonField::Ident("speed".into(), 2)
+            ]),
+            Err(AnonInitError::DuplicateField {
+                first: 0,
+                second: 1,
+                name: "speed".into(),
+            })
+        );
+        assert_eq!(
+            resolved(vec![
+                AnonField::Named("speed".into(), 1),
+                AnonField::Positional(2)
+            ]),
+            Err(AnonInitError::MixedFields { field: 1 })
+        );
+    }
+
+    fn variants(variants: &[(&str, Option<i64>)]) -> Vec<(Box<str>, Option<BigInt>)> {
+        variants
+            .iter()
+            .map(|(name, value)| ((*name).into(), value.map(BigInt::from)))
+            .collect()
+    }
+
+    fn variant_values(container: &ContainerType) -> Vec<BigInt> {
+        container
+            .instance_fields()
+            .map(|field| match &field.val {
+                Some(Expression::Value(value)) => match value.as_ref() {
+                    Value::Int(int) => int.value(),
+                    _ => panic!(),
+                },
+                _ => panic!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn enum_values() {
+        let u8_type = IntType {
+            signed: false,
+            bits: 8,
+        };
+        let color = ContainerType::new_enum(
+            Some(u8_type.clone()),
+            variants(&[("a", None), ("b", Some(5)), ("c", None)]),
+            false,
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(variant_values(&color), [0.into(), 5.into(), 6.into()]);
+
+        let inferred =
+            ContainerType::new_enum(None, variants(&[("a", None), ("b", Some(4))]), true, vec![])
+                .unwrap();
+        let ContainerVariant::Enum { backing_type, .. } = &inferred.variant else {
+            panic!()
+        };
+        assert_eq!(
+            backing_type,
+            &IntType {
+                signed: false,
+                bits: 3
+            }
+        );
+
+        assert_eq!(
+            ContainerType::new_enum(
+                Some(u8_type.clone()),
+                variants(&[("a", Some(255)), ("b", None)]),
+                true,
+                vec![],
+            )
+            .unwrap_err(),
+            ContainerError::VariantOverflow {
+                variant: "b".into(),
+                value: 256.into(),
+                backing_type: u8_type.clone(),
+            }
+        );
+        assert_eq!(
+            ContainerType::new_enum(
+                Some(u8_type),
+                variants(&[("a", Some(1)), ("b", Some(0)), ("c", None)]),
+                true,
+                vec![],
+            )
+            .unwrap_err(),
+            ContainerError::DuplicateVariantValue {
+                first: "a".into(),
+                second: "c".into(),
+                value: 1.into(),
+            }
+        );
+        assert_eq!(
+            ContainerType::new_enum(None, variants(&[("a", None)]), false, vec![]).unwrap_err(),
+            ContainerError::NonExhaustiveWithoutBackingType
+        );
+    }
+
+    #[test]
+    fn signed_enum_values() {
+        let i8_type = IntType {
+            signed: true,
+            bits: 8,
+        };
+        let sign = ContainerType::new_enum(
+            Some(i8_type),
+            variants(&[("neg", Some(-128))]),
+            true,
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(variant_values(&sign), [(-128).into()]);
+    }
+
+    #[test]
+    fn union_tags() {
+        let fields = || vec![field("int", Type::Void), field("float", Type::Void)];
+        let inferred = ContainerType::new_union(fields(), UnionTagging::Inferred).unwrap();
+        let ContainerVariant::Union { tag: Some(tag) } = &inferred.variant else {
+            panic!()
+        };
+        assert_eq!(variant_values(tag), [0.into(), 1.into()]);
+
+        let tag = ContainerType::new_enum(
+            None,
+            variants(&[("int", None), ("string", None)]),
+            true,
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(
+            ContainerType::new_union(fields(), UnionTagging::Explicit(tag)).unwrap_err(),
+            ContainerError::UnknownTagVariant {
+                field: "float".into(),
+                expected: Box::new(["int".into(), "string".into()]),
+            }
+        );
+        assert_eq!(
+            ContainerType::new_union(
+                vec![field("int", Type::Void), field("int", Type::Void)],
+                UnionTagging::Untagged
+            )
+            .unwrap_err(),
+            ContainerError::DuplicateName { name: "int".into() }
+        );
+    }
+
+    #[test]
+    fn field_suggestions() {
+        let mut container = spaceship();
+        let mut fields = container.fields.into_vec();
+        fields.push(ContainerField {
+            name: "Speed".into(),
+            visibility: Visibility::Public,
+            field_kind: FieldKind::Const,
+            field_type: None,
+            val: Some(Expression::Value(Box::new(Value::Type(Type::Void)))),
+            span: None,
+        });
+        fields.push(ContainerField {
+            name: "fuels".into(),
+            visibility: Visibility::Private,
+            field_kind: FieldKind::Instance,
+            field_type: None,
+            val: None,
+            span: Some(TextSpan { len: 5, idx: 10 }),
+        });
+        container.fields = fields.into();
+
+        let lookup = |name, expected, from_outside| {
+            container
+                .lookup_field(name, expected, from_outside)
+                .map(|field| field.name.clone())
+        };
+        let suggested = |names: &[&str]| {
+            Err(FieldError::NotFound {
+                suggestions: names
+                    .iter()
+                    .map(|name| FieldSuggestion {
+                        name: (*name).into(),
+                        declared_at: container.field(name).unwrap().span.clone(),
+                    })
+                    .collect(),
+            })
+        };
+        assert_eq!(lookup("speed", NameKind::Field, true), Ok("speed".into()));
+        assert_eq!(lookup("sped", NameKind::Field, true), suggested(&["speed"]));
+        assert_eq!(lookup("Sped", NameKind::Type, true), suggested(&["Speed"]));
+        assert_eq!(
+            lookup("fuels", NameKind::Field, true),
+            Err(FieldError::Private {
+                declared_at: Some(TextSpan { len: 5, idx: 10 })
+            })
+        );
+        assert_eq!(lookup("fuels", NameKind::Field, false), Ok("fuels".into()));
+        // Private fields are only suggested from inside of the container
+        assert_eq!(lookup("fue", NameKind::Field, true), suggested(&["fuel"]));
+        assert_eq!(
+            lookup("fuelz", NameKind::Field, false),
+            suggested(&["fuel", "fuels"])
+        );
+    }
+
+    #[test]
+    fn optional_target() {
+        let optional = Type::Option(OptionType {
+            some_type: Box::new(Type::Container(spaceship())),
+        });
+        assert!(optional.anon_init_target().is_some());
+        assert!(Type::Void.anon_init_target().is_none());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let container = Type::Container(spaceship());
+        let json = serde_json::to_string(&container).unwrap();
+        let loaded: Type = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, container);
+        // Containers are equal by id, so their fields are compared too
+        let fields = |value_type: &Type| -> Vec<_> {
+            let container = value_type.anon_init_target().unwrap();
+            container
+                .fields
+                .iter()
+                .map(|field| (field.name.clone(), field.val.is_some()))
+                .collect()
+        };
+        assert_eq!(fields(&loaded), fields(&container));
+    }
+}
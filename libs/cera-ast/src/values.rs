@@ -1,17 +1,30 @@
+use std::{cmp::Ordering, fmt};
+
+use cera_parse::literals::FloatValue;
+use num_bigint::{BigInt, BigUint, Sign};
+use serde::{Deserialize, Serialize};
+
 use crate::{
     expressions::Expression,
+    intern::normalize,
+    layout::Layout,
     types::{
-        ArrayType, ComptimeFunctionType, ContainerType, ErrorSetType, ErrorUnionType, FloatType,
-        IntType, OptionType, PointerType, Type,
+        ArrayType, CallingConvetion, ComptimeFunctionType, ContainerType, ErrorSetType,
+        ErrorUnionType, FileId, FloatType, IntType, OptionType, PointerType, RuntimeFunctionType,
+        SliceType, Type,
     },
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Value {
+    Bool(bool),
     Float(Float),
     Int(Int),
+    ComptimeInt(#[serde(with = "cera_parse::dump::decimal")] BigInt),
+    ComptimeFloat(ComptimeFloat),
     Container(Container),
     Pointer(Pointer),
+    Slice(Slice),
     Error(Error),
     ErrorUnion(ErrorUnion),
     Option(OptionValue),
@@ -25,16 +38,30 @@ pub enum Value {
 impl Value {
     pub fn get_type(&self) -> Type {
         match self {
-            Value::Float(Float { float_type, .. }) => todo!(),
-            Value::Int(Int { int_type, .. }) => todo!(),
-            Value::Container(Container { container_type, .. }) => todo!(),
-            Value::Pointer(Pointer { pointer_type, .. }) => todo!(),
-            Value::Error(Error { error_set_type, .. }) => todo!(),
+            Value::Bool(_) => Type::Bool,
+            Value::Float(Float { float_type, .. }) => Type::Float(float_type.clone()),
+            Value::Int(Int { int_type, .. }) => Type::Int(int_type.clone()),
+            Value::ComptimeInt(_) => Type::ComptimeInt,
+            Value::ComptimeFloat(_) => Type::ComptimeFloat,
+            Value::Container(Container { container_type, .. }) => {
+                Type::Container(container_type.clone())
+            }
+            Value::Pointer(Pointer { pointer_type, .. }) => Type::Pointer(pointer_type.clone()),
+            Value::Slice(Slice { slice_type, .. }) => Type::Slice(slice_type.clone()),
+            Value::Error(Error { error_set_type, .. }) => Type::ErrorSet(error_set_type.clone()),
             Value::ErrorUnion(ErrorUnion {
                 error_union_type, ..
-            }) => todo!(),
-            Value::Option(OptionValue { option_type, .. }) => todo!(),
-            Value::Array(Array { array_type, .. }) => todo!(),
+            }) => Type::ErrorUnion(error_union_type.clone()),
+            Value::Option(OptionValue { option_type, .. }) => Type::Option(option_type.clone()),
+            Value::Array(Array { array_type, .. }) => Type::Array(array_type.clone()),
+            Value::Function(Function {
+                function_type,
+                calling_convention: Some(calling_convention),
+                ..
+            }) => Type::RuntimeFunction(RuntimeFunctionType {
+                base_signature: function_type.clone(),
+                calling_convention: calling_convention.clone(),
+            }),
             Value::Function(Function { function_type, .. }) => {
                 Type::ComptimeFunction(function_type.clone())
             }
@@ -45,56 +72,781 @@ impl Value {
     }
 }
 
-#[derive(Debug, Clone)]
+impl Value {
+    /// The values of arrays, and of slices and pointers to arrays known at compile time, along
+    /// with whether they can't be assigned through the value
+    pub fn elements(&self) -> Option<(&Array, bool)> {
+        match self {
+            Value::Array(array) => Some((array, true)),
+            Value::Slice(Slice {
+                elements,
+                slice_type,
+            }) => Some((elements, slice_type.is_const)),
+            Value::Pointer(Pointer {
+                pointee: Some(pointee),
+                pointer_type,
+                ..
+            }) => match &**pointee {
+                Value::Array(array) => Some((array, pointer_type.is_const)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The `elements` of values which know how many they hold, unlike many-item pointers
+    pub fn counted_elements(&self) -> Option<(&Array, bool)> {
+        match self {
+            Value::Pointer(Pointer {
+                pointer_type: PointerType { is_many: true, .. },
+                ..
+            }) => None,
+            value => value.elements(),
+        }
+    }
+
+    /// The bytes storing values which can exist at runtime
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Float(Float { bytes, .. })
+            | Value::Int(Int { bytes, .. })
+            | Value::Container(Container { bytes, .. })
+            | Value::Pointer(Pointer { bytes, .. })
+            | Value::Error(Error { bytes, .. })
+            | Value::ErrorUnion(ErrorUnion { bytes, .. })
+            | Value::Option(OptionValue { bytes, .. })
+            | Value::Array(Array { bytes, .. }) => Some(bytes),
+            Value::Bool(value) => Some(if *value { &[1] } else { &[0] }),
+            Value::Void => Some(&[]),
+            Value::ComptimeInt(_)
+            | Value::ComptimeFloat(_)
+            | Value::Slice(_)
+            | Value::Function(_)
+            | Value::Type(_)
+            | Value::Undefined => None,
+        }
+    }
+
+    /// Reads a value of type `value_type` back from the bytes returned by `Value::bytes`
+    pub fn from_bytes(value_type: &Type, bytes: &[u8]) -> Option<Value> {
+        if value_type.byte_len()? != bytes.len() {
+            return None;
+        }
+        let bytes: Box<[u8]> = bytes.into();
+        Some(match value_type {
+            Type::Bool => match *bytes {
+                [0] => Value::Bool(false),
+                [1] => Value::Bool(true),
+                _ => return None,
+            },
+            Type::Container(container_type) => Value::Container(Container {
+                bytes,
+                container_type: container_type.clone(),
+            }),
+            Type::Int(int_type) => Value::Int(Int {
+                bytes,
+                int_type: int_type.clone(),
+            }),
+            Type::Float(float_type) => Value::Float(Float {
+                bytes,
+                float_type: float_type.clone(),
+            }),
+            Type::Option(option_type) => Value::Option(OptionValue {
+                bytes,
+                option_type: option_type.clone(),
+            }),
+            Type::Array(array_type) => Value::Array(Array {
+                bytes,
+                array_type: array_type.clone(),
+            }),
+            Type::Void => Value::Void,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Float {
     pub bytes: Box<[u8]>,
     pub float_type: FloatType,
 }
 
-#[derive(Debug, Clone)]
+impl Float {
+    /// Rounds `value` to the nearest value of `float_type`, which must be `f32` or `f64`
+    pub fn from_f64(value: f64, float_type: FloatType) -> Option<Self> {
+        let bytes: Box<[u8]> = match float_type {
+            FloatType::F32 => Box::new((value as f32).to_le_bytes()),
+            FloatType::F64 => Box::new(value.to_le_bytes()),
+            FloatType::F16 | FloatType::F80 | FloatType::F128 => return None,
+        };
+        Some(Float { bytes, float_type })
+    }
+
+    /// The value of `f32` and `f64` floats, which is exact in both cases
+    pub fn to_f64(&self) -> Option<f64> {
+        match self.float_type {
+            FloatType::F32 => Some(f32::from_le_bytes(self.bytes[..].try_into().ok()?) as f64),
+            FloatType::F64 => Some(f64::from_le_bytes(self.bytes[..].try_into().ok()?)),
+            FloatType::F16 | FloatType::F80 | FloatType::F128 => None,
+        }
+    }
+}
+
+/// The significant digits quotients of comptime floats are truncated to when they aren't exact,
+/// more than any float type holds
+pub const DIVISION_DIGITS: u32 = 40;
+
+/// The value of a `comptime_float`, an exact decimal like the ones float literals are written as
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComptimeFloat {
+    pub is_negative: bool,
+    /// Without trailing zeros in its digits, so that equal values have equal magnitudes
+    pub magnitude: FloatValue,
+}
+
+impl ComptimeFloat {
+    /// `mantissa * 10^exponent`
+    pub fn new(mantissa: BigInt, mut exponent: BigInt) -> Self {
+        let (sign, mut value) = mantissa.into_parts();
+        let ten = BigUint::from(10u8);
+        if value == BigUint::ZERO {
+            exponent = BigInt::ZERO;
+        }
+        while value != BigUint::ZERO && &value % &ten == BigUint::ZERO {
+            value /= &ten;
+            exponent += 1;
+        }
+        ComptimeFloat {
+            is_negative: sign == Sign::Minus,
+            magnitude: FloatValue { value, exponent },
+        }
+    }
+
+    pub fn from_int(value: &BigInt) -> Self {
+        Self::new(value.clone(), BigInt::ZERO)
+    }
+
+    pub fn mantissa(&self) -> BigInt {
+        let sign = match self.is_negative {
+            true => Sign::Minus,
+            false => Sign::Plus,
+        };
+        BigInt::from_biguint(sign, self.magnitude.value.clone())
+    }
+
+    pub fn exponent(&self) -> &BigInt {
+        &self.magnitude.exponent
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.value == BigUint::ZERO
+    }
+
+    /// The value as an integer, `None` if it has a fractional part
+    pub fn to_int(&self) -> Option<BigInt> {
+        let exponent = u32::try_from(self.exponent()).ok()?;
+        Some(self.mantissa() * BigInt::from(10u8).pow(exponent))
+    }
+
+    /// Written as `mantissa` `e` `exponent`, which is how float parsers read it
+    pub fn to_scientific(&self) -> String {
+        format!("{}e{}", self.mantissa(), self.exponent())
+    }
+
+    pub fn negated(&self) -> Self {
+        Self::new(-self.mantissa(), self.exponent().clone())
+    }
+
+    /// The mantissas of both values scaled to the lowest of their exponents, along with it.
+    /// `None` when the exponents are too far apart to scale.
+    fn aligned(&self, other: &Self) -> Option<(BigInt, BigInt, BigInt)> {
+        let exponent = self.exponent().min(other.exponent()).clone();
+        let scaled = |value: &Self| {
+            let shift = u32::try_from(value.exponent() - &exponent).ok()?;
+            Some(value.mantissa() * BigInt::from(10u8).pow(shift))
+        };
+        Some((scaled(self)?, scaled(other)?, exponent))
+    }
+
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        let (lhs, rhs, exponent) = self.aligned(other)?;
+        Some(Self::new(lhs + rhs, exponent))
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        self.checked_add(&other.negated())
+    }
+
+    pub fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(Self::new(
+            self.mantissa() * other.mantissa(),
+            self.exponent() + other.exponent(),
+        ))
+    }
+
+    /// The quotient, truncated to `DIVISION_DIGITS` significant digits when it isn't exact.
+    /// `None` when dividing by zero.
+    pub fn checked_div(&self, other: &Self) -> Option<Self> {
+        if other.is_zero() {
+            return None;
+        }
+        let digits = |value: &Self| value.magnitude.value.to_string().len() as u32;
+        let scale = (DIVISION_DIGITS + digits(other)).saturating_sub(digits(self));
+        let mantissa = self.mantissa() * BigInt::from(10u8).pow(scale) / other.mantissa();
+        Some(Self::new(
+            mantissa,
+            self.exponent() - other.exponent() - scale,
+        ))
+    }
+
+    /// The remainder of the division truncated to an integer, which has the sign of `self`.
+    /// `None` when dividing by zero.
+    pub fn checked_rem(&self, other: &Self) -> Option<Self> {
+        if other.is_zero() {
+            return None;
+        }
+        let (lhs, rhs, exponent) = self.aligned(other)?;
+        Some(Self::new(lhs % rhs, exponent))
+    }
+
+    pub fn compare(&self, other: &Self) -> Option<Ordering> {
+        let (lhs, rhs, _) = self.aligned(other)?;
+        Some(lhs.cmp(&rhs))
+    }
+}
+
+impl From<&FloatValue> for ComptimeFloat {
+    fn from(value: &FloatValue) -> Self {
+        Self::new(value.value.clone().into(), value.exponent.clone())
+    }
+}
+
+/// Written positionally, like `2900000000.0` or `0.015`, unless that takes too many zeros
+impl fmt::Display for ComptimeFloat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.is_negative { "-" } else { "" };
+        let digits = self.magnitude.value.to_string();
+        let Ok(exponent) = i64::try_from(self.exponent()) else {
+            return write!(f, "{sign}{digits}e{}", self.exponent());
+        };
+        // Where the decimal point is, counted in digits from the first one
+        let point = digits.len() as i64 + exponent;
+        match (exponent, point) {
+            (0.., ..=21) => write!(f, "{sign}{digits}{}.0", "0".repeat(exponent as usize)),
+            (..0, 1..) => {
+                let (int, fraction) = digits.split_at(point as usize);
+                write!(f, "{sign}{int}.{fraction}")
+            }
+            (..0, -5..=0) => write!(f, "{sign}0.{}{digits}", "0".repeat(-point as usize)),
+            _ => {
+                let (first, rest) = digits.split_at(1);
+                let rest = if rest.is_empty() { "0" } else { rest };
+                write!(f, "{sign}{first}.{rest}e{}", point - 1)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Int {
     pub bytes: Box<[u8]>,
     pub int_type: IntType,
 }
 
-#[derive(Debug, Clone)]
+impl Int {
+    /// Stores `value` in little-endian two's complement, if it fits in `int_type`
+    pub fn new(value: &BigInt, int_type: IntType) -> Option<Self> {
+        if !int_type.contains(value) {
+            return None;
+        }
+        let (mut bytes, fill) = match value.sign() {
+            Sign::Minus => (value.to_signed_bytes_le(), 0xFF),
+            _ => (value.to_bytes_le().1, 0),
+        };
+        // Extends the value to the size of the type, or removes the padding of its bytes as it
+        // fits
+        bytes.resize(Type::Int(int_type.clone()).byte_len()?, fill);
+        Some(Int {
+            bytes: bytes.into_boxed_slice(),
+            int_type,
+        })
+    }
+
+    pub fn value(&self) -> BigInt {
+        match self.int_type.signed {
+            true => BigInt::from_signed_bytes_le(&self.bytes),
+            false => BigInt::from_bytes_le(Sign::Plus, &self.bytes),
+        }
+    }
+}
+
+/// Containers store their instance fields at their offsets in `Layout::of`, or the value of their
+/// backing type for enums
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Container {
     pub bytes: Box<[u8]>,
     pub container_type: ContainerType,
 }
 
-#[derive(Debug, Clone)]
+impl Container {
+    fn layout(&self) -> Option<Layout> {
+        Layout::of(&Type::Container(self.container_type.clone())).ok()
+    }
+
+    /// Builds a struct from the values of its instance fields, in declaration order. The padding
+    /// between them is zeroed.
+    pub fn new(container_type: ContainerType, fields: &[Value]) -> Option<Self> {
+        let mut container = Container {
+            bytes: Box::new([]),
+            container_type,
+        };
+        let layout = container.layout()?;
+        if layout.fields.len() != fields.len() {
+            return None;
+        }
+        container.bytes = vec![0; usize::try_from(layout.size).ok()?].into_boxed_slice();
+        for (field, value) in layout.fields.iter().zip(fields) {
+            container.set(&field.name, value)?;
+        }
+        Some(container)
+    }
+
+    /// Where the instance field `name` is stored in the bytes of a struct, along with its type
+    fn field_position(&self, name: &str) -> Option<(usize, Type)> {
+        let field_type = self
+            .container_type
+            .instance_fields()
+            .find(|field| &*field.name == name)?
+            .field_type
+            .as_ref()
+            .and_then(normalize)?
+            .clone();
+        let offset = self.layout()?.field(name)?.offset;
+        Some((usize::try_from(offset).ok()?, field_type))
+    }
+
+    /// The value of the instance field `name` of a struct
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let (offset, field_type) = self.field_position(name)?;
+        let len = field_type.byte_len()?;
+        Value::from_bytes(&field_type, self.bytes.get(offset..offset + len)?)
+    }
+
+    /// Replaces the instance field `name` of a struct by `value`, which must have its type
+    pub fn set(&mut self, name: &str, value: &Value) -> Option<()> {
+        let (offset, field_type) = self.field_position(name)?;
+        let bytes = value.bytes()?;
+        if field_type.byte_len()? != bytes.len() {
+            return None;
+        }
+        self.bytes
+            .get_mut(offset..offset + bytes.len())?
+            .copy_from_slice(bytes);
+        Some(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Pointer {
     pub bytes: Box<[u8]>,
     pub pointer_type: PointerType,
+    /// The value pointed to when it is known at compile time, like the array of a string literal
+    pub pointee: Option<Box<Value>>,
+}
+
+impl Pointer {
+    /// A pointer to `pointee`, which only exists at compile time
+    pub fn comptime(pointee: Value, is_const: bool) -> Self {
+        Pointer {
+            bytes: Box::new([]),
+            pointer_type: PointerType {
+                pointed_value: Expression::Value(Box::new(Value::Type(pointee.get_type()))),
+                is_const,
+                is_many: false,
+                sentinel: None,
+            },
+            pointee: Some(Box::new(pointee)),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+/// Slices known at compile time hold the values they point to, which are copied with them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Slice {
+    /// An array of the values, ended by the sentinel of the slice
+    pub elements: Array,
+    pub slice_type: SliceType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Error {
     pub bytes: Box<[u8]>,
     pub error_set_type: ErrorSetType,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorUnion {
     pub bytes: Box<[u8]>,
     pub error_union_type: ErrorUnionType,
 }
 
-#[derive(Debug, Clone)]
+/// Optionals store their value followed by a byte which is 0 for `null`, as in `Layout::of`.
+/// Values which can't be all zero are stored alone, `null` being all zero.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptionValue {
     pub bytes: Box<[u8]>,
     pub option_type: OptionType,
 }
 
-#[derive(Debug, Clone)]
+impl OptionValue {
+    /// The layout of the optional and the length of the value it holds
+    fn layout(option_type: &OptionType) -> Option<(Layout, usize)> {
+        let layout = Layout::of(&Type::Option(option_type.clone())).ok()?;
+        Some((layout, option_type.some_type.byte_len()?))
+    }
+
+    pub fn null(option_type: OptionType) -> Option<Self> {
+        let (layout, _) = Self::layout(&option_type)?;
+        Some(OptionValue {
+            bytes: vec![0; usize::try_from(layout.size).ok()?].into_boxed_slice(),
+            option_type,
+        })
+    }
+
+    /// Wraps `value`, which must have the type of the values of `option_type`
+    pub fn some(value: &Value, option_type: OptionType) -> Option<Self> {
+        let value = value.bytes()?;
+        let (layout, len) = Self::layout(&option_type)?;
+        if len != value.len() {
+            return None;
+        }
+        let mut bytes = vec![0; usize::try_from(layout.size).ok()?];
+        bytes[..len].copy_from_slice(value);
+        if let Some(tag_offset) = layout.tag_offset {
+            *bytes.get_mut(usize::try_from(tag_offset).ok()?)? = 1;
+        }
+        Some(OptionValue {
+            bytes: bytes.into_boxed_slice(),
+            option_type,
+        })
+    }
+
+    /// The value held by the optional, `None` if it is `null`
+    pub fn unwrap(&self) -> Option<Value> {
+        let (layout, len) = Self::layout(&self.option_type)?;
+        let value = self.bytes.get(..len)?;
+        let is_null = match layout.tag_offset {
+            Some(tag_offset) => *self.bytes.get(usize::try_from(tag_offset).ok()?)? == 0,
+            None => value.iter().all(|byte| *byte == 0),
+        };
+        match is_null {
+            true => None,
+            false => Value::from_bytes(&self.option_type.some_type, value),
+        }
+    }
+}
+
+/// Arrays store the bytes of their values one after the other, followed by their sentinel
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Array {
     pub bytes: Box<[u8]>,
     pub array_type: ArrayType,
 }
 
-#[derive(Debug, Clone)]
+impl Array {
+    /// The array of the bytes of `text`, ended by a `0`, like string literals
+    pub fn string(text: &str) -> Self {
+        let u8_type = IntType {
+            signed: false,
+            bits: 8,
+        };
+        Array {
+            bytes: [text.as_bytes(), &[0]].concat().into_boxed_slice(),
+            array_type: ArrayType {
+                size: text.len(),
+                sentinel: Int::new(&BigInt::ZERO, u8_type.clone())
+                    .map(|zero| Box::new(Value::Int(zero))),
+                indexed_type: Box::new(Type::Int(u8_type)),
+            },
+        }
+    }
+
+    /// The value at `index`, which is the sentinel at `size`
+    pub fn get(&self, index: usize) -> Option<Value> {
+        if index >= self.array_type.stored_len() {
+            return None;
+        }
+        let len = self.array_type.indexed_type.byte_len()?;
+        let bytes = self.bytes.get(index * len..(index + 1) * len)?;
+        Value::from_bytes(&self.array_type.indexed_type, bytes)
+    }
+
+    /// The values from `start` to `end`, ended by `sentinel` which must be of the indexed type
+    pub fn sub_array(
+        &self,
+        start: usize,
+        end: usize,
+        sentinel: Option<Box<Value>>,
+    ) -> Option<Self> {
+        if start > end || end > self.array_type.size {
+            return None;
+        }
+        let len = self.array_type.indexed_type.byte_len()?;
+        let values = &self.bytes[start * len..end * len];
+        let sentinel_bytes = match &sentinel {
+            Some(sentinel) => sentinel.bytes()?,
+            None => &[],
+        };
+        Some(Array {
+            bytes: [values, sentinel_bytes].concat().into_boxed_slice(),
+            array_type: ArrayType {
+                size: end - start,
+                indexed_type: self.array_type.indexed_type.clone(),
+                sentinel,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Function {
+    /// The body, evaluated with the parameters bound to the arguments
     pub expression: Expression,
+    /// The names of the parameters, the types of which are in `function_type`
+    pub param_names: Box<[Box<str>]>,
     pub function_type: ComptimeFunctionType,
+    /// `None` for functions which can only be called at compile time, such as generic ones
+    pub calling_convention: Option<CallingConvetion>,
+    /// The file the function is declared in, the private declarations of which it can access
+    pub file: FileId,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builtin::{BuiltinFunction, EvalExecScope},
+        check::type_expression,
+        fixtures::{field, many_pointer, struct_type, u8_type, u8_value},
+        types::ErrorSetType,
+    };
+
+    #[test]
+    fn types_of_runtime_values() {
+        let error_set_type = ErrorSetType {
+            possible_errors: Box::new([1, 2]),
+        };
+        let option_type = OptionType {
+            some_type: Box::new(u8_type()),
+        };
+        let container_type = struct_type(Vec::new());
+        let types = [
+            Type::Int(IntType {
+                signed: true,
+                bits: 7,
+            }),
+            Type::Float(FloatType::F32),
+            Type::Container(container_type),
+            Type::Pointer(PointerType {
+                pointed_value: type_expression(u8_type()),
+                is_const: true,
+                is_many: false,
+                sentinel: None,
+            }),
+            many_pointer(u8_type(), Some(u8_value(0))),
+            Type::ErrorSet(error_set_type.clone()),
+            Type::ErrorUnion(ErrorUnionType {
+                ok: Box::new(u8_type()),
+                err: Some(error_set_type),
+            }),
+            Type::Option(option_type),
+            Type::Array(ArrayType {
+                size: 3,
+                indexed_type: Box::new(u8_type()),
+                sentinel: None,
+            }),
+        ];
+        for value_type in types {
+            let bytes: Box<[u8]> = Box::new([0]);
+            let value = match value_type.clone() {
+                Type::Int(int_type) => Value::Int(Int { bytes, int_type }),
+                Type::Float(float_type) => Value::Float(Float { bytes, float_type }),
+                Type::Container(container_type) => Value::Container(Container {
+                    bytes,
+                    container_type,
+                }),
+                Type::Pointer(pointer_type) => Value::Pointer(Pointer {
+                    bytes,
+                    pointer_type,
+                    pointee: None,
+                }),
+                Type::ErrorSet(error_set_type) => Value::Error(Error {
+                    bytes,
+                    error_set_type,
+                }),
+                Type::ErrorUnion(error_union_type) => Value::ErrorUnion(ErrorUnion {
+                    bytes,
+                    error_union_type,
+                }),
+                Type::Option(option_type) => Value::Option(OptionValue { bytes, option_type }),
+                Type::Array(array_type) => Value::Array(Array { bytes, array_type }),
+                _ => unreachable!(),
+            };
+            assert_eq!(value.get_type(), value_type);
+        }
+        assert_eq!(Value::Type(u8_type()).get_type(), Type::Type);
+        assert_eq!(Value::Bool(true).get_type(), Type::Bool);
+        assert_eq!(Value::Void.get_type(), Type::Void);
+        assert_eq!(Value::Undefined.get_type(), Type::Undefined);
+    }
+
+    #[test]
+    fn types_of_functions() {
+        let function_type = ComptimeFunctionType {
+            parameters: Expression::ExpressionList(Box::new([type_expression(u8_type())])),
+            result: type_expression(u8_type()),
+        };
+        let function = |calling_convention| {
+            Value::Function(Function {
+                expression: Expression::Name("x".into()),
+                param_names: Box::new(["x".into()]),
+                function_type: function_type.clone(),
+                calling_convention,
+                file: FileId::fresh(),
+            })
+        };
+        assert_eq!(
+            function(Some(CallingConvetion::Cera)).get_type(),
+            Type::RuntimeFunction(RuntimeFunctionType {
+                base_signature: function_type.clone(),
+                calling_convention: CallingConvetion::Cera,
+            }),
+        );
+        assert_eq!(
+            function(None).get_type(),
+            Type::ComptimeFunction(function_type.clone()),
+        );
+    }
+
+    #[test]
+    fn type_of_builtin() {
+        let scope = EvalExecScope::new();
+        let value = Int::new(
+            &BigInt::from(-3),
+            IntType {
+                signed: true,
+                bits: 16,
+            },
+        )
+        .unwrap();
+        let type_of = Expression::Builtin(
+            BuiltinFunction::TypeOf,
+            Box::new([Expression::Value(Box::new(Value::Int(value.clone())))]),
+        );
+        let Ok(Value::Type(int_type)) = type_of.eval(&scope) else {
+            panic!()
+        };
+        assert_eq!(Value::Int(value).get_type(), int_type);
+    }
+
+    #[test]
+    fn strings_and_sub_arrays() {
+        let byte = |value: Option<Value>| match value {
+            Some(Value::Int(int)) => int.value(),
+            value => panic!("{value:?}"),
+        };
+        let string = Array::string("cera");
+        assert_eq!(
+            Value::Pointer(Pointer::comptime(Value::Array(string.clone()), true)).get_type(),
+            Type::Pointer(PointerType {
+                pointed_value: type_expression(Type::Array(string.array_type.clone())),
+                is_const: true,
+                is_many: false,
+                sentinel: None,
+            }),
+        );
+        assert_eq!(string.array_type.size, 4);
+        assert_eq!(byte(string.get(1)), BigInt::from(b'e'));
+        // The sentinel follows the last value
+        assert_eq!(byte(string.get(4)), BigInt::ZERO);
+        assert!(string.get(5).is_none());
+
+        let middle = string.sub_array(1, 3, None).unwrap();
+        assert_eq!(&*middle.bytes, b"er");
+        assert!(middle.get(2).is_none());
+        let end = string
+            .sub_array(2, 4, string.array_type.sentinel.clone())
+            .unwrap();
+        assert_eq!(&*end.bytes, b"ra\0");
+        assert!(string.sub_array(3, 2, None).is_none());
+        assert!(string.sub_array(0, 5, None).is_none());
+    }
+
+    #[test]
+    fn bytes_follow_layouts() {
+        let int = |value: i64, bits| {
+            let int_type = IntType { signed: true, bits };
+            Value::Int(Int::new(&value.into(), int_type).unwrap())
+        };
+        let optional = |value_type: Type| OptionType {
+            some_type: Box::new(value_type),
+        };
+        let size_of = |value: &Value| {
+            let size_of = Expression::Builtin(
+                BuiltinFunction::SizeOf,
+                Box::new([type_expression(value.get_type())]),
+            );
+            match size_of.eval(&EvalExecScope::new()) {
+                Ok(Value::Int(size)) => usize::try_from(size.value()).unwrap(),
+                result => panic!("{result:?}"),
+            }
+        };
+
+        // The fields of structs are placed by decreasing alignment, as in their layout
+        let container_type = struct_type(vec![
+            field("a", int(0, 8).get_type()),
+            field("b", int(0, 32).get_type()),
+            field("c", int(0, 16).get_type()),
+        ]);
+        let fields = [int(-1, 8), int(-2, 32), int(-3, 16)];
+        let container = Container::new(container_type.clone(), &fields).unwrap();
+        assert_eq!(
+            &*container.bytes,
+            [0xFE, 0xFF, 0xFF, 0xFF, 0xFD, 0xFF, 0xFF, 0]
+        );
+        for (name, value) in ["a", "b", "c"].into_iter().zip(&fields) {
+            let field = container.get(name).unwrap();
+            assert_eq!(field.bytes(), value.bytes());
+        }
+
+        // Optionals are followed by their tag
+        let some = OptionValue::some(
+            &Value::Container(container.clone()),
+            optional(Type::Container(container_type)),
+        )
+        .unwrap();
+        assert_eq!(some.bytes.len(), 12);
+        assert_eq!(some.bytes[8], 1);
+        assert!(some.unwrap().is_some());
+        let null = OptionValue::null(optional(int(0, 16).get_type())).unwrap();
+        assert!(null.unwrap().is_none());
+        let small = OptionValue::some(&int(0, 16), optional(int(0, 16).get_type())).unwrap();
+        assert_eq!(&*small.bytes, [0, 0, 1, 0]);
+        assert!(small.unwrap().is_some());
+
+        let values = [
+            int(-5, 24),
+            int(1, 128),
+            Value::Container(container),
+            Value::Option(some),
+            Value::Option(null),
+            Value::Option(small),
+            Value::Array(Array::string("cera")),
+            Value::Bool(true),
+            Value::Void,
+        ];
+        for value in values {
+            assert_eq!(value.bytes().unwrap().len(), size_of(&value), "{value:?}");
+        }
+    }
 }
@@ -0,0 +1,218 @@
+//! Traversal of expressions. Each `visit_*` method defaults to the matching `walk_*` function,
+//! which visits the children of the expression.
+
+pub use cera_src_ast::visit::Order;
+
+/// Generates a visitor trait along with its `walk_*` functions, taking nodes by `&` or `&mut`
+macro_rules! make_visitor {
+    ($visitor:ident, $($mutability:ident)?) => {
+        use $crate::{expressions::Expression, types::AnonField, values::Value};
+
+        pub trait $visitor {
+            fn visit_expression(&mut self, expression: &$($mutability)? Expression) {
+                walk_expression(self, expression)
+            }
+            fn visit_value(&mut self, _value: &$($mutability)? Value) {}
+        }
+
+        pub fn walk_expression<V: $visitor + ?Sized>(
+            visitor: &mut V,
+            expression: &$($mutability)? Expression,
+        ) {
+            match expression {
+                Expression::Value(value) => visitor.visit_value(value),
+                Expression::Literal(_) | Expression::Name(_) | Expression::EnumLiteral(_) => {}
+                Expression::Builtin(_, expressions) | Expression::ExpressionList(expressions) => {
+                    for expression in &$($mutability)? **expressions {
+                        visitor.visit_expression(expression);
+                    }
+                }
+                Expression::Let(let_) => {
+                    if let Some(var_type) = &$($mutability)? let_.var_type {
+                        visitor.visit_expression(var_type);
+                    }
+                    visitor.visit_expression(&$($mutability)? let_.value);
+                }
+                Expression::Assign { target, value } => {
+                    visitor.visit_expression(target);
+                    visitor.visit_expression(value);
+                }
+                Expression::If {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    visitor.visit_expression(condition);
+                    visitor.visit_expression(then);
+                    visitor.visit_expression(otherwise);
+                }
+                Expression::Loop(expression)
+                | Expression::Labeled {
+                    body: expression, ..
+                }
+                | Expression::Break {
+                    value: expression, ..
+                }
+                | Expression::Return(expression)
+                | Expression::Field {
+                    base: expression, ..
+                }
+                | Expression::Spanned(_, expression) => visitor.visit_expression(expression),
+                Expression::Call { callee, args } => {
+                    visitor.visit_expression(callee);
+                    for arg in &$($mutability)? **args {
+                        visitor.visit_expression(arg);
+                    }
+                }
+                Expression::AnonInit(fields) => {
+                    for field in &$($mutability)? **fields {
+                        match field {
+                            AnonField::Named(_, value)
+                            | AnonField::Ident(_, value)
+                            | AnonField::Positional(value) => visitor.visit_expression(value),
+                        }
+                    }
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use make_visitor;
+
+make_visitor!(Visitor,);
+
+struct Fold<T, F> {
+    order: Order,
+    acc: Option<T>,
+    f: F,
+}
+
+impl<T, F: FnMut(T, &Expression) -> T> Fold<T, F> {
+    fn apply(&mut self, expression: &Expression) {
+        let acc = self.acc.take().unwrap();
+        self.acc = Some((self.f)(acc, expression));
+    }
+}
+
+impl<T, F: FnMut(T, &Expression) -> T> Visitor for Fold<T, F> {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if self.order == Order::Pre {
+            self.apply(expression);
+        }
+        walk_expression(self, expression);
+        if self.order == Order::Post {
+            self.apply(expression);
+        }
+    }
+}
+
+/// Folds the expression along with every expression nested in it
+pub fn fold<T>(
+    expression: &Expression,
+    order: Order,
+    init: T,
+    f: impl FnMut(T, &Expression) -> T,
+) -> T {
+    let mut fold = Fold {
+        order,
+        acc: Some(init),
+        f,
+    };
+    fold.visit_expression(expression);
+    fold.acc.unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{builtin::BuiltinFunction, expressions::Let, visit_mut::VisitorMut};
+
+    fn sample() -> Expression {
+        Expression::ExpressionList(Box::new([
+            Expression::Builtin(
+                BuiltinFunction::TypeOf,
+                Box::new([Expression::Value(Box::new(Value::Void))]),
+            ),
+            Expression::Value(Box::new(Value::Void)),
+        ]))
+    }
+
+    fn name(expression: &Expression) -> char {
+        match expression {
+            Expression::Value(_) => 'v',
+            Expression::Builtin(..) => 'b',
+            Expression::ExpressionList(_) => 'l',
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn core_forms() {
+        let name = |name: &str| Box::new(Expression::Name(name.into()));
+        let expression = Expression::If {
+            condition: name("a"),
+            then: Box::new(Expression::Call {
+                callee: name("f"),
+                args: Box::new([*name("b")]),
+            }),
+            otherwise: Box::new(Expression::Let(Box::new(Let {
+                name: "x".into(),
+                is_mutable: false,
+                var_type: Some(*name("T")),
+                value: Expression::Field {
+                    base: name("c"),
+                    name: "d".into(),
+                },
+            }))),
+        };
+        let names = fold(
+            &expression,
+            Order::Pre,
+            String::new(),
+            |mut acc, expression| {
+                if let Expression::Name(name) = expression {
+                    acc.push_str(name);
+                }
+                acc
+            },
+        );
+        assert_eq!(names, "afbTc");
+    }
+
+    #[test]
+    fn fold_order() {
+        let names = |order| {
+            fold(&sample(), order, String::new(), |mut acc, expression| {
+                acc.push(name(expression));
+                acc
+            })
+        };
+        assert_eq!(names(Order::Pre), "lbvv");
+        assert_eq!(names(Order::Post), "vbvl");
+    }
+
+    #[test]
+    fn mutable_visit() {
+        struct ReplaceVoid;
+
+        impl VisitorMut for ReplaceVoid {
+            fn visit_value(&mut self, value: &mut Value) {
+                *value = Value::Type(value.get_type());
+            }
+        }
+
+        let mut expression = sample();
+        ReplaceVoid.visit_expression(&mut expression);
+        let values = fold(
+            &expression,
+            Order::Pre,
+            0,
+            |count, expression| match expression {
+                Expression::Value(value) => count + matches!(**value, Value::Type(_)) as usize,
+                _ => count,
+            },
+        );
+        assert_eq!(values, 2);
+    }
+}
@@ -0,0 +1,3 @@
+//! Traversal of expressions which can modify them, the `&mut` version of [`crate::visit`].
+
+crate::visit::make_visitor!(VisitorMut, mut);
@@ -0,0 +1,485 @@
+//! Machine-readable forms of the data of each stage of the compiler: JSON for tools and fixtures,
+//! and compact S-expressions for golden files.
+
+use std::fmt::{self, Display, Write};
+
+use serde::{
+    ser::{self, Serialize},
+    Deserialize,
+};
+
+use crate::text_region::TextSpan;
+
+/// A value along with the text it comes from, like the tokens of the lexer
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, Deserialize)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: TextSpan,
+}
+
+impl<T> Spanned<T> {
+    /// Pairs values with their spans, as returned by [`crate::parsing::parse`]
+    pub fn zip(values: Vec<T>, spans: Vec<TextSpan>) -> Vec<Self> {
+        values
+            .into_iter()
+            .zip(spans)
+            .map(|(value, span)| Spanned { value, span })
+            .collect()
+    }
+}
+
+#[cfg(feature = "json")]
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string_pretty(value).expect("values of the compiler always serialize")
+}
+
+#[cfg(feature = "json")]
+pub fn from_json<T: serde::de::DeserializeOwned>(text: &str) -> Result<T, serde_json::Error> {
+    serde_json::from_str(text)
+}
+
+/// Serializes numbers which don't fit in JSON numbers, like big integers, as decimal strings
+pub mod decimal {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// The name [`TextSpan`]s are serialized with. It isn't a valid Rust name, so no other struct
+/// is taken for a span.
+pub const SPAN_NAME: &str = "$cera_parse::TextSpan";
+
+/// Prints a value as an S-expression: structs and enum variants with data are written
+/// `(Name field: value)`, sequences `[a b]`, maps `{key value}` and spans `idx..end`
+pub fn to_sexpr<T: Serialize + ?Sized>(value: &T) -> Result<String, SexprError> {
+    let mut serializer = SexprSerializer {
+        output: String::new(),
+    };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.output)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SexprError(String);
+
+impl Display for SexprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SexprError {}
+
+impl ser::Error for SexprError {
+    fn custom<T: Display>(msg: T) -> Self {
+        SexprError(msg.to_string())
+    }
+}
+
+struct SexprSerializer {
+    output: String,
+}
+
+impl SexprSerializer {
+    fn open(&mut self, open: &str, name: &'static str, close: &'static str) -> Compound<'_> {
+        self.output.push_str(open);
+        self.output.push_str(name);
+        Compound {
+            serializer: self,
+            is_first: name.is_empty(),
+            close,
+        }
+    }
+
+    fn display(&mut self, value: impl Display) -> Result<(), SexprError> {
+        write!(self.output, "{value}").map_err(ser::Error::custom)
+    }
+}
+
+/// A list being written, which ends with `close`
+struct Compound<'a> {
+    serializer: &'a mut SexprSerializer,
+    is_first: bool,
+    close: &'static str,
+}
+
+impl Compound<'_> {
+    fn element<T: Serialize + ?Sized>(
+        &mut self,
+        label: Option<&str>,
+        value: &T,
+    ) -> Result<(), SexprError> {
+        if !self.is_first {
+            self.serializer.output.push(' ');
+        }
+        self.is_first = false;
+        if let Some(label) = label {
+            self.serializer.output.push_str(label);
+            self.serializer.output.push_str(": ");
+        }
+        value.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> Result<(), SexprError> {
+        self.serializer.output.push_str(self.close);
+        Ok(())
+    }
+}
+
+/// A [`TextSpan`] being written, which only needs its two numbers
+struct SpanCompound<'a> {
+    output: &'a mut String,
+    len: Option<usize>,
+    idx: Option<usize>,
+}
+
+macro_rules! display {
+    ($($method:ident: $type:ty),* $(,)?) => {
+        $(fn $method(self, value: $type) -> Result<(), SexprError> {
+            self.display(value)
+        })*
+    };
+}
+
+impl<'a> ser::Serializer for &'a mut SexprSerializer {
+    type Ok = ();
+    type Error = SexprError;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = StructCompound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    display!(
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+    );
+
+    fn serialize_char(self, value: char) -> Result<(), SexprError> {
+        self.display(format_args!("{value:?}"))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), SexprError> {
+        self.display(format_args!("{value:?}"))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), SexprError> {
+        ser::Serializer::collect_seq(self, value)
+    }
+
+    fn serialize_none(self) -> Result<(), SexprError> {
+        self.output.push_str("nil");
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SexprError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SexprError> {
+        self.output.push_str("()");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(), SexprError> {
+        self.output.push_str(name);
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), SexprError> {
+        self.output.push_str(variant);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), SexprError> {
+        let mut compound = self.open("(", name, ")");
+        compound.element(None, value)?;
+        compound.end()
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SexprError> {
+        let mut compound = self.open("(", variant, ")");
+        compound.element(None, value)?;
+        compound.end()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, SexprError> {
+        Ok(self.open("[", "", "]"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>, SexprError> {
+        Ok(self.open("[", "", "]"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, SexprError> {
+        Ok(self.open("(", name, ")"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, SexprError> {
+        Ok(self.open("(", variant, ")"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, SexprError> {
+        Ok(self.open("{", "", "}"))
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<StructCompound<'a>, SexprError> {
+        Ok(match name {
+            SPAN_NAME => StructCompound::Span(SpanCompound {
+                output: &mut self.output,
+                len: None,
+                idx: None,
+            }),
+            _ => StructCompound::Struct(self.open("(", name, ")")),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, SexprError> {
+        Ok(self.open("(", variant, ")"))
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = SexprError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SexprError> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<(), SexprError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = SexprError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SexprError> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<(), SexprError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = SexprError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SexprError> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<(), SexprError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = SexprError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SexprError> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<(), SexprError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = SexprError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SexprError> {
+        self.element(None, key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SexprError> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<(), SexprError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = SexprError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SexprError> {
+        self.element(Some(key), value)
+    }
+
+    fn end(self) -> Result<(), SexprError> {
+        Compound::end(self)
+    }
+}
+
+enum StructCompound<'a> {
+    Struct(Compound<'a>),
+    Span(SpanCompound<'a>),
+}
+
+impl ser::SerializeStruct for StructCompound<'_> {
+    type Ok = ();
+    type Error = SexprError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SexprError> {
+        match self {
+            StructCompound::Struct(compound) => compound.element(Some(key), value),
+            StructCompound::Span(span) => {
+                let number = to_sexpr(value)?.parse().map_err(ser::Error::custom)?;
+                match key {
+                    "len" => span.len = Some(number),
+                    _ => span.idx = Some(number),
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn end(self) -> Result<(), SexprError> {
+        match self {
+            StructCompound::Struct(compound) => compound.end(),
+            StructCompound::Span(SpanCompound {
+                output,
+                len: Some(len),
+                idx: Some(idx),
+            }) => write!(output, "{idx}..{}", idx + len).map_err(ser::Error::custom),
+            StructCompound::Span(_) => Err(ser::Error::custom("incomplete span")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parsing::parse, src_token::Token};
+
+    #[test]
+    fn tokens() {
+        let (tokens, spans) = parse::<Token>("a = 1.5;").unwrap();
+        let tokens = Spanned::zip(tokens, spans);
+        assert_eq!(
+            to_sexpr(&tokens).unwrap(),
+            "[(Spanned value: (Identifier (Identifier name: \"a\")) span: 0..1) \
+             (Spanned value: (SpecialChar Equal) span: 2..3) \
+             (Spanned value: (Literal (Float (FloatValue value: \"15\" exponent: \"-1\"))) span: 4..7) \
+             (Spanned value: (SpecialChar SemiColon) span: 7..8)]"
+        );
+    }
+
+    #[test]
+    fn only_spans_are_ranges() {
+        #[derive(serde::Serialize)]
+        struct TextSpan {
+            len: usize,
+        }
+        assert_eq!(to_sexpr(&TextSpan { len: 1 }).unwrap(), "(TextSpan len: 1)");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        let (tokens, spans) = parse::<Token>("a = 1.5;").unwrap();
+        let tokens = Spanned::zip(tokens, spans);
+        let json = to_json(&tokens);
+        assert_eq!(from_json::<Vec<Spanned<Token>>>(&json).unwrap(), tokens);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn big_literals() {
+        use num_bigint::BigUint;
+
+        use crate::{literals::FloatValue, src_token::Literal};
+
+        let literal = Literal::Float(FloatValue {
+            value: BigUint::from(u128::MAX) * 3u8,
+            exponent: (-5).into(),
+        });
+        let json = to_json(&literal);
+        assert!(json.contains("\"1020847100762815390390123822295304634365\""));
+        assert_eq!(from_json::<Literal>(&json).unwrap(), literal);
+    }
+}
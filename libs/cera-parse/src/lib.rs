@@ -1,3 +1,4 @@
+pub mod dump;
 pub mod literals;
 pub mod parsing;
 pub mod src_token;
@@ -11,7 +12,11 @@ mod tests {
 
     use num_bigint::BigUint;
 
-    use crate::{literals::FloatValue, parsing::Parsable, src_token::Literal};
+    use crate::{
+        literals::FloatValue,
+        parsing::{parse, Parsable},
+        src_token::{Literal, LosslessToken, Token, Trivia},
+    };
 
     use super::*;
 
@@ -47,4 +52,44 @@ mod tests {
         test_parsable::<Literal>("\"test\"", (Literal::String("test".into()), 6));
         test_parsable::<Literal>("\"test\\\"\"", (Literal::String("test\"".into()), 8));
     }
+
+    #[test]
+    fn lossless_tokens() {
+        let text = "a /* b */ + // c\n\t1.5 /* d";
+        let (tokens, spans) = parse::<LosslessToken>(text).unwrap();
+        let trivia: Vec<_> = tokens
+            .iter()
+            .zip(&spans)
+            .filter_map(|(token, span)| match token {
+                LosslessToken::Trivia(trivia) => Some((*trivia, &text[span.idx..span.end()])),
+                LosslessToken::Token(_) => None,
+            })
+            .collect();
+        assert_eq!(
+            trivia,
+            [
+                (Trivia::Whitespace, " "),
+                (Trivia::BlockComment, "/* b */"),
+                (Trivia::Whitespace, " "),
+                (Trivia::Whitespace, " "),
+                (Trivia::LineComment, "// c"),
+                (Trivia::Whitespace, "\n\t"),
+                (Trivia::Whitespace, " "),
+                (Trivia::BlockComment, "/* d"),
+            ]
+        );
+        let rebuilt: String = spans
+            .iter()
+            .map(|span| &text[span.idx..span.end()])
+            .collect();
+        assert_eq!(rebuilt, text);
+        let tokens: Vec<_> = tokens
+            .into_iter()
+            .filter_map(|token| match token {
+                LosslessToken::Token(token) => Some(token),
+                LosslessToken::Trivia(_) => None,
+            })
+            .collect();
+        assert_eq!(tokens, parse::<Token>(text).unwrap().0);
+    }
 }
@@ -1,6 +1,7 @@
 use std::ops::{AddAssign, MulAssign};
 
 use num_bigint::{BigInt, BigUint};
+use serde::{Deserialize, Serialize};
 
 use crate::text_region::{TextPosition, TextSpan};
 
@@ -23,6 +24,32 @@ impl StringEscapeError {
     }
 }
 
+/// The char escaped by the `count` hexadecimal digits following the `\x` or `\u` at `idx`, when
+/// `to_char` accepts their value. The span of an invalid escape covers at most `count` chars
+/// after the escape.
+fn hex_escape(
+    str: &str,
+    idx: usize,
+    count: usize,
+    to_char: impl Fn(u32) -> Option<char>,
+) -> Result<char, StringEscapeError> {
+    let end = str[(idx + 2)..]
+        .char_indices()
+        .nth(count)
+        .map(|(len, _)| idx + 2 + len)
+        .unwrap_or(str.len());
+    let invalid = StringEscapeError::InvalidEscapedChar(TextSpan {
+        len: end - idx,
+        idx,
+    });
+    match str.get((idx + 2)..(idx + 2 + count)) {
+        Some(digits) if digits.chars().all(|char| char.is_ascii_hexdigit()) => {
+            to_char(u32::from_str_radix(digits, 16).unwrap()).ok_or(invalid)
+        }
+        _ => Err(invalid),
+    }
+}
+
 pub fn escape_str(str: &str) -> Result<Box<str>, StringEscapeError> {
     let mut output = String::new();
     let mut iter = str.char_indices();
@@ -37,25 +64,10 @@ pub fn escape_str(str: &str) -> Result<Box<str>, StringEscapeError> {
 
                 match next_char {
                     'x' => {
-                        if let Some(str) = str.get((idx + 2)..(idx + 4)) {
-                            let val = if let Ok(val) = parse_hexadecimal(str) {
-                                for _ in 0..2 {
-                                    let _ = iter.next();
-                                }
-                                val
-                            } else {
-                                return Err(StringEscapeError::InvalidEscapedChar(TextSpan {
-                                    len: 4,
-                                    idx,
-                                }));
-                            };
-                            if val > 0x7Fu8.into() {
-                                return Err(StringEscapeError::InvalidEscapedChar(TextSpan {
-                                    len: 4,
-                                    idx,
-                                }));
-                            }
-                            output.push(char::from_u32(val.try_into().unwrap()).unwrap())
+                        let ascii = |val| char::from_u32(val).filter(char::is_ascii);
+                        output.push(hex_escape(str, idx, 2, ascii)?);
+                        for _ in 0..2 {
+                            let _ = iter.next();
                         }
                     }
                     'n' => output.push('\n'),
@@ -64,33 +76,16 @@ pub fn escape_str(str: &str) -> Result<Box<str>, StringEscapeError> {
                     '\\' => output.push('\\'),
                     '0' => output.push('\0'),
                     'u' => {
-                        if let Some(str) = str.get((idx + 2)..(idx + 8)) {
-                            let val = if let Ok(val) = parse_hexadecimal(str) {
-                                for _ in 0..6 {
-                                    let _ = iter.next();
-                                }
-                                val
-                            } else {
-                                return Err(StringEscapeError::InvalidEscapedChar(TextSpan {
-                                    len: 8,
-                                    idx,
-                                }));
-                            };
-                            if let Some(char) = char::from_u32(val.try_into().unwrap()) {
-                                output.push(char);
-                            } else {
-                                return Err(StringEscapeError::InvalidEscapedChar(TextSpan {
-                                    len: 8,
-                                    idx,
-                                }));
-                            }
+                        output.push(hex_escape(str, idx, 6, char::from_u32)?);
+                        for _ in 0..6 {
+                            let _ = iter.next();
                         }
                     }
                     '\'' => output.push('\''),
                     '\"' => output.push('\"'),
                     _ => {
                         return Err(StringEscapeError::InvalidEscapedChar(TextSpan {
-                            len: next_idx - idx,
+                            len: next_idx + next_char.len_utf8() - idx,
                             idx,
                         }))
                     }
@@ -102,9 +97,11 @@ pub fn escape_str(str: &str) -> Result<Box<str>, StringEscapeError> {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FloatValue {
+    #[serde(with = "crate::dump::decimal")]
     pub value: BigUint,
+    #[serde(with = "crate::dump::decimal")]
     pub exponent: BigInt,
 }
 
@@ -138,58 +135,43 @@ pub fn parse_float(str: &str) -> Result<FloatValue, FloatParseError> {
             len: str.len(),
             idx: 0,
         }))?;
-    let e_idx = str
-        .get((dot_idx + 1)..)
-        .ok_or(IntParseError::ZeroLength(TextPosition { idx: dot_idx }))?
+    let e_idx = str[(dot_idx + 1)..]
         .find(|char| (char == 'e') | (char == 'E'))
         .map(|idx| idx + dot_idx + 1);
-    let mut value = parse_decimal(str.get(0..dot_idx).unwrap())?;
-    let decimal_str = str
-        .get((dot_idx + 1)..(e_idx.unwrap_or(str.len())))
-        .unwrap();
-    value.mul_assign(BigUint::from(10u8).pow(decimal_str.len() as u32));
-    value.add_assign(parse_decimal(decimal_str)?);
+    let offset_err = |offset: usize| {
+        move |mut err: IntParseError| {
+            err.offset(offset);
+            err
+        }
+    };
 
-    let exponent = -(BigInt::from(
-        decimal_str.len()
-            - decimal_str
-                .chars()
-                .fold(0, |acc, char| if char == '_' { acc + 1 } else { acc }),
-    )) + BigInt::from(match e_idx {
-        Some(idx) => {
-            let mut exp_str = str
-                .get((idx + 1)..)
-                .ok_or(IntParseError::ZeroLength(TextPosition { idx: str.len() }))?;
-            let mut exp = match exp_str
-                .chars()
-                .next()
-                .ok_or(IntParseError::ZeroLength(TextPosition { idx: str.len() }))?
-            {
-                '+' => {
-                    exp_str = exp_str
-                        .get(1..)
-                        .ok_or(IntParseError::ZeroLength(TextPosition { idx: str.len() }))?;
-                    1
-                }
-                '-' => {
-                    exp_str = exp_str
-                        .get(1..)
-                        .ok_or(IntParseError::ZeroLength(TextPosition { idx: str.len() }))?;
-                    -1
-                }
-                _ => 1,
-            };
-            exp.mul_assign(isize::try_from(parse_decimal(exp_str)?).map_err(|_| {
-                FloatParseError::ExponentTooLarge(TextSpan {
-                    len: exp_str.len(),
-                    idx: idx + 1,
-                })
-            })?);
+    let mut value = parse_decimal(&str[0..dot_idx])?;
+    let decimal_str = &str[(dot_idx + 1)..(e_idx.unwrap_or(str.len()))];
+    // Underscores are separators, only the digits themselves shift the decimal point
+    let decimal_digits = decimal_str.chars().filter(|char| *char != '_').count();
+    value.mul_assign(BigUint::from(10u8).pow(decimal_digits as u32));
+    value.add_assign(parse_decimal(decimal_str).map_err(offset_err(dot_idx + 1))?);
 
-            exp
-        }
-        None => 0,
-    });
+    let exponent = -BigInt::from(decimal_digits)
+        + BigInt::from(match e_idx {
+            Some(idx) => {
+                let (sign, exp_idx) = match str.get((idx + 1)..(idx + 2)) {
+                    Some("+") => (1, idx + 2),
+                    Some("-") => (-1, idx + 2),
+                    _ => (1, idx + 1),
+                };
+                let exp_str = &str[exp_idx..];
+                let exp = isize::try_from(parse_decimal(exp_str).map_err(offset_err(exp_idx))?)
+                    .map_err(|_| {
+                        FloatParseError::ExponentTooLarge(TextSpan {
+                            len: exp_str.len(),
+                            idx: exp_idx,
+                        })
+                    })?;
+                sign * exp
+            }
+            None => 0,
+        });
 
     Ok(FloatValue { value, exponent })
 }
@@ -251,10 +233,10 @@ pub fn parse_binary(str: &str) -> Result<BigUint, IntParseError> {
     if str.is_empty() {
         return Err(IntParseError::ZeroLength(TextPosition { idx: 0 }));
     }
-    if str.chars().next() == Some('_') {
+    if str.starts_with('_') {
         return Err(IntParseError::InvalidChar(TextPosition { idx: 0 }));
     }
-    if str.chars().next_back() == Some('_') {
+    if str.ends_with('_') {
         return Err(IntParseError::InvalidChar(TextPosition {
             idx: str.len() - 1,
         }));
@@ -271,17 +253,17 @@ pub fn parse_binary(str: &str) -> Result<BigUint, IntParseError> {
             _ => return Err(IntParseError::InvalidChar(TextPosition { idx })),
         }
     }
-    return Ok(output);
+    Ok(output)
 }
 
 pub fn parse_octal(str: &str) -> Result<BigUint, IntParseError> {
     if str.is_empty() {
         return Err(IntParseError::ZeroLength(TextPosition { idx: 0 }));
     }
-    if str.chars().next() == Some('_') {
+    if str.starts_with('_') {
         return Err(IntParseError::InvalidChar(TextPosition { idx: 0 }));
     }
-    if str.chars().next_back() == Some('_') {
+    if str.ends_with('_') {
         return Err(IntParseError::InvalidChar(TextPosition {
             idx: str.len() - 1,
         }));
@@ -304,17 +286,17 @@ pub fn parse_octal(str: &str) -> Result<BigUint, IntParseError> {
             _ => return Err(IntParseError::InvalidChar(TextPosition { idx })),
         }
     }
-    return Ok(output);
+    Ok(output)
 }
 
 pub fn parse_decimal(str: &str) -> Result<BigUint, IntParseError> {
     if str.is_empty() {
         return Err(IntParseError::ZeroLength(TextPosition { idx: 0 }));
     }
-    if str.chars().next() == Some('_') {
+    if str.starts_with('_') {
         return Err(IntParseError::InvalidChar(TextPosition { idx: 0 }));
     }
-    if str.chars().next_back() == Some('_') {
+    if str.ends_with('_') {
         return Err(IntParseError::InvalidChar(TextPosition {
             idx: str.len() - 1,
         }));
@@ -339,17 +321,17 @@ pub fn parse_decimal(str: &str) -> Result<BigUint, IntParseError> {
             _ => return Err(IntParseError::InvalidChar(TextPosition { idx })),
         }
     }
-    return Ok(output);
+    Ok(output)
 }
 
 pub fn parse_hexadecimal(str: &str) -> Result<BigUint, IntParseError> {
     if str.is_empty() {
         return Err(IntParseError::ZeroLength(TextPosition { idx: 0 }));
     }
-    if str.chars().next() == Some('_') {
+    if str.starts_with('_') {
         return Err(IntParseError::InvalidChar(TextPosition { idx: 0 }));
     }
-    if str.chars().next_back() == Some('_') {
+    if str.ends_with('_') {
         return Err(IntParseError::InvalidChar(TextPosition {
             idx: str.len() - 1,
         }));
@@ -380,5 +362,5 @@ pub fn parse_hexadecimal(str: &str) -> Result<BigUint, IntParseError> {
             _ => return Err(IntParseError::InvalidChar(TextPosition { idx })),
         }
     }
-    return Ok(output);
+    Ok(output)
 }
@@ -1,4 +1,4 @@
-use crate::text_region::{Text, TextPosition, TextSpan};
+use crate::text_region::{TextPosition, TextSpan};
 
 pub trait PositionnedErr {
     fn offset(&mut self, offset: usize);
@@ -11,7 +11,7 @@ pub trait Parsable: Sized {
     fn try_parse(str: &str) -> Result<(Option<Self>, usize), Self::Error>;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParsingError<T> {
     ParsableError(T),
     OutOfCharBoundError(TextPosition),
@@ -20,7 +20,10 @@ pub enum ParsingError<T> {
     ZeroLenSkip(TextPosition),
 }
 
-pub fn parse<T: Parsable>(text: &str) -> Result<(Vec<T>, Vec<TextSpan>), ParsingError<T::Error>> {
+/// The parsed elements, along with the span each one was parsed from
+pub type Parsed<T> = (Vec<T>, Vec<TextSpan>);
+
+pub fn parse<T: Parsable>(text: &str) -> Result<Parsed<T>, ParsingError<T::Error>> {
     let mut output = (Vec::new(), Vec::new());
     let mut curr_start = 0;
     let mut curr_str = text;
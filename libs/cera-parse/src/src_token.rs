@@ -1,6 +1,7 @@
 use std::iter::once;
 
 use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     literals::{
@@ -11,7 +12,7 @@ use crate::{
     text_region::TextPosition,
 };
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Token {
     Identifier(Identifier),
     Literal(Literal),
@@ -66,7 +67,7 @@ impl Parsable for Token {
             return Ok((Some(Self::SpecialChar(special_char)), len));
         }
 
-        return Err(TokenParseError::NoMatch(TextPosition { idx: 0 }));
+        Err(TokenParseError::NoMatch(TextPosition { idx: 0 }))
     }
 }
 
@@ -122,11 +123,69 @@ impl Token {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Whitespace or a comment, which `Token` skips
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Trivia {
+    Whitespace,
+    /// `// ...`, without the line break ending it
+    LineComment,
+    /// `/* ... */`, which goes to the end of the text if it isn't closed
+    BlockComment,
+}
+
+impl Parsable for Trivia {
+    type Error = TokenParseError;
+
+    fn try_parse(str: &str) -> Result<(Option<Self>, usize), Self::Error> {
+        let whitespace_len = str.len()
+            - str
+                .trim_start_matches(|char: char| char.is_ascii_whitespace())
+                .len();
+        if whitespace_len != 0 {
+            return Ok((Some(Self::Whitespace), whitespace_len));
+        }
+        if str.starts_with("//") {
+            let len = str.find('\n').unwrap_or(str.len());
+            return Ok((Some(Self::LineComment), len));
+        }
+        if let Some(comment) = str.strip_prefix("/*") {
+            let len = comment.find("*/").map_or(str.len(), |end| end + 4);
+            return Ok((Some(Self::BlockComment), len));
+        }
+        Ok((None, 0))
+    }
+}
+
+/// A token or the trivia around it, so that the text can be rebuilt from them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LosslessToken {
+    Token(Token),
+    Trivia(Trivia),
+}
+
+impl Parsable for LosslessToken {
+    type Error = TokenParseError;
+
+    fn try_parse(str: &str) -> Result<(Option<Self>, usize), Self::Error> {
+        if let (Some(trivia), len) = Trivia::try_parse(str)? {
+            return Ok((Some(Self::Trivia(trivia)), len));
+        }
+        let (token, len) = Token::try_parse(str)?;
+        Ok((token.map(Self::Token), len))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Identifier {
     name: Box<str>,
 }
 
+impl Identifier {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 impl Parsable for Identifier {
     type Error = TokenParseError;
 
@@ -136,7 +195,7 @@ impl Parsable for Identifier {
         if !((char.is_ascii_alphabetic()) | (char == '_')) {
             return Ok((None, 0));
         }
-        while let Some((idx, char)) = iter.next() {
+        for (idx, char) in iter {
             if !((char.is_ascii_alphanumeric()) | (char == '_')) {
                 return Ok((
                     Some(Identifier {
@@ -150,10 +209,10 @@ impl Parsable for Identifier {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Literal {
     String(Box<str>),
-    Int(BigUint),
+    Int(#[serde(with = "crate::dump::decimal")] BigUint),
     Float(FloatValue),
 }
 
@@ -166,7 +225,7 @@ impl Parsable for Literal {
             '\"' => {
                 let mut is_next_escaped = false;
 
-                while let Some((idx, char)) = iter.next() {
+                for (idx, char) in iter {
                     if is_next_escaped {
                         is_next_escaped = false;
                         continue;
@@ -186,60 +245,55 @@ impl Parsable for Literal {
                 Err(TokenParseError::UnexpectedEOF)
             }
             '0'..='9' => {
-                let parse_float_cloj = |idx_float: usize| {
-                    return match parse_float(str.get(0..idx_float).unwrap()) {
-                        Ok(int) => Ok((Some(Self::Float(int)), idx_float)),
-                        Err(err) => Err(TokenParseError::FloatParseError(err)),
-                    };
-                };
-                let parse_int_cloj = |idx: usize| match parse_int(str.get(0..idx).unwrap()) {
-                    Ok(int) => Ok((Some(Self::Int(int)), idx)),
-                    Err(err) => Err(TokenParseError::IntParseError(err)),
+                let bytes = str.as_bytes();
+                let skip_digits = |start: usize, is_digit: fn(&u8) -> bool| {
+                    start
+                        + bytes[start..]
+                            .iter()
+                            .take_while(|byte| is_digit(byte) | (**byte == b'_'))
+                            .count()
                 };
-                while let Some((idx, char)) = iter.next() {
-                    match char {
-                        '0'..='9' | '_' => {
-                            continue;
-                        }
-                        '.' => {
-                            let mut is_prev_e = false;
-                            let mut is_float = false;
-                            while let Some((idx_float, char)) = iter.next() {
-                                match char {
-                                    'e' => {
-                                        is_prev_e = true;
-                                        continue;
-                                    }
-                                    '0'..='9' => {
-                                        is_float = true;
-                                    }
-                                    '_' => {}
-                                    '+' | '-' => {
-                                        if !is_prev_e {
-                                            return parse_float_cloj(idx_float);
-                                        }
-                                    }
-                                    _ => {
-                                        if !is_float {
-                                            return parse_int_cloj(idx);
-                                        }
-                                        return parse_float_cloj(idx_float);
-                                    }
-                                }
-                                is_prev_e = false;
-                            }
-                        }
-                        _ => return parse_int_cloj(idx),
+
+                if let (b'0', Some(b'b' | b'o' | b'd' | b'x')) = (bytes[0], bytes.get(1)) {
+                    // Invalid digits are part of the literal so that they get reported
+                    let len = skip_digits(2, u8::is_ascii_alphanumeric);
+                    return match parse_int(&str[0..len]) {
+                        Ok(int) => Ok((Some(Self::Int(int)), len)),
+                        Err(err) => Err(TokenParseError::IntParseError(err)),
+                    };
+                }
+
+                let int_len = skip_digits(0, u8::is_ascii_digit);
+                // A dot that isn't followed by a digit is a field access or a range
+                if !matches!(
+                    (bytes.get(int_len), bytes.get(int_len + 1)),
+                    (Some(b'.'), Some(b'0'..=b'9'))
+                ) {
+                    return match parse_int(&str[0..int_len]) {
+                        Ok(int) => Ok((Some(Self::Int(int)), int_len)),
+                        Err(err) => Err(TokenParseError::IntParseError(err)),
+                    };
+                }
+
+                let mut len = skip_digits(int_len + 1, u8::is_ascii_digit);
+                if let Some(b'e' | b'E') = bytes.get(len) {
+                    len += 1;
+                    if let Some(b'+' | b'-') = bytes.get(len) {
+                        len += 1;
                     }
+                    len = skip_digits(len, u8::is_ascii_digit);
+                }
+                match parse_float(&str[0..len]) {
+                    Ok(float) => Ok((Some(Self::Float(float)), len)),
+                    Err(err) => Err(TokenParseError::FloatParseError(err)),
                 }
-                Err(TokenParseError::UnexpectedEOF)
             }
             _ => Ok((None, 0)),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SpecialChar {
     Dot,
     SemiColon,
@@ -261,6 +315,11 @@ pub enum SpecialChar {
     AtSign,
     Bang,
     QuestionMark,
+    Ampersand,
+    Pipe,
+    Caret,
+    Percent,
+    Tilde,
 }
 
 impl Parsable for SpecialChar {
@@ -286,10 +345,15 @@ impl Parsable for SpecialChar {
                     '{' => Self::OpenBrace,
                     '}' => Self::CloseBrace,
                     '[' => Self::OpenBracket,
-                    ']' => Self::CloseBrace,
+                    ']' => Self::CloseBracket,
                     '@' => Self::AtSign,
                     '!' => Self::Bang,
                     '?' => Self::QuestionMark,
+                    '&' => Self::Ampersand,
+                    '|' => Self::Pipe,
+                    '^' => Self::Caret,
+                    '%' => Self::Percent,
+                    '~' => Self::Tilde,
                     _ => return Ok((None, 0)),
                 }),
                 1,
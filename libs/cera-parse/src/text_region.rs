@@ -1,15 +1,44 @@
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+
+use crate::dump::SPAN_NAME;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub struct TextPosition {
     /// Points to the bit idx of the first char this position represents
     pub idx: usize,
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Deserialize)]
 pub struct TextSpan {
     pub len: usize,
     pub idx: usize,
 }
 
+/// Serialized as a struct named [`SPAN_NAME`], so that S-expressions write spans as `idx..end`
+impl Serialize for TextSpan {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut span = serializer.serialize_struct(SPAN_NAME, 2)?;
+        span.serialize_field("len", &self.len)?;
+        span.serialize_field("idx", &self.idx)?;
+        span.end()
+    }
+}
+
+impl TextSpan {
+    /// Returns the idx right after the last char of the span
+    pub fn end(&self) -> usize {
+        self.idx + self.len
+    }
+    /// Returns the smallest span containing both spans
+    pub fn join(&self, other: &TextSpan) -> TextSpan {
+        let idx = self.idx.min(other.idx);
+        TextSpan {
+            len: self.end().max(other.end()) - idx,
+            idx,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Text<'t> {
     inner: &'t str,
@@ -36,6 +65,22 @@ impl<'t> Text<'t> {
             Err(val) => val,
         }
     }
+    /// Returns the index of the first char of the given line, 0-indexed
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        if line == 0 {
+            return Some(0);
+        }
+        self.line_feed_indices.get(line - 1).map(|idx| idx + 1)
+    }
+    /// Returns the amount of bytes between the start of the line and the given idx, if that part
+    /// of the line is only made of whitespace
+    pub fn indentation(&self, idx: usize) -> Option<usize> {
+        let start = self.line_start(self.find_line(idx))?;
+        self.inner
+            .get(start..idx)
+            .filter(|prefix| prefix.chars().all(|char| char.is_ascii_whitespace()))
+            .map(str::len)
+    }
     pub fn inner(&self) -> &'t str {
         self.inner
     }
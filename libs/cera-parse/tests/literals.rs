@@ -0,0 +1,446 @@
+//! Generative tests for literal parsing, checked against a reference model.
+//!
+//! Every discrepancy found by these properties is pinned down as a regular test at the bottom of
+//! this file.
+
+use cera_parse::{
+    literals::{
+        escape_str, parse_float, parse_int, FloatParseError, FloatValue, IntParseError,
+        StringEscapeError,
+    },
+    num_bigint::{BigInt, BigUint},
+    parsing::{parse, Parsable, ParsingError},
+    src_token::{Literal, Token, TokenParseError},
+    text_region::{TextPosition, TextSpan},
+};
+use proptest::prelude::*;
+
+/// Joins the digits, inserting an underscore after the digits flagged in `separators`.
+/// Underscores are never leading nor trailing.
+fn with_separators(digits: &str, separators: &[bool]) -> String {
+    let mut output = String::new();
+    for (idx, char) in digits.chars().enumerate() {
+        output.push(char);
+        if (idx + 1 != digits.len()) && separators.get(idx).copied().unwrap_or(false) {
+            output.push('_');
+        }
+    }
+    output
+}
+
+fn digits(radix: u32, max_len: usize) -> impl Strategy<Value = String> {
+    prop::collection::vec(0..radix, 1..=max_len).prop_map(move |digits| {
+        digits
+            .into_iter()
+            .map(|digit| char::from_digit(digit, radix).unwrap())
+            .collect()
+    })
+}
+
+fn separated_digits(radix: u32, max_len: usize) -> impl Strategy<Value = String> {
+    (
+        digits(radix, max_len),
+        prop::collection::vec(any::<bool>(), max_len),
+    )
+        .prop_map(|(digits, separators)| with_separators(&digits, &separators))
+}
+
+/// The end of the region an error points to, which must always be inside of the parsed text
+fn int_err_end(err: &IntParseError) -> usize {
+    match err {
+        IntParseError::InvalidChar(TextPosition { idx }) => idx + 1,
+        IntParseError::ZeroLength(TextPosition { idx }) => *idx,
+    }
+}
+
+fn float_err_end(err: &FloatParseError) -> usize {
+    match err {
+        FloatParseError::NoDecimalDot(TextSpan { len, idx })
+        | FloatParseError::ExponentTooLarge(TextSpan { len, idx }) => idx + len,
+        FloatParseError::NumberParseError(err) => int_err_end(err),
+    }
+}
+
+fn escape_err_end(err: &StringEscapeError) -> usize {
+    match err {
+        StringEscapeError::UnexpectedStrEnd => 0,
+        StringEscapeError::InvalidEscapedChar(TextSpan { len, idx }) => idx + len,
+        StringEscapeError::ExpectedCloseBracket(TextPosition { idx }) => idx + 1,
+    }
+}
+
+fn token_err_end(err: &TokenParseError) -> usize {
+    match err {
+        TokenParseError::EmptyStr | TokenParseError::UnexpectedEOF => 0,
+        TokenParseError::NoMatch(TextPosition { idx })
+        | TokenParseError::UnexpectedChar(TextPosition { idx }) => idx + 1,
+        TokenParseError::StringEscapeError(err) => escape_err_end(err),
+        TokenParseError::FloatParseError(err) => float_err_end(err),
+        TokenParseError::IntParseError(err) => int_err_end(err),
+    }
+}
+
+/// A float as written in the source, along with the exact value it represents
+#[derive(Debug, Clone)]
+struct FloatCase {
+    text: String,
+    expected: FloatValue,
+}
+
+fn float_case() -> impl Strategy<Value = FloatCase> {
+    let exponent = (
+        prop::sample::select(vec!['e', 'E']),
+        prop::sample::select(vec!["", "+", "-"]),
+        0..400u32,
+    );
+    (
+        separated_digits(10, 12),
+        separated_digits(10, 12),
+        prop::option::of(exponent),
+    )
+        .prop_map(|(int, decimal, exponent)| {
+            let strip = |str: &str| str.replace('_', "");
+            let mut text = format!("{int}.{decimal}");
+            let mut exp = 0isize;
+            if let Some((e, sign, value)) = exponent {
+                text += &format!("{e}{sign}{value}");
+                exp = if sign == "-" {
+                    -(value as isize)
+                } else {
+                    value as isize
+                };
+            }
+            let all_digits = strip(&int) + &strip(&decimal);
+            FloatCase {
+                text,
+                expected: FloatValue {
+                    value: all_digits.parse().unwrap(),
+                    exponent: BigInt::from(exp - strip(&decimal).len() as isize),
+                },
+            }
+        })
+}
+
+/// An int written in any of the supported bases, along with its value
+fn int_case() -> impl Strategy<Value = (String, BigUint)> {
+    (
+        prop::sample::select(vec![("", 10), ("0b", 2), ("0o", 8), ("0d", 10), ("0x", 16)]),
+        any::<u128>(),
+        prop::collection::vec(any::<bool>(), 128),
+        any::<bool>(),
+    )
+        .prop_map(|((prefix, radix), value, separators, uppercase)| {
+            let mut digits = BigUint::from(value).to_str_radix(radix);
+            if uppercase {
+                digits = digits.to_uppercase();
+            }
+            (
+                format!("{prefix}{}", with_separators(&digits, &separators)),
+                BigUint::from(value),
+            )
+        })
+}
+
+/// Escapes a char in one of the ways the language allows
+fn escape_char(char: char, choice: u8) -> String {
+    let named = match char {
+        '\n' => Some("\\n"),
+        '\r' => Some("\\r"),
+        '\t' => Some("\\t"),
+        '\\' => Some("\\\\"),
+        '\0' => Some("\\0"),
+        '\'' => Some("\\'"),
+        '\"' => Some("\\\""),
+        _ => None,
+    };
+    match (choice % 3, named) {
+        (0, Some(named)) => named.into(),
+        (0, None) => char.into(),
+        (1, _) if char.is_ascii() => format!("\\x{:02x}", char as u32),
+        _ => format!("\\u{:06x}", char as u32),
+    }
+}
+
+fn float_to_f64(float: &FloatValue) -> f64 {
+    format!("{}e{}", float.value, float.exponent)
+        .parse()
+        .unwrap()
+}
+
+proptest! {
+    #[test]
+    fn floats_match_reference(case in float_case()) {
+        prop_assert_eq!(parse_float(&case.text), Ok(case.expected.clone()));
+        // Representable floats must round the same way as the standard library
+        let reference: f64 = case.text.replace('_', "").parse().unwrap();
+        prop_assert_eq!(float_to_f64(&case.expected), reference);
+
+        let source = format!("{};", case.text);
+        prop_assert_eq!(
+            Literal::try_parse(&source),
+            Ok((Some(Literal::Float(case.expected)), case.text.len()))
+        );
+    }
+
+    #[test]
+    fn ints_match_reference((text, value) in int_case()) {
+        prop_assert_eq!(parse_int(&text), Ok(value.clone()));
+
+        let source = format!("{text})");
+        prop_assert_eq!(
+            Literal::try_parse(&source),
+            Ok((Some(Literal::Int(value)), text.len()))
+        );
+    }
+
+    #[test]
+    fn misplaced_separators_are_rejected(
+        prefix in prop::sample::select(vec!["", "0b", "0o", "0x"]),
+        digits in digits(2, 10),
+        leading in any::<bool>(),
+    ) {
+        let text = if leading {
+            format!("{prefix}_{digits}")
+        } else {
+            format!("{prefix}{digits}_")
+        };
+        let underscore = if leading { prefix.len() } else { text.len() - 1 };
+        prop_assert_eq!(
+            parse_int(&text),
+            Err(IntParseError::InvalidChar(TextPosition { idx: underscore }))
+        );
+    }
+
+    #[test]
+    fn invalid_digits_are_located(
+        (prefix, invalid) in prop::sample::select(vec![
+            ("0b", '2'),
+            ("0o", '8'),
+            ("", 'a'),
+            ("0x", 'g'),
+        ]),
+        digits in digits(2, 10),
+        position in any::<prop::sample::Index>(),
+    ) {
+        let insert_at = position.index(digits.len() + 1);
+        let mut text = digits.clone();
+        text.insert(insert_at, invalid);
+        let text = format!("{prefix}{text}");
+        prop_assert_eq!(
+            parse_int(&text),
+            Err(IntParseError::InvalidChar(TextPosition { idx: prefix.len() + insert_at }))
+        );
+    }
+
+    #[test]
+    fn escapes_round_trip(
+        chars in prop::collection::vec((any::<char>(), any::<u8>()), 0..32),
+    ) {
+        let original: String = chars.iter().map(|(char, _)| *char).collect();
+        let escaped: String = chars
+            .iter()
+            .map(|(char, choice)| match char {
+                // Raw quotes and backslashes can't appear in a literal
+                '\"' | '\\' => escape_char(*char, 0),
+                _ => escape_char(*char, *choice),
+            })
+            .collect();
+        prop_assert_eq!(escape_str(&escaped), Ok(original.clone().into_boxed_str()));
+
+        let source = format!("\"{escaped}\"");
+        prop_assert_eq!(
+            Literal::try_parse(&source),
+            Ok((Some(Literal::String(original.into())), source.len()))
+        );
+    }
+
+    #[test]
+    fn invalid_escapes_point_at_backslash(
+        prefix in "[a-z ]{0,8}",
+        escaped in any::<char>().prop_filter("valid escape", |char| {
+            !"nrt\\0'\"xu".contains(*char)
+        }),
+        suffix in "[a-z ]{0,8}",
+    ) {
+        let text = format!("{prefix}\\{escaped}{suffix}");
+        prop_assert_eq!(
+            escape_str(&text),
+            Err(StringEscapeError::InvalidEscapedChar(TextSpan {
+                len: 1 + escaped.len_utf8(),
+                idx: prefix.len(),
+            }))
+        );
+    }
+
+    #[test]
+    fn literal_errors_stay_in_bounds(text in "[0-9a-fA-FxXobe_.+\\-\\\\u{}\"]{1,16}") {
+        if let Err(err) = parse_int(&text) {
+            prop_assert!(int_err_end(&err) <= text.len(), "{:?}", err);
+        }
+        if let Err(err) = parse_float(&text) {
+            prop_assert!(float_err_end(&err) <= text.len(), "{:?}", err);
+        }
+        if let Err(err) = escape_str(&text) {
+            prop_assert!(escape_err_end(&err) <= text.len(), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn token_spans_stay_in_bounds(text in any::<String>()) {
+        token_spans_are_ordered(&text)?;
+    }
+
+    #[test]
+    fn literal_heavy_token_spans_stay_in_bounds(text in "([0-9_.eExob+\\-]|\"[a-z\\\\]*\"| |//)*") {
+        token_spans_are_ordered(&text)?;
+    }
+}
+
+fn token_spans_are_ordered(text: &str) -> Result<(), TestCaseError> {
+    match parse::<Token>(text) {
+        Ok((tokens, spans)) => {
+            prop_assert_eq!(tokens.len(), spans.len());
+            let mut prev_end = 0;
+            for span in spans {
+                prop_assert!(span.len != 0);
+                prop_assert!(span.idx >= prev_end, "overlapping span {:?}", span);
+                prev_end = span.idx + span.len;
+                prop_assert!(prev_end <= text.len(), "span {:?} out of bounds", span);
+            }
+        }
+        Err(ParsingError::ParsableError(err)) => {
+            prop_assert!(token_err_end(&err) <= text.len(), "{:?}", err);
+        }
+        Err(ParsingError::OutOfCharBoundError(TextPosition { idx }))
+        | Err(ParsingError::ZeroLenSkip(TextPosition { idx })) => {
+            prop_assert!(idx <= text.len());
+        }
+    }
+    Ok(())
+}
+
+fn lex_literal(str: &str) -> (Literal, usize) {
+    match Literal::try_parse(str) {
+        Ok((Some(literal), len)) => (literal, len),
+        res => panic!("{str:?} didn't lex as a literal: {res:?}"),
+    }
+}
+
+fn float(value: usize, exponent: isize) -> Literal {
+    Literal::Float(FloatValue {
+        value: value.into(),
+        exponent: exponent.into(),
+    })
+}
+
+#[test]
+fn uppercase_exponent_is_part_of_the_float() {
+    assert_eq!(lex_literal("2.9E9;"), (float(29, 8), 5));
+    assert_eq!(lex_literal("2.9E-9;"), (float(29, -10), 6));
+}
+
+#[test]
+fn prefixed_ints_are_lexed_whole() {
+    assert_eq!(lex_literal("0x1F;"), (Literal::Int(31usize.into()), 4));
+    assert_eq!(lex_literal("0xe5"), (Literal::Int(0xe5usize.into()), 4));
+    assert_eq!(lex_literal("0b1_01 "), (Literal::Int(5usize.into()), 6));
+    assert_eq!(lex_literal("0o17)"), (Literal::Int(15usize.into()), 4));
+    assert_eq!(
+        Literal::try_parse("0b102"),
+        Err(TokenParseError::IntParseError(IntParseError::InvalidChar(
+            TextPosition { idx: 4 }
+        )))
+    );
+}
+
+#[test]
+fn dot_without_decimal_digit_is_not_a_float() {
+    assert_eq!(lex_literal("1.foo"), (Literal::Int(1usize.into()), 1));
+    assert_eq!(lex_literal("0..10"), (Literal::Int(0usize.into()), 1));
+    assert_eq!(lex_literal("1.e5"), (Literal::Int(1usize.into()), 1));
+}
+
+#[test]
+fn separators_dont_shift_the_decimal_point() {
+    assert_eq!(
+        parse_float("1.0_5"),
+        Ok(FloatValue {
+            value: 105usize.into(),
+            exponent: (-2isize).into(),
+        })
+    );
+}
+
+#[test]
+fn float_errors_are_relative_to_the_whole_float() {
+    assert_eq!(
+        parse_float("1.2a"),
+        Err(FloatParseError::NumberParseError(
+            IntParseError::InvalidChar(TextPosition { idx: 3 })
+        ))
+    );
+    assert_eq!(
+        parse_float("1.0e+1a"),
+        Err(FloatParseError::NumberParseError(
+            IntParseError::InvalidChar(TextPosition { idx: 6 })
+        ))
+    );
+    assert_eq!(
+        parse_float("1.0e-99999999999999999999"),
+        Err(FloatParseError::ExponentTooLarge(TextSpan {
+            len: 20,
+            idx: 5
+        }))
+    );
+}
+
+#[test]
+fn truncated_escapes_are_errors() {
+    assert_eq!(
+        escape_str("\\x4"),
+        Err(StringEscapeError::InvalidEscapedChar(TextSpan {
+            len: 3,
+            idx: 0
+        }))
+    );
+    assert_eq!(
+        escape_str("\\u12"),
+        Err(StringEscapeError::InvalidEscapedChar(TextSpan {
+            len: 4,
+            idx: 0
+        }))
+    );
+}
+
+// `StringEscapeError::ExpectedCloseBracket` describes a `\u{...}` syntax, which the lexer doesn't
+// implement: `\u` is followed by exactly 6 digits. Changing the syntax is left to a request of
+// its own.
+#[test]
+fn unicode_escapes_have_six_digits() {
+    assert_eq!(escape_str("\\u01F600").as_deref(), Ok("\u{1F600}"));
+    assert_eq!(
+        escape_str("\\u00D800"),
+        Err(StringEscapeError::InvalidEscapedChar(TextSpan {
+            len: 8,
+            idx: 0
+        }))
+    );
+    assert_eq!(
+        escape_str("\\u{1F600}"),
+        Err(StringEscapeError::InvalidEscapedChar(TextSpan {
+            len: 8,
+            idx: 0
+        }))
+    );
+}
+
+#[test]
+fn invalid_escape_span_covers_the_escaped_char() {
+    assert_eq!(
+        escape_str("a\\é"),
+        Err(StringEscapeError::InvalidEscapedChar(TextSpan {
+            len: 3,
+            idx: 1
+        }))
+    );
+}
@@ -0,0 +1,449 @@
+//! Checks the types of the functions of a module, once `declare_module` bound its declarations.
+//! The values of the other declarations are evaluated when they are bound, which checks them.
+
+use cera_ast::{
+    builtin::EvalExecScope,
+    check::{TypeChecker, TypeError},
+    expressions::Expression,
+    types::{ContainerType, Type},
+    values::Value,
+};
+
+use crate::decls::{Decl, DeclKind, Module};
+
+/// The type errors of the functions of `module`, including the ones declared in containers
+pub fn check_module(module: &Module, scope: &EvalExecScope) -> Vec<TypeError> {
+    let mut checker = TypeChecker::new();
+    let value = |name: &str| scope.get(name).map(|(value, _)| value);
+    check_decls(&mut checker, &module.decls, &value, scope);
+    checker.into_errors()
+}
+
+/// Checks the functions among `decls`, the values of which are bound by `value`
+fn check_decls(
+    checker: &mut TypeChecker,
+    decls: &[Decl],
+    value: &dyn Fn(&str) -> Option<Value>,
+    scope: &EvalExecScope,
+) {
+    for decl in decls {
+        match (&decl.kind, value(&decl.name)) {
+            (DeclKind::Fn(_), Some(Value::Function(function))) => {
+                checker.check_function(&function, scope)
+            }
+            (
+                DeclKind::Container(container),
+                Some(Value::Type(Type::Container(container_type))),
+            ) => {
+                let value = |name: &str| field_value(&container_type, name);
+                check_decls(checker, &container.decls, &value, scope)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The value of the declaration `name` of a container
+fn field_value(container_type: &ContainerType, name: &str) -> Option<Value> {
+    match container_type.field(name)?.val.as_ref()? {
+        Expression::Value(value) => Some((**value).clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cera_src_ast::parser::parse_file;
+
+    use super::*;
+    use crate::{comptime::declare_module, lower::lower_file};
+
+    fn check(text: &str) -> Vec<String> {
+        check_in(text, &EvalExecScope::new())
+    }
+
+    /// Checks `text` as the file declared in `scope`
+    fn check_in(text: &str, scope: &EvalExecScope) -> Vec<String> {
+        let (module, errors) = lower_file(&parse_file(text).unwrap());
+        assert_eq!(errors, []);
+        declare_module(&module, scope).unwrap();
+        check_module(&module, scope)
+            .into_iter()
+            .map(|error| error.kind.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn sample() {
+        let errors = check(
+            "const c: f32 = 2.9e9;
+            fn is_valid_speed(speed: f32) -> bool {
+                return speed < c;
+            }
+            pub struct spaceship {
+                speed: f32,
+                fn new(speed: f32) -> ?spaceship {
+                    if !is_valid_speed(speed) {
+                        return null;
+                    }
+                    return .{ speed };
+                }
+            }",
+        );
+        assert_eq!(errors, Vec::<String>::new());
+    }
+
+    #[test]
+    fn coercions() {
+        let errors = check(
+            "struct point {
+                x: i32,
+                y: i32 = 0,
+            }
+            enum color { red, green }
+            fn some(x: u8) -> ?u8 {
+                return x;
+            }
+            fn wrapped(x: u8) -> !u8 {
+                return x;
+            }
+            fn nested() -> !?u8 {
+                return null;
+            }
+            fn literal() -> ?u16 {
+                return 300;
+            }
+            fn origin() -> ?point {
+                return .{ .x = 1 };
+            }
+            fn red() -> color {
+                return .red;
+            }
+            fn pick(flag: bool, a: u8) -> u8 {
+                var b: u8 = a * 2;
+                if (flag) {
+                    b = b + 1;
+                }
+                return b;
+            }
+            fn sum(n: u32) -> u32 {
+                var total: u32 = 0;
+                var i: u32 = 0;
+                while (i < n) {
+                    total += i;
+                    i += 1;
+                }
+                return total;
+            }
+            fn call() -> u8 {
+                return pick(true, some(1) orelse 0);
+            }
+            fn scale(x: f32) -> f32 {
+                const offset = 1 << 3;
+                return x * 2 + 0.5 - offset;
+            }
+            fn third(x: u32) -> u32 {
+                const shift = 40;
+                return x + (1 << shift) / (1 << 38);
+            }",
+        );
+        assert_eq!(errors, Vec::<String>::new());
+    }
+
+    #[test]
+    fn mismatches() {
+        let errors = check(
+            "pub struct spaceship {
+                speed: f32,
+            }
+            fn speed(x: f32) -> u8 {
+                return x;
+            }
+            fn ship(s: spaceship) -> ?u8 {
+                return s;
+            }
+            fn condition(x: f32) {
+                if (x) {}
+            }
+            fn optional(x: ?u8) -> bool {
+                return !x;
+            }
+            fn present(x: ?u8) {
+                while (x) {}
+            }
+            fn arguments() -> u8 {
+                return speed(1.0, 2.0);
+            }
+            fn add_one(a: u8, b: u8) -> u8 {
+                return a + b;
+            }
+            fn too_few() -> u8 {
+                return add_one(true);
+            }
+            fn overflow() -> u8 {
+                return 256;
+            }
+            fn missing() -> u8 {}
+            fn add(a: u8, b: u16) -> u8 {
+                return a + b;
+            }
+            fn constant() {
+                const x: u8 = 1;
+                x = 2;
+            }
+            struct point {
+                x: u8,
+            }
+            fn constant_field() {
+                const p: point = .{ .x = 1 };
+                p.x = 2;
+            }
+            fn constant_element(a: [4]u8) {
+                a[0] = 1;
+            }
+            fn through_pointer(p: *point) {
+                p.x = 2;
+            }
+            fn lossy(x: u8) -> u8 {
+                return x + 0.5;
+            }
+            fn counter() {
+                var i = 0;
+            }
+            fn divide() -> u8 {
+                return 1 / 0;
+            }
+            fn constant_division() -> u8 {
+                const x = 1 % 0;
+                return x;
+            }",
+        );
+        assert_eq!(
+            errors,
+            [
+                "expected `u8`, found `f32`",
+                "expected `?u8`, found `spaceship`",
+                "expected `bool`, found `f32`, compare it with `!= 0`",
+                "`!` can't be applied to `?u8`",
+                "expected `bool`, found `?u8`, compare it with `!= null`",
+                "expected 1 argument, found 2",
+                "expected 2 arguments, found 1",
+                "expected `u8`, found `bool`",
+                "`256` doesn't fit in `u8`",
+                "expected `u8`, found `void`",
+                "expected `u8`, found `u16`",
+                "`x` is constant",
+                "`p` is constant",
+                "`a` is constant",
+                "`0.5` can't be represented as `u8`",
+                "`var i` of type `comptime_int` must be `const`, or have a sized type",
+                "division by zero",
+                "division by zero",
+            ]
+        );
+    }
+
+    #[test]
+    fn initializers() {
+        let errors = check(
+            "struct point {
+                x: u8,
+                y: u8,
+                z: u8 = 0,
+            }
+            union raw { int: u32, float: f32 }
+            enum color { red, green }
+            fn missing() -> point {
+                return .{ .z = 1 };
+            }
+            fn unknown() -> point {
+                return .{ .x = 1, .y = 2, .w = 3 };
+            }
+            fn twice() -> point {
+                return .{ .x = 1, .x = 2, .y = 3 };
+            }
+            fn mixed() -> point {
+                return .{ .x = 1, 2 };
+            }
+            fn elements() -> point {
+                return .{ 1, 2, 3, 4 };
+            }
+            fn both() -> raw {
+                return .{ .int = 1, .float = 2.0 };
+            }
+            fn variant() -> color {
+                return .blue;
+            }
+            fn number() -> u8 {
+                return .{ 1 };
+            }",
+        );
+        assert_eq!(
+            errors,
+            [
+                "missing fields `x` and `y` of `point`",
+                "`point` has no field `w`, expected `x`, `y` and `z`",
+                "field `x` of `point` is given twice",
+                "the fields of `point` are given both by name and by position",
+                "`point` has 3 fields, more values are given",
+                "union `raw` must be given exactly one of its fields, `int` and `float`",
+                "`color` has no field `blue`, expected `red` and `green`",
+                "`u8` can't be initialized with `.{ ... }` or `.variant`",
+            ]
+        );
+    }
+
+    #[test]
+    fn private_fields() {
+        let point = "struct point {
+                x: i32,
+                pub y: i32,
+                fn get_x(p: point) -> i32 {
+                    return p.x;
+                }
+                struct inner {
+                    fn outer_x(p: point) -> i32 {
+                        return p.x;
+                    }
+                }
+            }
+            fn x(p: point) -> i32 {
+                return point.get_x(p) + p.x;
+            }";
+        // Private declarations are visible throughout the file declaring them
+        let declared = EvalExecScope::new();
+        assert_eq!(check_in(point, &declared), Vec::<String>::new());
+
+        let scope = EvalExecScope::new();
+        let (value, _) = declared.get("point").unwrap();
+        scope.declare("point", value, None, false);
+        let errors = check_in(
+            "fn x(p: point) -> i32 {
+                return p.x;
+            }
+            fn y(p: point) -> i32 {
+                return p.y;
+            }
+            fn get(p: point) -> i32 {
+                return point.get_x(p);
+            }
+            fn typo(p: point) -> i32 {
+                return p.yy;
+            }",
+            &scope,
+        );
+        assert_eq!(
+            errors,
+            [
+                "`x` is private (declared at 31..37)",
+                "`get_x` is private (declared at 83..160)",
+                "no field `yy`, did you mean `y` (declared at 55..65)?",
+            ]
+        );
+    }
+
+    #[test]
+    fn sample_code() {
+        let errors = check(include_str!("../../../sample-code/main.cera"));
+        assert_eq!(errors, Vec::<String>::new());
+    }
+
+    #[test]
+    fn slices() {
+        let errors = check(
+            "fn name() -> [:0]const u8 {
+                return \"cera\";
+            }
+            fn bytes() -> []const u8 {
+                return \"cera\";
+            }
+            fn tail(s: [:0]const u8) -> [:0]const u8 {
+                return s[1..];
+            }
+            fn middle(s: [:0]const u8, n: usize) -> []const u8 {
+                return s[1..n];
+            }
+            fn first(s: []const u8) -> u8 {
+                return s[0];
+            }
+            fn length(s: []const u8) -> usize {
+                return s.len;
+            }
+            fn terminator(a: [4:0]u8) -> u8 {
+                return a[4];
+            }
+            fn mutable() -> []u8 {
+                return \"cera\";
+            }
+            fn unterminated(s: [:0]const u8) -> [:0]const u8 {
+                return s[1..2];
+            }
+            fn index(a: [4]u8) -> u8 {
+                return a[4];
+            }
+            fn bounds(a: [4]u8) -> []const u8 {
+                return a[2..5];
+            }
+            fn reversed(a: [4]u8) -> []const u8 {
+                return a[3..1];
+            }
+            fn resize(s: []const u8) {
+                s.len = 2;
+            }",
+        );
+        assert_eq!(
+            errors,
+            [
+                "expected `[]u8`, found `*const [4:0]u8`",
+                "expected `[:0]const u8`, found `[]const u8`",
+                "index `4` is out of bounds of length 4",
+                "`2..5` is out of bounds of length 4",
+                "`3..1` ends before it starts",
+                "`len` is constant",
+            ]
+        );
+    }
+
+    #[test]
+    fn many_item_pointers() {
+        let errors = check(
+            "fn name() -> [*:0]const u8 {
+                return \"cera\";
+            }
+            fn second(p: [*]const u8) -> u8 {
+                return p[1];
+            }
+            fn window(p: [*:0]const u8) -> []const u8 {
+                return p[1..3];
+            }
+            fn forget(p: [*:0]const u8) -> [*]const u8 {
+                return p;
+            }
+            fn length(p: [*]const u8) -> usize {
+                return p.len;
+            }
+            fn first(p: [*]const u8) -> u8 {
+                return p.*;
+            }
+            fn tail(p: [*:0]const u8) -> [:0]const u8 {
+                return p[1..];
+            }
+            fn terminated(p: [*]const u8) -> [*:0]const u8 {
+                return p;
+            }
+            fn mutable() -> [*]u8 {
+                return \"cera\";
+            }",
+        );
+        assert_eq!(
+            errors,
+            [
+                "`[*]const u8` has no fields",
+                "`.*` can't be applied to `[*]const u8`",
+                "`[start..end]` can't be applied to `[*:0]const u8`",
+                "expected `[*:0]const u8`, found `[*]const u8`",
+                "expected `[*]u8`, found `*const [4:0]u8`",
+            ]
+        );
+    }
+}
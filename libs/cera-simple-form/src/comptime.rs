@@ -0,0 +1,888 @@
+//! Binds the declarations of the simple form in the outermost scope of the compile time
+//! evaluator, so that expressions using them can be evaluated.
+
+use cera_ast::{
+    builtin::EvalExecScope,
+    eval::{EvaluationError, EvaluationErrorKind},
+    expressions::{Expression, Let},
+    types::{
+        CallingConvetion, ComptimeFunctionType, ContainerError, ContainerField, ContainerId,
+        ContainerType, ContainerVariant, FieldKind, IntType, Type, UnionTagging,
+    },
+    values::{self, Value},
+    visit::{walk_expression, Visitor},
+};
+use num_bigint::BigInt;
+
+use crate::decls::{Container, ContainerKind, Decl, DeclKind, Function, Module, Param, UnionTag};
+
+/// Binds the declarations of `module` in `scope`. Functions are bound first, so that they can be
+/// called by the values of the other declarations. The others are evaluated once the declarations
+/// they use are bound, whatever their order, and declarations using each other are reported as a
+/// cycle. The declarations of containers are their fields, so functions only refer to them
+/// through their container.
+pub fn declare_module(module: &Module, scope: &EvalExecScope) -> Result<(), EvaluationError> {
+    for decl in module.decls.iter() {
+        if let DeclKind::Fn(function) = &decl.kind {
+            scope.declare(&decl.name, function_value(function, scope), None, false);
+        }
+    }
+    let mut pending: Vec<&Decl> = module
+        .decls
+        .iter()
+        .filter(|decl| !matches!(decl.kind, DeclKind::Fn(_)))
+        .collect();
+    while !pending.is_empty() {
+        // The declarations using one which isn't bound yet, along with its name
+        let mut waiting = Vec::new();
+        for decl in pending.iter() {
+            let error = match declare(decl, scope) {
+                Ok(()) => continue,
+                Err(error) => error,
+            };
+            match &error.kind {
+                EvaluationErrorKind::Undefined(name)
+                    if pending.iter().any(|decl| decl.name == *name) =>
+                {
+                    waiting.push((*decl, name.clone(), error))
+                }
+                _ => return Err(error),
+            }
+        }
+        if waiting.len() == pending.len() {
+            return Err(cycle(waiting));
+        }
+        pending = waiting.into_iter().map(|(decl, _, _)| decl).collect();
+    }
+    Ok(())
+}
+
+/// Binds the declaration `decl` which isn't a function
+fn declare(decl: &Decl, scope: &EvalExecScope) -> Result<(), EvaluationError> {
+    match &decl.kind {
+        DeclKind::Var {
+            is_mutable,
+            var_type,
+            value,
+        } => {
+            let let_ = Expression::Let(Box::new(Let {
+                name: decl.name.clone(),
+                is_mutable: *is_mutable,
+                var_type: var_type.clone(),
+                value: value.clone(),
+            }));
+            let_.eval(scope)?;
+        }
+        DeclKind::Fn(_) => {}
+        DeclKind::Container(container) => {
+            let container_type = container_type(&decl.name, container, scope)?;
+            let value = Value::Type(Type::Container(container_type));
+            scope.declare(&decl.name, value, None, false);
+        }
+    }
+    Ok(())
+}
+
+/// The cycle of declarations found by following the ones `waiting` declarations use, none of
+/// which can be bound. It is reported where the first declaration in it uses the next one.
+fn cycle(waiting: Vec<(&Decl, Box<str>, EvaluationError)>) -> EvaluationError {
+    let mut path: Vec<usize> = vec![0];
+    loop {
+        let (_, used, _) = &waiting[*path.last().unwrap()];
+        let next = waiting
+            .iter()
+            .position(|(decl, _, _)| decl.name == *used)
+            .expect("declarations only wait for other waiting ones");
+        if let Some(start) = path.iter().position(|idx| *idx == next) {
+            path.drain(..start);
+            break;
+        }
+        path.push(next);
+    }
+    let names = path
+        .iter()
+        .map(|idx| waiting[*idx].0.name.clone())
+        .collect();
+    EvaluationError {
+        kind: EvaluationErrorKind::DeclarationCycle(names),
+        ..waiting[path[0]].2.clone()
+    }
+}
+
+/// Finds the names a function signature refers to which make it only callable at compile time:
+/// `type`, and parameters, the types depending on them being generic
+struct ComptimeNames<'a> {
+    params: &'a [Param],
+    found: bool,
+}
+
+impl Visitor for ComptimeNames<'_> {
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Name(name) => {
+                self.found |=
+                    &**name == "type" || self.params.iter().any(|param| param.name == *name)
+            }
+            _ => walk_expression(self, expression),
+        }
+    }
+}
+
+/// Whether the function takes or returns types, or has types depending on its parameters
+fn is_comptime_only(function: &Function) -> bool {
+    let mut names = ComptimeNames {
+        params: &function.params,
+        found: false,
+    };
+    for param in function.params.iter() {
+        names.visit_expression(&param.param_type);
+    }
+    if let Some(return_type) = &function.return_type {
+        names.visit_expression(return_type);
+    }
+    names.found
+}
+
+/// The value of a function declared in the file of `scope`
+fn function_value(function: &Function, scope: &EvalExecScope) -> Value {
+    let param_types = function
+        .params
+        .iter()
+        .map(|param| param.param_type.clone())
+        .collect();
+    Value::Function(values::Function {
+        expression: function.body.clone(),
+        param_names: function
+            .params
+            .iter()
+            .map(|param| param.name.clone())
+            .collect(),
+        function_type: ComptimeFunctionType {
+            parameters: Expression::ExpressionList(param_types),
+            result: match &function.return_type {
+                Some(return_type) => return_type.clone(),
+                None => Expression::Value(Box::new(Value::Type(Type::Void))),
+            },
+        },
+        calling_convention: (!is_comptime_only(function)).then_some(CallingConvetion::Cera),
+        file: scope.file(),
+    })
+}
+
+fn value_expression(value: Value) -> Expression {
+    Expression::Value(Box::new(value))
+}
+
+fn eval_type(expression: &Expression, scope: &EvalExecScope) -> Result<Type, EvaluationError> {
+    match expression.eval(scope)? {
+        Value::Type(value_type) => Ok(value_type),
+        _ => Err(EvaluationErrorKind::NotAType.into()),
+    }
+}
+
+/// The value of a declaration of a container, stored in its field
+fn decl_field(decl: &Decl, scope: &EvalExecScope) -> Result<ContainerField, EvaluationError> {
+    let (field_kind, field_type, value) = match &decl.kind {
+        DeclKind::Var {
+            is_mutable,
+            var_type,
+            value,
+        } => {
+            let var_type = match var_type {
+                Some(var_type) => Some(eval_type(var_type, scope)?),
+                None => None,
+            };
+            let value = value.eval_expecting(scope, var_type.as_ref())?;
+            let field_kind = match is_mutable {
+                true => FieldKind::Static,
+                false => FieldKind::Const,
+            };
+            let var_type = var_type.map(|var_type| value_expression(Value::Type(var_type)));
+            (field_kind, var_type, value)
+        }
+        DeclKind::Fn(function) => (FieldKind::Const, None, function_value(function, scope)),
+        DeclKind::Container(container) => {
+            let container_type = container_type(&decl.name, container, scope)?;
+            let value = Value::Type(Type::Container(container_type));
+            (FieldKind::Const, None, value)
+        }
+    };
+    Ok(ContainerField {
+        name: decl.name.clone(),
+        visibility: decl.visibility.clone(),
+        field_kind,
+        field_type,
+        val: Some(value_expression(value)),
+        span: Some(decl.span.clone()),
+    })
+}
+
+/// The value of an enum variant, which is the previous one plus one when it isn't given
+fn variant_value(
+    value: &Expression,
+    backing_type: Option<&IntType>,
+    scope: &EvalExecScope,
+) -> Result<BigInt, EvaluationError> {
+    match (value, backing_type) {
+        (value, None) => match value.eval(scope)? {
+            Value::ComptimeInt(value) => Ok(value),
+            _ => Err(EvaluationErrorKind::UntypedLiteral.into()),
+        },
+        (value, Some(backing_type)) => {
+            match value.eval_expecting(scope, Some(&Type::Int(backing_type.clone())))? {
+                Value::Int(int) => Ok(int.value()),
+                _ => Err(EvaluationErrorKind::Mismatch {
+                    expected: Box::new(Type::Int(backing_type.clone())),
+                }
+                .into()),
+            }
+        }
+    }
+}
+
+/// Builds the type of the container declared as `name` in the file of `scope`, evaluating the
+/// types of its fields and its declarations
+pub fn container_type(
+    name: &str,
+    container: &Container,
+    scope: &EvalExecScope,
+) -> Result<ContainerType, EvaluationError> {
+    Ok(ContainerType {
+        name: Some(name.into()),
+        file: Some(scope.file()),
+        ..build_container(container, scope)?
+    })
+}
+
+fn build_container(
+    container: &Container,
+    scope: &EvalExecScope,
+) -> Result<ContainerType, EvaluationError> {
+    let decls = container
+        .decls
+        .iter()
+        .map(|decl| decl_field(decl, scope))
+        .collect::<Result<Vec<_>, _>>()?;
+    let container_error = |error| EvaluationError::from(EvaluationErrorKind::Container(error));
+
+    if let ContainerKind::Enum {
+        backing_type,
+        is_exhaustive,
+    } = &container.kind
+    {
+        let backing_type = match backing_type {
+            Some(backing_type) => match eval_type(backing_type, scope)? {
+                Type::Int(int_type) => Some(int_type),
+                _ => return Err(EvaluationErrorKind::NotAType.into()),
+            },
+            None => None,
+        };
+        let mut variants = Vec::with_capacity(container.fields.len());
+        for field in container.fields.iter() {
+            let value = match &field.value {
+                Some(value) => Some(variant_value(value, backing_type.as_ref(), scope)?),
+                None => None,
+            };
+            variants.push((field.name.clone(), value));
+        }
+        return ContainerType::new_enum(backing_type, variants, *is_exhaustive, decls).map_err(
+            |error| {
+                // Values are blamed on the variant given them, or following the one given them
+                let variant = match &error {
+                    ContainerError::VariantOverflow { variant, .. }
+                    | ContainerError::DuplicateVariantValue {
+                        second: variant, ..
+                    } => container.fields.iter().find(|field| field.name == *variant),
+                    _ => None,
+                };
+                let span = variant.map(|field| {
+                    let value_span = field.value.as_ref().and_then(Expression::span);
+                    value_span.unwrap_or(&field.span).clone()
+                });
+                EvaluationError {
+                    span,
+                    ..container_error(error)
+                }
+            },
+        );
+    }
+
+    let mut fields = Vec::with_capacity(container.fields.len() + decls.len());
+    for field in container.fields.iter() {
+        let field_type = match &field.field_type {
+            Some(field_type) => eval_type(field_type, scope)?,
+            None => return Err(EvaluationErrorKind::NotAType.into()),
+        };
+        let value = match &field.value {
+            Some(value) => Some(value_expression(
+                value.eval_expecting(scope, Some(&field_type))?,
+            )),
+            None => None,
+        };
+        fields.push(ContainerField {
+            name: field.name.clone(),
+            visibility: field.visibility.clone(),
+            field_kind: FieldKind::Instance,
+            field_type: Some(value_expression(Value::Type(field_type))),
+            val: value,
+            span: Some(field.span.clone()),
+        });
+    }
+    fields.extend(decls);
+
+    let variant = match &container.kind {
+        ContainerKind::Struct => ContainerVariant::Struct {
+            ptr_coerce_target: None,
+            droppers: Box::new([]),
+        },
+        ContainerKind::Trait => ContainerVariant::Trait {
+            ptr_coerce_target: None,
+        },
+        ContainerKind::Union { tag } => {
+            let tagging = match tag {
+                UnionTag::Untagged => UnionTagging::Untagged,
+                UnionTag::Inferred => UnionTagging::Inferred,
+                UnionTag::Explicit(tag) => match eval_type(tag, scope)? {
+                    Type::Container(tag) => UnionTagging::Explicit(tag),
+                    _ => return Err(EvaluationErrorKind::NotAType.into()),
+                },
+            };
+            return ContainerType::new_union(fields, tagging).map_err(container_error);
+        }
+        ContainerKind::Enum { .. } => unreachable!("enums are built above"),
+    };
+    Ok(ContainerType {
+        id: ContainerId::fresh(),
+        name: None,
+        file: None,
+        fields: fields.into_boxed_slice(),
+        variant,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cera_ast::{layout::LayoutError, types::FieldError};
+    use cera_parse::text_region::TextSpan;
+    use cera_src_ast::parser::parse_file;
+
+    use super::*;
+    use crate::lower::lower_file;
+
+    fn declare(text: &str) -> Result<EvalExecScope<'static>, EvaluationError> {
+        let scope = EvalExecScope::new();
+        declare_in(text, &scope)?;
+        Ok(scope)
+    }
+
+    /// Binds the declarations of `text` as the file declared in `scope`
+    fn declare_in(text: &str, scope: &EvalExecScope) -> Result<(), EvaluationError> {
+        let (module, errors) = lower_file(&parse_file(text).unwrap());
+        assert_eq!(errors, []);
+        declare_module(&module, scope)
+    }
+
+    fn get(scope: &EvalExecScope, name: &str) -> Value {
+        scope.get(name).unwrap().0
+    }
+
+    fn int(scope: &EvalExecScope, name: &str) -> BigInt {
+        match get(scope, name) {
+            Value::Int(int) => int.value(),
+            value => panic!("{value:?}"),
+        }
+    }
+
+    fn error(text: &str) -> EvaluationErrorKind {
+        declare(text).err().unwrap().kind
+    }
+
+    fn error_span(text: &str) -> Option<TextSpan> {
+        declare(text).err().unwrap().span
+    }
+
+    #[test]
+    fn conditions() {
+        // The right operand of `and` and `or` is only evaluated when it decides the result
+        let scope = declare(
+            "fn fails() -> bool {
+                return 1 / 0 == 0;
+            }
+            const skipped_and = false and fails();
+            const skipped_or = true or fails();
+            const both = 1 < 2 and !(2 > 3);
+            const size = @sizeOf(bool);",
+        )
+        .unwrap();
+        assert!(matches!(get(&scope, "skipped_and"), Value::Bool(false)));
+        assert!(matches!(get(&scope, "skipped_or"), Value::Bool(true)));
+        assert!(matches!(get(&scope, "both"), Value::Bool(true)));
+        assert_eq!(int(&scope, "size"), 1.into());
+        assert!(matches!(
+            error("const failed = true and fails(); fn fails() -> bool { return 1 / 0 == 0; }"),
+            EvaluationErrorKind::DivisionByZero
+        ));
+        assert!(matches!(
+            error("fn pick(x: u8) { if (x) {} } const picked = pick(1);"),
+            EvaluationErrorKind::NotACondition(_)
+        ));
+    }
+
+    #[test]
+    fn strings_and_slices() {
+        let scope = declare(
+            "const s = \"cera\";
+            const len = s.len;
+            const second = s[1];
+            const terminator = s[4];
+            const middle: []const u8 = s[1..3];
+            const middle_len = middle.len;
+            const tail: [:0]const u8 = s[2..];
+            const last = tail[1];
+            const array_size = @sizeOf([3:0]u8);
+            const slice_size = @sizeOf([]u8);",
+        )
+        .unwrap();
+        assert_eq!(int(&scope, "len"), 4.into());
+        assert_eq!(int(&scope, "second"), b'e'.into());
+        assert_eq!(int(&scope, "terminator"), 0.into());
+        assert_eq!(int(&scope, "middle_len"), 2.into());
+        assert_eq!(int(&scope, "last"), b'a'.into());
+        assert_eq!(int(&scope, "array_size"), 4.into());
+        assert_eq!(int(&scope, "slice_size"), 16.into());
+        assert!(matches!(
+            error("const s = \"cera\"; const t = s[3..2];"),
+            EvaluationErrorKind::SliceBounds {
+                start: 3,
+                end: 2,
+                len: 4
+            }
+        ));
+        assert!(matches!(
+            error("const s = \"cera\"; const c = s[5];"),
+            EvaluationErrorKind::IndexOutOfBounds { len: 4, .. }
+        ));
+        // Dropping the sentinel or the `const` is not a coercion
+        assert!(matches!(
+            error("const s = \"cera\"; const t: [:0]const u8 = s[1..3];"),
+            EvaluationErrorKind::Mismatch { .. }
+        ));
+        assert!(matches!(
+            error("const t: []u8 = \"cera\";"),
+            EvaluationErrorKind::Mismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn many_item_pointers() {
+        let scope = declare(
+            "const p: [*:0]const u8 = \"cera\";
+            const second = p[1];
+            const terminator = p[4];
+            const middle: []const u8 = p[1..3];
+            const middle_len = middle.len;
+            const q: [*]const u8 = p;
+            const third = q[2];
+            const pointer_size = @sizeOf([*:0]const u8);",
+        )
+        .unwrap();
+        assert_eq!(int(&scope, "second"), b'e'.into());
+        assert_eq!(int(&scope, "terminator"), 0.into());
+        assert_eq!(int(&scope, "middle_len"), 2.into());
+        assert_eq!(int(&scope, "third"), b'r'.into());
+        assert_eq!(int(&scope, "pointer_size"), 8.into());
+        // Many-item pointers don't know how many values they point to
+        assert!(matches!(
+            error("const p: [*]const u8 = \"cera\"; const len = p.len;"),
+            EvaluationErrorKind::NotAContainer
+        ));
+        assert!(matches!(
+            error("const p: [*]const u8 = \"cera\"; const t = p[1..];"),
+            EvaluationErrorKind::InvalidOperands(_)
+        ));
+        assert!(matches!(
+            error("const p: [*]u8 = \"cera\";"),
+            EvaluationErrorKind::Mismatch { .. }
+        ));
+        assert!(matches!(
+            error("const p: [*:1]const u8 = \"cera\";"),
+            EvaluationErrorKind::Mismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn sample() {
+        let scope = declare(
+            "const c: f32 = 2.9e9;
+            fn is_valid_speed(speed: f32) -> bool {
+                return speed < c;
+            }
+            pub struct spaceship {
+                speed: f32,
+                fn new(speed: f32) -> ?spaceship {
+                    if !is_valid_speed(speed) {
+                        return null;
+                    }
+                    return .{ speed };
+                }
+            }
+            const valid = is_valid_speed(300.0);
+            const invalid = is_valid_speed(3.0e9);
+            const ship = spaceship.new(300.0);
+            const no_ship = spaceship.new(3.0e9);",
+        )
+        .unwrap();
+        assert!(matches!(get(&scope, "valid"), Value::Bool(true)));
+        assert!(matches!(get(&scope, "invalid"), Value::Bool(false)));
+
+        let Value::Option(ship) = get(&scope, "ship") else {
+            panic!()
+        };
+        let Some(Value::Container(ship)) = ship.unwrap() else {
+            panic!()
+        };
+        let Some(Value::Float(speed)) = ship.get("speed") else {
+            panic!()
+        };
+        assert_eq!(speed.to_f64(), Some(300.0));
+        let Value::Option(no_ship) = get(&scope, "no_ship") else {
+            panic!()
+        };
+        assert!(no_ship.unwrap().is_none());
+    }
+
+    #[test]
+    fn recursion_and_loops() {
+        let scope = declare(
+            "fn fact(n: u32) -> u32 {
+                if (n == 0) {
+                    return 1;
+                }
+                return n * fact(n - 1);
+            }
+            fn sum(n: u32) -> u32 {
+                var total: u32 = 0;
+                var i: u32 = 0;
+                while (i < n) : (i += 1) {
+                    total += i;
+                }
+                return total;
+            }
+            fn isqrt(n: u32) -> u32 {
+                var i: u32 = 0;
+                while (true) {
+                    if (i * i > n) {
+                        break;
+                    }
+                    i += 1;
+                }
+                return i - 1;
+            }
+            const a = fact(10);
+            const b = sum(100);
+            const c = isqrt(50);",
+        )
+        .unwrap();
+        assert_eq!(int(&scope, "a"), 3628800.into());
+        assert_eq!(int(&scope, "b"), 4950.into());
+        assert_eq!(int(&scope, "c"), 7.into());
+    }
+
+    #[test]
+    fn containers() {
+        let scope = declare(
+            "struct point {
+                x: i32,
+                y: i32 = -2,
+                const scale: i32 = 3;
+                fn moved(p: point, dx: i32) -> point {
+                    var q = p;
+                    q.x = q.x + dx * point.scale;
+                    return q;
+                }
+            }
+            enum(u8) color { red, green = 4, blue }
+            const p = point.moved(.{ .x = 1 }, 3);
+            const x = p.x;
+            const y = p.y;
+            const origin: point = .{ .x = 0, .y = 0 };
+            const origin_y = origin.y;
+            const scale = point.scale;
+            const blue: color = .blue;
+            const point_size = @sizeOf(point);
+            const point_align = @alignOf(point);
+            const optional_size = @sizeOf(?u32);",
+        )
+        .unwrap();
+        assert_eq!(int(&scope, "x"), 10.into());
+        assert_eq!(int(&scope, "y"), BigInt::from(-2));
+        assert_eq!(int(&scope, "origin_y"), 0.into());
+        assert_eq!(int(&scope, "scale"), 3.into());
+        let Value::Container(blue) = get(&scope, "blue") else {
+            panic!()
+        };
+        assert_eq!(&*blue.bytes, [5]);
+        assert_eq!(int(&scope, "point_size"), 8.into());
+        assert_eq!(int(&scope, "point_align"), 4.into());
+        assert_eq!(int(&scope, "optional_size"), 8.into());
+    }
+
+    #[test]
+    fn declaration_order() {
+        let scope = declare(
+            "const x = y;
+            const y: u8 = 1;
+            const ship: spaceship = .{ .speed = x + 1 };
+            const speed = ship.speed;
+            pub struct spaceship {
+                speed: u8,
+            }",
+        )
+        .unwrap();
+        assert_eq!(int(&scope, "x"), 1.into());
+        assert_eq!(int(&scope, "speed"), 2.into());
+        let error = declare("const a: u8 = b; const b: u8 = c + 1; const c: u8 = b;")
+            .err()
+            .unwrap();
+        assert!(matches!(
+            &error.kind,
+            EvaluationErrorKind::DeclarationCycle(names) if **names == ["b".into(), "c".into()]
+        ));
+        assert!(error.span.is_some());
+    }
+
+    #[test]
+    fn compound_assignments() {
+        let scope = declare(
+            "struct t {
+                y: u8,
+            }
+            struct s {
+                x: u8,
+                inner: t,
+            }
+            fn bump() -> u8 {
+                var v: s = .{ .x = 1, .inner = .{ .y = 4 } };
+                v.x += 2;
+                v.inner.y *= v.x;
+                return v.x + v.inner.y * 10;
+            }
+            const bumped = bump();",
+        )
+        .unwrap();
+        assert_eq!(int(&scope, "bumped"), 123.into());
+    }
+
+    #[test]
+    fn equality() {
+        let scope = declare(
+            "enum color { red, green }
+            fn is_red(c: color) -> bool {
+                return c == .red;
+            }
+            const red = is_red(.red);
+            const green = is_red(.green);
+            const c: color = .green;
+            const not_red = c != .red;
+            const none: ?u8 = null;
+            const some: ?u8 = 3;
+            const none_is_null = none == null;
+            const some_is_null = some == null;
+            const same_payload = some == 3;
+            const other_payload = some != 4;
+            const same_type = u8 == u8;
+            const other_type = ?u8 == u8;",
+        )
+        .unwrap();
+        for (name, expected) in [
+            ("red", true),
+            ("green", false),
+            ("not_red", true),
+            ("none_is_null", true),
+            ("some_is_null", false),
+            ("same_payload", true),
+            ("other_payload", true),
+            ("same_type", true),
+            ("other_type", false),
+        ] {
+            assert!(
+                matches!(get(&scope, name), Value::Bool(value) if value == expected),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn private_fields() {
+        let source = "struct counter {
+                count: u8 = 1,
+                pub fn get(c: counter) -> u8 {
+                    return c.count;
+                }
+            }
+            const c: counter = .{};";
+        // Private declarations are visible throughout the file declaring them
+        let declared = declare(&format!("{source} const count = c.count;")).unwrap();
+        assert_eq!(int(&declared, "count"), 1.into());
+
+        let scope = EvalExecScope::new();
+        for name in ["counter", "c"] {
+            scope.declare(name, get(&declared, name), None, false);
+        }
+        declare_in("const read = counter.get(c);", &scope).unwrap();
+        assert_eq!(int(&scope, "read"), 1.into());
+        assert!(matches!(
+            declare_in("const leaked = c.count;", &scope)
+                .err()
+                .unwrap()
+                .kind,
+            EvaluationErrorKind::Field {
+                error: FieldError::Private {
+                    declared_at: Some(TextSpan { idx: 33, len: 13 })
+                },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn function_types() {
+        let scope = declare(
+            "fn double(x: u8) -> u8 { return x * 2; }
+            fn identity(T: type, x: T) -> T { return x; }
+            fn make() -> type { return u8; }
+            const runtime = double;
+            const comptime = identity;
+            const also_comptime = make;
+            const x = identity(u8, 3);",
+        )
+        .unwrap();
+        assert!(matches!(
+            get(&scope, "runtime").get_type(),
+            Type::RuntimeFunction(_)
+        ));
+        assert!(matches!(
+            get(&scope, "comptime").get_type(),
+            Type::ComptimeFunction(_)
+        ));
+        assert!(matches!(
+            get(&scope, "also_comptime").get_type(),
+            Type::ComptimeFunction(_)
+        ));
+        assert_eq!(int(&scope, "x"), 3.into());
+    }
+
+    #[test]
+    fn comptime_numbers() {
+        let scope = declare(
+            "const big = 1 << 70;
+            const small: u8 = big >> 63;
+            const half = 1.0 / 2;
+            const ratio: f32 = half * 3;
+            const typed: comptime_float = 7;",
+        )
+        .unwrap();
+        assert!(matches!(
+            get(&scope, "big"),
+            Value::ComptimeInt(value) if value == BigInt::from(1) << 70
+        ));
+        assert_eq!(int(&scope, "small"), 128.into());
+        let Value::ComptimeFloat(half) = get(&scope, "half") else {
+            panic!()
+        };
+        assert_eq!(half.to_string(), "0.5");
+        let Value::Float(ratio) = get(&scope, "ratio") else {
+            panic!()
+        };
+        assert_eq!(ratio.to_f64(), Some(1.5));
+        assert!(matches!(get(&scope, "typed"), Value::ComptimeFloat(_)));
+
+        // Coercions to sized types keep the exact value
+        assert!(matches!(
+            error("const x: u8 = 256;"),
+            EvaluationErrorKind::IntOverflow { value, .. } if value == 256.into()
+        ));
+        assert_eq!(
+            error_span("const x: u8 = 256;"),
+            Some(TextSpan { idx: 14, len: 3 })
+        );
+        assert!(matches!(
+            error("const x: u8 = 2.5;"),
+            EvaluationErrorKind::LossyCoercion { .. }
+        ));
+    }
+
+    #[test]
+    fn evaluation_errors() {
+        assert!(matches!(
+            error("fn f() -> u8 { return 200 + 100; } const x = f();"),
+            EvaluationErrorKind::IntOverflow { value, .. } if value == 300.into()
+        ));
+        assert_eq!(
+            error_span("fn f() -> u8 { return 200 + 100; } const x = f();"),
+            Some(TextSpan { idx: 22, len: 9 })
+        );
+        // Variant values are blamed on the variant given them, or following the one given them
+        assert!(matches!(
+            error("enum(u8) e { a = 256 }"),
+            EvaluationErrorKind::IntOverflow { value, .. } if value == 256.into()
+        ));
+        assert_eq!(
+            error_span("enum(u8) e { a = 256 }"),
+            Some(TextSpan { idx: 17, len: 3 })
+        );
+        assert!(matches!(
+            error("enum(u8) e { a = 255, b }"),
+            EvaluationErrorKind::Container(ContainerError::VariantOverflow { .. })
+        ));
+        assert_eq!(
+            error_span("enum(u8) e { a = 255, b }"),
+            Some(TextSpan { idx: 22, len: 1 })
+        );
+        assert!(matches!(
+            error("enum e { a = 1, b = 1 }"),
+            EvaluationErrorKind::Container(ContainerError::DuplicateVariantValue { .. })
+        ));
+        assert_eq!(
+            error_span("enum e { a = 1, b = 1 }"),
+            Some(TextSpan { idx: 20, len: 1 })
+        );
+        assert!(matches!(
+            error("fn f(n: u32) -> u32 { return f(n); } const x = f(1);"),
+            EvaluationErrorKind::CallDepthExceeded
+        ));
+        assert!(matches!(
+            error("fn f() -> u32 { while (true) {} return 0; } const x = f();"),
+            EvaluationErrorKind::BranchQuotaExceeded
+        ));
+        assert!(matches!(
+            error("fn f(n: u32) -> u32 { return n / 0; } const x = f(1);"),
+            EvaluationErrorKind::DivisionByZero
+        ));
+        assert!(matches!(
+            error("fn f(n: u32) -> u32 { return n; } const x = f(1, 2);"),
+            EvaluationErrorKind::ArgumentCount {
+                expected: 1,
+                found: 2
+            }
+        ));
+        assert!(matches!(
+            error("const size = @sizeOf([1 << 62]u64);"),
+            EvaluationErrorKind::Layout(LayoutError::TooLarge)
+        ));
+        assert!(matches!(
+            error("const size = @sizeOf([1 << 40][1 << 40]u64);"),
+            EvaluationErrorKind::Layout(LayoutError::TooLarge)
+        ));
+        assert!(matches!(
+            error("struct s { x: u8 } const x = s.y;"),
+            EvaluationErrorKind::Field { name, .. } if &*name == "y"
+        ));
+        // Functions don't see the locals of their caller
+        let error = declare("fn f() -> u8 { return x; } fn g() -> u8 { const x: u8 = 1; return f(); } const y = g();")
+            .err()
+            .unwrap();
+        assert!(matches!(error.kind, EvaluationErrorKind::Undefined(name) if &*name == "x"));
+        assert!(error.span.is_some());
+    }
+}
@@ -0,0 +1,99 @@
+//! Declarations of the simple form. Their bodies, values and types are core expressions, in which
+//! the syntactic sugar of the source has been lowered.
+
+use cera_ast::{expressions::Expression, types::Visibility};
+use cera_parse::text_region::TextSpan;
+use serde::{Deserialize, Serialize};
+
+/// A file, which behaves like the body of a struct
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Module {
+    pub decls: Box<[Decl]>,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Decl {
+    pub name: Box<str>,
+    pub visibility: Visibility,
+    pub kind: DeclKind,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DeclKind {
+    Var {
+        is_mutable: bool,
+        var_type: Option<Expression>,
+        value: Expression,
+    },
+    Fn(Function),
+    Container(Container),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Function {
+    pub params: Box<[Param]>,
+    /// `None` if the function returns `void`
+    pub return_type: Option<Expression>,
+    pub body: Expression,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Param {
+    pub name: Box<str>,
+    pub param_type: Expression,
+    pub span: TextSpan,
+}
+
+/// A function without a body, required by a trait
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signature {
+    pub name: Box<str>,
+    pub params: Box<[Param]>,
+    pub return_type: Option<Expression>,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Container {
+    pub kind: ContainerKind,
+    /// Fields, or the variants of enums, in declaration order
+    pub fields: Box<[Field]>,
+    pub decls: Box<[Decl]>,
+    /// The functions traits require, empty for other containers
+    pub methods: Box<[Signature]>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ContainerKind {
+    Struct,
+    Enum {
+        backing_type: Option<Expression>,
+        /// `false` if the enum has a `_` member
+        is_exhaustive: bool,
+    },
+    Union {
+        tag: UnionTag,
+    },
+    Trait,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum UnionTag {
+    Untagged,
+    /// `union(enum)`
+    Inferred,
+    Explicit(Expression),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Field {
+    pub name: Box<str>,
+    pub visibility: Visibility,
+    /// `None` for the variants of enums
+    pub field_type: Option<Expression>,
+    /// The default value of fields, or the value given to variants
+    pub value: Option<Expression>,
+    pub span: TextSpan,
+}
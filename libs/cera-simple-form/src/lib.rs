@@ -0,0 +1,4 @@
+pub mod check;
+pub mod comptime;
+pub mod decls;
+pub mod lower;
@@ -0,0 +1,1162 @@
+//! Lowering of the source AST into the simple form. Loops become `Loop`s exited with `Break`s,
+//! the operators on optionals and error unions become `If`s, and `defer`s are copied to every
+//! exit of their block. Expressions written in the source keep their span with
+//! `Expression::Spanned`, while the ones introduced by lowering don't have any.
+
+use cera_ast::{
+    builtin::{BuiltinFunction, PointerSize},
+    expressions::{Expression, LabelId, Let},
+    types::{AnonField, Visibility},
+    values::Value,
+};
+use cera_parse::{src_token::Literal, text_region::TextSpan};
+use cera_src_ast::ast::{
+    self, BinaryOp, Block, ContainerDecl, Expr, ExprKind, File, Ident, Item, ItemKind, Member,
+    Mutability, Param, PointerKind, PostfixOp, PrefixOp, Stmt, StmtKind, VarDecl,
+};
+use num_bigint::BigUint;
+
+use crate::decls::{
+    Container, ContainerKind, Decl, DeclKind, Field, Function, Module, Signature, UnionTag,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LowerError {
+    /// `@name(...)` where `name` isn't a builtin
+    UnknownBuiltin(Ident),
+    /// `break` or `continue` outside of a loop
+    NoLoop(TextSpan),
+    UndefinedLabel(Ident),
+    /// `continue :label` where `label` is a block
+    ContinueBlock(Ident),
+    /// `return`, `try`, `break` or `continue` leaving the statement of a `defer`
+    ExitFromDefer(TextSpan),
+    /// A `for` with more or less captures than inputs
+    CaptureCount(TextSpan),
+    /// A `for` of which every input is a range without an end
+    UnboundedFor(TextSpan),
+}
+
+/// Lowers a file, skipping the declarations the parser couldn't parse
+pub fn lower_file(file: &File) -> (Module, Vec<LowerError>) {
+    let mut lowerer = Lowerer::default();
+    let decls = lowerer.decls(&file.items);
+    let module = Module {
+        decls,
+        span: file.span.clone(),
+    };
+    (module, lowerer.errors)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Deferred<'a> {
+    Always(&'a Stmt),
+    OnError(Option<&'a Ident>, &'a Stmt),
+}
+
+enum Scope<'a> {
+    /// A block, with the `defer`s declared so far
+    Block(Vec<Deferred<'a>>),
+    /// A loop or a labeled block, which `break` can exit
+    Target {
+        name: Option<&'a str>,
+        break_label: LabelId,
+        /// `None` for blocks
+        continue_label: Option<LabelId>,
+    },
+    /// The statement of a `defer`, which can't be left early
+    Defer,
+}
+
+#[derive(Default)]
+struct Lowerer<'a> {
+    scopes: Vec<Scope<'a>>,
+    errors: Vec<LowerError>,
+    next_label: u32,
+    next_temp: u32,
+}
+
+fn visibility(visibility: ast::Visibility) -> Visibility {
+    match visibility {
+        ast::Visibility::Private => Visibility::Private,
+        ast::Visibility::Public => Visibility::Public,
+    }
+}
+
+fn name(name: &str) -> Expression {
+    Expression::Name(name.into())
+}
+
+fn int(value: u32) -> Expression {
+    Expression::Literal(Literal::Int(BigUint::from(value)))
+}
+
+fn void() -> Expression {
+    Expression::Value(Box::new(Value::Void))
+}
+
+fn bool(value: bool) -> Expression {
+    Expression::Value(Box::new(Value::Bool(value)))
+}
+
+/// Stands in for expressions which couldn't be lowered
+fn undefined() -> Expression {
+    Expression::Value(Box::new(Value::Undefined))
+}
+
+fn list(expressions: Vec<Expression>) -> Expression {
+    Expression::ExpressionList(expressions.into_boxed_slice())
+}
+
+fn builtin(function: BuiltinFunction, args: Vec<Expression>) -> Expression {
+    Expression::Builtin(function, args.into_boxed_slice())
+}
+
+fn let_(name: &str, is_mutable: bool, value: Expression) -> Expression {
+    Expression::Let(Box::new(Let {
+        name: name.into(),
+        is_mutable,
+        var_type: None,
+        value,
+    }))
+}
+
+fn if_(condition: Expression, then: Expression, otherwise: Expression) -> Expression {
+    Expression::If {
+        condition: Box::new(condition),
+        then: Box::new(then),
+        otherwise: Box::new(otherwise),
+    }
+}
+
+fn labeled(label: LabelId, body: Expression) -> Expression {
+    Expression::Labeled {
+        label,
+        body: Box::new(body),
+    }
+}
+
+fn break_(label: LabelId, value: Expression) -> Expression {
+    Expression::Break {
+        label,
+        value: Box::new(value),
+    }
+}
+
+impl<'a> Lowerer<'a> {
+    fn label(&mut self) -> LabelId {
+        self.next_label += 1;
+        LabelId(self.next_label - 1)
+    }
+
+    /// A name for a value introduced by lowering, which can't collide with identifiers
+    fn temp(&mut self, hint: &str) -> Box<str> {
+        self.next_temp += 1;
+        format!("{hint}%{}", self.next_temp - 1).into()
+    }
+
+    fn decls(&mut self, items: &'a [Item]) -> Box<[Decl]> {
+        items.iter().filter_map(|item| self.item(item)).collect()
+    }
+
+    fn item(&mut self, item: &'a Item) -> Option<Decl> {
+        let (name, kind) = match &item.kind {
+            ItemKind::Fn(decl) => {
+                let function = Function {
+                    params: self.params(&decl.params),
+                    return_type: decl.return_type.as_ref().map(|expr| self.expr(expr)),
+                    body: self.block(&decl.body),
+                };
+                (&decl.name, DeclKind::Fn(function))
+            }
+            ItemKind::Var(decl) => {
+                let Expression::Let(let_) = self.var(decl) else {
+                    unreachable!()
+                };
+                let Let {
+                    is_mutable,
+                    var_type,
+                    value,
+                    ..
+                } = *let_;
+                let kind = DeclKind::Var {
+                    is_mutable,
+                    var_type,
+                    value,
+                };
+                (&decl.name, kind)
+            }
+            ItemKind::Container(decl) => (&decl.name, DeclKind::Container(self.container(decl))),
+            ItemKind::Error => return None,
+        };
+        Some(Decl {
+            name: name.name.clone(),
+            visibility: visibility(item.visibility),
+            kind,
+            span: item.span.clone(),
+        })
+    }
+
+    fn params(&mut self, params: &'a [Param]) -> Box<[crate::decls::Param]> {
+        params
+            .iter()
+            .map(|param| crate::decls::Param {
+                name: param.name.name.clone(),
+                param_type: self.expr(&param.param_type),
+                span: param.span.clone(),
+            })
+            .collect()
+    }
+
+    fn container(&mut self, decl: &'a ContainerDecl) -> Container {
+        let mut fields = Vec::new();
+        let mut decls = Vec::new();
+        let mut methods = Vec::new();
+        let mut is_exhaustive = true;
+        for member in &*decl.members {
+            match member {
+                Member::Field(field) => fields.push(Field {
+                    name: field.name.name.clone(),
+                    visibility: visibility(field.visibility),
+                    field_type: Some(self.expr(&field.field_type)),
+                    value: field.default.as_ref().map(|expr| self.expr(expr)),
+                    span: field.span.clone(),
+                }),
+                Member::Variant(variant) => fields.push(Field {
+                    name: variant.name.name.clone(),
+                    visibility: Visibility::Public,
+                    field_type: None,
+                    value: variant.value.as_ref().map(|expr| self.expr(expr)),
+                    span: variant.span.clone(),
+                }),
+                Member::NonExhaustive(_) => is_exhaustive = false,
+                Member::Method(signature) => methods.push(Signature {
+                    name: signature.name.name.clone(),
+                    params: self.params(&signature.params),
+                    return_type: signature.return_type.as_ref().map(|expr| self.expr(expr)),
+                    span: signature.span.clone(),
+                }),
+                Member::Item(item) => decls.extend(self.item(item)),
+                Member::Error(_) => {}
+            }
+        }
+        let kind = match &decl.kind {
+            ast::ContainerKind::Struct => ContainerKind::Struct,
+            ast::ContainerKind::Enum { backing_type } => ContainerKind::Enum {
+                backing_type: backing_type.as_ref().map(|expr| self.expr(expr)),
+                is_exhaustive,
+            },
+            ast::ContainerKind::Union { tag } => ContainerKind::Union {
+                tag: match tag {
+                    None => UnionTag::Untagged,
+                    Some(ast::UnionTag::Inferred(_)) => UnionTag::Inferred,
+                    Some(ast::UnionTag::Explicit(expr)) => UnionTag::Explicit(self.expr(expr)),
+                },
+            },
+            ast::ContainerKind::Trait => ContainerKind::Trait,
+        };
+        Container {
+            kind,
+            fields: fields.into_boxed_slice(),
+            decls: decls.into_boxed_slice(),
+            methods: methods.into_boxed_slice(),
+        }
+    }
+
+    fn var(&mut self, decl: &'a VarDecl) -> Expression {
+        Expression::Let(Box::new(Let {
+            name: decl.name.name.clone(),
+            is_mutable: decl.mutability == Mutability::Var,
+            var_type: decl.var_type.as_ref().map(|expr| self.expr(expr)),
+            value: self.expr(&decl.value),
+        }))
+    }
+
+    fn block(&mut self, block: &'a Block) -> Expression {
+        self.scopes.push(Scope::Block(Vec::new()));
+        let mut expressions = Vec::with_capacity(block.stmts.len());
+        for stmt in &*block.stmts {
+            let deferred = match &stmt.kind {
+                StmtKind::Defer(stmt) => Deferred::Always(stmt),
+                StmtKind::ErrDefer { capture, stmt } => Deferred::OnError(capture.as_ref(), stmt),
+                _ => {
+                    expressions.push(self.stmt(stmt));
+                    continue;
+                }
+            };
+            let Some(Scope::Block(scope)) = self.scopes.last_mut() else {
+                unreachable!()
+            };
+            scope.push(deferred);
+        }
+        let depth = self.scopes.len() - 1;
+        expressions.extend(self.unwind(depth, None));
+        self.scopes.pop();
+        Expression::Spanned(block.span.clone(), Box::new(list(expressions)))
+    }
+
+    fn labeled_block(&mut self, block: &'a Block) -> Expression {
+        let Some(label) = &block.label else {
+            return self.block(block);
+        };
+        let break_label = self.label();
+        self.scopes.push(Scope::Target {
+            name: Some(&label.name),
+            break_label,
+            continue_label: None,
+        });
+        let body = self.block(block);
+        self.scopes.pop();
+        labeled(break_label, body)
+    }
+
+    /// The `defer`s executed when leaving the scopes from `depth`, innermost first. The ones of
+    /// `errdefer`s are only included when returning the value named `returned`, and only
+    /// executed if it is an error.
+    fn unwind(&mut self, depth: usize, returned: Option<&str>) -> Vec<Expression> {
+        let deferred: Vec<_> = self.scopes[depth..]
+            .iter()
+            .rev()
+            .filter_map(|scope| match scope {
+                Scope::Block(deferred) => Some(deferred),
+                _ => None,
+            })
+            .flat_map(|deferred| deferred.iter().rev().copied())
+            .collect();
+        deferred
+            .into_iter()
+            .filter_map(|deferred| match deferred {
+                Deferred::Always(stmt) => Some(self.deferred(stmt)),
+                Deferred::OnError(capture, stmt) => {
+                    let returned = returned?;
+                    let mut then = Vec::with_capacity(2);
+                    if let Some(capture) = capture {
+                        let error = builtin(BuiltinFunction::ErrorOf, vec![name(returned)]);
+                        then.push(let_(&capture.name, false, error));
+                    }
+                    then.push(self.deferred(stmt));
+                    let is_error = builtin(BuiltinFunction::IsError, vec![name(returned)]);
+                    Some(if_(is_error, list(then), void()))
+                }
+            })
+            .collect()
+    }
+
+    fn deferred(&mut self, stmt: &'a Stmt) -> Expression {
+        self.scopes.push(Scope::Defer);
+        let expression = self.stmt(stmt);
+        self.scopes.pop();
+        expression
+    }
+
+    fn exit_return(&mut self, value: Option<Expression>, span: TextSpan) -> Expression {
+        if self
+            .scopes
+            .iter()
+            .any(|scope| matches!(scope, Scope::Defer))
+        {
+            self.errors.push(LowerError::ExitFromDefer(span));
+            return Expression::Return(Box::new(value.unwrap_or_else(void)));
+        }
+        let Some(value) = value else {
+            let mut expressions = self.unwind(0, None);
+            if expressions.is_empty() {
+                return Expression::Return(Box::new(void()));
+            }
+            expressions.push(Expression::Return(Box::new(void())));
+            return list(expressions);
+        };
+        let returned = self.temp("return");
+        let unwound = self.unwind(0, Some(&returned));
+        if unwound.is_empty() {
+            return Expression::Return(Box::new(value));
+        }
+        let mut expressions = vec![let_(&returned, false, value)];
+        expressions.extend(unwound);
+        expressions.push(Expression::Return(Box::new(name(&returned))));
+        list(expressions)
+    }
+
+    /// `break` if `is_continue` is false, `continue` otherwise
+    fn exit_loop(
+        &mut self,
+        label: Option<&'a Ident>,
+        value: Option<Expression>,
+        is_continue: bool,
+        span: TextSpan,
+    ) -> Expression {
+        let mut in_defer = false;
+        let target = self
+            .scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, scope)| match scope {
+                Scope::Target {
+                    name,
+                    break_label,
+                    continue_label,
+                } => {
+                    let found = match label {
+                        Some(label) => *name == Some(&*label.name),
+                        None => continue_label.is_some(),
+                    };
+                    found.then_some((depth, *break_label, *continue_label))
+                }
+                Scope::Defer => {
+                    in_defer = true;
+                    None
+                }
+                Scope::Block(_) => None,
+            });
+        let error = match (target, label) {
+            (Some(_), _) if in_defer => LowerError::ExitFromDefer(span),
+            (Some((_, _, None)), Some(label)) if is_continue => {
+                LowerError::ContinueBlock(label.clone())
+            }
+            (Some((depth, break_label, continue_label)), _) => {
+                let label = continue_label
+                    .filter(|_| is_continue)
+                    .unwrap_or(break_label);
+                let mut expressions = self.unwind(depth + 1, None);
+                let value = match value {
+                    Some(value) if !expressions.is_empty() => {
+                        let temp = self.temp("break");
+                        expressions.insert(0, let_(&temp, false, value));
+                        name(&temp)
+                    }
+                    value => value.unwrap_or_else(void),
+                };
+                expressions.push(break_(label, value));
+                return match expressions.len() {
+                    1 => expressions.pop().unwrap(),
+                    _ => list(expressions),
+                };
+            }
+            (None, Some(label)) => LowerError::UndefinedLabel(label.clone()),
+            (None, None) => LowerError::NoLoop(span),
+        };
+        self.errors.push(error);
+        void()
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt) -> Expression {
+        let expression = match &stmt.kind {
+            StmtKind::Var(decl) => self.var(decl),
+            StmtKind::Assign { target, op, value } => self.assign(target, *op, value),
+            StmtKind::Return(value) => {
+                let value = value.as_ref().map(|expr| self.expr(expr));
+                self.exit_return(value, stmt.span.clone())
+            }
+            StmtKind::Break { label, value } => {
+                let value = value.as_ref().map(|expr| self.expr(expr));
+                self.exit_loop(label.as_ref(), value, false, stmt.span.clone())
+            }
+            StmtKind::Continue { label } => {
+                self.exit_loop(label.as_ref(), None, true, stmt.span.clone())
+            }
+            StmtKind::If {
+                condition,
+                capture,
+                then_block,
+                else_branch,
+            } => {
+                let condition = self.expr(condition);
+                let then = self.block(then_block);
+                let otherwise = match else_branch {
+                    Some(stmt) => self.stmt(stmt),
+                    None => void(),
+                };
+                self.conditional(condition, capture.as_ref(), then, otherwise)
+            }
+            StmtKind::While {
+                label,
+                condition,
+                capture,
+                continue_stmt,
+                body,
+            } => {
+                let condition = self.expr(condition);
+                let break_label = self.label();
+                let continue_label = self.label();
+                self.scopes.push(Scope::Target {
+                    name: label.as_ref().map(|label| &*label.name),
+                    break_label,
+                    continue_label: Some(continue_label),
+                });
+                let mut iteration = vec![labeled(continue_label, self.block(body))];
+                if let Some(stmt) = continue_stmt {
+                    iteration.push(self.stmt(stmt));
+                }
+                self.scopes.pop();
+                let exit = break_(break_label, void());
+                let iteration =
+                    self.conditional(condition, capture.as_ref(), list(iteration), exit);
+                labeled(break_label, Expression::Loop(Box::new(iteration)))
+            }
+            StmtKind::For {
+                label,
+                inputs,
+                captures,
+                body,
+            } => self.for_loop(label.as_ref(), inputs, captures, body, stmt.span.clone()),
+            // Only blocks declare `defer`s, so these are the statement of another `defer`, which
+            // is executed right away as it has nothing to wait for
+            StmtKind::Defer(stmt) | StmtKind::ErrDefer { stmt, .. } => return self.stmt(stmt),
+            StmtKind::Block(block) => return self.labeled_block(block),
+            StmtKind::Expr(expr) => return self.expr(expr),
+            StmtKind::Error => void(),
+        };
+        Expression::Spanned(stmt.span.clone(), Box::new(expression))
+    }
+
+    /// `if (condition) |capture| then else otherwise`, where `condition` is an optional if there
+    /// is a capture
+    fn conditional(
+        &mut self,
+        condition: Expression,
+        capture: Option<&Ident>,
+        then: Expression,
+        otherwise: Expression,
+    ) -> Expression {
+        let Some(capture) = capture else {
+            return if_(condition, then, otherwise);
+        };
+        let optional = self.temp("if");
+        let is_null = builtin(BuiltinFunction::IsNull, vec![name(&optional)]);
+        let some = builtin(BuiltinFunction::UnwrapOptional, vec![name(&optional)]);
+        list(vec![
+            let_(&optional, false, condition),
+            if_(
+                is_null,
+                otherwise,
+                list(vec![let_(&capture.name, false, some), then]),
+            ),
+        ])
+    }
+
+    /// Iterates with an index going up to the length of the first input which has one
+    fn for_loop(
+        &mut self,
+        label: Option<&'a Ident>,
+        inputs: &'a [Expr],
+        captures: &'a [Ident],
+        body: &'a Block,
+        span: TextSpan,
+    ) -> Expression {
+        if inputs.len() != captures.len() {
+            self.errors.push(LowerError::CaptureCount(span.clone()));
+        }
+        let index = self.temp("index");
+        let mut expressions = Vec::with_capacity(inputs.len() + 3);
+        let mut elements = Vec::with_capacity(inputs.len());
+        let mut len = None;
+        for input in inputs {
+            let element = match &input.kind {
+                ExprKind::Range { start, end } => {
+                    let start_name = self.temp("start");
+                    let start = self.expr(start);
+                    expressions.push(let_(&start_name, false, start));
+                    if let Some(end) = end {
+                        let end_name = self.temp("end");
+                        let end = self.expr(end);
+                        expressions.push(let_(&end_name, false, end));
+                        len.get_or_insert_with(|| {
+                            builtin(
+                                BuiltinFunction::Sub,
+                                vec![name(&end_name), name(&start_name)],
+                            )
+                        });
+                    }
+                    builtin(BuiltinFunction::Add, vec![name(&start_name), name(&index)])
+                }
+                _ => {
+                    let input_name = self.temp("input");
+                    let input = self.expr(input);
+                    expressions.push(let_(&input_name, false, input));
+                    len.get_or_insert_with(|| {
+                        builtin(BuiltinFunction::Len, vec![name(&input_name)])
+                    });
+                    builtin(
+                        BuiltinFunction::Index,
+                        vec![name(&input_name), name(&index)],
+                    )
+                }
+            };
+            elements.push(element);
+        }
+        let Some(len) = len else {
+            self.errors.push(LowerError::UnboundedFor(span));
+            return void();
+        };
+        let len_name = self.temp("len");
+        expressions.push(let_(&len_name, false, len));
+        expressions.push(let_(&index, true, int(0)));
+
+        let break_label = self.label();
+        let continue_label = self.label();
+        self.scopes.push(Scope::Target {
+            name: label.map(|label| &*label.name),
+            break_label,
+            continue_label: Some(continue_label),
+        });
+        let body = self.block(body);
+        self.scopes.pop();
+
+        let mut iteration: Vec<_> = captures
+            .iter()
+            .zip(elements)
+            .map(|(capture, element)| let_(&capture.name, false, element))
+            .collect();
+        iteration.push(labeled(continue_label, body));
+        iteration.push(Expression::Assign {
+            target: Box::new(name(&index)),
+            value: Box::new(builtin(BuiltinFunction::Add, vec![name(&index), int(1)])),
+        });
+        let in_bounds = builtin(
+            BuiltinFunction::LessThan,
+            vec![name(&index), name(&len_name)],
+        );
+        let iteration = if_(in_bounds, list(iteration), break_(break_label, void()));
+        expressions.push(labeled(break_label, Expression::Loop(Box::new(iteration))));
+        list(expressions)
+    }
+
+    /// `target op= value` assigns `target op value`. The indices, pointers and other values
+    /// `target` is made of are evaluated once, before it is both read and assigned.
+    fn assign(&mut self, target: &'a Expr, op: Option<BinaryOp>, value: &'a Expr) -> Expression {
+        let target = self.expr(target);
+        let value = self.expr(value);
+        let Some(op) = op else {
+            return Expression::Assign {
+                target: Box::new(target),
+                value: Box::new(value),
+            };
+        };
+        let mut expressions = Vec::new();
+        let target = self.evaluate_once(target, &mut expressions);
+        let value = self.binary(op, target.clone(), value);
+        let assign = Expression::Assign {
+            target: Box::new(target),
+            value: Box::new(value),
+        };
+        if expressions.is_empty() {
+            return assign;
+        }
+        expressions.push(assign);
+        list(expressions)
+    }
+
+    /// The place `place` with its indices, dereferenced pointers and other values, like the call
+    /// in `f().x`, bound to temporaries by `expressions`, so that it refers to the same place
+    /// every time it is evaluated
+    fn evaluate_once(
+        &mut self,
+        place: Expression,
+        expressions: &mut Vec<Expression>,
+    ) -> Expression {
+        match place {
+            Expression::Spanned(span, place) => {
+                Expression::Spanned(span, Box::new(self.evaluate_once(*place, expressions)))
+            }
+            Expression::Field { base, name } => Expression::Field {
+                base: Box::new(self.evaluate_once(*base, expressions)),
+                name,
+            },
+            Expression::Builtin(
+                function @ (BuiltinFunction::Index | BuiltinFunction::Deref),
+                args,
+            ) => {
+                let hint = match function {
+                    BuiltinFunction::Index => "index",
+                    _ => "pointer",
+                };
+                let mut operands = Vec::with_capacity(args.len());
+                for arg in args.into_vec() {
+                    operands.push(match (&function, operands.is_empty()) {
+                        // The indexed array is a place itself, unlike the index
+                        (BuiltinFunction::Index, true) => self.evaluate_once(arg, expressions),
+                        _ => self.bind_temp(hint, arg, expressions),
+                    });
+                }
+                builtin(function, operands)
+            }
+            place @ Expression::Name(_) => place,
+            value => self.bind_temp("base", value, expressions),
+        }
+    }
+
+    /// A name bound to `value` by `expressions`, or `value` itself when evaluating it again gives
+    /// the same value
+    fn bind_temp(
+        &mut self,
+        hint: &str,
+        value: Expression,
+        expressions: &mut Vec<Expression>,
+    ) -> Expression {
+        if let Expression::Name(_) | Expression::Literal(_) = value {
+            return value;
+        }
+        let temp = self.temp(hint);
+        expressions.push(let_(&temp, false, value));
+        name(&temp)
+    }
+
+    fn binary(&mut self, op: BinaryOp, lhs: Expression, rhs: Expression) -> Expression {
+        let function = match op {
+            BinaryOp::And => return if_(lhs, rhs, bool(false)),
+            BinaryOp::Or => return if_(lhs, bool(true), rhs),
+            BinaryOp::Orelse => {
+                let optional = self.temp("orelse");
+                let is_null = builtin(BuiltinFunction::IsNull, vec![name(&optional)]);
+                let some = builtin(BuiltinFunction::UnwrapOptional, vec![name(&optional)]);
+                return list(vec![let_(&optional, false, lhs), if_(is_null, rhs, some)]);
+            }
+            BinaryOp::Add => BuiltinFunction::Add,
+            BinaryOp::Sub => BuiltinFunction::Sub,
+            BinaryOp::Mul => BuiltinFunction::Mul,
+            BinaryOp::Div => BuiltinFunction::Div,
+            BinaryOp::Mod => BuiltinFunction::Mod,
+            BinaryOp::ErrorUnion => BuiltinFunction::ErrorUnionType,
+            BinaryOp::ShiftLeft => BuiltinFunction::ShiftLeft,
+            BinaryOp::ShiftRight => BuiltinFunction::ShiftRight,
+            BinaryOp::BitAnd => BuiltinFunction::BitAnd,
+            BinaryOp::BitXor => BuiltinFunction::BitXor,
+            BinaryOp::BitOr => BuiltinFunction::BitOr,
+            BinaryOp::Equal => BuiltinFunction::Equal,
+            BinaryOp::NotEqual => BuiltinFunction::NotEqual,
+            BinaryOp::LessThan => BuiltinFunction::LessThan,
+            BinaryOp::LessOrEqual => BuiltinFunction::LessOrEqual,
+            BinaryOp::GreaterThan => BuiltinFunction::GreaterThan,
+            BinaryOp::GreaterOrEqual => BuiltinFunction::GreaterOrEqual,
+        };
+        builtin(function, vec![lhs, rhs])
+    }
+
+    fn catch(&mut self, lhs: Expression, capture: Option<&Ident>, rhs: Expression) -> Expression {
+        let union = self.temp("catch");
+        let handler = match capture {
+            Some(capture) => {
+                let error = builtin(BuiltinFunction::ErrorOf, vec![name(&union)]);
+                list(vec![let_(&capture.name, false, error), rhs])
+            }
+            None => rhs,
+        };
+        let is_error = builtin(BuiltinFunction::IsError, vec![name(&union)]);
+        let payload = builtin(BuiltinFunction::UnwrapPayload, vec![name(&union)]);
+        list(vec![
+            let_(&union, false, lhs),
+            if_(is_error, handler, payload),
+        ])
+    }
+
+    /// `try operand` returns the error of `operand` if there is one, like
+    /// `operand catch |err| return err`
+    fn try_(&mut self, operand: Expression, span: TextSpan) -> Expression {
+        let union = self.temp("try");
+        let error = builtin(BuiltinFunction::ErrorOf, vec![name(&union)]);
+        let exit = self.exit_return(Some(error), span);
+        let is_error = builtin(BuiltinFunction::IsError, vec![name(&union)]);
+        let payload = builtin(BuiltinFunction::UnwrapPayload, vec![name(&union)]);
+        list(vec![
+            let_(&union, false, operand),
+            if_(is_error, exit, payload),
+        ])
+    }
+
+    fn exprs(&mut self, exprs: &'a [Expr]) -> Vec<Expression> {
+        exprs.iter().map(|expr| self.expr(expr)).collect()
+    }
+
+    fn expr(&mut self, expr: &'a Expr) -> Expression {
+        let expression = match &expr.kind {
+            ExprKind::Ident(ident) => name(ident),
+            ExprKind::Literal(literal) => Expression::Literal(literal.clone()),
+            ExprKind::Paren(expr) => return self.expr(expr),
+            ExprKind::Prefix {
+                op: PrefixOp::Try,
+                operand,
+            } => {
+                let operand = self.expr(operand);
+                self.try_(operand, expr.span.clone())
+            }
+            ExprKind::Prefix { op, operand } => {
+                let function = match op {
+                    PrefixOp::Not => BuiltinFunction::Not,
+                    PrefixOp::Negate => BuiltinFunction::Negate,
+                    PrefixOp::BitNot => BuiltinFunction::BitNot,
+                    PrefixOp::AddressOf => BuiltinFunction::AddressOf,
+                    PrefixOp::Optional => BuiltinFunction::OptionalType,
+                    PrefixOp::Try => unreachable!(),
+                };
+                builtin(function, vec![self.expr(operand)])
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let lhs = self.expr(lhs);
+                let rhs = self.expr(rhs);
+                self.binary(*op, lhs, rhs)
+            }
+            ExprKind::Postfix { op, operand } => {
+                let function = match op {
+                    PostfixOp::Deref => BuiltinFunction::Deref,
+                    PostfixOp::Unwrap => BuiltinFunction::UnwrapOptional,
+                };
+                builtin(function, vec![self.expr(operand)])
+            }
+            ExprKind::Call { callee, args } => Expression::Call {
+                callee: Box::new(self.expr(callee)),
+                args: self.exprs(args).into_boxed_slice(),
+            },
+            ExprKind::BuiltinCall { name, args } => {
+                let args = self.exprs(args);
+                match BuiltinFunction::from_name(&name.name) {
+                    Some(function) => builtin(function, args),
+                    None => {
+                        self.errors.push(LowerError::UnknownBuiltin(name.clone()));
+                        undefined()
+                    }
+                }
+            }
+            ExprKind::Field { base, field } => Expression::Field {
+                base: Box::new(self.expr(base)),
+                name: field.name.clone(),
+            },
+            // `base[start..end]` slices `base`
+            ExprKind::Index { base, index } => match &index.kind {
+                ExprKind::Range { start, end } => {
+                    let mut args = vec![self.expr(base), self.expr(start)];
+                    args.extend(end.as_deref().map(|end| self.expr(end)));
+                    builtin(BuiltinFunction::Slice, args)
+                }
+                _ => {
+                    let args = vec![self.expr(base), self.expr(index)];
+                    builtin(BuiltinFunction::Index, args)
+                }
+            },
+            ExprKind::Catch { lhs, capture, rhs } => {
+                let lhs = self.expr(lhs);
+                let rhs = self.expr(rhs);
+                self.catch(lhs, capture.as_ref(), rhs)
+            }
+            ExprKind::AnonInit(fields) => Expression::AnonInit(
+                fields
+                    .iter()
+                    .map(|field| {
+                        let value = self.expr(&field.value);
+                        match (&field.name, &field.value.kind) {
+                            (Some(name), _) => AnonField::Named(name.name.clone(), value),
+                            (None, ExprKind::Ident(name)) => AnonField::Ident(name.clone(), value),
+                            (None, _) => AnonField::Positional(value),
+                        }
+                    })
+                    .collect(),
+            ),
+            ExprKind::EnumLiteral(variant) => Expression::EnumLiteral(variant.name.clone()),
+            ExprKind::Range { .. } => {
+                unreachable!("ranges are only parsed as `for` inputs and slice bounds")
+            }
+            ExprKind::Block(block) => return self.labeled_block(block),
+            ExprKind::PointerType {
+                kind,
+                is_const,
+                sentinel,
+                pointee,
+            } => {
+                let size = match kind {
+                    PointerKind::Single => PointerSize::One,
+                    PointerKind::Many => PointerSize::Many,
+                    PointerKind::Slice => PointerSize::Slice,
+                };
+                let function = BuiltinFunction::PointerType {
+                    size,
+                    is_const: *is_const,
+                };
+                let mut args = vec![self.expr(pointee)];
+                args.extend(sentinel.as_deref().map(|sentinel| self.expr(sentinel)));
+                builtin(function, args)
+            }
+            ExprKind::ArrayType {
+                len,
+                sentinel,
+                element,
+            } => {
+                let mut args = vec![self.expr(len), self.expr(element)];
+                args.extend(sentinel.as_deref().map(|sentinel| self.expr(sentinel)));
+                builtin(BuiltinFunction::ArrayType, args)
+            }
+            ExprKind::FnType {
+                params,
+                return_type,
+            } => {
+                let mut args: Vec<_> = params
+                    .iter()
+                    .map(|param| self.expr(&param.param_type))
+                    .collect();
+                args.push(self.expr(return_type));
+                builtin(BuiltinFunction::FnType, args)
+            }
+        };
+        Expression::Spanned(expr.span.clone(), Box::new(expression))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cera_src_ast::parser::parse_file;
+
+    use super::*;
+
+    /// Prints expressions as S-expressions, without their spans
+    fn print(expression: &Expression) -> String {
+        let sexpr = |parts: Vec<String>| format!("({})", parts.join(" "));
+        let all = |expressions: &[Expression]| expressions.iter().map(print).collect::<Vec<_>>();
+        match expression {
+            Expression::Value(value) => match **value {
+                Value::Void => "void".into(),
+                Value::Undefined => "undefined".into(),
+                _ => format!("{:?}", value),
+            },
+            Expression::Literal(Literal::Int(int)) => int.to_string(),
+            Expression::Literal(literal) => format!("{:?}", literal),
+            Expression::Builtin(function, args) => {
+                sexpr([vec![format!("@{:?}", function)], all(args)].concat())
+            }
+            Expression::ExpressionList(expressions) => {
+                format!("{{{}}}", all(expressions).join(" "))
+            }
+            Expression::Name(name) => name.to_string(),
+            Expression::Let(let_) => sexpr(vec![
+                if let_.is_mutable { "var" } else { "let" }.into(),
+                let_.name.to_string(),
+                print(&let_.value),
+            ]),
+            Expression::Assign { target, value } => {
+                sexpr(vec!["=".into(), print(target), print(value)])
+            }
+            Expression::If {
+                condition,
+                then,
+                otherwise,
+            } => sexpr(vec![
+                "if".into(),
+                print(condition),
+                print(then),
+                print(otherwise),
+            ]),
+            Expression::Loop(body) => sexpr(vec!["loop".into(), print(body)]),
+            Expression::Labeled { label, body } => {
+                sexpr(vec![format!("L{}:", label.0), print(body)])
+            }
+            Expression::Break { label, value } => {
+                sexpr(vec!["break".into(), format!("L{}", label.0), print(value)])
+            }
+            Expression::Return(value) => sexpr(vec!["return".into(), print(value)]),
+            Expression::Call { callee, args } => sexpr([vec![print(callee)], all(args)].concat()),
+            Expression::Field { base, name } => format!("{}.{}", print(base), name),
+            Expression::AnonInit(fields) => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|field| match field {
+                        AnonField::Named(name, value) => format!(".{name}={}", print(value)),
+                        AnonField::Ident(_, value) | AnonField::Positional(value) => print(value),
+                    })
+                    .collect();
+                format!(".{{{}}}", fields.join(" "))
+            }
+            Expression::EnumLiteral(name) => format!(".{name}"),
+            Expression::Spanned(_, expression) => print(expression),
+        }
+    }
+
+    fn lower(text: &str) -> (Module, Vec<LowerError>) {
+        lower_file(&parse_file(text).unwrap())
+    }
+
+    /// The lowered body of a function made of `body`
+    fn body(body: &str) -> String {
+        let (module, errors) = lower(&format!("fn f() {{ {body} }}"));
+        assert_eq!(errors, []);
+        let DeclKind::Fn(function) = &module.decls[0].kind else {
+            panic!()
+        };
+        print(&function.body)
+    }
+
+    fn errors(body: &str) -> Vec<LowerError> {
+        lower(&format!("fn f() {{ {body} }}")).1
+    }
+
+    #[test]
+    fn loops() {
+        assert_eq!(
+            body("while (i < n) : (i += 1) { f(i); }"),
+            "{(L0: (loop (if (@LessThan i n) {(L1: {(f i)}) (= i (@Add i 1))} (break L0 void))))}"
+        );
+        assert_eq!(body("while (it.next()) |x| { continue; }"),
+            "{(L0: (loop {(let if%0 (it.next)) (if (@IsNull if%0) (break L0 void) {(let x (@UnwrapOptional if%0)) {(L1: {(break L1 void)})}})}))}");
+        assert_eq!(body("for (xs, 0..) |x, i| { f(x, i); }"),
+            "{{(let input%1 xs) (let start%2 0) (let len%3 (@Len input%1)) (var index%0 0) (L0: (loop (if (@LessThan index%0 len%3) {(let x (@Index input%1 index%0)) (let i (@Add start%2 index%0)) (L1: {(f x i)}) (= index%0 (@Add index%0 1))} (break L0 void))))}}");
+        assert_eq!(
+            body("outer: for (0..n) |i| { while (true) { break :outer; } }"),
+            "{{(let start%1 0) (let end%2 n) (let len%3 (@Sub end%2 start%1)) (var index%0 0) (L0: (loop (if (@LessThan index%0 len%3) {(let i (@Add start%1 index%0)) (L1: {(L2: (loop (if true {(L3: {(break L0 void)})} (break L2 void))))}) (= index%0 (@Add index%0 1))} (break L0 void))))}}"
+        );
+    }
+
+    #[test]
+    fn conditionals() {
+        assert_eq!(
+            body("if (a) { f(); } else if (b) { g(); } else { h(); }"),
+            "{(if a {(f)} (if b {(g)} {(h)}))}"
+        );
+        assert_eq!(
+            body("if (x) |y| { f(y); }"),
+            "{{(let if%0 x) (if (@IsNull if%0) void {(let y (@UnwrapOptional if%0)) {(f y)}})}}"
+        );
+        assert_eq!(
+            body("const x = a and b or c;"),
+            "{(let x (if (if a b Bool(false)) Bool(true) c))}"
+        );
+    }
+
+    #[test]
+    fn compound_assignments() {
+        assert_eq!(body("x += 1;"), "{(= x (@Add x 1))}");
+        assert_eq!(body("s.x *= 2;"), "{(= s.x (@Mul s.x 2))}");
+        assert_eq!(
+            body("a[i + 1].x -= 1;"),
+            "{{(let index%0 (@Add i 1)) (= (@Index a index%0).x (@Sub (@Index a index%0).x 1))}}"
+        );
+        assert_eq!(
+            body("f().* += 1;"),
+            "{{(let pointer%0 (f)) (= (@Deref pointer%0) (@Add (@Deref pointer%0) 1))}}"
+        );
+        let call_base = body("g().x += 2;");
+        assert_eq!(
+            call_base,
+            "{{(let base%0 (g)) (= base%0.x (@Add base%0.x 2))}}"
+        );
+        assert_eq!(call_base.matches("(g)").count(), 1);
+        let indexed_call = body("g()[h()] -= 1;");
+        assert_eq!(indexed_call.matches("(g)").count(), 1);
+        assert_eq!(indexed_call.matches("(h)").count(), 1);
+    }
+
+    #[test]
+    fn slices() {
+        assert_eq!(
+            body("const t = s[i + 1..];"),
+            "{(let t (@Slice s (@Add i 1)))}"
+        );
+        assert_eq!(body("const t = b[1..n];"), "{(let t (@Slice b 1 n))}");
+        assert_eq!(
+            body("const T = [:0]const u8;"),
+            "{(let T (@PointerType { size: Slice, is_const: true } u8 0))}"
+        );
+        assert_eq!(body("const T = [4:0]u8;"), "{(let T (@ArrayType 4 u8 0))}");
+    }
+
+    #[test]
+    fn optionals_and_errors() {
+        assert_eq!(
+            body("const x = a orelse b;"),
+            "{(let x {(let orelse%0 a) (if (@IsNull orelse%0) b (@UnwrapOptional orelse%0))})}"
+        );
+        assert_eq!(body("const x = f() catch |err| g(err);"),
+            "{(let x {(let catch%0 (f)) (if (@IsError catch%0) {(let err (@ErrorOf catch%0)) (g err)} (@UnwrapPayload catch%0))})}");
+        assert_eq!(body("const x = try f();"),
+            "{(let x {(let try%0 (f)) (if (@IsError try%0) (return (@ErrorOf try%0)) (@UnwrapPayload try%0))})}");
+    }
+
+    #[test]
+    fn defers() {
+        assert_eq!(
+            body("defer a(); { defer b(); c(); } d();"),
+            "{{(c) (b)} (d) (a)}"
+        );
+        assert_eq!(body("defer a(); errdefer |err| b(err); return f();"),
+            "{{(let return%0 (f)) (if (@IsError return%0) {(let err (@ErrorOf return%0)) (b err)} void) (a) (return return%0)} (a)}");
+        assert_eq!(body("errdefer b(); const x = try f(); return;"),
+            "{(let x {(let try%0 (f)) (if (@IsError try%0) {(let return%1 (@ErrorOf try%0)) (if (@IsError return%1) {(b)} void) (return return%1)} (@UnwrapPayload try%0))}) (return void)}");
+        assert_eq!(body("while (true) { defer a(); if (b) { break; } }"),
+            "{(L0: (loop (if true {(L1: {(if b {{(a) (break L0 void)}} void) (a)})} (break L0 void))))}");
+        assert_eq!(
+            body("const x = blk: { defer a(); break :blk 1; };"),
+            "{(let x (L0: {{(let break%0 1) (a) (break L0 break%0)} (a)}))}"
+        );
+    }
+
+    #[test]
+    fn lowering_errors() {
+        assert!(matches!(
+            errors("const x = @nope(1);")[..],
+            [LowerError::UnknownBuiltin(_)]
+        ));
+        assert!(matches!(errors("break;")[..], [LowerError::NoLoop(_)]));
+        assert!(matches!(
+            errors("while (a) { break :b; }")[..],
+            [LowerError::UndefinedLabel(_)]
+        ));
+        assert!(matches!(
+            errors("b: { continue :b; }")[..],
+            [LowerError::ContinueBlock(_)]
+        ));
+        assert!(matches!(
+            errors("while (a) { defer { break; } }")[..],
+            [LowerError::ExitFromDefer(_)]
+        ));
+        assert!(matches!(
+            errors("defer return;")[..],
+            [LowerError::ExitFromDefer(_)]
+        ));
+        assert!(matches!(
+            errors("for (0..) |i| {}")[..],
+            [LowerError::UnboundedFor(_)]
+        ));
+        assert!(matches!(
+            errors("for (xs) |x, i| {}")[..],
+            [LowerError::CaptureCount(_)]
+        ));
+    }
+
+    #[test]
+    fn declarations() {
+        let (module, errors) = lower(
+            "pub const c: u8 = 1;\n\
+             enum(u8) e { a, b = 4, _ }\n\
+             union(enum) u { x: u8, fn f() {} }",
+        );
+        assert_eq!(errors, []);
+        let names: Vec<_> = module.decls.iter().map(|decl| &*decl.name).collect();
+        assert_eq!(names, ["c", "e", "u"]);
+        let DeclKind::Var {
+            is_mutable: false,
+            var_type: Some(var_type),
+            value,
+        } = &module.decls[0].kind
+        else {
+            panic!()
+        };
+        assert_eq!((print(var_type), print(value)), ("u8".into(), "1".into()));
+        let DeclKind::Container(e) = &module.decls[1].kind else {
+            panic!()
+        };
+        assert!(matches!(
+            e.kind,
+            ContainerKind::Enum {
+                backing_type: Some(_),
+                is_exhaustive: false
+            }
+        ));
+        assert_eq!(e.fields.len(), 2);
+        let DeclKind::Container(u) = &module.decls[2].kind else {
+            panic!()
+        };
+        assert!(matches!(
+            u.kind,
+            ContainerKind::Union {
+                tag: UnionTag::Inferred
+            }
+        ));
+        assert_eq!((u.fields.len(), u.decls.len()), (1, 1));
+    }
+}
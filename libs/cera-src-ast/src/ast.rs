@@ -0,0 +1,445 @@
+use cera_parse::{src_token::Literal, text_region::TextSpan};
+use serde::{Deserialize, Serialize};
+
+use crate::suggest::NameKind;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ident {
+    pub name: Box<str>,
+    pub span: TextSpan,
+}
+
+/// A whole source file, which behaves like the body of a struct
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct File {
+    pub items: Box<[Item]>,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Visibility {
+    Private,
+    Public,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Item {
+    pub visibility: Visibility,
+    pub kind: ItemKind,
+    pub span: TextSpan,
+}
+
+impl Item {
+    /// `None` for declarations that couldn't be parsed
+    pub fn name(&self) -> Option<&Ident> {
+        match &self.kind {
+            ItemKind::Fn(decl) => Some(&decl.name),
+            ItemKind::Var(decl) => Some(&decl.name),
+            ItemKind::Container(decl) => Some(&decl.name),
+            ItemKind::Error => None,
+        }
+    }
+    /// Whether the item is a type, as far as its syntax tells
+    pub fn name_kind(&self) -> NameKind {
+        match self.kind {
+            ItemKind::Container(_) => NameKind::Type,
+            _ => NameKind::Value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemKind {
+    Fn(FnDecl),
+    Var(VarDecl),
+    Container(ContainerDecl),
+    /// A declaration that couldn't be parsed
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FnDecl {
+    pub name: Ident,
+    pub params: Box<[Param]>,
+    /// `None` if the function returns `void`
+    pub return_type: Option<Expr>,
+    pub body: Block,
+}
+
+/// A function without a body, declared by traits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FnSignature {
+    pub name: Ident,
+    pub params: Box<[Param]>,
+    pub return_type: Option<Expr>,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Param {
+    pub name: Ident,
+    pub param_type: Expr,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mutability {
+    Const,
+    Var,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VarDecl {
+    pub mutability: Mutability,
+    pub name: Ident,
+    pub var_type: Option<Expr>,
+    pub value: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContainerKind {
+    Struct,
+    /// `enum(backing_type) name { ... }`
+    Enum {
+        backing_type: Option<Expr>,
+    },
+    /// `union(tag) name { ... }`
+    Union {
+        tag: Option<UnionTag>,
+    },
+    Trait,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnionTag {
+    /// `union(enum)`, which declares an enum with a variant for each field
+    Inferred(TextSpan),
+    Explicit(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContainerDecl {
+    pub kind: ContainerKind,
+    pub name: Ident,
+    pub members: Box<[Member]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Member {
+    Field(FieldDecl),
+    /// A variant of an enum
+    Variant(VariantDecl),
+    /// `_`, making an enum non-exhaustive
+    NonExhaustive(TextSpan),
+    /// A function traits require implementations of
+    Method(FnSignature),
+    Item(Item),
+    /// A member that couldn't be parsed
+    Error(TextSpan),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VariantDecl {
+    pub name: Ident,
+    /// The value given with `name = value`, which is the value of the previous variant plus one
+    /// by default
+    pub value: Option<Expr>,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldDecl {
+    pub visibility: Visibility,
+    pub name: Ident,
+    pub field_type: Expr,
+    pub default: Option<Expr>,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Block {
+    /// The label `break` and `continue` can refer to
+    pub label: Option<Ident>,
+    pub stmts: Box<[Stmt]>,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StmtKind {
+    Var(VarDecl),
+    /// `target = value`, or `target op= value` for compound assignments
+    Assign {
+        target: Expr,
+        op: Option<BinaryOp>,
+        value: Expr,
+    },
+    Return(Option<Expr>),
+    Break {
+        label: Option<Ident>,
+        value: Option<Expr>,
+    },
+    Continue {
+        label: Option<Ident>,
+    },
+    If {
+        condition: Expr,
+        /// The unwrapped value, if the condition is an optional
+        capture: Option<Ident>,
+        then_block: Block,
+        /// Either a block or another `if` statement
+        else_branch: Option<Box<Stmt>>,
+    },
+    While {
+        label: Option<Ident>,
+        condition: Expr,
+        capture: Option<Ident>,
+        /// Executed at the end of every iteration, like the `i += 1` of `while (i < n) : (i += 1)`
+        continue_stmt: Option<Box<Stmt>>,
+        body: Block,
+    },
+    For {
+        label: Option<Ident>,
+        /// Slices or ranges iterated over in lockstep
+        inputs: Box<[Expr]>,
+        captures: Box<[Ident]>,
+        body: Block,
+    },
+    Defer(Box<Stmt>),
+    /// A `defer` only executed when returning an error
+    ErrDefer {
+        capture: Option<Ident>,
+        stmt: Box<Stmt>,
+    },
+    Block(Block),
+    Expr(Expr),
+    /// A statement that couldn't be parsed
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExprKind {
+    /// Also covers `null`, `true`, `false`, `undefined` and the primitive types, which are
+    /// resolved like any other name
+    Ident(Box<str>),
+    Literal(Literal),
+    /// An expression surrounded by parentheses
+    Paren(Box<Expr>),
+    Prefix {
+        op: PrefixOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Postfix {
+        op: PostfixOp,
+        operand: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Box<[Expr]>,
+    },
+    /// `@name(args)`, a function provided by the compiler like `@import`
+    BuiltinCall {
+        name: Ident,
+        args: Box<[Expr]>,
+    },
+    Field {
+        base: Box<Expr>,
+        field: Ident,
+    },
+    Index {
+        base: Box<Expr>,
+        index: Box<Expr>,
+    },
+    /// `lhs catch |capture| rhs`
+    Catch {
+        lhs: Box<Expr>,
+        capture: Option<Ident>,
+        rhs: Box<Expr>,
+    },
+    /// `.{ .a = x, b }`, the type of which is inferred from the context
+    AnonInit(Box<[InitField]>),
+    /// `.variant`, the enum of which is inferred from the context
+    EnumLiteral(Ident),
+    /// `start..end`, only allowed as a `for` input or as the index of `base[start..end]`
+    Range {
+        start: Box<Expr>,
+        end: Option<Box<Expr>>,
+    },
+    /// `label: { ... }`, which evaluates to the value given to `break :label`
+    Block(Block),
+    /// `*T`, `[*]T` or `[]T`, each of which can point to `const` values. The last two can be
+    /// ended by a sentinel, like `[:0]const u8`.
+    PointerType {
+        kind: PointerKind,
+        is_const: bool,
+        sentinel: Option<Box<Expr>>,
+        pointee: Box<Expr>,
+    },
+    /// `[len]T`, or `[len:sentinel]T`
+    ArrayType {
+        len: Box<Expr>,
+        sentinel: Option<Box<Expr>>,
+        element: Box<Expr>,
+    },
+    /// `fn (a: A, B) -> R`, where parameter names are optional
+    FnType {
+        params: Box<[FnTypeParam]>,
+        return_type: Box<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InitField {
+    /// `None` for `.{ a }`, which is either a tuple element or short for `.{ .a = a }`
+    /// depending on the fields of the inferred type
+    pub name: Option<Ident>,
+    pub value: Expr,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PointerKind {
+    /// `*T`, pointing to a single value
+    Single,
+    /// `[*]T`, pointing to an unknown amount of values
+    Many,
+    /// `[]T`, a pointer along with the amount of values it points to
+    Slice,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FnTypeParam {
+    pub name: Option<Ident>,
+    pub param_type: Expr,
+    pub span: TextSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrefixOp {
+    /// `!`, which is an inferred error union when applied to a type
+    Not,
+    /// `-`
+    Negate,
+    /// `~`
+    BitNot,
+    /// `&`
+    AddressOf,
+    /// `?`
+    Optional,
+    /// `try`, returning early if the operand is an error
+    Try,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PostfixOp {
+    /// `.*`
+    Deref,
+    /// `.?`
+    Unwrap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    /// `E!T`, the error union of the error set `E` and the type `T`
+    ErrorUnion,
+    ShiftLeft,
+    ShiftRight,
+    BitAnd,
+    BitXor,
+    BitOr,
+    Orelse,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Associativity {
+    Left,
+    /// Chaining the operator without parentheses is an error
+    None,
+}
+
+impl BinaryOp {
+    /// Operators with a higher precedence bind tighter
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::LessThan
+            | BinaryOp::LessOrEqual
+            | BinaryOp::GreaterThan
+            | BinaryOp::GreaterOrEqual => 3,
+            BinaryOp::Orelse => 4,
+            BinaryOp::BitOr => 5,
+            BinaryOp::BitXor => 6,
+            BinaryOp::BitAnd => 7,
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => 8,
+            BinaryOp::Add | BinaryOp::Sub => 9,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 10,
+            BinaryOp::ErrorUnion => 11,
+        }
+    }
+    pub fn associativity(&self) -> Associativity {
+        match self.precedence() {
+            3 => Associativity::None,
+            _ => Associativity::Left,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cera_parse::dump::{from_json, to_json, to_sexpr};
+
+    use super::*;
+    use crate::parser::parse_file;
+
+    const SAMPLE: &str = include_str!("../../../sample-code/main.cera");
+
+    #[test]
+    fn json_round_trip() {
+        let file = parse_file(SAMPLE).unwrap();
+        assert_eq!(from_json::<File>(&to_json(&file)).unwrap(), file);
+    }
+
+    #[test]
+    fn sexpr() {
+        let file = parse_file("const a = -b;").unwrap();
+        assert_eq!(
+            to_sexpr(&file).unwrap(),
+            "(File items: [(Item visibility: Private kind: (Var (VarDecl mutability: Const \
+             name: (Ident name: \"a\" span: 6..7) var_type: nil value: (Expr kind: (Prefix \
+             op: Negate operand: (Expr kind: (Ident \"b\") span: 11..12)) span: 10..12))) \
+             span: 0..13)] span: 0..13)"
+        );
+    }
+}
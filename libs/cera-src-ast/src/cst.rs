@@ -0,0 +1,521 @@
+//! A lossless syntax tree, keeping every token along with the whitespace and comments around
+//! them. Green nodes are immutable and shared between trees, while syntax nodes are cursors
+//! over them knowing their parent and position.
+
+use std::{fmt, rc::Rc, sync::Arc};
+
+use cera_parse::text_region::TextSpan;
+
+mod build;
+pub mod nodes;
+
+pub use build::{build_cst, parse_cst};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    // Tokens
+    Whitespace,
+    LineComment,
+    BlockComment,
+    Ident,
+    Keyword,
+    Literal,
+    /// A single special char, like `+` or `{`
+    Punct,
+    /// Text that couldn't be lexed
+    ErrorToken,
+
+    // Nodes
+    File,
+    /// The name of a declaration, a label or a capture
+    Name,
+    FnItem,
+    VarItem,
+    ContainerItem,
+    ErrorItem,
+    Param,
+    FieldDecl,
+    VariantDecl,
+    NonExhaustive,
+    Method,
+    ErrorMember,
+    Block,
+    VarStmt,
+    AssignStmt,
+    ReturnStmt,
+    BreakStmt,
+    ContinueStmt,
+    IfStmt,
+    WhileStmt,
+    ForStmt,
+    DeferStmt,
+    ErrDeferStmt,
+    BlockStmt,
+    ExprStmt,
+    ErrorStmt,
+    IdentExpr,
+    LiteralExpr,
+    ParenExpr,
+    PrefixExpr,
+    BinaryExpr,
+    PostfixExpr,
+    CallExpr,
+    BuiltinCallExpr,
+    FieldExpr,
+    IndexExpr,
+    CatchExpr,
+    AnonInitExpr,
+    InitField,
+    EnumLiteralExpr,
+    RangeExpr,
+    BlockExpr,
+    PointerTypeExpr,
+    ArrayTypeExpr,
+    FnTypeExpr,
+    FnTypeParam,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Whitespace | SyntaxKind::LineComment | SyntaxKind::BlockComment
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: Box<str>,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxKind, text: &str) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    /// The length of the text of every token in the node
+    len: usize,
+    children: Box<[GreenElement]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GreenElement {
+    Node(Arc<GreenNode>),
+    Token(Arc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        Self {
+            kind,
+            len: children.iter().map(GreenElement::len).sum(),
+            children: children.into_boxed_slice(),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    fn write_text(&self, output: &mut String) {
+        for child in self.children.iter() {
+            match child {
+                GreenElement::Node(node) => node.write_text(output),
+                GreenElement::Token(token) => output.push_str(&token.text),
+            }
+        }
+    }
+}
+
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = String::with_capacity(self.len);
+        self.write_text(&mut text);
+        f.write_str(&text)
+    }
+}
+
+/// A green node along with its position in a tree
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Arc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    /// The index of the node among the children of its parent
+    index: usize,
+    offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    green: Arc<GreenToken>,
+    parent: SyntaxNode,
+    index: usize,
+    offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Arc<GreenNode>) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            parent: None,
+            index: 0,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Arc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn span(&self) -> TextSpan {
+        TextSpan {
+            len: self.0.green.len,
+            idx: self.0.offset,
+        }
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    /// This node, then its parent up to the root
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        std::iter::successors(Some(self.clone()), |node| node.parent().cloned())
+    }
+
+    pub fn children_with_tokens(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children
+            .iter()
+            .enumerate()
+            .map(move |(index, child)| {
+                let child_offset = offset;
+                offset += child.len();
+                match child {
+                    GreenElement::Node(green) => {
+                        SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                            green: green.clone(),
+                            parent: Some(self.clone()),
+                            index,
+                            offset: child_offset,
+                        })))
+                    }
+                    GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                        green: green.clone(),
+                        parent: self.clone(),
+                        index,
+                        offset: child_offset,
+                    }),
+                }
+            })
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children_with_tokens().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// The tokens directly in this node, without the ones of child nodes
+    pub fn child_tokens(&self) -> impl Iterator<Item = SyntaxToken> + '_ {
+        self.children_with_tokens().filter_map(|child| match child {
+            SyntaxElement::Node(_) => None,
+            SyntaxElement::Token(token) => Some(token),
+        })
+    }
+
+    /// This node and every node in it, in source order
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut nodes = vec![self.clone()];
+        for child in self.children() {
+            nodes.extend(child.descendants());
+        }
+        nodes
+    }
+
+    /// Every token in this node, in source order
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    pub fn text(&self) -> String {
+        self.0.green.to_string()
+    }
+
+    /// Returns the root of a tree where this node is replaced, leaving this tree untouched
+    pub fn replace_with(&self, replacement: Arc<GreenNode>) -> Arc<GreenNode> {
+        match self.parent() {
+            Some(parent) => parent.with_child(self.0.index, GreenElement::Node(replacement)),
+            None => replacement,
+        }
+    }
+
+    /// Replaces the child at `index`, then rebuilds the ancestors up to the root
+    fn with_child(&self, index: usize, replacement: GreenElement) -> Arc<GreenNode> {
+        let mut children = self.0.green.children.to_vec();
+        children[index] = replacement;
+        let green = Arc::new(GreenNode::new(self.kind(), children));
+        match self.parent() {
+            Some(parent) => parent.with_child(self.0.index, GreenElement::Node(green)),
+            None => green,
+        }
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+    }
+}
+
+impl Eq for SyntaxNode {}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        write!(f, "{:?}@{}..{}", self.kind(), span.idx, span.end())
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.green.fmt(f)
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn span(&self) -> TextSpan {
+        TextSpan {
+            len: self.green.text.len(),
+            idx: self.offset,
+        }
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+
+    /// Returns the root of a tree where this token is replaced, leaving this tree untouched
+    pub fn replace_with(&self, replacement: GreenToken) -> Arc<GreenNode> {
+        self.parent
+            .with_child(self.index, GreenElement::Token(Arc::new(replacement)))
+    }
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            SyntaxElement::Node(node) => node.kind(),
+            SyntaxElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn span(&self) -> TextSpan {
+        match self {
+            SyntaxElement::Node(node) => node.span(),
+            SyntaxElement::Token(token) => token.span(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        nodes::{AstNode, FnItem, SourceFile},
+        *,
+    };
+
+    const SAMPLE: &str = include_str!("../../../sample-code/main.cera");
+
+    #[test]
+    fn sample_round_trip() {
+        let (root, errors) = parse_cst(SAMPLE);
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(root.text(), SAMPLE);
+        assert_eq!(root.span().len, SAMPLE.len());
+    }
+
+    #[test]
+    fn trivia_placement() {
+        let text = "// leading\npub fn a() /* inner */ {\n    b(); // trailing\n}\n";
+        let (root, _) = parse_cst(text);
+        let file = SourceFile::cast(root.clone()).unwrap();
+        let item = file.items().next().unwrap();
+        assert!(item.is_pub());
+        assert_eq!(
+            item.syntax().text(),
+            "pub fn a() /* inner */ {\n    b(); // trailing\n}"
+        );
+        let fn_item = FnItem::cast(item.syntax().clone()).unwrap();
+        assert_eq!(fn_item.name().unwrap().text(), "a");
+        let body = fn_item.body().unwrap();
+        let stmts: Vec<_> = body.stmts().map(|stmt| stmt.syntax().text()).collect();
+        assert_eq!(stmts, ["b();"]);
+        let comments: Vec<_> = root
+            .tokens()
+            .into_iter()
+            .filter(|token| token.kind() == SyntaxKind::LineComment)
+            .map(|token| (token.text().to_string(), token.parent().kind()))
+            .collect();
+        assert_eq!(
+            comments,
+            [
+                ("// leading".into(), SyntaxKind::File),
+                ("// trailing".into(), SyntaxKind::Block)
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_text_round_trip() {
+        for text in [
+            "fn a( { const = ; } $ é",
+            "struct s { a b, c: }",
+            "\"unclosed",
+            "/* unclosed",
+            "fn a() { if x { } else }",
+        ] {
+            assert_eq!(parse_cst(text).0.text(), text);
+        }
+    }
+
+    #[test]
+    fn parents_and_spans() {
+        let text = "const a = b + c;";
+        let (root, _) = parse_cst(text);
+        let token = root
+            .tokens()
+            .into_iter()
+            .find(|token| token.text() == "c")
+            .unwrap();
+        assert_eq!(token.span(), TextSpan { len: 1, idx: 14 });
+        let kinds: Vec<_> = token.parent().ancestors().map(|node| node.kind()).collect();
+        assert_eq!(
+            kinds,
+            [
+                SyntaxKind::IdentExpr,
+                SyntaxKind::BinaryExpr,
+                SyntaxKind::VarItem,
+                SyntaxKind::File
+            ]
+        );
+        let binary = token.parent().parent().unwrap();
+        assert_eq!(binary.span(), TextSpan { len: 5, idx: 10 });
+    }
+
+    #[test]
+    fn editing() {
+        let text = "const a = b + c;";
+        let (root, _) = parse_cst(text);
+        let token = root
+            .tokens()
+            .into_iter()
+            .find(|token| token.text() == "b")
+            .unwrap();
+        let edited = token.replace_with(GreenToken::new(SyntaxKind::Ident, "speed"));
+        assert_eq!(edited.to_string(), "const a = speed + c;");
+        // The original tree is left untouched, and unchanged nodes are shared
+        assert_eq!(root.text(), text);
+        let GreenElement::Node(item) = &edited.children()[0] else {
+            panic!()
+        };
+        let GreenElement::Node(original_item) = &root.green().children()[0] else {
+            panic!()
+        };
+        let (GreenElement::Token(keyword), GreenElement::Token(original_keyword)) =
+            (&item.children()[0], &original_item.children()[0])
+        else {
+            panic!()
+        };
+        assert!(Arc::ptr_eq(keyword, original_keyword));
+
+        let binary = root
+            .descendants()
+            .into_iter()
+            .find(|node| node.kind() == SyntaxKind::BinaryExpr)
+            .unwrap();
+        let replacement = GreenNode::new(
+            SyntaxKind::IdentExpr,
+            vec![GreenElement::Token(Arc::new(GreenToken::new(
+                SyntaxKind::Ident,
+                "d",
+            )))],
+        );
+        assert_eq!(
+            binary.replace_with(Arc::new(replacement)).to_string(),
+            "const a = d;"
+        );
+    }
+}
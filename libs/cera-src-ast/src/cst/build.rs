@@ -0,0 +1,439 @@
+use std::sync::Arc;
+
+use cera_parse::{
+    parsing::Parsable,
+    src_token::{LosslessToken, Token, Trivia},
+    text_region::TextSpan,
+};
+
+use crate::{
+    ast::{
+        Block, ContainerKind, Expr, ExprKind, File, Ident, Item, ItemKind, Member, Stmt, StmtKind,
+        UnionTag,
+    },
+    cst::{GreenElement, GreenNode, GreenToken, SyntaxKind, SyntaxNode},
+    parser::{parse_file_recovering, ParseError, KEYWORDS},
+};
+
+/// Parses `text` into a syntax tree, which is built even if the text has errors
+pub fn parse_cst(text: &str) -> (SyntaxNode, Vec<ParseError>) {
+    let (file, errors) = parse_file_recovering(text);
+    (build_cst(text, &file), errors)
+}
+
+/// Builds the syntax tree of `text`, grouping its tokens into nodes according to the spans of
+/// `file`. Every byte of `text` ends up in exactly one token, whatever the spans are.
+pub fn build_cst(text: &str, file: &File) -> SyntaxNode {
+    let mut nodes = Vec::new();
+    for item in file.items.iter() {
+        collect_item(item, &mut nodes);
+    }
+    // Stable, so that nodes with the same span stay ordered from the outermost one
+    nodes.sort_by_key(|(_, span)| (span.idx, usize::MAX - span.len));
+
+    struct OpenNode {
+        kind: SyntaxKind,
+        end: usize,
+        children: Vec<GreenElement>,
+    }
+    fn close(stack: &mut Vec<OpenNode>) {
+        let node = stack.pop().unwrap();
+        let green = GreenNode::new(node.kind, node.children);
+        let parent = stack.last_mut().unwrap();
+        parent.children.push(GreenElement::Node(Arc::new(green)));
+    }
+
+    let mut stack = vec![OpenNode {
+        kind: SyntaxKind::File,
+        end: usize::MAX,
+        children: Vec::new(),
+    }];
+    let mut nodes = nodes.into_iter().peekable();
+    for (kind, span) in lex(text) {
+        while stack.len() > 1 && stack.last().unwrap().end <= span.idx {
+            close(&mut stack);
+        }
+        // Trivia never opens nodes, so that it stays out of the nodes following it
+        if !kind.is_trivia() {
+            while let Some((_, node_span)) = nodes.peek() {
+                if node_span.idx > span.idx {
+                    break;
+                }
+                let (node_kind, node_span) = nodes.next().unwrap();
+                // Spans not starting at a token or not nested in the current node are dropped
+                let is_nested = node_span.idx == span.idx
+                    && node_span.end() >= span.end()
+                    && node_span.end() <= stack.last().unwrap().end;
+                if is_nested {
+                    stack.push(OpenNode {
+                        kind: node_kind,
+                        end: node_span.end(),
+                        children: Vec::new(),
+                    });
+                }
+            }
+        }
+        let token = GreenToken::new(kind, &text[span.idx..span.end()]);
+        let parent = stack.last_mut().unwrap();
+        parent.children.push(GreenElement::Token(Arc::new(token)));
+    }
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    let root = stack.pop().unwrap();
+    SyntaxNode::new_root(Arc::new(GreenNode::new(root.kind, root.children)))
+}
+
+/// Splits the whole text into tokens, turning what couldn't be lexed into error tokens of a
+/// single char
+fn lex(text: &str) -> Vec<(SyntaxKind, TextSpan)> {
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    while idx < text.len() {
+        let (kind, len) = match LosslessToken::try_parse(&text[idx..]) {
+            Ok((Some(token), len)) if len != 0 && text.is_char_boundary(idx + len) => {
+                let kind = match token {
+                    LosslessToken::Trivia(Trivia::Whitespace) => SyntaxKind::Whitespace,
+                    LosslessToken::Trivia(Trivia::LineComment) => SyntaxKind::LineComment,
+                    LosslessToken::Trivia(Trivia::BlockComment) => SyntaxKind::BlockComment,
+                    LosslessToken::Token(Token::Identifier(ident)) => {
+                        match KEYWORDS.contains(&ident.name()) {
+                            true => SyntaxKind::Keyword,
+                            false => SyntaxKind::Ident,
+                        }
+                    }
+                    LosslessToken::Token(Token::Literal(_)) => SyntaxKind::Literal,
+                    LosslessToken::Token(Token::SpecialChar(_)) => SyntaxKind::Punct,
+                };
+                (kind, len)
+            }
+            _ => {
+                let len = text[idx..].chars().next().unwrap().len_utf8();
+                (SyntaxKind::ErrorToken, len)
+            }
+        };
+        tokens.push((kind, TextSpan { len, idx }));
+        idx += len;
+    }
+    tokens
+}
+
+type Nodes = Vec<(SyntaxKind, TextSpan)>;
+
+fn collect_name(name: &Ident, nodes: &mut Nodes) {
+    nodes.push((SyntaxKind::Name, name.span.clone()));
+}
+
+fn collect_item(item: &Item, nodes: &mut Nodes) {
+    let kind = match &item.kind {
+        ItemKind::Fn(_) => SyntaxKind::FnItem,
+        ItemKind::Var(_) => SyntaxKind::VarItem,
+        ItemKind::Container(_) => SyntaxKind::ContainerItem,
+        ItemKind::Error => SyntaxKind::ErrorItem,
+    };
+    nodes.push((kind, item.span.clone()));
+    match &item.kind {
+        ItemKind::Fn(decl) => {
+            collect_name(&decl.name, nodes);
+            for param in decl.params.iter() {
+                nodes.push((SyntaxKind::Param, param.span.clone()));
+                collect_name(&param.name, nodes);
+                collect_expr(&param.param_type, nodes);
+            }
+            if let Some(return_type) = &decl.return_type {
+                collect_expr(return_type, nodes);
+            }
+            collect_block(&decl.body, nodes);
+        }
+        ItemKind::Var(decl) => {
+            collect_name(&decl.name, nodes);
+            if let Some(var_type) = &decl.var_type {
+                collect_expr(var_type, nodes);
+            }
+            collect_expr(&decl.value, nodes);
+        }
+        ItemKind::Container(decl) => {
+            match &decl.kind {
+                ContainerKind::Enum {
+                    backing_type: Some(backing_type),
+                } => collect_expr(backing_type, nodes),
+                ContainerKind::Union {
+                    tag: Some(UnionTag::Explicit(tag)),
+                } => collect_expr(tag, nodes),
+                _ => {}
+            }
+            collect_name(&decl.name, nodes);
+            for member in decl.members.iter() {
+                collect_member(member, nodes);
+            }
+        }
+        ItemKind::Error => {}
+    }
+}
+
+fn collect_member(member: &Member, nodes: &mut Nodes) {
+    match member {
+        Member::Field(field) => {
+            nodes.push((SyntaxKind::FieldDecl, field.span.clone()));
+            collect_name(&field.name, nodes);
+            collect_expr(&field.field_type, nodes);
+            if let Some(default) = &field.default {
+                collect_expr(default, nodes);
+            }
+        }
+        Member::Variant(variant) => {
+            nodes.push((SyntaxKind::VariantDecl, variant.span.clone()));
+            collect_name(&variant.name, nodes);
+            if let Some(value) = &variant.value {
+                collect_expr(value, nodes);
+            }
+        }
+        Member::NonExhaustive(span) => nodes.push((SyntaxKind::NonExhaustive, span.clone())),
+        Member::Method(signature) => {
+            nodes.push((SyntaxKind::Method, signature.span.clone()));
+            collect_name(&signature.name, nodes);
+            for param in signature.params.iter() {
+                nodes.push((SyntaxKind::Param, param.span.clone()));
+                collect_name(&param.name, nodes);
+                collect_expr(&param.param_type, nodes);
+            }
+            if let Some(return_type) = &signature.return_type {
+                collect_expr(return_type, nodes);
+            }
+        }
+        Member::Item(item) => collect_item(item, nodes),
+        Member::Error(span) => nodes.push((SyntaxKind::ErrorMember, span.clone())),
+    }
+}
+
+fn collect_block(block: &Block, nodes: &mut Nodes) {
+    if let Some(label) = &block.label {
+        collect_name(label, nodes);
+    }
+    nodes.push((SyntaxKind::Block, block.span.clone()));
+    for stmt in block.stmts.iter() {
+        collect_stmt(stmt, nodes);
+    }
+}
+
+fn collect_stmt(stmt: &Stmt, nodes: &mut Nodes) {
+    let kind = match &stmt.kind {
+        StmtKind::Var(_) => SyntaxKind::VarStmt,
+        StmtKind::Assign { .. } => SyntaxKind::AssignStmt,
+        StmtKind::Return(_) => SyntaxKind::ReturnStmt,
+        StmtKind::Break { .. } => SyntaxKind::BreakStmt,
+        StmtKind::Continue { .. } => SyntaxKind::ContinueStmt,
+        StmtKind::If { .. } => SyntaxKind::IfStmt,
+        StmtKind::While { .. } => SyntaxKind::WhileStmt,
+        StmtKind::For { .. } => SyntaxKind::ForStmt,
+        StmtKind::Defer(_) => SyntaxKind::DeferStmt,
+        StmtKind::ErrDefer { .. } => SyntaxKind::ErrDeferStmt,
+        StmtKind::Block(_) => SyntaxKind::BlockStmt,
+        StmtKind::Expr(_) => SyntaxKind::ExprStmt,
+        StmtKind::Error => SyntaxKind::ErrorStmt,
+    };
+    nodes.push((kind, stmt.span.clone()));
+    match &stmt.kind {
+        StmtKind::Var(decl) => {
+            collect_name(&decl.name, nodes);
+            if let Some(var_type) = &decl.var_type {
+                collect_expr(var_type, nodes);
+            }
+            collect_expr(&decl.value, nodes);
+        }
+        StmtKind::Assign { target, value, .. } => {
+            collect_expr(target, nodes);
+            collect_expr(value, nodes);
+        }
+        StmtKind::Return(value) => {
+            if let Some(value) = value {
+                collect_expr(value, nodes);
+            }
+        }
+        StmtKind::Break { label, value } => {
+            if let Some(label) = label {
+                collect_name(label, nodes);
+            }
+            if let Some(value) = value {
+                collect_expr(value, nodes);
+            }
+        }
+        StmtKind::Continue { label } => {
+            if let Some(label) = label {
+                collect_name(label, nodes);
+            }
+        }
+        StmtKind::If {
+            condition,
+            capture,
+            then_block,
+            else_branch,
+        } => {
+            collect_expr(condition, nodes);
+            if let Some(capture) = capture {
+                collect_name(capture, nodes);
+            }
+            collect_block(then_block, nodes);
+            if let Some(else_branch) = else_branch {
+                collect_stmt(else_branch, nodes);
+            }
+        }
+        StmtKind::While {
+            label,
+            condition,
+            capture,
+            continue_stmt,
+            body,
+        } => {
+            if let Some(label) = label {
+                collect_name(label, nodes);
+            }
+            collect_expr(condition, nodes);
+            if let Some(capture) = capture {
+                collect_name(capture, nodes);
+            }
+            if let Some(continue_stmt) = continue_stmt {
+                collect_stmt(continue_stmt, nodes);
+            }
+            collect_block(body, nodes);
+        }
+        StmtKind::For {
+            label,
+            inputs,
+            captures,
+            body,
+        } => {
+            if let Some(label) = label {
+                collect_name(label, nodes);
+            }
+            for input in inputs.iter() {
+                collect_expr(input, nodes);
+            }
+            for capture in captures.iter() {
+                collect_name(capture, nodes);
+            }
+            collect_block(body, nodes);
+        }
+        StmtKind::Defer(stmt) => collect_stmt(stmt, nodes),
+        StmtKind::ErrDefer { capture, stmt } => {
+            if let Some(capture) = capture {
+                collect_name(capture, nodes);
+            }
+            collect_stmt(stmt, nodes);
+        }
+        StmtKind::Block(block) => collect_block(block, nodes),
+        StmtKind::Expr(expr) => collect_expr(expr, nodes),
+        StmtKind::Error => {}
+    }
+}
+
+fn collect_expr(expr: &Expr, nodes: &mut Nodes) {
+    let kind = match &expr.kind {
+        ExprKind::Ident(_) => SyntaxKind::IdentExpr,
+        ExprKind::Literal(_) => SyntaxKind::LiteralExpr,
+        ExprKind::Paren(_) => SyntaxKind::ParenExpr,
+        ExprKind::Prefix { .. } => SyntaxKind::PrefixExpr,
+        ExprKind::Binary { .. } => SyntaxKind::BinaryExpr,
+        ExprKind::Postfix { .. } => SyntaxKind::PostfixExpr,
+        ExprKind::Call { .. } => SyntaxKind::CallExpr,
+        ExprKind::BuiltinCall { .. } => SyntaxKind::BuiltinCallExpr,
+        ExprKind::Field { .. } => SyntaxKind::FieldExpr,
+        ExprKind::Index { .. } => SyntaxKind::IndexExpr,
+        ExprKind::Catch { .. } => SyntaxKind::CatchExpr,
+        ExprKind::AnonInit(_) => SyntaxKind::AnonInitExpr,
+        ExprKind::EnumLiteral(_) => SyntaxKind::EnumLiteralExpr,
+        ExprKind::Range { .. } => SyntaxKind::RangeExpr,
+        ExprKind::Block(_) => SyntaxKind::BlockExpr,
+        ExprKind::PointerType { .. } => SyntaxKind::PointerTypeExpr,
+        ExprKind::ArrayType { .. } => SyntaxKind::ArrayTypeExpr,
+        ExprKind::FnType { .. } => SyntaxKind::FnTypeExpr,
+    };
+    nodes.push((kind, expr.span.clone()));
+    match &expr.kind {
+        ExprKind::Ident(_) | ExprKind::Literal(_) => {}
+        ExprKind::Paren(inner) => collect_expr(inner, nodes),
+        ExprKind::Prefix { operand, .. } | ExprKind::Postfix { operand, .. } => {
+            collect_expr(operand, nodes)
+        }
+        ExprKind::Binary { lhs, rhs, .. } => {
+            collect_expr(lhs, nodes);
+            collect_expr(rhs, nodes);
+        }
+        ExprKind::Call { callee, args } => {
+            collect_expr(callee, nodes);
+            for arg in args.iter() {
+                collect_expr(arg, nodes);
+            }
+        }
+        ExprKind::BuiltinCall { name, args } => {
+            collect_name(name, nodes);
+            for arg in args.iter() {
+                collect_expr(arg, nodes);
+            }
+        }
+        ExprKind::Field { base, field } => {
+            collect_expr(base, nodes);
+            collect_name(field, nodes);
+        }
+        ExprKind::Index { base, index } => {
+            collect_expr(base, nodes);
+            collect_expr(index, nodes);
+        }
+        ExprKind::Catch { lhs, capture, rhs } => {
+            collect_expr(lhs, nodes);
+            if let Some(capture) = capture {
+                collect_name(capture, nodes);
+            }
+            collect_expr(rhs, nodes);
+        }
+        ExprKind::AnonInit(fields) => {
+            for field in fields.iter() {
+                nodes.push((SyntaxKind::InitField, field.span.clone()));
+                if let Some(name) = &field.name {
+                    collect_name(name, nodes);
+                }
+                collect_expr(&field.value, nodes);
+            }
+        }
+        ExprKind::EnumLiteral(variant) => collect_name(variant, nodes),
+        ExprKind::Range { start, end } => {
+            collect_expr(start, nodes);
+            if let Some(end) = end {
+                collect_expr(end, nodes);
+            }
+        }
+        ExprKind::Block(block) => collect_block(block, nodes),
+        ExprKind::PointerType {
+            sentinel, pointee, ..
+        } => {
+            if let Some(sentinel) = sentinel {
+                collect_expr(sentinel, nodes);
+            }
+            collect_expr(pointee, nodes);
+        }
+        ExprKind::ArrayType {
+            len,
+            sentinel,
+            element,
+        } => {
+            collect_expr(len, nodes);
+            if let Some(sentinel) = sentinel {
+                collect_expr(sentinel, nodes);
+            }
+            collect_expr(element, nodes);
+        }
+        ExprKind::FnType {
+            params,
+            return_type,
+        } => {
+            for param in params.iter() {
+                nodes.push((SyntaxKind::FnTypeParam, param.span.clone()));
+                if let Some(name) = &param.name {
+                    collect_name(name, nodes);
+                }
+                collect_expr(&param.param_type, nodes);
+            }
+            collect_expr(return_type, nodes);
+        }
+    }
+}
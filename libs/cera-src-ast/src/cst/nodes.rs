@@ -0,0 +1,155 @@
+//! Typed views over syntax nodes
+
+use crate::cst::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+pub trait AstNode: Sized {
+    fn can_cast(kind: SyntaxKind) -> bool;
+    fn cast(node: SyntaxNode) -> Option<Self>;
+    fn syntax(&self) -> &SyntaxNode;
+}
+
+macro_rules! ast_node {
+    ($(#[$attr:meta])* $name:ident: $($kind:ident)|+) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $name(SyntaxNode);
+
+        impl AstNode for $name {
+            fn can_cast(kind: SyntaxKind) -> bool {
+                matches!(kind, $(SyntaxKind::$kind)|+)
+            }
+
+            fn cast(node: SyntaxNode) -> Option<Self> {
+                Self::can_cast(node.kind()).then_some(Self(node))
+            }
+
+            fn syntax(&self) -> &SyntaxNode {
+                &self.0
+            }
+        }
+    };
+}
+
+ast_node!(SourceFile: File);
+ast_node!(Item: FnItem | VarItem | ContainerItem | ErrorItem);
+ast_node!(FnItem: FnItem);
+ast_node!(VarItem: VarItem);
+ast_node!(ContainerItem: ContainerItem);
+ast_node!(Param: Param);
+ast_node!(
+    /// A field, a variant, a method or a declaration of a container
+    Member: FieldDecl | VariantDecl | NonExhaustive | Method | ErrorMember | FnItem | VarItem
+        | ContainerItem | ErrorItem
+);
+ast_node!(Block: Block);
+ast_node!(
+    Stmt: VarStmt | AssignStmt | ReturnStmt | BreakStmt | ContinueStmt | IfStmt | WhileStmt
+        | ForStmt | DeferStmt | ErrDeferStmt | BlockStmt | ExprStmt | ErrorStmt
+);
+ast_node!(
+    Expr: IdentExpr | LiteralExpr | ParenExpr | PrefixExpr | BinaryExpr | PostfixExpr | CallExpr
+        | BuiltinCallExpr | FieldExpr | IndexExpr | CatchExpr | AnonInitExpr | EnumLiteralExpr
+        | RangeExpr | BlockExpr | PointerTypeExpr | ArrayTypeExpr | FnTypeExpr
+);
+ast_node!(Name: Name);
+
+fn child<N: AstNode>(node: &SyntaxNode) -> Option<N> {
+    node.children().find_map(N::cast)
+}
+
+fn children<'a, N: AstNode + 'a>(node: &'a SyntaxNode) -> impl Iterator<Item = N> + 'a {
+    node.children().filter_map(N::cast)
+}
+
+fn has_keyword(node: &SyntaxNode, keyword: &str) -> bool {
+    node.child_tokens()
+        .any(|token| token.kind() == SyntaxKind::Keyword && token.text() == keyword)
+}
+
+impl SourceFile {
+    pub fn items(&self) -> impl Iterator<Item = Item> + '_ {
+        children(&self.0)
+    }
+}
+
+impl Item {
+    pub fn is_pub(&self) -> bool {
+        has_keyword(&self.0, "pub")
+    }
+
+    pub fn name(&self) -> Option<Name> {
+        child(&self.0)
+    }
+}
+
+impl FnItem {
+    pub fn name(&self) -> Option<Name> {
+        child(&self.0)
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = Param> + '_ {
+        children(&self.0)
+    }
+
+    /// The first expression after the parameters
+    pub fn return_type(&self) -> Option<Expr> {
+        child(&self.0)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0)
+    }
+}
+
+impl VarItem {
+    pub fn is_const(&self) -> bool {
+        has_keyword(&self.0, "const")
+    }
+
+    pub fn name(&self) -> Option<Name> {
+        child(&self.0)
+    }
+
+    /// The type, if given, then the value
+    pub fn exprs(&self) -> impl Iterator<Item = Expr> + '_ {
+        children(&self.0)
+    }
+}
+
+impl ContainerItem {
+    pub fn name(&self) -> Option<Name> {
+        child(&self.0)
+    }
+
+    pub fn members(&self) -> impl Iterator<Item = Member> + '_ {
+        children(&self.0)
+    }
+}
+
+impl Param {
+    pub fn name(&self) -> Option<Name> {
+        child(&self.0)
+    }
+
+    pub fn param_type(&self) -> Option<Expr> {
+        child(&self.0)
+    }
+}
+
+impl Block {
+    pub fn stmts(&self) -> impl Iterator<Item = Stmt> + '_ {
+        children(&self.0)
+    }
+}
+
+impl Name {
+    pub fn ident(&self) -> Option<SyntaxToken> {
+        self.0
+            .child_tokens()
+            .find(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn text(&self) -> String {
+        self.0.text()
+    }
+}
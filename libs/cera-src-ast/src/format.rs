@@ -0,0 +1,611 @@
+//! The canonical layout of Cera code, produced from the lossless syntax tree. Only trivia is
+//! changed: tokens, including optional commas, are kept as they are.
+
+use cera_parse::{
+    parsing::parse,
+    src_token::{LosslessToken, Trivia},
+    text_region::TextSpan,
+};
+
+use crate::{
+    cst::{parse_cst, SyntaxElement, SyntaxKind, SyntaxNode},
+    parser::ParseError,
+};
+
+use self::doc::Doc;
+
+pub mod doc;
+
+pub const MAX_WIDTH: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// Text with syntax errors isn't formatted, as its structure is unknown
+    Parse(Vec<ParseError>),
+    /// The output has different tokens or comments than the input, which is a bug of the
+    /// formatter
+    TokensChanged,
+}
+
+/// Formats a whole file
+pub fn format_source(text: &str) -> Result<String, FormatError> {
+    let (root, errors) = parse_cst(text);
+    if !errors.is_empty() {
+        return Err(FormatError::Parse(errors));
+    }
+    let output = doc::print(&file(&root), MAX_WIDTH);
+    if kept_tokens(&output) != kept_tokens(text) {
+        return Err(FormatError::TokensChanged);
+    }
+    Ok(output)
+}
+
+/// The tokens and comments of `text`, along with the text of comments, which formatting keeps
+fn kept_tokens(text: &str) -> Option<Vec<(LosslessToken, &str)>> {
+    let (tokens, spans) = parse::<LosslessToken>(text).ok()?;
+    let kept = tokens
+        .into_iter()
+        .zip(spans)
+        .filter_map(|(token, span)| match token {
+            LosslessToken::Trivia(Trivia::Whitespace) => None,
+            LosslessToken::Trivia(_) => Some((token, &text[span.idx..span.end()])),
+            LosslessToken::Token(_) => Some((token, "")),
+        })
+        .collect();
+    Some(kept)
+}
+
+fn file(root: &SyntaxNode) -> Doc {
+    let elements: Vec<_> = root.children_with_tokens().collect();
+    let entries = entries(&elements);
+    if entries == Doc::Concat(Vec::new()) {
+        return entries;
+    }
+    Doc::Concat(vec![entries, Doc::HardLine])
+}
+
+/// A token or a node, as seen when deciding what goes between two of them
+struct Piece<'a> {
+    kind: SyntaxKind,
+    /// Empty for nodes
+    text: &'a str,
+    span: TextSpan,
+}
+
+impl<'a> Piece<'a> {
+    fn new(element: &'a SyntaxElement) -> Self {
+        match element {
+            SyntaxElement::Node(node) => Piece {
+                kind: node.kind(),
+                text: "",
+                span: node.span(),
+            },
+            SyntaxElement::Token(token) => Piece {
+                kind: token.kind(),
+                text: token.text(),
+                span: token.span(),
+            },
+        }
+    }
+
+    /// Operator chars written without anything in between, like the two of `->`
+    fn is_joint_with(&self, next: &Piece) -> bool {
+        let is_operator = |piece: &Piece| {
+            piece.kind == SyntaxKind::Punct
+                && !matches!(piece.text, "(" | ")" | "[" | "]" | "{" | "}" | "," | ";")
+        };
+        is_operator(self) && is_operator(next) && self.span.end() == next.span.idx
+    }
+}
+
+fn node(node: &SyntaxNode) -> Doc {
+    continued_node(node).0
+}
+
+/// A node, along with whether it ends on the continuation of one of its lines
+fn continued_node(node: &SyntaxNode) -> (Doc, bool) {
+    let elements: Vec<_> = node.children_with_tokens().collect();
+    continued_sequence(node.kind(), &elements)
+}
+
+fn element(element: &SyntaxElement) -> Doc {
+    match element {
+        SyntaxElement::Node(child) => node(child),
+        SyntaxElement::Token(token) => Doc::text(token.text()),
+    }
+}
+
+/// Lays out the children of a node on a single line, apart from the delimited lists in them.
+/// What follows a line comment ending a line of the node is indented as its continuation.
+fn sequence(parent: SyntaxKind, elements: &[SyntaxElement]) -> Doc {
+    continued_sequence(parent, elements).0
+}
+
+/// A sequence, along with whether it ends on a continuation line. What follows a child ending on
+/// one is indented as the continuation too, so that blocks opened on it are indented from it.
+fn continued_sequence(parent: SyntaxKind, elements: &[SyntaxElement]) -> (Doc, bool) {
+    let mut docs = Vec::new();
+    let mut prev: Option<Piece> = None;
+    let mut line_start = true;
+    let mut continuation = None;
+    let mut has_continued_child = false;
+    let mut idx = 0;
+    while idx < elements.len() {
+        let element = &elements[idx];
+        let piece = Piece::new(element);
+        match piece.kind {
+            SyntaxKind::Whitespace => {}
+            SyntaxKind::LineComment => {
+                if !line_start {
+                    docs.push(Doc::text(" "));
+                }
+                docs.push(Doc::text(piece.text));
+                if continuation.is_none() && prev.is_some() {
+                    continuation = Some(docs.len());
+                }
+                docs.push(Doc::HardLine);
+                line_start = true;
+            }
+            _ => {
+                if let Some(prev) = &prev {
+                    if !line_start
+                        && !prev.is_joint_with(&piece)
+                        && needs_space(parent, prev, &piece)
+                    {
+                        docs.push(Doc::text(" "));
+                    }
+                }
+                line_start = false;
+                match closing_delimiter(elements, idx) {
+                    Some(close) => {
+                        docs.push(delimited(parent, &elements[idx..=close]));
+                        idx = close;
+                        prev = Some(Piece::new(&elements[close]));
+                        idx += 1;
+                        continue;
+                    }
+                    None => match element {
+                        SyntaxElement::Node(child) => {
+                            let (doc, is_continued) = continued_node(child);
+                            docs.push(doc);
+                            has_continued_child |= is_continued;
+                            if is_continued && continuation.is_none() {
+                                continuation = Some(docs.len());
+                            }
+                        }
+                        SyntaxElement::Token(_) => docs.push(self::element(element)),
+                    },
+                }
+                prev = Some(piece);
+            }
+        }
+        idx += 1;
+    }
+    let continuation = continuation.filter(|start| {
+        docs[*start..]
+            .iter()
+            .any(|doc| !matches!(doc, Doc::HardLine))
+    });
+    if let Some(start) = continuation {
+        let rest = docs.split_off(start);
+        docs.push(Doc::indent(Doc::Concat(rest)));
+    }
+    (
+        Doc::Concat(docs),
+        continuation.is_some() || has_continued_child,
+    )
+}
+
+/// Returns the index of the delimiter closing the one at `open`, if there is one
+fn closing_delimiter(elements: &[SyntaxElement], open: usize) -> Option<usize> {
+    let SyntaxElement::Token(token) = &elements[open] else {
+        return None;
+    };
+    let close_text = match token.text() {
+        "(" => ")",
+        "[" => "]",
+        "{" => "}",
+        _ => return None,
+    };
+    let mut depth = 0;
+    for (idx, element) in elements.iter().enumerate().skip(open) {
+        let SyntaxElement::Token(token) = element else {
+            continue;
+        };
+        if token.kind() != SyntaxKind::Punct {
+            continue;
+        }
+        match token.text() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => {
+                depth -= 1;
+                if depth == 0 {
+                    return (token.text() == close_text).then_some(idx);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn needs_space(parent: SyntaxKind, prev: &Piece, next: &Piece) -> bool {
+    use SyntaxKind as K;
+    if matches!(next.text, "," | ";" | ")" | "]") || matches!(prev.text, "(" | "[" | "@") {
+        return false;
+    }
+    let is_dotted = matches!(parent, K::FieldExpr | K::PostfixExpr | K::RangeExpr);
+    if prev.text == "." || (next.text == "." && is_dotted) {
+        return false;
+    }
+    match parent {
+        K::PrefixExpr => return prev.kind == K::Keyword,
+        // Error unions, like `E!T`
+        K::BinaryExpr if prev.text == "!" || next.text == "!" => return false,
+        // Like `*T`, `[4]T` or `[:0]T`
+        K::PointerTypeExpr | K::ArrayTypeExpr if matches!(prev.text, "*" | "]" | ":") => {
+            return false
+        }
+        _ => {}
+    }
+    match (prev.text, next.text) {
+        (_, "(") => prev.kind == K::Keyword && !matches!(prev.text, "enum" | "union"),
+        (_, "[") => parent != K::IndexExpr,
+        // Labels of loops are followed by a colon, while `while (a) : (b)` has spaces around it
+        (_, ":") => match parent {
+            K::WhileStmt => prev.kind != K::Name,
+            K::BreakStmt | K::ContinueStmt => true,
+            _ => false,
+        },
+        (":", _) => !matches!(parent, K::BreakStmt | K::ContinueStmt),
+        // Captures, like `|value|`
+        ("|", _) if next.kind == K::Name => false,
+        (_, "|") if prev.kind == K::Name => false,
+        _ => true,
+    }
+}
+
+/// Lays out a delimited list, including its delimiters
+fn delimited(parent: SyntaxKind, elements: &[SyntaxElement]) -> Doc {
+    let (open, inner, close) = match elements {
+        [open, inner @ .., close] => (open, inner, close),
+        _ => unreachable!(),
+    };
+    let open_doc = element(open);
+    let close_doc = element(close);
+    let is_brace = Piece::new(open).text == "{";
+    if is_brace && matches!(parent, SyntaxKind::Block | SyntaxKind::ContainerItem) {
+        let entries = entries(inner);
+        if entries == Doc::Concat(Vec::new()) {
+            return Doc::Concat(vec![open_doc, close_doc]);
+        }
+        return Doc::Concat(vec![
+            open_doc,
+            Doc::indent(Doc::Concat(vec![Doc::HardLine, entries])),
+            Doc::HardLine,
+            close_doc,
+        ]);
+    }
+
+    // A list of elements separated by commas
+    let mut items: Vec<(Vec<SyntaxElement>, Vec<Doc>)> = vec![(Vec::new(), Vec::new())];
+    let mut has_trailing_comma = false;
+    let mut has_line_comment = false;
+    for element in inner {
+        let piece = Piece::new(element);
+        let current = &mut items.last_mut().unwrap().0;
+        let is_after_comma = current.iter().all(|element| match element {
+            SyntaxElement::Token(token) => {
+                token.kind() == SyntaxKind::Whitespace && !token.text().contains('\n')
+            }
+            SyntaxElement::Node(_) => false,
+        });
+        match piece.kind {
+            SyntaxKind::Punct if piece.text == "," => {
+                items.push((Vec::new(), Vec::new()));
+                has_trailing_comma = true;
+                continue;
+            }
+            // Comments on the line of a comma stay after it
+            SyntaxKind::LineComment | SyntaxKind::BlockComment
+                if is_after_comma && items.len() > 1 =>
+            {
+                has_line_comment |= piece.kind == SyntaxKind::LineComment;
+                let prev_suffix = &mut items.iter_mut().rev().nth(1).unwrap().1;
+                prev_suffix.extend([Doc::text(" "), Doc::text(piece.text)]);
+                continue;
+            }
+            SyntaxKind::LineComment => has_line_comment = true,
+            SyntaxKind::Whitespace | SyntaxKind::BlockComment => {}
+            _ => has_trailing_comma = false,
+        }
+        items.last_mut().unwrap().0.push(element.clone());
+    }
+    // A line comment ending the last item is kept after it, rather than before the closing
+    // delimiter
+    let (last, last_suffix) = items.last_mut().unwrap();
+    let is_whitespace = |element: &SyntaxElement| element.kind() == SyntaxKind::Whitespace;
+    while last.last().is_some_and(is_whitespace) {
+        last.pop();
+    }
+    if last
+        .last()
+        .is_some_and(|element| element.kind() == SyntaxKind::LineComment)
+    {
+        let comment = last.pop().unwrap();
+        while last.last().is_some_and(is_whitespace) {
+            last.pop();
+        }
+        if !last.is_empty() {
+            last_suffix.push(Doc::text(" "));
+        }
+        last_suffix.push(self::element(&comment));
+    }
+    let is_last_empty = items
+        .last()
+        .is_some_and(|(item, suffix)| suffix.is_empty() && item.iter().all(is_whitespace));
+    if is_last_empty {
+        items.pop();
+    }
+    // When the last item only holds comments, the trailing comma is written before them
+    let ends_with_comma = has_trailing_comma && is_last_empty;
+    if items.is_empty() {
+        return Doc::Concat(vec![open_doc, close_doc]);
+    }
+
+    let padding = match is_brace {
+        true => Doc::Line,
+        false => Doc::SoftLine,
+    };
+    let item_count = items.len();
+    let mut inner_docs = vec![padding.clone()];
+    for (idx, (item, suffix)) in items.into_iter().enumerate() {
+        inner_docs.push(sequence(parent, &item));
+        if idx + 1 != item_count || ends_with_comma {
+            inner_docs.push(Doc::text(","));
+        }
+        inner_docs.extend(suffix);
+        if idx + 1 != item_count {
+            inner_docs.push(Doc::Line);
+        }
+    }
+    Doc::group(
+        Doc::Concat(vec![
+            open_doc,
+            Doc::indent(Doc::Concat(inner_docs)),
+            padding,
+            close_doc,
+        ]),
+        has_trailing_comma || has_line_comment,
+    )
+}
+
+/// Lays out declarations, statements or members one per line, keeping single empty lines
+/// between them along with comments
+fn entries(elements: &[SyntaxElement]) -> Doc {
+    let mut entries: Vec<(bool, Vec<Doc>)> = Vec::new();
+    let mut newlines = 0;
+    // Whether the last entry ends with a line comment, which nothing can follow on its line
+    let mut is_line_ended = false;
+    for element in elements {
+        let piece = Piece::new(element);
+        match piece.kind {
+            SyntaxKind::Whitespace => {
+                newlines += piece.text.matches('\n').count();
+                continue;
+            }
+            // Comments on the line of an entry stay on it
+            SyntaxKind::LineComment | SyntaxKind::BlockComment
+                if newlines == 0 && !entries.is_empty() =>
+            {
+                let (_, docs) = entries.last_mut().unwrap();
+                docs.extend([Doc::text(" "), Doc::text(piece.text)]);
+            }
+            SyntaxKind::Punct if matches!(piece.text, "," | ";") && !entries.is_empty() => {
+                let (_, docs) = entries.last_mut().unwrap();
+                if is_line_ended {
+                    docs.push(Doc::HardLine);
+                }
+                docs.push(Doc::text(piece.text));
+            }
+            _ => {
+                let is_blank_before = newlines >= 2 && !entries.is_empty();
+                entries.push((is_blank_before, vec![self::element(element)]));
+            }
+        }
+        is_line_ended = piece.kind == SyntaxKind::LineComment;
+        newlines = 0;
+    }
+
+    let mut docs = Vec::new();
+    for (idx, (is_blank_before, entry)) in entries.into_iter().enumerate() {
+        if idx != 0 {
+            docs.push(Doc::HardLine);
+        }
+        if is_blank_before {
+            docs.push(Doc::HardLine);
+        }
+        docs.extend(entry);
+    }
+    Doc::Concat(docs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = include_str!("../../../sample-code/main.cera");
+
+    fn format(text: &str) -> String {
+        let output = format_source(text).unwrap();
+        assert_eq!(format_source(&output).unwrap(), output, "not idempotent");
+        output
+    }
+
+    #[test]
+    fn sample() {
+        assert_eq!(
+            format(SAMPLE),
+            "pub fn main() !void {
+    spaceship.new(300.0);
+}
+
+const c: f32 = 2.9e9;
+fn is_valid_speed(speed: f32) -> bool {
+    return speed < c;
+}
+
+pub struct spaceship {
+    speed: f32,
+    fn new(speed: f32) -> ?spaceship {
+        if !is_valid_speed(speed) {
+            return null;
+        }
+        return .{
+            speed,
+        }
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn spacing() {
+        assert_eq!(
+            format("const   a:u8=b+c*  d;var x=.{ .y=1 ,z};"),
+            "const a: u8 = b + c * d;\nvar x = .{ .y = 1, z };\n"
+        );
+        assert_eq!(
+            format("fn f(a:*const u8,b:[]u8)->E!?[4]u8{x.*=a[0]; y=-try z.?; return .red;}"),
+            "fn f(a: *const u8, b: []u8) -> E!?[4]u8 {
+    x.* = a[0];
+    y = -try z.?;
+    return .red;
+}
+"
+        );
+        assert_eq!(
+            format(
+                "fn f() { outer: while(i<n) : (i+=1) { for (xs, 0..) |x,j| { break :outer; } } }"
+            ),
+            "fn f() {
+    outer: while (i < n) : (i += 1) {
+        for (xs, 0..) |x, j| {
+            break :outer;
+        }
+    }
+}
+"
+        );
+        assert_eq!(
+            format("const s:[ : 0 ]const u8=a[ 1 .. n ];const t:[4:0]u8=b[2..];"),
+            "const s: [:0]const u8 = a[1..n];\nconst t: [4:0]u8 = b[2..];\n"
+        );
+        assert_eq!(
+            format("enum(u8) e { a, b = 2, _ } union(enum) u {a: u8}"),
+            "enum(u8) e {\n    a,\n    b = 2,\n    _\n}\nunion(enum) u {\n    a: u8\n}\n"
+        );
+    }
+
+    #[test]
+    fn builtin_calls() {
+        assert_eq!(
+            format("pub const m = @ import ( \"m.cera\" ) . vec;"),
+            "pub const m = @import(\"m.cera\").vec;\n"
+        );
+    }
+
+    #[test]
+    fn trailing_commas_break_lists() {
+        assert_eq!(
+            format("const a = f(b, c,);"),
+            "const a = f(\n    b,\n    c,\n);\n"
+        );
+        assert_eq!(format("const a = f(b, c);"), "const a = f(b, c);\n");
+    }
+
+    #[test]
+    fn long_lists_are_broken() {
+        let args: Vec<_> = (0..12).map(|idx| format!("argument_{idx}")).collect();
+        let output = format(&format!("const a = f({});", args.join(", ")));
+        assert_eq!(
+            output,
+            format!("const a = f(\n    {}\n);\n", args.join(",\n    "))
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            format(
+                "// file\n\n\n\nfn a() { // after brace\n  b(); // trailing\n\n\n  /* block */ c();\n  // own line\n}"
+            ),
+            "// file
+
+fn a() {
+    // after brace
+    b(); // trailing
+
+    /* block */
+    c();
+    // own line
+}
+"
+        );
+        assert_eq!(
+            format("const a = f(b, // first\n c);"),
+            "const a = f(\n    b, // first\n    c\n);\n"
+        );
+        assert_eq!(
+            format("const a = b + // why\n c;"),
+            "const a = b + // why\n    c;\n"
+        );
+        assert_eq!(
+            format("fn f() { x = a + // why\n b // and\n + c; }"),
+            "fn f() {\n    x = a + // why\n        b // and\n        + c;\n}\n"
+        );
+    }
+
+    #[test]
+    fn blocks_on_continuation_lines() {
+        assert_eq!(
+            format("fn f() -> ? // why\nu8 {\nreturn 1;\n}"),
+            "fn f() -> ? // why\n    u8 {\n        return 1;\n    }\n"
+        );
+    }
+
+    #[test]
+    fn comments_in_lists() {
+        // Lists holding only comments aren't empty
+        assert_eq!(
+            format("const b = .{ // c\n};"),
+            "const b = .{\n    // c\n};\n"
+        );
+        assert_eq!(format("const a = f( /* x */ );"), "const a = f(/* x */);\n");
+        assert_eq!(
+            format("const a = f(b // x\n);"),
+            "const a = f(\n    b // x\n);\n"
+        );
+        assert_eq!(
+            format("struct s {\n    a: u8 // x\n,\n}"),
+            "struct s {\n    a: u8 // x\n    ,\n}\n"
+        );
+        assert_eq!(
+            format("const a = .{ b,\n /* x */ };"),
+            "const a = .{\n    b,\n    /* x */\n};\n"
+        );
+        assert_eq!(
+            format("const a = f(b, // x\n // y\n c);"),
+            "const a = f(\n    b, // x\n    // y\n    c\n);\n"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            format_source("fn a( {"),
+            Err(FormatError::Parse(_))
+        ));
+    }
+}
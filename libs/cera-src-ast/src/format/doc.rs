@@ -0,0 +1,179 @@
+//! A Wadler-style pretty-printer: documents are laid out flat when their group fits in the
+//! remaining width, and broken into lines otherwise
+
+pub const INDENT: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Doc {
+    /// Text without line breaks
+    Text(Box<str>),
+    /// A space, or a line break when the enclosing group is broken
+    Line,
+    /// Nothing, or a line break when the enclosing group is broken
+    SoftLine,
+    /// A line break, which breaks every enclosing group
+    HardLine,
+    Indent(Box<Doc>),
+    Group {
+        doc: Box<Doc>,
+        force_break: bool,
+    },
+    Concat(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: &str) -> Self {
+        Doc::Text(text.into())
+    }
+
+    pub fn indent(doc: Doc) -> Self {
+        Doc::Indent(Box::new(doc))
+    }
+
+    pub fn group(doc: Doc, force_break: bool) -> Self {
+        Doc::Group {
+            doc: Box::new(doc),
+            force_break,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Lays out the document, keeping lines under `width` when possible
+pub fn print(doc: &Doc, width: usize) -> String {
+    let mut output = String::new();
+    let mut column = 0;
+    let mut stack = vec![(0, Mode::Break, doc)];
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                output.push_str(text);
+                column += text.chars().count();
+            }
+            Doc::Line if mode == Mode::Flat => {
+                output.push(' ');
+                column += 1;
+            }
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                let trimmed_len = output.trim_end_matches(' ').len();
+                output.truncate(trimmed_len);
+                output.push('\n');
+                output.extend(std::iter::repeat_n(' ', indent));
+                column = indent;
+            }
+            Doc::Indent(doc) => stack.push((indent + INDENT, mode, doc)),
+            Doc::Group { doc, force_break } => {
+                let mode = match mode {
+                    Mode::Flat => Mode::Flat,
+                    Mode::Break if *force_break => Mode::Break,
+                    Mode::Break => match fits(width as isize - column as isize, doc, &stack) {
+                        true => Mode::Flat,
+                        false => Mode::Break,
+                    },
+                };
+                stack.push((indent, mode, doc));
+            }
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+        }
+    }
+    let trimmed_len = output.trim_end_matches(' ').len();
+    output.truncate(trimmed_len);
+    output
+}
+
+/// Whether `doc` fits flat in the remaining width, along with what follows it up to the next
+/// line break
+fn fits(mut remaining: isize, doc: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut pending = vec![(Mode::Flat, doc)];
+    let mut rest = rest.iter().rev();
+    loop {
+        if remaining < 0 {
+            return false;
+        }
+        let (mode, doc) = match pending.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some((_, mode, doc)) => (*mode, *doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Line | Doc::SoftLine if mode == Mode::Break => return true,
+            Doc::Line => remaining -= 1,
+            Doc::SoftLine => {}
+            Doc::HardLine => return mode == Mode::Break,
+            Doc::Indent(doc) => pending.push((mode, doc)),
+            Doc::Group { doc, force_break } => {
+                if *force_break && mode == Mode::Flat {
+                    return false;
+                }
+                pending.push((mode, doc));
+            }
+            Doc::Concat(docs) => pending.extend(docs.iter().rev().map(|doc| (mode, doc))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(elems: &[&str], force_break: bool) -> Doc {
+        let mut inner = vec![Doc::SoftLine];
+        for (idx, elem) in elems.iter().enumerate() {
+            if idx != 0 {
+                inner.extend([Doc::text(","), Doc::Line]);
+            }
+            inner.push(Doc::text(elem));
+        }
+        Doc::group(
+            Doc::Concat(vec![
+                Doc::text("f("),
+                Doc::indent(Doc::Concat(inner)),
+                Doc::SoftLine,
+                Doc::text(")"),
+            ]),
+            force_break,
+        )
+    }
+
+    #[test]
+    fn fitting() {
+        let doc = list(&["aaa", "bbb"], false);
+        assert_eq!(print(&doc, 20), "f(aaa, bbb)");
+        assert_eq!(print(&doc, 10), "f(\n    aaa,\n    bbb\n)");
+        assert_eq!(
+            print(&list(&["a"], true), 20),
+            "f(\n    a\n)",
+            "forced breaks ignore the width"
+        );
+    }
+
+    #[test]
+    fn text_after_group_counts() {
+        let doc = Doc::Concat(vec![list(&["aaa"], false), Doc::text(";;;;")]);
+        assert_eq!(print(&doc, 10), "f(aaa);;;;");
+        assert_eq!(print(&doc, 9), "f(\n    aaa\n);;;;");
+    }
+
+    #[test]
+    fn hard_lines_break_groups() {
+        let doc = Doc::group(
+            Doc::Concat(vec![
+                Doc::text("a"),
+                Doc::Line,
+                Doc::text("b"),
+                Doc::HardLine,
+            ]),
+            false,
+        );
+        assert_eq!(print(&doc, 80), "a\nb\n");
+    }
+}
@@ -1 +1,10 @@
+pub mod ast;
+pub mod cst;
+pub mod format;
+pub mod modules;
+pub mod parser;
+pub mod resolve;
+pub mod suggest;
 pub mod token_tree;
+pub mod visit;
+pub mod visit_mut;
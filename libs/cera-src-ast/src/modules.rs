@@ -0,0 +1,601 @@
+//! Loading of the files a program is made of. Each file is a struct whose declarations are its
+//! items, and `@import("path/to/file.cera")` evaluates to the struct of the file at that path,
+//! relative to the root of the project.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use cera_parse::{src_token::Literal, text_region::TextSpan};
+
+use crate::{
+    ast::{Expr, ExprKind, File, Item, ItemKind, Mutability, Visibility},
+    parser::{parse_file_recovering, ParseError},
+    suggest::{suggest, NameKind},
+    visit::{walk_expr, Visitor},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModuleId(usize);
+
+/// A place in a module, like the `@import` of another one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Site {
+    pub module: ModuleId,
+    pub span: TextSpan,
+}
+
+#[derive(Debug)]
+pub struct Module {
+    /// Relative to the root of the project
+    pub path: PathBuf,
+    pub text: String,
+    pub file: File,
+    pub imports: Box<[Import]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    /// The span of the whole `@import(...)`
+    pub span: TextSpan,
+    /// `None` if the file couldn't be loaded
+    pub target: Option<ModuleId>,
+}
+
+#[derive(Debug)]
+pub enum ModuleError {
+    /// The file couldn't be read, `imported_at` being `None` for the root module
+    Io {
+        path: PathBuf,
+        error: io::Error,
+        imported_at: Option<Site>,
+    },
+    Parse {
+        module: ModuleId,
+        errors: Vec<ParseError>,
+    },
+    /// `@import` takes a single string literal
+    InvalidImport(Site),
+    /// Absolute paths, or paths going out of the root of the project, `imported_at` being `None`
+    /// for the root module
+    OutsideRoot {
+        path: Box<str>,
+        imported_at: Option<Site>,
+    },
+    /// Modules importing themselves through the imports, in the order they are followed from the
+    /// first module of the cycle back to it
+    Cycle(Box<[Site]>),
+}
+
+/// What a name of a module refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Module(ModuleId),
+    /// The item at this index of the items of the module
+    Item {
+        module: ModuleId,
+        index: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LookupError {
+    NotFound {
+        module: ModuleId,
+        name: Box<str>,
+        /// The indices of the closest items which could have been looked up
+        suggestions: Box<[usize]>,
+    },
+    /// The item isn't `pub`, and is looked up from another module
+    Private { module: ModuleId, index: usize },
+    /// Constants defined as each other, like `const a = b; const b = a;`
+    AliasCycle(Site),
+}
+
+type Loader = Box<dyn FnMut(&Path) -> io::Result<String>>;
+
+/// The modules of a project, loaded once each however many times they are imported
+pub struct ModuleGraph {
+    root: PathBuf,
+    loader: Loader,
+    modules: Vec<Module>,
+    by_path: HashMap<PathBuf, ModuleId>,
+    errors: Vec<ModuleError>,
+    /// The modules being loaded, each of which is imported by the one before
+    loading: Vec<ModuleId>,
+    /// The import of each module of `loading` being followed
+    import_stack: Vec<Site>,
+}
+
+impl ModuleGraph {
+    /// Loads the files from the filesystem, in the `root` directory
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let fs_root = root.clone();
+        Self::with_loader(root, move |path| {
+            std::fs::read_to_string(fs_root.join(path))
+        })
+    }
+
+    /// Loads the files with `loader`, which is given paths relative to `root`
+    pub fn with_loader(
+        root: impl Into<PathBuf>,
+        loader: impl FnMut(&Path) -> io::Result<String> + 'static,
+    ) -> Self {
+        ModuleGraph {
+            root: root.into(),
+            loader: Box::new(loader),
+            modules: Vec::new(),
+            by_path: HashMap::new(),
+            errors: Vec::new(),
+            loading: Vec::new(),
+            import_stack: Vec::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Loads the module at `path`, relative to the root, along with everything it imports
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Option<ModuleId> {
+        match normalize(path.as_ref()) {
+            Some(path) => self.load_module(path, None),
+            None => {
+                self.errors.push(ModuleError::OutsideRoot {
+                    path: path.as_ref().to_string_lossy().into(),
+                    imported_at: None,
+                });
+                None
+            }
+        }
+    }
+
+    pub fn module(&self, id: ModuleId) -> &Module {
+        &self.modules[id.0]
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = (ModuleId, &Module)> {
+        self.modules
+            .iter()
+            .enumerate()
+            .map(|(idx, module)| (ModuleId(idx), module))
+    }
+
+    pub fn errors(&self) -> &[ModuleError] {
+        &self.errors
+    }
+
+    pub fn take_errors(&mut self) -> Vec<ModuleError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn load_module(&mut self, path: PathBuf, imported_at: Option<Site>) -> Option<ModuleId> {
+        if let Some(&id) = self.by_path.get(&path) {
+            if let Some(first) = self.loading.iter().position(|loading| *loading == id) {
+                // The import closing the cycle is the last one of the stack
+                let cycle = self.import_stack[first..].into();
+                self.errors.push(ModuleError::Cycle(cycle));
+            }
+            return Some(id);
+        }
+
+        let text = match (self.loader)(&path) {
+            Ok(text) => text,
+            Err(error) => {
+                self.errors.push(ModuleError::Io {
+                    path,
+                    error,
+                    imported_at,
+                });
+                return None;
+            }
+        };
+        let id = ModuleId(self.modules.len());
+        let (file, errors) = parse_file_recovering(&text);
+        if !errors.is_empty() {
+            self.errors.push(ModuleError::Parse { module: id, errors });
+        }
+        let mut collector = ImportCollector::default();
+        collector.visit_file(&file);
+        self.by_path.insert(path.clone(), id);
+        self.modules.push(Module {
+            path,
+            text,
+            file,
+            imports: Box::new([]),
+        });
+
+        self.loading.push(id);
+        let mut imports = Vec::new();
+        for (span, import_path) in collector.imports {
+            let site = Site { module: id, span };
+            let target = match import_path
+                .as_deref()
+                .map(|path| (path, normalize(path.as_ref())))
+            {
+                Some((_, Some(path))) => {
+                    self.import_stack.push(site.clone());
+                    let target = self.load_module(path, Some(site.clone()));
+                    self.import_stack.pop();
+                    target
+                }
+                Some((path, None)) => {
+                    self.errors.push(ModuleError::OutsideRoot {
+                        path: path.into(),
+                        imported_at: Some(site.clone()),
+                    });
+                    None
+                }
+                None => {
+                    self.errors.push(ModuleError::InvalidImport(site.clone()));
+                    None
+                }
+            };
+            imports.push(Import {
+                span: site.span,
+                target,
+            });
+        }
+        self.loading.pop();
+        self.modules[id.0].imports = imports.into_boxed_slice();
+        Some(id)
+    }
+
+    /// Resolves a declaration of a module, following the constants that are other modules or
+    /// declarations of them, which is how `pub const` re-exports them. Only `pub` declarations
+    /// can be looked up when `from_outside` is true. Declarations of the `expected` kind are
+    /// suggested first when there is none named `name`.
+    pub fn lookup(
+        &self,
+        module: ModuleId,
+        name: &str,
+        expected: NameKind,
+        from_outside: bool,
+    ) -> Result<Resolution, LookupError> {
+        let mut followed = Vec::new();
+        self.lookup_following(module, name, expected, from_outside, &mut followed)
+    }
+
+    fn lookup_following(
+        &self,
+        module: ModuleId,
+        name: &str,
+        expected: NameKind,
+        from_outside: bool,
+        followed: &mut Vec<(ModuleId, usize)>,
+    ) -> Result<Resolution, LookupError> {
+        let items = &self.module(module).file.items;
+        let Some(index) = items.iter().position(|item| item_name(item) == Some(name)) else {
+            let candidates = items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| !from_outside || item.visibility == Visibility::Public)
+                .filter_map(|(index, item)| Some((index, item_name(item)?, item.name_kind())));
+            return Err(LookupError::NotFound {
+                module,
+                name: name.into(),
+                suggestions: suggest(name, expected, candidates).into(),
+            });
+        };
+        let item = &items[index];
+        if from_outside && item.visibility != Visibility::Public {
+            return Err(LookupError::Private { module, index });
+        }
+        let ItemKind::Var(decl) = &item.kind else {
+            return Ok(Resolution::Item { module, index });
+        };
+        if decl.mutability != Mutability::Const || !is_alias(&decl.value) {
+            return Ok(Resolution::Item { module, index });
+        }
+        if followed.contains(&(module, index)) {
+            return Err(LookupError::AliasCycle(Site {
+                module,
+                span: item.span.clone(),
+            }));
+        }
+        followed.push((module, index));
+        self.resolve_alias(module, &decl.value, expected, followed)?
+            .map_or(Ok(Resolution::Item { module, index }), Ok)
+    }
+
+    /// Resolves `@import(...)`, names and fields of them, giving `None` for imports that couldn't
+    /// be loaded
+    fn resolve_alias(
+        &self,
+        module: ModuleId,
+        expr: &Expr,
+        expected: NameKind,
+        followed: &mut Vec<(ModuleId, usize)>,
+    ) -> Result<Option<Resolution>, LookupError> {
+        match &expr.kind {
+            ExprKind::BuiltinCall { .. } => Ok(self
+                .module(module)
+                .imports
+                .iter()
+                .find(|import| import.span == expr.span)
+                .and_then(|import| import.target)
+                .map(Resolution::Module)),
+            ExprKind::Ident(name) => self
+                .lookup_following(module, name, expected, false, followed)
+                .map(Some),
+            // Modules are named by constants
+            ExprKind::Field { base, field } => {
+                match self.resolve_alias(module, base, NameKind::Value, followed)? {
+                    Some(Resolution::Module(base)) => self
+                        .lookup_following(base, &field.name, expected, true, followed)
+                        .map(Some),
+                    _ => Ok(None),
+                }
+            }
+            ExprKind::Paren(inner) => self.resolve_alias(module, inner, expected, followed),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Whether the expression names another declaration or module, rather than computing a value
+fn is_alias(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::BuiltinCall { name, .. } => &*name.name == "import",
+        ExprKind::Ident(_) => true,
+        ExprKind::Field { base, .. } | ExprKind::Paren(base) => is_alias(base),
+        _ => false,
+    }
+}
+
+pub fn item_name(item: &Item) -> Option<&str> {
+    item.name().map(|name| &*name.name)
+}
+
+/// Removes the `.` and `..` of a relative path, returning `None` if it goes out of its root
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(normalized)
+}
+
+/// Finds the `@import`s of a file, along with their path if it is a string literal
+#[derive(Default)]
+struct ImportCollector {
+    imports: Vec<(TextSpan, Option<Box<str>>)>,
+}
+
+impl Visitor for ImportCollector {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::BuiltinCall { name, args } = &expr.kind {
+            if &*name.name == "import" {
+                let path = match &**args {
+                    [Expr {
+                        kind: ExprKind::Literal(Literal::String(path)),
+                        ..
+                    }] => Some(path.clone()),
+                    _ => None,
+                };
+                self.imports.push((expr.span.clone(), path));
+            }
+        }
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(files: &[(&str, &str)]) -> ModuleGraph {
+        let files: HashMap<PathBuf, String> = files
+            .iter()
+            .map(|(path, text)| (PathBuf::from(path), text.to_string()))
+            .collect();
+        ModuleGraph::with_loader("project", move |path| {
+            files
+                .get(path)
+                .cloned()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        })
+    }
+
+    fn span_of(text: &str, pattern: &str) -> TextSpan {
+        TextSpan {
+            len: pattern.len(),
+            idx: text.find(pattern).unwrap(),
+        }
+    }
+
+    const MAIN: &str = "const math = @import(\"lib/math.cera\");
+const vec = @import(\"./lib/../lib/vec.cera\");
+pub fn main() void {}";
+    const MATH: &str = "pub const vec = @import(\"lib/vec.cera\");
+pub const vec2 = vec.vec2;
+const secret = 1;
+pub fn add(a: u8, b: u8) u8 { return a + b; }";
+    const VEC: &str = "pub struct vec2 { x: f32, y: f32 }";
+
+    #[test]
+    fn files_are_loaded_once() {
+        let mut graph = graph(&[
+            ("main.cera", MAIN),
+            ("lib/math.cera", MATH),
+            ("lib/vec.cera", VEC),
+        ]);
+        let main = graph.load("main.cera").unwrap();
+        assert!(graph.errors().is_empty(), "{:?}", graph.errors());
+        assert_eq!(graph.modules().count(), 3);
+
+        let imports = &graph.module(main).imports;
+        let math = imports[0].target.unwrap();
+        let vec = imports[1].target.unwrap();
+        assert_eq!(graph.module(math).path, Path::new("lib/math.cera"));
+        assert_eq!(graph.module(vec).path, Path::new("lib/vec.cera"));
+        assert_eq!(graph.module(math).imports[0].target, Some(vec));
+    }
+
+    #[test]
+    fn re_exports() {
+        let mut graph = graph(&[
+            ("main.cera", MAIN),
+            ("lib/math.cera", MATH),
+            ("lib/vec.cera", VEC),
+        ]);
+        let main = graph.load("main.cera").unwrap();
+        let Ok(Resolution::Module(math)) = graph.lookup(main, "math", NameKind::Value, false)
+        else {
+            panic!()
+        };
+        let Ok(Resolution::Module(vec)) = graph.lookup(main, "vec", NameKind::Value, false) else {
+            panic!()
+        };
+        let vec2 = Resolution::Item {
+            module: vec,
+            index: 0,
+        };
+        assert_eq!(graph.lookup(math, "vec2", NameKind::Value, true), Ok(vec2));
+        assert_eq!(
+            graph.lookup(math, "add", NameKind::Value, true),
+            Ok(Resolution::Item {
+                module: math,
+                index: 3
+            })
+        );
+        assert_eq!(
+            graph.lookup(math, "secret", NameKind::Value, true),
+            Err(LookupError::Private {
+                module: math,
+                index: 2
+            })
+        );
+        assert!(graph.lookup(math, "secret", NameKind::Value, false).is_ok());
+        assert_eq!(
+            graph.lookup(main, "missing", NameKind::Value, false),
+            Err(LookupError::NotFound {
+                module: main,
+                name: "missing".into(),
+                suggestions: Box::new([])
+            })
+        );
+        assert_eq!(
+            graph.lookup(math, "ad", NameKind::Value, true),
+            Err(LookupError::NotFound {
+                module: math,
+                name: "ad".into(),
+                suggestions: Box::new([3])
+            })
+        );
+        // Private items aren't suggested from other modules
+        let Err(LookupError::NotFound { suggestions, .. }) =
+            graph.lookup(math, "secre", NameKind::Value, true)
+        else {
+            panic!()
+        };
+        assert!(suggestions.is_empty());
+        let Err(LookupError::NotFound { suggestions, .. }) =
+            graph.lookup(math, "secre", NameKind::Value, false)
+        else {
+            panic!()
+        };
+        assert_eq!(&*suggestions, [2]);
+    }
+
+    #[test]
+    fn suggestions_of_the_expected_kind() {
+        let mut graph = graph(&[(
+            "main.cera",
+            "pub const vec3 = 1;\npub struct vec2 { x: f32, y: f32 }",
+        )]);
+        let main = graph.load("main.cera").unwrap();
+        let suggestions = |expected| match graph.lookup(main, "vec", expected, true) {
+            Err(LookupError::NotFound { suggestions, .. }) => suggestions,
+            result => panic!("{result:?}"),
+        };
+        assert_eq!(&*suggestions(NameKind::Value), [0, 1]);
+        assert_eq!(&*suggestions(NameKind::Type), [1, 0]);
+    }
+
+    #[test]
+    fn alias_cycles() {
+        let mut graph = graph(&[("main.cera", "const a = b;\nconst b = a;")]);
+        let main = graph.load("main.cera").unwrap();
+        assert!(matches!(
+            graph.lookup(main, "a", NameKind::Value, false),
+            Err(LookupError::AliasCycle(_))
+        ));
+    }
+
+    #[test]
+    fn import_cycles() {
+        let a = "const b = @import(\"b.cera\");";
+        let b = "const c = @import(\"c.cera\");";
+        let c = "const unrelated = 1;\nconst a = @import(\"a.cera\");";
+        let mut graph = graph(&[("a.cera", a), ("b.cera", b), ("c.cera", c)]);
+        let first = graph.load("a.cera").unwrap();
+        let [ModuleError::Cycle(cycle)] = graph.errors() else {
+            panic!("{:?}", graph.errors())
+        };
+        let modules: Vec<_> = cycle
+            .iter()
+            .map(|site| graph.module(site.module).path.to_str().unwrap())
+            .collect();
+        assert_eq!(modules, ["a.cera", "b.cera", "c.cera"]);
+        assert_eq!(cycle[0].module, first);
+        assert_eq!(cycle[2].span, span_of(c, "@import(\"a.cera\")"));
+    }
+
+    #[test]
+    fn import_errors() {
+        let main = "const a = @import(\"missing.cera\");
+const b = @import(\"../outside.cera\");
+const c = @import(name);
+const d = @import(\"broken.cera\");";
+        let mut graph = graph(&[("main.cera", main), ("broken.cera", "fn {")]);
+        let id = graph.load("main.cera").unwrap();
+        let errors = graph.take_errors();
+        assert!(matches!(
+            &errors[0],
+            ModuleError::Io { path, imported_at: Some(site), .. }
+                if path == Path::new("missing.cera")
+                    && site.span == span_of(main, "@import(\"missing.cera\")")
+        ));
+        assert!(matches!(
+            &errors[1],
+            ModuleError::OutsideRoot { path, .. } if &**path == "../outside.cera"
+        ));
+        assert!(matches!(
+            &errors[2],
+            ModuleError::InvalidImport(site) if site.span == span_of(main, "@import(name)")
+        ));
+        assert!(matches!(&errors[3], ModuleError::Parse { .. }));
+        assert_eq!(errors.len(), 4);
+        assert_eq!(graph.module(id).imports[0].target, None);
+        assert!(graph.module(id).imports[3].target.is_some());
+    }
+
+    #[test]
+    fn loading_outside_of_the_root() {
+        let mut graph = graph(&[("main.cera", "")]);
+        assert_eq!(graph.load("../main.cera"), None);
+        assert_eq!(graph.load("/main.cera"), None);
+        let errors = graph.take_errors();
+        assert!(matches!(
+            &errors[..],
+            [
+                ModuleError::OutsideRoot { path: first, imported_at: None },
+                ModuleError::OutsideRoot { path: second, imported_at: None },
+            ] if &**first == "../main.cera" && &**second == "/main.cera"
+        ));
+        assert_eq!(graph.modules().count(), 0);
+    }
+}
@@ -0,0 +1,849 @@
+use std::{cell::RefCell, rc::Rc};
+
+use cera_parse::{
+    parsing::{parse, ParsingError},
+    src_token::{SpecialChar, Token, TokenParseError},
+    text_region::{Text, TextPosition, TextSpan},
+};
+
+mod expressions;
+mod statements;
+
+use crate::{
+    ast::{
+        ContainerDecl, ContainerKind, FieldDecl, File, FnDecl, FnSignature, Ident, Item, ItemKind,
+        Member, Mutability, Param, UnionTag, VarDecl, VariantDecl, Visibility,
+    },
+    token_tree::{Delimiter, Group, TokenNode, TokenTree, TokenTreeParseError},
+};
+
+/// Identifiers that can't be used as names
+pub const KEYWORDS: &[&str] = &[
+    "pub", "fn", "const", "var", "struct", "enum", "union", "trait", "return", "if", "else",
+    "while", "for", "break", "continue", "defer", "errdefer", "try", "and", "or", "orelse",
+    "catch",
+];
+
+const CONTAINER_KEYWORDS: &[&str] = &["struct", "enum", "union", "trait"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Lexing(ParsingError<TokenParseError>),
+    TokenTree(TokenTreeParseError),
+    /// `expected` describes what the parser was looking for
+    UnexpectedToken {
+        expected: &'static str,
+        found: TextSpan,
+    },
+    UnexpectedEnd {
+        expected: &'static str,
+        position: TextPosition,
+    },
+    /// A statement that isn't the last of its block and doesn't end with a block needs a `;`
+    /// right after it
+    MissingSemicolon(TextPosition),
+    /// Operators that can't be chained without parentheses, like `a < b < c`
+    NonAssociative {
+        first: TextSpan,
+        second: TextSpan,
+    },
+}
+
+pub fn parse_file(text: &str) -> Result<File, ParseError> {
+    let (file, mut errors) = parse_file_recovering(text);
+    match errors.is_empty() {
+        true => Ok(file),
+        false => Err(errors.swap_remove(0)),
+    }
+}
+
+/// Parses as much of the file as possible, replacing the declarations and statements that
+/// couldn't be parsed with error nodes
+pub fn parse_file_recovering(text: &str) -> (File, Vec<ParseError>) {
+    let span = TextSpan {
+        len: text.len(),
+        idx: 0,
+    };
+    let (tokens, spans) = match parse::<Token>(text) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            let file = File {
+                items: Box::new([]),
+                span,
+            };
+            return (file, vec![ParseError::Lexing(error)]);
+        }
+    };
+    let (tree, tree_errors) = TokenTree::from_tokens(&Text::new(text), &tokens, &spans);
+    let mut errors: Vec<_> = tree_errors.into_iter().map(ParseError::TokenTree).collect();
+    let mut parser = Parser::new(&tree.nodes, text.len());
+    let items = parser.parse_items();
+    errors.extend(parser.take_errors());
+    (File { items, span }, errors)
+}
+
+/// A cursor over the nodes of a token tree, parsing the content of a single group at a time
+pub struct Parser<'a> {
+    nodes: &'a [TokenNode],
+    pos: usize,
+    /// Where the parsed nodes end, used for errors about missing tokens
+    end: usize,
+    prev_span: Option<TextSpan>,
+    /// Set while parsing a condition outside of parentheses, where `|` starts a capture
+    pipe_is_capture: bool,
+    /// The errors recovered from, shared with the parsers of nested groups
+    errors: Rc<RefCell<Vec<ParseError>>>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(nodes: &'a [TokenNode], end: usize) -> Self {
+        Self {
+            nodes,
+            pos: 0,
+            end,
+            prev_span: None,
+            pipe_is_capture: false,
+            errors: Rc::default(),
+        }
+    }
+
+    fn group_parser(&self, group: &'a Group) -> Self {
+        Self {
+            errors: self.errors.clone(),
+            ..Self::new(&group.nodes, group.close.idx)
+        }
+    }
+
+    /// Records an error the parser recovered from
+    fn report(&self, error: ParseError) {
+        self.errors.borrow_mut().push(error);
+    }
+
+    pub fn take_errors(&mut self) -> Vec<ParseError> {
+        self.errors.take()
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.pos == self.nodes.len()
+    }
+
+    fn peek(&self) -> Option<&'a TokenNode> {
+        self.nodes.get(self.pos)
+    }
+
+    fn peek_nth(&self, nth: usize) -> Option<&'a TokenNode> {
+        self.nodes.get(self.pos + nth)
+    }
+
+    fn bump(&mut self) -> Option<&'a TokenNode> {
+        let node = self.nodes.get(self.pos)?;
+        self.pos += 1;
+        self.prev_span = Some(node.span());
+        Some(node)
+    }
+
+    /// Returns the span going from `start` to the end of the last consumed node
+    fn span_from(&self, start: &TextSpan) -> TextSpan {
+        match &self.prev_span {
+            Some(prev) => start.join(prev),
+            None => start.clone(),
+        }
+    }
+
+    fn peek_span(&self) -> TextSpan {
+        match self.peek() {
+            Some(node) => node.span(),
+            None => TextSpan {
+                len: 0,
+                idx: self.end,
+            },
+        }
+    }
+
+    fn unexpected(&self, expected: &'static str) -> ParseError {
+        match self.peek() {
+            Some(TokenNode::Group(group)) => ParseError::UnexpectedToken {
+                expected,
+                found: group.open.clone(),
+            },
+            Some(TokenNode::Token(_, span)) => ParseError::UnexpectedToken {
+                expected,
+                found: span.clone(),
+            },
+            None => ParseError::UnexpectedEnd {
+                expected,
+                position: TextPosition { idx: self.end },
+            },
+        }
+    }
+
+    fn expect_end(&self, expected: &'static str) -> Result<(), ParseError> {
+        if self.is_at_end() {
+            Ok(())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn peek_keyword_nth(&self, nth: usize, keyword: &str) -> bool {
+        matches!(
+            self.peek_nth(nth),
+            Some(TokenNode::Token(Token::Identifier(ident), _)) if ident.name() == keyword
+        )
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.peek_keyword_nth(0, keyword)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> Option<TextSpan> {
+        if self.peek_keyword(keyword) {
+            self.bump().map(TokenNode::span)
+        } else {
+            None
+        }
+    }
+
+    fn expect_keyword(
+        &mut self,
+        keyword: &str,
+        expected: &'static str,
+    ) -> Result<TextSpan, ParseError> {
+        self.eat_keyword(keyword)
+            .ok_or_else(|| self.unexpected(expected))
+    }
+
+    fn peek_special_nth(&self, nth: usize, special_char: &SpecialChar) -> Option<&'a TextSpan> {
+        match self.peek_nth(nth) {
+            Some(TokenNode::Token(Token::SpecialChar(char), span)) if char == special_char => {
+                Some(span)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the span of the operator if the next tokens are the given chars without anything
+    /// in between
+    fn peek_joint(&self, chars: &[SpecialChar]) -> Option<TextSpan> {
+        let mut span: Option<TextSpan> = None;
+        for (nth, char) in chars.iter().enumerate() {
+            let char_span = self.peek_special_nth(nth, char)?;
+            if let Some(prev) = &span {
+                if prev.end() != char_span.idx {
+                    return None;
+                }
+            }
+            span = Some(match span {
+                Some(prev) => prev.join(char_span),
+                None => char_span.clone(),
+            });
+        }
+        span
+    }
+
+    fn eat_joint(&mut self, chars: &[SpecialChar]) -> Option<TextSpan> {
+        let span = self.peek_joint(chars)?;
+        for _ in chars {
+            self.bump();
+        }
+        Some(span)
+    }
+
+    fn eat_special(&mut self, special_char: SpecialChar) -> Option<TextSpan> {
+        self.eat_joint(&[special_char])
+    }
+
+    fn expect_special(
+        &mut self,
+        special_char: SpecialChar,
+        expected: &'static str,
+    ) -> Result<TextSpan, ParseError> {
+        self.eat_special(special_char)
+            .ok_or_else(|| self.unexpected(expected))
+    }
+
+    fn peek_group(&self, delimiter: Delimiter) -> Option<&'a Group> {
+        match self.peek() {
+            Some(TokenNode::Group(group)) if group.delimiter == delimiter => Some(group),
+            _ => None,
+        }
+    }
+
+    fn peek_group_nth(&self, nth: usize, delimiter: Delimiter) -> Option<&'a Group> {
+        match self.peek_nth(nth) {
+            Some(TokenNode::Group(group)) if group.delimiter == delimiter => Some(group),
+            _ => None,
+        }
+    }
+
+    fn eat_group(&mut self, delimiter: Delimiter) -> Option<&'a Group> {
+        let group = self.peek_group(delimiter)?;
+        self.bump();
+        Some(group)
+    }
+
+    fn expect_group(
+        &mut self,
+        delimiter: Delimiter,
+        expected: &'static str,
+    ) -> Result<&'a Group, ParseError> {
+        self.eat_group(delimiter)
+            .ok_or_else(|| self.unexpected(expected))
+    }
+
+    fn peek_ident(&self) -> Option<Ident> {
+        self.peek_ident_nth(0)
+    }
+
+    fn peek_ident_nth(&self, nth: usize) -> Option<Ident> {
+        match self.peek_nth(nth) {
+            Some(TokenNode::Token(Token::Identifier(ident), span))
+                if !KEYWORDS.contains(&ident.name()) =>
+            {
+                Some(Ident {
+                    name: ident.name().into(),
+                    span: span.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn expect_ident(&mut self, expected: &'static str) -> Result<Ident, ParseError> {
+        let ident = self.peek_ident().ok_or_else(|| self.unexpected(expected))?;
+        self.bump();
+        Ok(ident)
+    }
+
+    /// Parses comma separated elements until the end, allowing a trailing comma
+    fn parse_comma_separated<T>(
+        &mut self,
+        mut parse_elem: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Box<[T]>, ParseError> {
+        let mut elems = Vec::new();
+        while !self.is_at_end() {
+            elems.push(parse_elem(self)?);
+            if self.eat_special(SpecialChar::Comma).is_none() {
+                self.expect_end("`,`")?;
+            }
+        }
+        Ok(elems.into_boxed_slice())
+    }
+}
+
+// Declarations
+impl<'a> Parser<'a> {
+    pub fn parse_items(&mut self) -> Box<[Item]> {
+        let mut items = Vec::new();
+        while !self.is_at_end() {
+            let start = (self.pos, self.peek_span());
+            match self.parse_item() {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    self.report(error);
+                    self.skip_past(start.0, |parser| parser.is_item_start(), |_| false);
+                    items.push(Item {
+                        visibility: Visibility::Private,
+                        kind: ItemKind::Error,
+                        span: self.span_from(&start.1),
+                    });
+                }
+            }
+        }
+        items.into_boxed_slice()
+    }
+
+    /// Skips the nodes of something that couldn't be parsed, stopping before a node `is_next`
+    /// is true for or after a node `is_last` is true for. At least one node is skipped if the
+    /// parser didn't move since `start`.
+    fn skip_past(
+        &mut self,
+        start: usize,
+        is_next: impl Fn(&Self) -> bool,
+        is_last: impl Fn(&TokenNode) -> bool,
+    ) {
+        if self.pos == start {
+            self.bump();
+        }
+        while !self.is_at_end() && !is_next(self) {
+            if self.bump().is_some_and(&is_last) {
+                return;
+            }
+        }
+    }
+
+    fn is_item_start(&self) -> bool {
+        let nth = if self.peek_keyword("pub") { 1 } else { 0 };
+        ["fn", "const", "var"]
+            .iter()
+            .chain(CONTAINER_KEYWORDS)
+            .any(|keyword| self.peek_keyword_nth(nth, keyword))
+    }
+
+    fn parse_item(&mut self) -> Result<Item, ParseError> {
+        let start = self.peek_span();
+        let visibility = match self.eat_keyword("pub") {
+            Some(_) => Visibility::Public,
+            None => Visibility::Private,
+        };
+        let kind = if self.peek_keyword("fn") {
+            ItemKind::Fn(self.parse_fn()?)
+        } else if self.peek_keyword("const") | self.peek_keyword("var") {
+            let decl = self.parse_var_decl()?;
+            self.expect_special(SpecialChar::SemiColon, "`;`")?;
+            ItemKind::Var(decl)
+        } else if CONTAINER_KEYWORDS
+            .iter()
+            .any(|keyword| self.peek_keyword(keyword))
+        {
+            ItemKind::Container(self.parse_container()?)
+        } else {
+            return Err(self.unexpected("a declaration"));
+        };
+        Ok(Item {
+            visibility,
+            kind,
+            span: self.span_from(&start),
+        })
+    }
+
+    fn parse_fn(&mut self) -> Result<FnDecl, ParseError> {
+        let signature = self.parse_fn_signature()?;
+        let body = self.parse_block()?;
+        Ok(FnDecl {
+            name: signature.name,
+            params: signature.params,
+            return_type: signature.return_type,
+            body,
+        })
+    }
+
+    /// Parses everything before the body of a function
+    fn parse_fn_signature(&mut self) -> Result<FnSignature, ParseError> {
+        let start = self.expect_keyword("fn", "`fn`")?;
+        let name = self.expect_ident("a function name")?;
+        let params = self.expect_group(Delimiter::Paren, "a parameter list")?;
+        let params = self.group_parser(params).parse_comma_separated(|parser| {
+            let name = parser.expect_ident("a parameter name")?;
+            parser.expect_special(SpecialChar::Colon, "`:`")?;
+            let param_type = parser.parse_expr()?;
+            Ok(Param {
+                span: name.span.join(&param_type.span),
+                name,
+                param_type,
+            })
+        })?;
+        let return_type = if self
+            .eat_joint(&[SpecialChar::Minus, SpecialChar::GreaterThan])
+            .is_some()
+            || (self.peek_group(Delimiter::Brace).is_none()
+                && self.peek_special_nth(0, &SpecialChar::SemiColon).is_none())
+        {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(FnSignature {
+            name,
+            params,
+            return_type,
+            span: self.span_from(&start),
+        })
+    }
+
+    fn parse_var_decl(&mut self) -> Result<VarDecl, ParseError> {
+        let mutability = if self.eat_keyword("const").is_some() {
+            Mutability::Const
+        } else {
+            self.expect_keyword("var", "`const` or `var`")?;
+            Mutability::Var
+        };
+        let name = self.expect_ident("a name")?;
+        let var_type = match self.eat_special(SpecialChar::Colon) {
+            Some(_) => Some(self.parse_expr()?),
+            None => None,
+        };
+        self.expect_special(SpecialChar::Equal, "`=`")?;
+        let value = self.parse_expr()?;
+        Ok(VarDecl {
+            mutability,
+            name,
+            var_type,
+            value,
+        })
+    }
+
+    fn parse_container(&mut self) -> Result<ContainerDecl, ParseError> {
+        let kind = if self.eat_keyword("struct").is_some() {
+            ContainerKind::Struct
+        } else if self.eat_keyword("enum").is_some() {
+            let backing_type = match self.eat_group(Delimiter::Paren) {
+                Some(group) => {
+                    let mut parser = self.group_parser(group);
+                    let backing_type = parser.parse_expr()?;
+                    parser.expect_end("`)`")?;
+                    Some(backing_type)
+                }
+                None => None,
+            };
+            ContainerKind::Enum { backing_type }
+        } else if self.eat_keyword("union").is_some() {
+            let tag = match self.eat_group(Delimiter::Paren) {
+                Some(group) => {
+                    let mut parser = self.group_parser(group);
+                    let tag = match parser.eat_keyword("enum") {
+                        Some(span) => UnionTag::Inferred(span),
+                        None => UnionTag::Explicit(parser.parse_expr()?),
+                    };
+                    parser.expect_end("`)`")?;
+                    Some(tag)
+                }
+                None => None,
+            };
+            ContainerKind::Union { tag }
+        } else {
+            self.expect_keyword("trait", "a container")?;
+            ContainerKind::Trait
+        };
+        let name = self.expect_ident("a name")?;
+        let body = self.expect_group(Delimiter::Brace, "`{`")?;
+        let mut parser = self.group_parser(body);
+        let mut members = Vec::new();
+        while !parser.is_at_end() {
+            let start = (parser.pos, parser.peek_span());
+            match parser.parse_member(&kind) {
+                Ok(member) => members.push(member),
+                Err(error) => {
+                    parser.report(error);
+                    parser.skip_past(
+                        start.0,
+                        |parser| parser.is_item_start(),
+                        |node| {
+                            matches!(
+                                node,
+                                TokenNode::Token(Token::SpecialChar(SpecialChar::Comma), _)
+                            )
+                        },
+                    );
+                    members.push(Member::Error(parser.span_from(&start.1)));
+                }
+            }
+        }
+        Ok(ContainerDecl {
+            kind,
+            name,
+            members: members.into_boxed_slice(),
+        })
+    }
+
+    fn parse_member(&mut self, kind: &ContainerKind) -> Result<Member, ParseError> {
+        let start = self.peek_span();
+        if matches!(kind, ContainerKind::Trait) && self.peek_keyword("fn") {
+            let signature = self.parse_fn_signature()?;
+            self.expect_special(SpecialChar::SemiColon, "`;`")?;
+            return Ok(Member::Method(signature));
+        }
+        if self.is_item_start() {
+            return Ok(Member::Item(self.parse_item()?));
+        }
+
+        let member = match kind {
+            ContainerKind::Enum { .. } => {
+                let name = self.expect_ident("a variant or a declaration")?;
+                if &*name.name == "_" {
+                    Member::NonExhaustive(name.span)
+                } else {
+                    let value = match self.eat_special(SpecialChar::Equal) {
+                        Some(_) => Some(self.parse_expr()?),
+                        None => None,
+                    };
+                    Member::Variant(VariantDecl {
+                        name,
+                        value,
+                        span: self.span_from(&start),
+                    })
+                }
+            }
+            ContainerKind::Struct | ContainerKind::Union { .. } => {
+                let visibility = match self.eat_keyword("pub") {
+                    Some(_) => Visibility::Public,
+                    None => Visibility::Private,
+                };
+                let name = self.expect_ident("a field or a declaration")?;
+                self.expect_special(SpecialChar::Colon, "`:`")?;
+                let field_type = self.parse_expr()?;
+                let default = match self.eat_special(SpecialChar::Equal) {
+                    Some(_) => Some(self.parse_expr()?),
+                    None => None,
+                };
+                Member::Field(FieldDecl {
+                    visibility,
+                    name,
+                    field_type,
+                    default,
+                    span: self.span_from(&start),
+                })
+            }
+            ContainerKind::Trait => return Err(self.unexpected("a method or a declaration")),
+        };
+        if self.eat_special(SpecialChar::Comma).is_none() && !self.is_at_end() {
+            return Err(self.unexpected("`,`"));
+        }
+        Ok(member)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{ExprKind, PrefixOp, StmtKind};
+
+    use super::*;
+
+    const SAMPLE: &str = include_str!("../../../sample-code/main.cera");
+
+    fn text_of<'t>(str: &'t str, span: &TextSpan) -> &'t str {
+        &str[span.idx..span.end()]
+    }
+
+    #[test]
+    fn sample_items() {
+        let file = parse_file(SAMPLE).unwrap();
+        let items: Vec<_> = file
+            .items
+            .iter()
+            .map(|item| {
+                let name = match &item.kind {
+                    ItemKind::Fn(decl) => &decl.name,
+                    ItemKind::Var(decl) => &decl.name,
+                    ItemKind::Container(decl) => &decl.name,
+                    ItemKind::Error => panic!(),
+                };
+                (item.visibility, &*name.name)
+            })
+            .collect();
+        assert_eq!(
+            items,
+            [
+                (Visibility::Public, "main"),
+                (Visibility::Private, "c"),
+                (Visibility::Private, "is_valid_speed"),
+                (Visibility::Public, "spaceship"),
+            ]
+        );
+        assert!(text_of(SAMPLE, &file.items[3].span).starts_with("pub struct spaceship {"));
+        assert!(text_of(SAMPLE, &file.items[3].span).ends_with('}'));
+    }
+
+    #[test]
+    fn sample_struct_members() {
+        let file = parse_file(SAMPLE).unwrap();
+        let ItemKind::Container(spaceship) = &file.items[3].kind else {
+            panic!()
+        };
+        let [Member::Field(speed), Member::Item(new)] = &spaceship.members[..] else {
+            panic!("{:?}", spaceship.members)
+        };
+        assert_eq!(text_of(SAMPLE, &speed.span), "speed: f32");
+        let ItemKind::Fn(new) = &new.kind else {
+            panic!()
+        };
+        let return_type = new.return_type.as_ref().unwrap();
+        assert_eq!(text_of(SAMPLE, &return_type.span), "?spaceship");
+        assert!(matches!(
+            return_type.kind,
+            ExprKind::Prefix {
+                op: PrefixOp::Optional,
+                ..
+            }
+        ));
+        assert_eq!(new.body.stmts.len(), 2);
+        assert_eq!(
+            text_of(SAMPLE, &new.body.stmts[1].span)
+                .split_whitespace()
+                .collect::<String>(),
+            "return.{speed,}"
+        );
+    }
+
+    #[test]
+    fn return_types() {
+        let file = parse_file("fn a() !void {} fn b() -> ?T {} fn c() {}").unwrap();
+        let return_types: Vec<_> = file
+            .items
+            .iter()
+            .map(|item| match &item.kind {
+                ItemKind::Fn(decl) => decl.return_type.as_ref().map(|expr| match &expr.kind {
+                    ExprKind::Prefix { op, .. } => *op,
+                    _ => panic!(),
+                }),
+                _ => panic!(),
+            })
+            .collect();
+        assert_eq!(
+            return_types,
+            [Some(PrefixOp::Not), Some(PrefixOp::Optional), None]
+        );
+    }
+
+    #[test]
+    fn var_decls() {
+        let file = parse_file("const a: u8 = 1; var b = a;").unwrap();
+        let decls: Vec<_> = file
+            .items
+            .iter()
+            .map(|item| match &item.kind {
+                ItemKind::Var(decl) => (decl.mutability, decl.var_type.is_some()),
+                _ => panic!(),
+            })
+            .collect();
+        assert_eq!(decls, [(Mutability::Const, true), (Mutability::Var, false)]);
+    }
+
+    #[test]
+    fn other_containers() {
+        let src =
+            "enum(u8) color { red, green = 5, _, fn is_red(c: color) bool { return c == .red; } }
+            union(enum) shape { circle: f32, square: f32 }
+            union(color) paint { red: u8, green: u8 }
+            union raw { int: u32, float: f32, }
+            trait speed { fn speed(self: *const Self) -> f32; fn stop(self: *Self); }";
+        let file = parse_file(src).unwrap();
+        let containers: Vec<_> = file
+            .items
+            .iter()
+            .map(|item| match &item.kind {
+                ItemKind::Container(decl) => decl,
+                _ => panic!(),
+            })
+            .collect();
+
+        let ContainerKind::Enum {
+            backing_type: Some(_),
+        } = &containers[0].kind
+        else {
+            panic!()
+        };
+        let [Member::Variant(red), Member::Variant(green), Member::NonExhaustive(_), Member::Item(_)] =
+            &containers[0].members[..]
+        else {
+            panic!("{:?}", containers[0].members)
+        };
+        assert!(red.value.is_none());
+        assert!(green.value.is_some());
+
+        assert!(matches!(
+            containers[1].kind,
+            ContainerKind::Union {
+                tag: Some(UnionTag::Inferred(_))
+            }
+        ));
+        assert!(matches!(
+            containers[2].kind,
+            ContainerKind::Union {
+                tag: Some(UnionTag::Explicit(_))
+            }
+        ));
+        assert!(matches!(
+            containers[3].kind,
+            ContainerKind::Union { tag: None }
+        ));
+        assert_eq!(containers[3].members.len(), 2);
+
+        let [Member::Method(speed), Member::Method(stop)] = &containers[4].members[..] else {
+            panic!("{:?}", containers[4].members)
+        };
+        assert!(speed.return_type.is_some());
+        assert!(stop.return_type.is_none());
+        assert_eq!(text_of(src, &stop.span), "fn stop(self: *Self)");
+    }
+
+    #[test]
+    fn recovery() {
+        let src = "fn a() {
+                const x = ;
+                b() c();
+                if { }
+                return x;
+            }
+            const = 1;
+            struct s { a: u8, b u8, c: u8 }
+            fn d() {}";
+        let (file, errors) = parse_file_recovering(src);
+        assert_eq!(
+            errors,
+            [
+                ParseError::UnexpectedToken {
+                    expected: "an expression",
+                    found: TextSpan { len: 1, idx: 35 },
+                },
+                ParseError::MissingSemicolon(TextPosition { idx: 56 }),
+                ParseError::UnexpectedToken {
+                    expected: "an expression",
+                    found: TextSpan { len: 1, idx: 81 },
+                },
+                ParseError::UnexpectedToken {
+                    expected: "a name",
+                    found: TextSpan { len: 1, idx: 143 },
+                },
+                ParseError::UnexpectedToken {
+                    expected: "`:`",
+                    found: TextSpan { len: 2, idx: 180 },
+                },
+            ]
+        );
+
+        let ItemKind::Fn(a) = &file.items[0].kind else {
+            panic!()
+        };
+        let stmts: Vec<_> = a
+            .body
+            .stmts
+            .iter()
+            .map(|stmt| text_of(src, &stmt.span))
+            .collect();
+        assert_eq!(stmts, ["const x = ;", "b()", "c();", "if { }", "return x;"]);
+        assert!(matches!(a.body.stmts[0].kind, StmtKind::Error));
+        assert!(matches!(a.body.stmts[3].kind, StmtKind::Error));
+
+        assert!(matches!(file.items[1].kind, ItemKind::Error));
+        assert_eq!(text_of(src, &file.items[1].span), "const = 1;");
+        let ItemKind::Container(s) = &file.items[2].kind else {
+            panic!()
+        };
+        let [Member::Field(_), Member::Error(error), Member::Field(_)] = &s.members[..] else {
+            panic!("{:?}", s.members)
+        };
+        assert_eq!(text_of(src, error), "b u8,");
+        assert!(matches!(file.items[3].kind, ItemKind::Fn(_)));
+    }
+
+    #[test]
+    fn recovery_from_unclosed_groups() {
+        let (file, errors) = parse_file_recovering("fn a() { b(; }\nfn c() {}");
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(matches!(errors[0], ParseError::TokenTree(_)));
+        assert_eq!(file.items.len(), 2);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_file("fn (a: u8) {}"),
+            Err(ParseError::UnexpectedToken {
+                expected: "a function name",
+                found: TextSpan { len: 1, idx: 3 },
+            })
+        );
+        assert_eq!(
+            parse_file("const a = 1"),
+            Err(ParseError::UnexpectedEnd {
+                expected: "`;`",
+                position: TextPosition { idx: 11 },
+            })
+        );
+        assert_eq!(
+            parse_file("fn a() { b() c(); }"),
+            Err(ParseError::MissingSemicolon(TextPosition { idx: 12 }))
+        );
+    }
+}
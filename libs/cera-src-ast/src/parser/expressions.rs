@@ -0,0 +1,712 @@
+use cera_parse::{
+    src_token::{SpecialChar, Token},
+    text_region::TextSpan,
+};
+
+use crate::{
+    ast::{
+        Associativity, BinaryOp, Expr, ExprKind, FnTypeParam, Ident, InitField, PointerKind,
+        PostfixOp, PrefixOp,
+    },
+    parser::{ParseError, Parser},
+    token_tree::{Delimiter, TokenNode},
+};
+
+/// Operators made of special chars, ordered so that no operator is matched while a longer one
+/// starting with it is present
+const SPECIAL_CHAR_OPS: &[(&[SpecialChar], BinaryOp)] = &[
+    (&[SpecialChar::Equal, SpecialChar::Equal], BinaryOp::Equal),
+    (&[SpecialChar::Bang, SpecialChar::Equal], BinaryOp::NotEqual),
+    (
+        &[SpecialChar::LessThan, SpecialChar::LessThan],
+        BinaryOp::ShiftLeft,
+    ),
+    (
+        &[SpecialChar::GreaterThan, SpecialChar::GreaterThan],
+        BinaryOp::ShiftRight,
+    ),
+    (
+        &[SpecialChar::LessThan, SpecialChar::Equal],
+        BinaryOp::LessOrEqual,
+    ),
+    (
+        &[SpecialChar::GreaterThan, SpecialChar::Equal],
+        BinaryOp::GreaterOrEqual,
+    ),
+    (&[SpecialChar::LessThan], BinaryOp::LessThan),
+    (&[SpecialChar::GreaterThan], BinaryOp::GreaterThan),
+    (&[SpecialChar::Plus], BinaryOp::Add),
+    (&[SpecialChar::Minus], BinaryOp::Sub),
+    (&[SpecialChar::Star], BinaryOp::Mul),
+    (&[SpecialChar::Slash], BinaryOp::Div),
+    (&[SpecialChar::Percent], BinaryOp::Mod),
+    (&[SpecialChar::Ampersand], BinaryOp::BitAnd),
+    (&[SpecialChar::Caret], BinaryOp::BitXor),
+    (&[SpecialChar::Pipe], BinaryOp::BitOr),
+    (&[SpecialChar::Bang], BinaryOp::ErrorUnion),
+];
+
+const KEYWORD_OPS: &[(&str, BinaryOp)] = &[
+    ("and", BinaryOp::And),
+    ("or", BinaryOp::Or),
+    ("orelse", BinaryOp::Orelse),
+];
+
+enum Infix {
+    Binary(BinaryOp),
+    /// `catch` has the precedence of `orelse`, but can capture the error
+    Catch,
+}
+
+impl Infix {
+    fn precedence(&self) -> u8 {
+        match self {
+            Infix::Binary(op) => op.precedence(),
+            Infix::Catch => BinaryOp::Orelse.precedence(),
+        }
+    }
+}
+
+enum TypePrefix {
+    /// The kind of pointer, its sentinel and whether it is `const`
+    Pointer(PointerKind, Option<Expr>, bool),
+    /// The length of the array and its sentinel
+    Array(Expr, Option<Expr>),
+}
+
+impl<'a> Parser<'a> {
+    pub fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        self.parse_binary(0)
+    }
+
+    /// Returns the next infix operator, along with the amount of tokens it is made of
+    fn peek_infix(&self) -> Option<(Infix, usize, TextSpan)> {
+        for (keyword, op) in KEYWORD_OPS {
+            if self.peek_keyword(keyword) {
+                return Some((Infix::Binary(*op), 1, self.peek_span()));
+            }
+        }
+        if self.peek_keyword("catch") {
+            return Some((Infix::Catch, 1, self.peek_span()));
+        }
+        for (chars, op) in SPECIAL_CHAR_OPS {
+            if (*op == BinaryOp::BitOr) && self.pipe_is_capture {
+                continue;
+            }
+            if let Some(span) = self.peek_joint(chars) {
+                // `+=`, `<<=` and the like are assignments, which end the expression
+                let is_assignment = self
+                    .peek_special_nth(chars.len(), &SpecialChar::Equal)
+                    .is_some_and(|equal| equal.idx == span.end());
+                if is_assignment {
+                    return None;
+                }
+                return Some((Infix::Binary(*op), chars.len(), span));
+            }
+        }
+        None
+    }
+
+    /// Parses operators binding at least as tightly as `min_precedence`
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let mut lhs = self.parse_prefix()?;
+        // The last non-associative operator applied to `lhs`, to reject chains of them
+        let mut non_associative: Option<(u8, TextSpan)> = None;
+        while let Some((infix, len, op_span)) = self.peek_infix() {
+            let precedence = infix.precedence();
+            if precedence < min_precedence {
+                break;
+            }
+            if let Some((prev_precedence, prev_span)) = &non_associative {
+                if *prev_precedence == precedence {
+                    return Err(ParseError::NonAssociative {
+                        first: prev_span.clone(),
+                        second: op_span,
+                    });
+                }
+            }
+            for _ in 0..len {
+                self.bump();
+            }
+            lhs = match infix {
+                Infix::Catch => {
+                    let capture = self.parse_capture()?;
+                    let rhs = self.parse_binary(precedence + 1)?;
+                    non_associative = None;
+                    Expr {
+                        span: lhs.span.join(&rhs.span),
+                        kind: ExprKind::Catch {
+                            lhs: Box::new(lhs),
+                            capture,
+                            rhs: Box::new(rhs),
+                        },
+                    }
+                }
+                Infix::Binary(op) => {
+                    let rhs = self.parse_binary(precedence + 1)?;
+                    non_associative = match op.associativity() {
+                        Associativity::None => Some((precedence, op_span)),
+                        Associativity::Left => None,
+                    };
+                    Expr {
+                        span: lhs.span.join(&rhs.span),
+                        kind: ExprKind::Binary {
+                            op,
+                            lhs: Box::new(lhs),
+                            rhs: Box::new(rhs),
+                        },
+                    }
+                }
+            };
+        }
+        Ok(lhs)
+    }
+
+    /// Parses an optional `|name|`
+    pub(crate) fn parse_capture(&mut self) -> Result<Option<Ident>, ParseError> {
+        if self.eat_special(SpecialChar::Pipe).is_none() {
+            return Ok(None);
+        }
+        let name = self.expect_ident("a capture name")?;
+        self.expect_special(SpecialChar::Pipe, "`|`")?;
+        Ok(Some(name))
+    }
+
+    /// Parses an expression, or a range if it is followed by `..`, like the inputs of `for` and
+    /// the bounds of slices
+    pub(crate) fn parse_range(&mut self) -> Result<Expr, ParseError> {
+        let start = self.parse_expr()?;
+        if self
+            .eat_joint(&[SpecialChar::Dot, SpecialChar::Dot])
+            .is_none()
+        {
+            return Ok(start);
+        }
+        let is_open = self.is_at_end()
+            || self.peek_special_nth(0, &SpecialChar::Comma).is_some()
+            || self.peek_special_nth(0, &SpecialChar::Pipe).is_some();
+        let end = match is_open {
+            true => None,
+            false => Some(Box::new(self.parse_expr()?)),
+        };
+        Ok(Expr {
+            span: self.span_from(&start.span),
+            kind: ExprKind::Range {
+                start: Box::new(start),
+                end,
+            },
+        })
+    }
+
+    fn parse_prefix(&mut self) -> Result<Expr, ParseError> {
+        let start = self.peek_span();
+        if let Some(kind) = self.parse_type_prefix()? {
+            let operand = self.parse_prefix()?;
+            return Ok(Expr {
+                span: start.join(&operand.span),
+                kind: match kind {
+                    TypePrefix::Pointer(kind, sentinel, is_const) => ExprKind::PointerType {
+                        kind,
+                        is_const,
+                        sentinel: sentinel.map(Box::new),
+                        pointee: Box::new(operand),
+                    },
+                    TypePrefix::Array(len, sentinel) => ExprKind::ArrayType {
+                        len: Box::new(len),
+                        sentinel: sentinel.map(Box::new),
+                        element: Box::new(operand),
+                    },
+                },
+            });
+        }
+        let op = if self.eat_special(SpecialChar::Bang).is_some() {
+            PrefixOp::Not
+        } else if self.eat_special(SpecialChar::Minus).is_some() {
+            PrefixOp::Negate
+        } else if self.eat_special(SpecialChar::Tilde).is_some() {
+            PrefixOp::BitNot
+        } else if self.eat_special(SpecialChar::Ampersand).is_some() {
+            PrefixOp::AddressOf
+        } else if self.eat_special(SpecialChar::QuestionMark).is_some() {
+            PrefixOp::Optional
+        } else if self.eat_keyword("try").is_some() {
+            PrefixOp::Try
+        } else {
+            return self.parse_postfix();
+        };
+        let operand = self.parse_prefix()?;
+        Ok(Expr {
+            span: start.join(&operand.span),
+            kind: ExprKind::Prefix {
+                op,
+                operand: Box::new(operand),
+            },
+        })
+    }
+
+    /// Parses `*`, `[*]`, `[]` or `[len]`, the last three of which can end with a `:sentinel`,
+    /// along with the `const` following pointers
+    fn parse_type_prefix(&mut self) -> Result<Option<TypePrefix>, ParseError> {
+        let (pointer_kind, sentinel) = if self.eat_special(SpecialChar::Star).is_some() {
+            (PointerKind::Single, None)
+        } else if let Some(group) = self.eat_group(Delimiter::Bracket) {
+            let mut parser = self.group_parser(group);
+            let pointer_kind = if parser.eat_special(SpecialChar::Star).is_some() {
+                PointerKind::Many
+            } else if parser.is_at_end()
+                || parser.peek_special_nth(0, &SpecialChar::Colon).is_some()
+            {
+                PointerKind::Slice
+            } else {
+                let len = parser.parse_expr()?;
+                let sentinel = parser.parse_sentinel()?;
+                parser.expect_end("`]`")?;
+                return Ok(Some(TypePrefix::Array(len, sentinel)));
+            };
+            let sentinel = parser.parse_sentinel()?;
+            parser.expect_end("`]`")?;
+            (pointer_kind, sentinel)
+        } else {
+            return Ok(None);
+        };
+        let is_const = self.eat_keyword("const").is_some();
+        Ok(Some(TypePrefix::Pointer(pointer_kind, sentinel, is_const)))
+    }
+
+    /// Parses an optional `:sentinel`
+    fn parse_sentinel(&mut self) -> Result<Option<Expr>, ParseError> {
+        match self.eat_special(SpecialChar::Colon) {
+            Some(_) => Ok(Some(self.parse_expr()?)),
+            None => Ok(None),
+        }
+    }
+
+    fn parse_fn_type(&mut self) -> Result<Expr, ParseError> {
+        let start = self.expect_keyword("fn", "`fn`")?;
+        let params = self.expect_group(Delimiter::Paren, "a parameter list")?;
+        let params = self.group_parser(params).parse_comma_separated(|parser| {
+            let name = match parser.peek_special_nth(1, &SpecialChar::Colon) {
+                Some(_) => {
+                    let name = parser.expect_ident("a parameter name")?;
+                    parser.bump();
+                    Some(name)
+                }
+                None => None,
+            };
+            let param_type = parser.parse_expr()?;
+            Ok(FnTypeParam {
+                span: match &name {
+                    Some(name) => name.span.join(&param_type.span),
+                    None => param_type.span.clone(),
+                },
+                name,
+                param_type,
+            })
+        })?;
+        self.eat_joint(&[SpecialChar::Minus, SpecialChar::GreaterThan]);
+        // Only error unions are allowed, other binary operators would be ambiguous
+        let return_type = self.parse_binary(BinaryOp::ErrorUnion.precedence())?;
+        Ok(Expr {
+            span: start.join(&return_type.span),
+            kind: ExprKind::FnType {
+                params,
+                return_type: Box::new(return_type),
+            },
+        })
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            let postfix_op = [
+                (SpecialChar::Star, PostfixOp::Deref),
+                (SpecialChar::QuestionMark, PostfixOp::Unwrap),
+            ]
+            .into_iter()
+            .find_map(|(char, op)| {
+                self.peek_joint(&[SpecialChar::Dot, char])
+                    .map(|span| (op, span))
+            });
+            expr = if let Some((op, span)) = postfix_op {
+                self.bump();
+                self.bump();
+                Expr {
+                    span: expr.span.join(&span),
+                    kind: ExprKind::Postfix {
+                        op,
+                        operand: Box::new(expr),
+                    },
+                }
+            } else if let Some(args) = self.eat_group(Delimiter::Paren) {
+                let args = self
+                    .group_parser(args)
+                    .parse_comma_separated(Self::parse_expr)?;
+                Expr {
+                    span: self.span_from(&expr.span),
+                    kind: ExprKind::Call {
+                        callee: Box::new(expr),
+                        args,
+                    },
+                }
+            } else if let Some(index) = self.eat_group(Delimiter::Bracket) {
+                let mut parser = self.group_parser(index);
+                let index = parser.parse_range()?;
+                parser.expect_end("`]`")?;
+                Expr {
+                    span: self.span_from(&expr.span),
+                    kind: ExprKind::Index {
+                        base: Box::new(expr),
+                        index: Box::new(index),
+                    },
+                }
+            } else if self.peek_special_nth(0, &SpecialChar::Dot).is_some()
+                && self.peek_special_nth(1, &SpecialChar::Dot).is_none()
+                && self.peek_group_nth(1, Delimiter::Brace).is_none()
+            {
+                self.bump();
+                let field = self.expect_ident("a field name")?;
+                Expr {
+                    span: expr.span.join(&field.span),
+                    kind: ExprKind::Field {
+                        base: Box::new(expr),
+                        field,
+                    },
+                }
+            } else {
+                return Ok(expr);
+            };
+        }
+    }
+
+    /// Parses `.name = value` or a lone `value`
+    fn parse_init_field(&mut self) -> Result<InitField, ParseError> {
+        let is_named = self.peek_special_nth(0, &SpecialChar::Dot).is_some()
+            && self.peek_ident_nth(1).is_some()
+            && self.peek_special_nth(2, &SpecialChar::Equal).is_some();
+        if !is_named {
+            let value = self.parse_expr()?;
+            return Ok(InitField {
+                name: None,
+                span: value.span.clone(),
+                value,
+            });
+        }
+        let start = self.peek_span();
+        self.bump();
+        let name = self.expect_ident("a field name")?;
+        self.bump();
+        let value = self.parse_expr()?;
+        Ok(InitField {
+            span: start.join(&value.span),
+            name: Some(name),
+            value,
+        })
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        if let Some(block) = self.parse_block_expr()? {
+            return Ok(block);
+        }
+        if self.peek_keyword("fn") {
+            return self.parse_fn_type();
+        }
+        if let Some(ident) = self.peek_ident() {
+            self.bump();
+            return Ok(Expr {
+                kind: ExprKind::Ident(ident.name),
+                span: ident.span,
+            });
+        }
+        if let Some(start) = self.peek_special_nth(0, &SpecialChar::AtSign).cloned() {
+            self.bump();
+            let name = self.expect_ident("the name of a builtin")?;
+            let args = self.expect_group(Delimiter::Paren, "the arguments of the builtin")?;
+            let args = self
+                .group_parser(args)
+                .parse_comma_separated(Self::parse_expr)?;
+            return Ok(Expr {
+                span: self.span_from(&start),
+                kind: ExprKind::BuiltinCall { name, args },
+            });
+        }
+        if let Some(TokenNode::Token(Token::Literal(literal), span)) = self.peek() {
+            self.bump();
+            return Ok(Expr {
+                kind: ExprKind::Literal(literal.clone()),
+                span: span.clone(),
+            });
+        }
+        if let Some(group) = self.eat_group(Delimiter::Paren) {
+            let mut parser = self.group_parser(group);
+            let inner = parser.parse_expr()?;
+            parser.expect_end("`)`")?;
+            return Ok(Expr {
+                kind: ExprKind::Paren(Box::new(inner)),
+                span: group.span(),
+            });
+        }
+        if self.peek_special_nth(0, &SpecialChar::Dot).is_some() {
+            if let Some(group) = self.peek_group_nth(1, Delimiter::Brace) {
+                let start = self.peek_span();
+                self.bump();
+                self.bump();
+                let fields = self
+                    .group_parser(group)
+                    .parse_comma_separated(Self::parse_init_field)?;
+                return Ok(Expr {
+                    kind: ExprKind::AnonInit(fields),
+                    span: start.join(&group.span()),
+                });
+            }
+            if self.peek_ident_nth(1).is_some() {
+                let start = self.peek_span();
+                self.bump();
+                let variant = self.expect_ident("a variant name")?;
+                return Ok(Expr {
+                    span: start.join(&variant.span),
+                    kind: ExprKind::EnumLiteral(variant),
+                });
+            }
+        }
+        Err(self.unexpected("an expression"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cera_parse::{parsing::parse, text_region::Text};
+
+    use crate::token_tree::TokenTree;
+
+    use super::*;
+
+    fn parse_expr(str: &str) -> Result<Expr, ParseError> {
+        let (tokens, spans) = parse::<Token>(str).unwrap();
+        let tree = TokenTree::try_from_tokens(&Text::new(str), &tokens, &spans).unwrap();
+        let mut parser = Parser::new(&tree.nodes, str.len());
+        let expr = parser.parse_expr()?;
+        parser.expect_end("the end of the expression")?;
+        Ok(expr)
+    }
+
+    /// Prints the expression with every operation surrounded by parentheses
+    fn grouped(str: &str) -> String {
+        fn print(str: &str, expr: &Expr) -> String {
+            match &expr.kind {
+                ExprKind::Ident(_) | ExprKind::Literal(_) => {
+                    str[expr.span.idx..expr.span.end()].into()
+                }
+                ExprKind::Paren(inner) => print(str, inner),
+                ExprKind::Prefix { op, operand } => format!("({op:?} {})", print(str, operand)),
+                ExprKind::Postfix { op, operand } => format!("({op:?} {})", print(str, operand)),
+                ExprKind::Binary { op, lhs, rhs } => {
+                    format!("({} {op:?} {})", print(str, lhs), print(str, rhs))
+                }
+                ExprKind::Call { callee, args } => {
+                    let args: Vec<_> = args.iter().map(|arg| print(str, arg)).collect();
+                    format!("{}({})", print(str, callee), args.join(", "))
+                }
+                ExprKind::BuiltinCall { name, args } => {
+                    let args: Vec<_> = args.iter().map(|arg| print(str, arg)).collect();
+                    format!("@{}({})", name.name, args.join(", "))
+                }
+                ExprKind::Field { base, field } => format!("{}.{}", print(str, base), field.name),
+                ExprKind::Index { base, index } => {
+                    format!("{}[{}]", print(str, base), print(str, index))
+                }
+                ExprKind::Catch { lhs, capture, rhs } => format!(
+                    "({} catch {:?} {})",
+                    print(str, lhs),
+                    capture.as_ref().map(|capture| &capture.name),
+                    print(str, rhs)
+                ),
+                ExprKind::AnonInit(fields) => {
+                    let fields: Vec<_> = fields
+                        .iter()
+                        .map(|field| match &field.name {
+                            Some(name) => format!(".{} = {}", name.name, print(str, &field.value)),
+                            None => print(str, &field.value),
+                        })
+                        .collect();
+                    format!(".{{{}}}", fields.join(", "))
+                }
+                ExprKind::EnumLiteral(variant) => format!(".{}", variant.name),
+                ExprKind::PointerType {
+                    kind,
+                    is_const,
+                    sentinel,
+                    pointee,
+                } => format!(
+                    "({kind:?}{}{} {})",
+                    sentinel.as_ref().map_or(String::new(), |sentinel| format!(
+                        ":{}",
+                        print(str, sentinel)
+                    )),
+                    if *is_const { " const" } else { "" },
+                    print(str, pointee)
+                ),
+                ExprKind::ArrayType {
+                    len,
+                    sentinel,
+                    element,
+                } => {
+                    let sentinel = sentinel.as_ref().map_or(String::new(), |sentinel| {
+                        format!(":{}", print(str, sentinel))
+                    });
+                    format!("([{}{sentinel}] {})", print(str, len), print(str, element))
+                }
+                ExprKind::FnType {
+                    params,
+                    return_type,
+                } => {
+                    let params: Vec<_> = params
+                        .iter()
+                        .map(|param| match &param.name {
+                            Some(name) => {
+                                format!("{}: {}", name.name, print(str, &param.param_type))
+                            }
+                            None => print(str, &param.param_type),
+                        })
+                        .collect();
+                    format!("fn({}) {}", params.join(", "), print(str, return_type))
+                }
+                ExprKind::Range { start, end } => format!(
+                    "{}..{}",
+                    print(str, start),
+                    end.as_ref().map_or(String::new(), |end| print(str, end))
+                ),
+                ExprKind::Block(_) => unreachable!(),
+            }
+        }
+        print(str, &parse_expr(str).unwrap())
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(grouped("a + b * c"), "(a Add (b Mul c))");
+        assert_eq!(grouped("a - b - c"), "((a Sub b) Sub c)");
+        assert_eq!(grouped("a or b and c == d"), "(a Or (b And (c Equal d)))");
+        assert_eq!(
+            grouped("a | b ^ c & d << 1"),
+            "(a BitOr (b BitXor (c BitAnd (d ShiftLeft 1))))"
+        );
+        assert_eq!(grouped("a orelse b == c"), "((a Orelse b) Equal c)");
+        assert_eq!(
+            grouped("f(x) catch |err| g(err) orelse y"),
+            "((f(x) catch Some(\"err\") g(err)) Orelse y)"
+        );
+        assert_eq!(grouped("(a + b) * c"), "((a Add b) Mul c)");
+        assert_eq!(
+            grouped("a % b <= c >> d"),
+            "((a Mod b) LessOrEqual (c ShiftRight d))"
+        );
+    }
+
+    #[test]
+    fn unary_and_postfix() {
+        assert_eq!(grouped("-a.b"), "(Negate a.b)");
+        assert_eq!(
+            grouped("!is_valid_speed(speed)"),
+            "(Not is_valid_speed(speed))"
+        );
+        assert_eq!(grouped("&a[0].*"), "(AddressOf (Deref a[0]))");
+        assert_eq!(grouped("a[1..n - 1]"), "a[1..(n Sub 1)]");
+        assert_eq!(grouped("s[i + 1..]"), "s[(i Add 1)..]");
+        assert_eq!(grouped("~a.?.b"), "(BitNot (Unwrap a).b)");
+        assert_eq!(grouped("- -a * b"), "((Negate (Negate a)) Mul b)");
+        assert_eq!(grouped("speed < c"), "(speed LessThan c)");
+    }
+
+    #[test]
+    fn type_expressions() {
+        assert_eq!(grouped("?u8"), "(Optional u8)");
+        assert_eq!(grouped("!void"), "(Not void)");
+        assert_eq!(grouped("E!?T"), "(E ErrorUnion (Optional T))");
+        assert_eq!(grouped("a != b"), "(a NotEqual b)");
+        assert_eq!(grouped("*const u8"), "(Single const u8)");
+        assert_eq!(grouped("?*T"), "(Optional (Single T))");
+        assert_eq!(grouped("[]const u8"), "(Slice const u8)");
+        assert_eq!(grouped("[*]u8"), "(Many u8)");
+        assert_eq!(grouped("[4][n + 1]f32"), "([4] ([(n Add 1)] f32))");
+        assert_eq!(grouped("[:0]const u8"), "(Slice:0 const u8)");
+        assert_eq!(grouped("[*:0]u8"), "(Many:0 u8)");
+        assert_eq!(grouped("[n:0]u8"), "([n:0] u8)");
+        assert_eq!(
+            grouped("fn(a: u8, *T) -> E!u8"),
+            "fn(a: u8, (Single T)) (E ErrorUnion u8)"
+        );
+        assert_eq!(grouped("fn() void"), "fn() void");
+        assert_eq!(parse_expr("*T").unwrap().span, TextSpan { len: 2, idx: 0 });
+    }
+
+    #[test]
+    fn anonymous_literals() {
+        assert_eq!(grouped(".{ speed, }"), ".{speed}");
+        assert_eq!(
+            grouped(".{ .x = 1, .y = a + b }"),
+            ".{.x = 1, .y = (a Add b)}"
+        );
+        assert_eq!(grouped(".{ a, .b = c, .d }"), ".{a, .b = c, .d}");
+        assert_eq!(grouped(".{}"), ".{}");
+        assert_eq!(grouped("x == .red"), "(x Equal .red)");
+        assert_eq!(
+            parse_expr(".{ .x = 1 }").unwrap().span,
+            TextSpan { len: 11, idx: 0 }
+        );
+    }
+
+    #[test]
+    fn builtin_calls() {
+        assert_eq!(
+            grouped("@import(\"std.cera\").io"),
+            "@import(\"std.cera\").io"
+        );
+        assert_eq!(grouped("@TypeOf(a + b)"), "@TypeOf((a Add b))");
+        assert_eq!(
+            parse_expr("@import(\"a\")").unwrap().span,
+            TextSpan { len: 12, idx: 0 }
+        );
+        assert!(parse_expr("@import").is_err());
+    }
+
+    #[test]
+    fn non_associative_operators() {
+        assert_eq!(
+            parse_expr("a < b < c"),
+            Err(ParseError::NonAssociative {
+                first: TextSpan { len: 1, idx: 2 },
+                second: TextSpan { len: 1, idx: 6 },
+            })
+        );
+        assert_eq!(
+            parse_expr("a == b != c"),
+            Err(ParseError::NonAssociative {
+                first: TextSpan { len: 2, idx: 2 },
+                second: TextSpan { len: 2, idx: 7 },
+            })
+        );
+        assert!(parse_expr("(a < b) == c").is_ok());
+        assert!(parse_expr("a < b and b < c").is_ok());
+    }
+
+    #[test]
+    fn compound_assignment_ends_expression() {
+        let (tokens, spans) = parse::<Token>("a += b").unwrap();
+        let tree = TokenTree::try_from_tokens(&Text::new("a += b"), &tokens, &spans).unwrap();
+        let mut parser = Parser::new(&tree.nodes, 6);
+        let expr = parser.parse_expr().unwrap();
+        assert_eq!(expr.kind, ExprKind::Ident("a".into()));
+        assert!(!parser.is_at_end());
+    }
+
+    #[test]
+    fn spans() {
+        let expr = parse_expr("foo.bar(1, 2) + x[3]").unwrap();
+        assert_eq!(expr.span, TextSpan { len: 20, idx: 0 });
+        let ExprKind::Binary { lhs, rhs, .. } = expr.kind else {
+            panic!()
+        };
+        assert_eq!(lhs.span, TextSpan { len: 13, idx: 0 });
+        assert_eq!(rhs.span, TextSpan { len: 4, idx: 16 });
+    }
+}
@@ -0,0 +1,463 @@
+use cera_parse::{
+    src_token::{SpecialChar, Token},
+    text_region::TextPosition,
+};
+
+use crate::{
+    ast::{BinaryOp, Block, Expr, ExprKind, Ident, Stmt, StmtKind},
+    parser::{ParseError, Parser},
+    token_tree::{Delimiter, TokenNode},
+};
+
+/// Operators that form a compound assignment when directly followed by `=`
+const ASSIGN_OPS: &[(&[SpecialChar], BinaryOp)] = &[
+    (
+        &[SpecialChar::LessThan, SpecialChar::LessThan],
+        BinaryOp::ShiftLeft,
+    ),
+    (
+        &[SpecialChar::GreaterThan, SpecialChar::GreaterThan],
+        BinaryOp::ShiftRight,
+    ),
+    (&[SpecialChar::Plus], BinaryOp::Add),
+    (&[SpecialChar::Minus], BinaryOp::Sub),
+    (&[SpecialChar::Star], BinaryOp::Mul),
+    (&[SpecialChar::Slash], BinaryOp::Div),
+    (&[SpecialChar::Percent], BinaryOp::Mod),
+    (&[SpecialChar::Ampersand], BinaryOp::BitAnd),
+    (&[SpecialChar::Caret], BinaryOp::BitXor),
+    (&[SpecialChar::Pipe], BinaryOp::BitOr),
+];
+
+impl<'a> Parser<'a> {
+    pub fn parse_block(&mut self) -> Result<Block, ParseError> {
+        self.parse_labeled_block(None)
+    }
+
+    pub(crate) fn parse_labeled_block(
+        &mut self,
+        label: Option<Ident>,
+    ) -> Result<Block, ParseError> {
+        let group = self.expect_group(Delimiter::Brace, "a block")?;
+        let mut parser = self.group_parser(group);
+        let mut stmts = Vec::new();
+        while !parser.is_at_end() {
+            let start = (parser.pos, parser.peek_span());
+            match parser.parse_stmt() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(error) => {
+                    parser.report(error);
+                    // Statements end with a semicolon or a block
+                    parser.skip_past(
+                        start.0,
+                        |_| false,
+                        |node| match node {
+                            TokenNode::Token(Token::SpecialChar(SpecialChar::SemiColon), _) => true,
+                            TokenNode::Group(group) => group.delimiter == Delimiter::Brace,
+                            _ => false,
+                        },
+                    );
+                    stmts.push(Stmt {
+                        kind: StmtKind::Error,
+                        span: parser.span_from(&start.1),
+                    });
+                }
+            }
+        }
+        Ok(Block {
+            label,
+            stmts: stmts.into_boxed_slice(),
+            span: group.span(),
+        })
+    }
+
+    /// Parses `name:` if it is followed by something that can be labeled
+    fn parse_label(&mut self, allow_loops: bool) -> Option<Ident> {
+        let ident = self.peek_ident()?;
+        self.peek_special_nth(1, &SpecialChar::Colon)?;
+        let is_labeled = self.peek_group_nth(2, Delimiter::Brace).is_some()
+            || (allow_loops
+                && (self.peek_keyword_nth(2, "while") || self.peek_keyword_nth(2, "for")));
+        if !is_labeled {
+            return None;
+        }
+        self.bump();
+        self.bump();
+        Some(ident)
+    }
+
+    /// Parses `label: { ... }` in expression position
+    pub(crate) fn parse_block_expr(&mut self) -> Result<Option<Expr>, ParseError> {
+        let start = self.peek_span();
+        let Some(label) = self.parse_label(false) else {
+            return Ok(None);
+        };
+        let block = self.parse_labeled_block(Some(label))?;
+        Ok(Some(Expr {
+            span: start.join(&block.span),
+            kind: ExprKind::Block(block),
+        }))
+    }
+
+    /// The last statement of a block doesn't need to be followed by a semicolon. A missing one
+    /// is reported without failing, as the statement is still complete.
+    fn expect_stmt_end(&mut self) {
+        if self.eat_special(SpecialChar::SemiColon).is_none() && !self.is_at_end() {
+            let idx = self.prev_span.as_ref().map_or(0, |span| span.end());
+            self.report(ParseError::MissingSemicolon(TextPosition { idx }));
+        }
+    }
+
+    /// Returns whether nothing is left in the statement, as in `return;` or `break;`
+    fn is_at_stmt_end(&self) -> bool {
+        self.is_at_end() || self.peek_special_nth(0, &SpecialChar::SemiColon).is_some()
+    }
+
+    /// Parses `:label`, as used by `break` and `continue`
+    fn parse_label_ref(&mut self) -> Result<Option<Ident>, ParseError> {
+        if self.eat_special(SpecialChar::Colon).is_none() {
+            return Ok(None);
+        }
+        self.expect_ident("a label").map(Some)
+    }
+
+    pub fn parse_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.peek_span();
+        let label = self.parse_label(true);
+        let kind = if self.peek_keyword("while") {
+            self.parse_while(label)?
+        } else if self.peek_keyword("for") {
+            self.parse_for(label)?
+        } else if label.is_some() || self.peek_group(Delimiter::Brace).is_some() {
+            StmtKind::Block(self.parse_labeled_block(label)?)
+        } else if self.peek_keyword("if") {
+            self.parse_if()?
+        } else if self.eat_keyword("defer").is_some() {
+            StmtKind::Defer(Box::new(self.parse_stmt()?))
+        } else if self.eat_keyword("errdefer").is_some() {
+            let capture = self.parse_capture()?;
+            StmtKind::ErrDefer {
+                capture,
+                stmt: Box::new(self.parse_stmt()?),
+            }
+        } else {
+            let kind = if self.eat_keyword("return").is_some() {
+                let value = match self.is_at_stmt_end() {
+                    true => None,
+                    false => Some(self.parse_expr()?),
+                };
+                StmtKind::Return(value)
+            } else if self.eat_keyword("break").is_some() {
+                let label = self.parse_label_ref()?;
+                let value = match self.is_at_stmt_end() {
+                    true => None,
+                    false => Some(self.parse_expr()?),
+                };
+                StmtKind::Break { label, value }
+            } else if self.eat_keyword("continue").is_some() {
+                StmtKind::Continue {
+                    label: self.parse_label_ref()?,
+                }
+            } else if self.peek_keyword("const") | self.peek_keyword("var") {
+                StmtKind::Var(self.parse_var_decl()?)
+            } else {
+                self.parse_simple_stmt()?
+            };
+            self.expect_stmt_end();
+            kind
+        };
+        Ok(Stmt {
+            kind,
+            span: self.span_from(&start),
+        })
+    }
+
+    /// Parses an assignment or an expression
+    fn parse_simple_stmt(&mut self) -> Result<StmtKind, ParseError> {
+        let target = self.parse_expr()?;
+        let op = if self.eat_special(SpecialChar::Equal).is_some() {
+            None
+        } else {
+            let op = ASSIGN_OPS.iter().find_map(|(chars, op)| {
+                let mut chars = chars.to_vec();
+                chars.push(SpecialChar::Equal);
+                self.eat_joint(&chars).map(|_| *op)
+            });
+            match op {
+                Some(op) => Some(op),
+                None => return Ok(StmtKind::Expr(target)),
+            }
+        };
+        let value = self.parse_expr()?;
+        Ok(StmtKind::Assign { target, op, value })
+    }
+
+    /// Parses a condition, in which `|` starts a capture unless it is between parentheses
+    fn parse_condition(&mut self) -> Result<(Expr, Option<Ident>), ParseError> {
+        self.pipe_is_capture = true;
+        let condition = self.parse_expr();
+        self.pipe_is_capture = false;
+        Ok((condition?, self.parse_capture()?))
+    }
+
+    fn parse_if(&mut self) -> Result<StmtKind, ParseError> {
+        self.expect_keyword("if", "`if`")?;
+        let (condition, capture) = self.parse_condition()?;
+        let then_block = self.parse_block()?;
+        let else_branch = if self.eat_keyword("else").is_some() {
+            let start = self.peek_span();
+            let kind = if self.peek_keyword("if") {
+                self.parse_if()?
+            } else {
+                StmtKind::Block(self.parse_block()?)
+            };
+            Some(Box::new(Stmt {
+                kind,
+                span: self.span_from(&start),
+            }))
+        } else {
+            None
+        };
+        Ok(StmtKind::If {
+            condition,
+            capture,
+            then_block,
+            else_branch,
+        })
+    }
+
+    fn parse_while(&mut self, label: Option<Ident>) -> Result<StmtKind, ParseError> {
+        self.expect_keyword("while", "`while`")?;
+        let (condition, capture) = self.parse_condition()?;
+        let continue_stmt = if self.eat_special(SpecialChar::Colon).is_some() {
+            let group = self.expect_group(Delimiter::Paren, "`(`")?;
+            let mut parser = self.group_parser(group);
+            let kind = parser.parse_simple_stmt()?;
+            parser.expect_end("`)`")?;
+            Some(Box::new(Stmt {
+                kind,
+                span: group.span(),
+            }))
+        } else {
+            None
+        };
+        let body = self.parse_block()?;
+        Ok(StmtKind::While {
+            label,
+            condition,
+            capture,
+            continue_stmt,
+            body,
+        })
+    }
+
+    fn parse_for(&mut self, label: Option<Ident>) -> Result<StmtKind, ParseError> {
+        self.expect_keyword("for", "`for`")?;
+        let inputs = match self.eat_group(Delimiter::Paren) {
+            Some(group) => self
+                .group_parser(group)
+                .parse_comma_separated(Self::parse_range)?,
+            None => {
+                self.pipe_is_capture = true;
+                let input = self.parse_range();
+                self.pipe_is_capture = false;
+                Box::new([input?])
+            }
+        };
+        self.expect_special(SpecialChar::Pipe, "`|`")?;
+        let mut captures = Vec::new();
+        loop {
+            captures.push(self.expect_ident("a capture name")?);
+            if self.eat_special(SpecialChar::Pipe).is_some() {
+                break;
+            }
+            self.expect_special(SpecialChar::Comma, "`,` or `|`")?;
+        }
+        let body = self.parse_block()?;
+        Ok(StmtKind::For {
+            label,
+            inputs,
+            captures: captures.into_boxed_slice(),
+            body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cera_parse::{parsing::parse, src_token::Token, text_region::Text};
+
+    use crate::token_tree::TokenTree;
+
+    use super::*;
+
+    fn parse_block(str: &str) -> Result<Block, ParseError> {
+        let (tokens, spans) = parse::<Token>(str).unwrap();
+        let tree = TokenTree::try_from_tokens(&Text::new(str), &tokens, &spans).unwrap();
+        let mut parser = Parser::new(&tree.nodes, str.len());
+        let block = parser.parse_block()?;
+        parser.expect_end("the end of the block")?;
+        match parser.take_errors().into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(block),
+        }
+    }
+
+    fn stmt(str: &str) -> StmtKind {
+        let block = parse_block(&format!("{{ {str} }}")).unwrap();
+        assert_eq!(block.stmts.len(), 1, "{:?}", block.stmts);
+        block.stmts[0].kind.clone()
+    }
+
+    #[test]
+    fn if_else() {
+        let StmtKind::If {
+            condition,
+            else_branch,
+            ..
+        } = stmt("if (a) { b(); } else if c { d(); } else { e(); }")
+        else {
+            panic!()
+        };
+        assert!(matches!(condition.kind, ExprKind::Paren(_)));
+        let StmtKind::If {
+            condition,
+            else_branch,
+            ..
+        } = else_branch.unwrap().kind
+        else {
+            panic!()
+        };
+        assert_eq!(condition.kind, ExprKind::Ident("c".into()));
+        assert!(matches!(else_branch.unwrap().kind, StmtKind::Block(_)));
+
+        let StmtKind::If { capture, .. } = stmt("if maybe |value| { use(value); }") else {
+            panic!()
+        };
+        assert_eq!(&*capture.unwrap().name, "value");
+    }
+
+    #[test]
+    fn sample_if() {
+        let StmtKind::If {
+            condition,
+            then_block,
+            ..
+        } = stmt("if !is_valid_speed(speed) { \n return null; \n }")
+        else {
+            panic!()
+        };
+        assert!(matches!(condition.kind, ExprKind::Prefix { .. }));
+        assert!(matches!(
+            then_block.stmts[0].kind,
+            StmtKind::Return(Some(_))
+        ));
+    }
+
+    #[test]
+    fn loops() {
+        let StmtKind::While {
+            label,
+            continue_stmt,
+            ..
+        } = stmt("outer: while (i < 10) : (i += 1) { continue :outer; }")
+        else {
+            panic!()
+        };
+        assert_eq!(&*label.unwrap().name, "outer");
+        assert!(matches!(
+            continue_stmt.unwrap().kind,
+            StmtKind::Assign {
+                op: Some(BinaryOp::Add),
+                ..
+            }
+        ));
+
+        let StmtKind::While { capture, .. } = stmt("while it.next() |elem| { break; }") else {
+            panic!()
+        };
+        assert_eq!(&*capture.unwrap().name, "elem");
+
+        let StmtKind::For {
+            inputs, captures, ..
+        } = stmt("for (items, 0..) |item, idx| { sum += item * idx; }")
+        else {
+            panic!()
+        };
+        assert_eq!(inputs.len(), 2);
+        assert!(matches!(inputs[1].kind, ExprKind::Range { end: None, .. }));
+        assert_eq!(captures.len(), 2);
+
+        let StmtKind::For { inputs, .. } = stmt("for 0..n |i| {}") else {
+            panic!()
+        };
+        assert!(matches!(
+            inputs[0].kind,
+            ExprKind::Range { end: Some(_), .. }
+        ));
+    }
+
+    #[test]
+    fn jumps_and_defers() {
+        assert_eq!(
+            stmt("break :blk 5;"),
+            StmtKind::Break {
+                label: Some(Ident {
+                    name: "blk".into(),
+                    span: cera_parse::text_region::TextSpan { len: 3, idx: 9 }
+                }),
+                value: Some(Expr {
+                    kind: ExprKind::Literal(cera_parse::src_token::Literal::Int(5u8.into())),
+                    span: cera_parse::text_region::TextSpan { len: 1, idx: 13 }
+                }),
+            }
+        );
+        assert!(matches!(stmt("return;"), StmtKind::Return(None)));
+        assert!(matches!(stmt("defer close(file);"), StmtKind::Defer(_)));
+        assert!(matches!(
+            stmt("errdefer |err| { log(err); }"),
+            StmtKind::ErrDefer {
+                capture: Some(_),
+                ..
+            }
+        ));
+        let StmtKind::Var(decl) = stmt("const x = blk: { break :blk try f(); };") else {
+            panic!()
+        };
+        assert!(matches!(decl.value.kind, ExprKind::Block(_)));
+    }
+
+    #[test]
+    fn assignments() {
+        assert!(matches!(
+            stmt("a.b[0] = c;"),
+            StmtKind::Assign { op: None, .. }
+        ));
+        assert!(matches!(
+            stmt("a <<= 2;"),
+            StmtKind::Assign {
+                op: Some(BinaryOp::ShiftLeft),
+                ..
+            }
+        ));
+        assert!(matches!(
+            stmt("a == b;"),
+            StmtKind::Expr(Expr {
+                kind: ExprKind::Binary { .. },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn missing_semicolon() {
+        // The last statement of a block may omit it
+        let block = parse_block("{ return .{ speed, } }").unwrap();
+        assert!(matches!(block.stmts[0].kind, StmtKind::Return(Some(_))));
+
+        assert_eq!(
+            parse_block("{ return .{ speed, }\n x(); }"),
+            Err(ParseError::MissingSemicolon(TextPosition { idx: 20 }))
+        );
+    }
+}
@@ -0,0 +1,787 @@
+//! Lexical name resolution. Every name used by an expression is bound to the declaration it
+//! refers to. The declarations of modules and containers are visible everywhere in them whatever
+//! their order, while locals are only visible after the statement declaring them. Declarations
+//! can't shadow any name visible where they are declared.
+
+use std::collections::{HashMap, HashSet};
+
+use cera_parse::text_region::TextSpan;
+
+use crate::{
+    ast::{
+        BinaryOp, Block, ContainerDecl, ContainerKind, Expr, ExprKind, FieldDecl, File, FnDecl,
+        FnSignature, Ident, Item, Member, Mutability, Param, PrefixOp, Stmt, StmtKind, UnionTag,
+        VarDecl, Visibility,
+    },
+    suggest::{suggest, NameKind},
+    visit::{walk_expr, walk_stmt, Visitor},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScopeId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeclId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Module,
+    Container,
+    /// The parameters of a function
+    Function,
+    /// A block, or the captures of the statement or expression they are given to
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    /// `None` for the module
+    pub parent: Option<ScopeId>,
+    /// In declaration order
+    pub decls: Vec<DeclId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclKind {
+    /// A declaration of a module or container
+    Item(Visibility),
+    Param,
+    Local(Mutability),
+    /// The value unwrapped by `|capture|`
+    Capture,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decl {
+    pub name: Ident,
+    pub kind: DeclKind,
+    /// Whether it is a type, as far as the syntax of the declaration tells
+    pub name_kind: NameKind,
+    pub scope: ScopeId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Decl(DeclId),
+    /// Names provided by the language, like `u8`, `void` or `true`
+    Primitive,
+}
+
+/// A name close to one which couldn't be found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub name: Box<str>,
+    /// Where the suggested name is declared
+    pub resolution: Resolution,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    Undefined {
+        name: Ident,
+        /// The closest names visible where it is used
+        suggestions: Box<[Suggestion]>,
+    },
+    /// A local used before the statement declaring it
+    UsedBeforeDeclaration { name: Ident, decl: DeclId },
+    /// A declaration with the name of another one visible where it is declared
+    Shadowing { name: Ident, shadowed: Resolution },
+    /// Two declarations with the same name in a module or container
+    Duplicate { name: Ident, first: DeclId },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Resolutions {
+    pub scopes: Vec<Scope>,
+    pub decls: Vec<Decl>,
+    /// What each name used by an expression refers to, by the span of the name
+    pub uses: HashMap<TextSpan, Resolution>,
+}
+
+impl Resolutions {
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0]
+    }
+    pub fn decl(&self, id: DeclId) -> &Decl {
+        &self.decls[id.0]
+    }
+    /// What the name used at `span` refers to, `None` if it couldn't be resolved
+    pub fn resolution(&self, span: &TextSpan) -> Option<Resolution> {
+        self.uses.get(span).copied()
+    }
+}
+
+/// The names provided by the language, apart from the integer types of uncommon sizes
+const PRIMITIVES: &[(&str, NameKind)] = &[
+    ("bool", NameKind::Type),
+    ("void", NameKind::Type),
+    ("type", NameKind::Type),
+    ("noreturn", NameKind::Type),
+    ("anyerror", NameKind::Type),
+    ("comptime_int", NameKind::Type),
+    ("comptime_float", NameKind::Type),
+    ("usize", NameKind::Type),
+    ("isize", NameKind::Type),
+    ("u8", NameKind::Type),
+    ("u16", NameKind::Type),
+    ("u32", NameKind::Type),
+    ("u64", NameKind::Type),
+    ("i8", NameKind::Type),
+    ("i16", NameKind::Type),
+    ("i32", NameKind::Type),
+    ("i64", NameKind::Type),
+    ("f16", NameKind::Type),
+    ("f32", NameKind::Type),
+    ("f64", NameKind::Type),
+    ("f80", NameKind::Type),
+    ("f128", NameKind::Type),
+    ("true", NameKind::Value),
+    ("false", NameKind::Value),
+    ("null", NameKind::Value),
+    ("undefined", NameKind::Value),
+];
+
+/// Whether `name` is provided by the language
+pub fn is_primitive(name: &str) -> bool {
+    PRIMITIVES.iter().any(|(primitive, _)| *primitive == name)
+        || match name.strip_prefix(['i', 'u']) {
+            Some(bits) => !bits.starts_with('0') && bits.parse::<u16>().is_ok(),
+            None => false,
+        }
+}
+
+/// Resolves the names used in a file, with the scope of the file as the first scope
+pub fn resolve(file: &File) -> (Resolutions, Vec<ResolveError>) {
+    let mut resolver = Resolver::default();
+    resolver.visit_file(file);
+    (resolver.resolutions, resolver.errors)
+}
+
+enum Lookup {
+    Found(Resolution),
+    /// Declared later in an enclosing block
+    Pending(DeclId),
+    NotFound,
+}
+
+#[derive(Default)]
+struct Resolver {
+    resolutions: Resolutions,
+    errors: Vec<ResolveError>,
+    current: Option<ScopeId>,
+    /// Whether the names being resolved should be types
+    expected: NameKind,
+    /// The locals of blocks which haven't been reached yet, in declaration order
+    pending: HashMap<ScopeId, Vec<DeclId>>,
+}
+
+impl Resolver {
+    fn enter(&mut self, kind: ScopeKind) -> ScopeId {
+        let id = ScopeId(self.resolutions.scopes.len());
+        self.resolutions.scopes.push(Scope {
+            kind,
+            parent: self.current,
+            decls: Vec::new(),
+        });
+        self.current = Some(id);
+        id
+    }
+
+    fn exit(&mut self) {
+        let current = self.current.unwrap();
+        self.pending.remove(&current);
+        self.current = self.resolutions.scope(current).parent;
+    }
+
+    fn lookup(&self, name: &str) -> Lookup {
+        let mut scope = self.current;
+        while let Some(id) = scope {
+            let decls = self.resolutions.scope(id).decls.iter();
+            let pending = self.pending.get(&id).into_iter().flatten();
+            let named = |decl: &&DeclId| &*self.resolutions.decl(**decl).name.name == name;
+            if let Some(decl) = decls.clone().find(named) {
+                return Lookup::Found(Resolution::Decl(*decl));
+            }
+            if let Some(decl) = pending.clone().find(named) {
+                return Lookup::Pending(*decl);
+            }
+            scope = self.resolutions.scope(id).parent;
+        }
+        match is_primitive(name) {
+            true => Lookup::Found(Resolution::Primitive),
+            false => Lookup::NotFound,
+        }
+    }
+
+    fn new_decl(&mut self, name: &Ident, kind: DeclKind) -> DeclId {
+        self.new_decl_of_kind(name, kind, NameKind::Value)
+    }
+
+    fn new_decl_of_kind(&mut self, name: &Ident, kind: DeclKind, name_kind: NameKind) -> DeclId {
+        let id = DeclId(self.resolutions.decls.len());
+        self.resolutions.decls.push(Decl {
+            name: name.clone(),
+            kind,
+            name_kind,
+            scope: self.current.unwrap(),
+        });
+        id
+    }
+
+    /// Makes a declaration visible in the current scope, unless it shadows another one
+    fn declare(&mut self, id: DeclId) {
+        let name = self.resolutions.decl(id).name.clone();
+        if let Lookup::Found(shadowed) = self.lookup(&name.name) {
+            self.errors.push(ResolveError::Shadowing { name, shadowed });
+        }
+        let current = self.current.unwrap();
+        self.resolutions.scopes[current.0].decls.push(id);
+    }
+
+    /// Declares the items of a module or container, which are all visible at once
+    fn declare_items<'a>(&mut self, items: impl Iterator<Item = &'a Item>) {
+        for item in items {
+            let Some(name) = item.name() else {
+                continue;
+            };
+            let current = self.current.unwrap();
+            let first = self
+                .resolutions
+                .scope(current)
+                .decls
+                .iter()
+                .find(|decl| self.resolutions.decl(**decl).name.name == name.name);
+            if let Some(first) = first {
+                self.errors.push(ResolveError::Duplicate {
+                    name: name.clone(),
+                    first: *first,
+                });
+                continue;
+            }
+            let kind = DeclKind::Item(item.visibility);
+            let id = self.new_decl_of_kind(name, kind, item.name_kind());
+            self.declare(id);
+        }
+    }
+
+    fn resolve_use(&mut self, name: &str, span: &TextSpan) {
+        let ident = || Ident {
+            name: name.into(),
+            span: span.clone(),
+        };
+        match self.lookup(name) {
+            Lookup::Found(resolution) => {
+                self.resolutions.uses.insert(span.clone(), resolution);
+            }
+            Lookup::Pending(decl) => self.errors.push(ResolveError::UsedBeforeDeclaration {
+                name: ident(),
+                decl,
+            }),
+            Lookup::NotFound => self.errors.push(ResolveError::Undefined {
+                name: ident(),
+                suggestions: self.suggestions(name),
+            }),
+        }
+    }
+
+    /// The visible names closest to `name`, which inner declarations hide
+    fn suggestions(&self, name: &str) -> Box<[Suggestion]> {
+        let mut seen = HashSet::new();
+        let mut candidates = Vec::new();
+        let mut scope = self.current;
+        while let Some(id) = scope {
+            for decl_id in &self.resolutions.scope(id).decls {
+                let decl = self.resolutions.decl(*decl_id);
+                if seen.insert(&decl.name.name) {
+                    let resolution = Resolution::Decl(*decl_id);
+                    candidates.push((resolution, &*decl.name.name, decl.name_kind));
+                }
+            }
+            scope = self.resolutions.scope(id).parent;
+        }
+        let primitives = PRIMITIVES
+            .iter()
+            .map(|(name, kind)| (Resolution::Primitive, *name, *kind));
+        let candidates = candidates
+            .into_iter()
+            .chain(primitives)
+            .map(|(resolution, name, kind)| ((resolution, name), name, kind));
+        suggest(name, self.expected, candidates)
+            .into_iter()
+            .map(|(resolution, name)| Suggestion {
+                name: name.into(),
+                resolution,
+            })
+            .collect()
+    }
+
+    fn with_expected(&mut self, expected: NameKind, f: impl FnOnce(&mut Self)) {
+        let prev = std::mem::replace(&mut self.expected, expected);
+        f(self);
+        self.expected = prev;
+    }
+
+    fn visit_type(&mut self, expr: &Expr) {
+        self.with_expected(NameKind::Type, |this| this.visit_expr(expr));
+    }
+
+    fn params(&mut self, params: &[Param]) {
+        for param in params {
+            self.visit_type(&param.param_type);
+            let id = self.new_decl(&param.name, DeclKind::Param);
+            self.declare(id);
+        }
+    }
+
+    fn var_decl_value(&mut self, decl: &VarDecl) {
+        if let Some(var_type) = &decl.var_type {
+            self.visit_type(var_type);
+        }
+        self.visit_expr(&decl.value);
+    }
+
+    /// Visits what `captures` are visible in, in a scope of their own if there are any
+    fn captured(&mut self, captures: &[&Ident], f: impl FnOnce(&mut Self)) {
+        if captures.is_empty() {
+            return f(self);
+        }
+        self.enter(ScopeKind::Block);
+        for capture in captures {
+            let id = self.new_decl(capture, DeclKind::Capture);
+            self.declare(id);
+        }
+        f(self);
+        self.exit();
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_file(&mut self, file: &File) {
+        self.enter(ScopeKind::Module);
+        self.declare_items(file.items.iter());
+        for item in &*file.items {
+            self.visit_item(item);
+        }
+        self.exit();
+    }
+
+    fn visit_container_decl(&mut self, decl: &ContainerDecl) {
+        // The backing type and the tag are outside of the container
+        match &decl.kind {
+            ContainerKind::Enum {
+                backing_type: Some(backing_type),
+            } => self.visit_type(backing_type),
+            ContainerKind::Union {
+                tag: Some(UnionTag::Explicit(tag)),
+            } => self.visit_type(tag),
+            _ => {}
+        }
+        self.enter(ScopeKind::Container);
+        self.declare_items(decl.members.iter().filter_map(|member| match member {
+            Member::Item(item) => Some(item),
+            _ => None,
+        }));
+        for member in &*decl.members {
+            self.visit_member(member);
+        }
+        self.exit();
+    }
+
+    fn visit_fn_decl(&mut self, decl: &FnDecl) {
+        self.enter(ScopeKind::Function);
+        self.params(&decl.params);
+        if let Some(return_type) = &decl.return_type {
+            self.visit_type(return_type);
+        }
+        self.visit_block(&decl.body);
+        self.exit();
+    }
+
+    fn visit_fn_signature(&mut self, signature: &FnSignature) {
+        self.enter(ScopeKind::Function);
+        self.params(&signature.params);
+        if let Some(return_type) = &signature.return_type {
+            self.visit_type(return_type);
+        }
+        self.exit();
+    }
+
+    fn visit_field_decl(&mut self, decl: &FieldDecl) {
+        self.visit_type(&decl.field_type);
+        if let Some(default) = &decl.default {
+            self.visit_expr(default);
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        let scope = self.enter(ScopeKind::Block);
+        let locals = block
+            .stmts
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Var(decl) => {
+                    Some(self.new_decl(&decl.name, DeclKind::Local(decl.mutability)))
+                }
+                _ => None,
+            })
+            .collect();
+        self.pending.insert(scope, locals);
+        for stmt in &*block.stmts {
+            match &stmt.kind {
+                StmtKind::Var(decl) => {
+                    self.var_decl_value(decl);
+                    let id = self.pending.get_mut(&scope).unwrap().remove(0);
+                    self.declare(id);
+                }
+                _ => self.visit_stmt(stmt),
+            }
+        }
+        self.exit();
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            // Declared by a statement which isn't directly in a block, like `defer const x = f();`
+            StmtKind::Var(decl) => {
+                self.var_decl_value(decl);
+                let id = self.new_decl(&decl.name, DeclKind::Local(decl.mutability));
+                self.declare(id);
+            }
+            StmtKind::If {
+                condition,
+                capture,
+                then_block,
+                else_branch,
+            } => {
+                self.visit_expr(condition);
+                let captures: Vec<_> = capture.iter().collect();
+                self.captured(&captures, |this| this.visit_block(then_block));
+                if let Some(else_branch) = else_branch {
+                    self.visit_stmt(else_branch);
+                }
+            }
+            StmtKind::While {
+                condition,
+                capture,
+                continue_stmt,
+                body,
+                ..
+            } => {
+                self.visit_expr(condition);
+                let captures: Vec<_> = capture.iter().collect();
+                self.captured(&captures, |this| {
+                    if let Some(continue_stmt) = continue_stmt {
+                        this.visit_stmt(continue_stmt);
+                    }
+                    this.visit_block(body);
+                });
+            }
+            StmtKind::For {
+                inputs,
+                captures,
+                body,
+                ..
+            } => {
+                for input in &**inputs {
+                    self.visit_expr(input);
+                }
+                let captures: Vec<_> = captures.iter().collect();
+                self.captured(&captures, |this| this.visit_block(body));
+            }
+            StmtKind::ErrDefer { capture, stmt } => {
+                let captures: Vec<_> = capture.iter().collect();
+                self.captured(&captures, |this| this.visit_stmt(stmt));
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Ident(name) => self.resolve_use(name, &expr.span),
+            ExprKind::Catch { lhs, capture, rhs } => {
+                self.visit_expr(lhs);
+                let captures: Vec<_> = capture.iter().collect();
+                self.captured(&captures, |this| this.visit_expr(rhs));
+            }
+            ExprKind::Prefix {
+                op: PrefixOp::Optional,
+                operand: element,
+            } => self.visit_type(element),
+            ExprKind::PointerType {
+                sentinel, pointee, ..
+            } => {
+                if let Some(sentinel) = sentinel {
+                    self.with_expected(NameKind::Value, |this| this.visit_expr(sentinel));
+                }
+                self.visit_type(pointee);
+            }
+            ExprKind::Binary {
+                op: BinaryOp::ErrorUnion,
+                lhs,
+                rhs,
+            } => {
+                self.visit_type(lhs);
+                self.visit_type(rhs);
+            }
+            ExprKind::ArrayType {
+                len,
+                sentinel,
+                element,
+            } => {
+                self.with_expected(NameKind::Value, |this| {
+                    this.visit_expr(len);
+                    if let Some(sentinel) = sentinel {
+                        this.visit_expr(sentinel);
+                    }
+                });
+                self.visit_type(element);
+            }
+            ExprKind::FnType {
+                params,
+                return_type,
+            } => {
+                for param in &**params {
+                    self.visit_type(&param.param_type);
+                }
+                self.visit_type(return_type);
+            }
+            // The values of blocks and initializers are usually not types
+            ExprKind::Block(_) | ExprKind::AnonInit(_) | ExprKind::Call { .. } => {
+                self.with_expected(NameKind::Value, |this| walk_expr(this, expr))
+            }
+            _ => walk_expr(self, expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_file;
+
+    fn resolved(text: &str) -> (Resolutions, Vec<ResolveError>) {
+        resolve(&parse_file(text).unwrap())
+    }
+
+    /// The names of the errors, along with the name they are about
+    fn errors(text: &str) -> Vec<(&'static str, Box<str>)> {
+        resolved(text)
+            .1
+            .into_iter()
+            .map(|error| match error {
+                ResolveError::Undefined { name, .. } => ("undefined", name.name),
+                ResolveError::UsedBeforeDeclaration { name, .. } => ("before", name.name),
+                ResolveError::Shadowing { name, .. } => ("shadowing", name.name),
+                ResolveError::Duplicate { name, .. } => ("duplicate", name.name),
+            })
+            .collect()
+    }
+
+    /// The kind of declaration the first use of `name` refers to
+    fn use_of(resolutions: &Resolutions, text: &str, name: &str) -> Option<DeclKind> {
+        let idx = text.find(&format!("{name})")).unwrap();
+        let span = TextSpan {
+            idx,
+            len: name.len(),
+        };
+        match resolutions.resolution(&span)? {
+            Resolution::Decl(decl) => Some(resolutions.decl(decl).kind),
+            Resolution::Primitive => None,
+        }
+    }
+
+    #[test]
+    fn sample() {
+        let text = std::fs::read_to_string("../../sample-code/main.cera").unwrap();
+        let (resolutions, errors) = resolved(&text);
+        assert_eq!(errors, []);
+        let uses = |name: &str| {
+            resolutions
+                .uses
+                .iter()
+                .filter(|(span, _)| &text[span.idx..span.end()] == name)
+                .map(|(_, resolution)| *resolution)
+                .collect::<Vec<_>>()
+        };
+        // Used before being declared, which is fine for the items of a module
+        let [Resolution::Decl(spaceship), ..] = uses("spaceship")[..] else {
+            panic!()
+        };
+        assert_eq!(
+            resolutions.decl(spaceship).kind,
+            DeclKind::Item(Visibility::Public)
+        );
+        assert_eq!(uses("null"), [Resolution::Primitive]);
+        assert!(uses("speed").iter().all(|resolution| matches!(
+            resolution,
+            Resolution::Decl(decl) if resolutions.decl(*decl).kind == DeclKind::Param
+        )));
+    }
+
+    #[test]
+    fn kinds_of_declarations() {
+        let text = "const c = 1;\n\
+                    fn f(p: u8) void {\n\
+                        var l = g(c);\n\
+                        if (o) |x| { g(p); g(l); g(x); }\n\
+                        const r = h() catch |e| g(e);\n\
+                    }";
+        let (resolutions, errors) = resolved(text);
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| match error {
+                    ResolveError::Undefined { name, .. } => &*name.name,
+                    _ => panic!("{:?}", error),
+                })
+                .collect::<Vec<_>>(),
+            ["g", "o", "g", "g", "g", "h", "g"]
+        );
+        assert_eq!(
+            use_of(&resolutions, text, "c"),
+            Some(DeclKind::Item(Visibility::Private))
+        );
+        assert_eq!(use_of(&resolutions, text, "p"), Some(DeclKind::Param));
+        assert_eq!(
+            use_of(&resolutions, text, "l"),
+            Some(DeclKind::Local(Mutability::Var))
+        );
+        assert_eq!(use_of(&resolutions, text, "x"), Some(DeclKind::Capture));
+        assert_eq!(use_of(&resolutions, text, "e"), Some(DeclKind::Capture));
+    }
+
+    #[test]
+    fn order() {
+        assert_eq!(
+            errors("fn f() void { g(); } fn g() void { h(); } const h = f;"),
+            []
+        );
+        assert_eq!(
+            errors("fn f() void { g(x); const x = 1; }"),
+            [("undefined", "g".into()), ("before", "x".into())]
+        );
+        assert_eq!(
+            errors("fn f() void { const x = x; }"),
+            [("before", "x".into())]
+        );
+        assert_eq!(
+            errors("fn f() void { { const y = 1; } const z = y; }"),
+            [("undefined", "y".into())]
+        );
+    }
+
+    #[test]
+    fn shadowing() {
+        assert_eq!(
+            errors("const x = 1; fn f(x: u8) void {}"),
+            [("shadowing", "x".into())]
+        );
+        assert_eq!(
+            errors("fn f() void { const x = 1; { var x = 2; } }"),
+            [("shadowing", "x".into())]
+        );
+        assert_eq!(
+            errors("fn f(x: ?u8) void { while (x) |x| {} }"),
+            [("shadowing", "x".into())]
+        );
+        assert_eq!(
+            errors("fn f() void { const u8 = 1; const true = 2; }"),
+            [("shadowing", "u8".into()), ("shadowing", "true".into())]
+        );
+        // Siblings don't see each other
+        assert_eq!(
+            errors("fn f() void { { const x = 1; } { const x = 2; } }"),
+            []
+        );
+    }
+
+    #[test]
+    fn duplicates() {
+        assert_eq!(
+            errors("const a = 1; fn a() void {} struct s { const b = 1; const b = 2; }"),
+            [("duplicate", "a".into()), ("duplicate", "b".into())]
+        );
+    }
+
+    /// The names suggested for each undefined name
+    fn suggestions(text: &str) -> Vec<Vec<String>> {
+        resolved(text)
+            .1
+            .into_iter()
+            .filter_map(|error| match error {
+                ResolveError::Undefined { suggestions, .. } => Some(
+                    suggestions
+                        .iter()
+                        .map(|suggestion| suggestion.name.to_string())
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn did_you_mean() {
+        let text = "fn is_valid_speed(speed: f32) bool { return is_valid_sped(sped); }";
+        let (resolutions, errors) = resolved(text);
+        let [ResolveError::Undefined {
+            suggestions: function,
+            ..
+        }, ResolveError::Undefined {
+            suggestions: param, ..
+        }] = &errors[..]
+        else {
+            panic!("{:?}", errors)
+        };
+        let [Suggestion {
+            name,
+            resolution: Resolution::Decl(decl),
+        }] = &function[..]
+        else {
+            panic!()
+        };
+        assert_eq!(&**name, "is_valid_speed");
+        // Points to where the suggestion is declared
+        assert_eq!(resolutions.decl(*decl).name.span.idx, 3);
+        assert_eq!(&*param[0].name, "speed");
+
+        assert_eq!(suggestions("fn f(x: boo) void {}"), [["bool"]]);
+        // Types are preferred where types are expected, and values elsewhere
+        let spec = "struct Spec {} const spek = 1;";
+        assert_eq!(
+            suggestions(&format!("{spec} fn f(x: spec) void {{}}")),
+            [["Spec", "spek"]]
+        );
+        assert_eq!(
+            suggestions(&format!("{spec} const x = spec;")),
+            [["spek", "Spec"]]
+        );
+        // Inner declarations hide outer ones
+        assert_eq!(
+            suggestions("fn f(abc: u8) void { { const abd = 1; log(ab); } }"),
+            [vec![], vec!["abd", "abc"]]
+        );
+    }
+
+    #[test]
+    fn scope_tree() {
+        let (resolutions, _) = resolved("struct s { fn f(a: u8) void { { } } }");
+        let tree: Vec<_> = resolutions
+            .scopes
+            .iter()
+            .map(|scope| (scope.kind, scope.parent.map(|parent| parent.0)))
+            .collect();
+        assert_eq!(
+            tree,
+            [
+                (ScopeKind::Module, None),
+                (ScopeKind::Container, Some(0)),
+                (ScopeKind::Function, Some(1)),
+                (ScopeKind::Block, Some(2)),
+                (ScopeKind::Block, Some(3)),
+            ]
+        );
+    }
+}
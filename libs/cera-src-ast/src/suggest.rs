@@ -0,0 +1,95 @@
+//! "Did you mean" suggestions for names which couldn't be found, ranked by how many edits turn
+//! them into the name that was written.
+
+/// What a name refers to, as suggesting names of the expected kind is more helpful
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameKind {
+    #[default]
+    Value,
+    Type,
+    /// An instance field of a container
+    Field,
+}
+
+/// At most how many names are suggested
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// The amount of insertions, deletions, substitutions and transpositions of adjacent characters
+/// turning `a` into `b`
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut before_prev = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution = prev[j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            current[j] = substitution.min(prev[j] + 1).min(current[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_prev[j - 2] + 1);
+            }
+        }
+        before_prev = std::mem::replace(&mut prev, current);
+    }
+    prev[b.len()]
+}
+
+/// The candidates close enough to `name` to be typos of it, closest first. Candidates of another
+/// kind than `expected` count as one edit further away, and ties keep the order of `candidates`.
+pub fn suggest<'a, T>(
+    name: &str,
+    expected: NameKind,
+    candidates: impl IntoIterator<Item = (T, &'a str, NameKind)>,
+) -> Vec<T> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut ranked: Vec<_> = candidates
+        .into_iter()
+        .filter_map(|(value, candidate, kind)| {
+            let distance = edit_distance(name, candidate);
+            (distance <= max_distance).then_some((distance + usize::from(kind != expected), value))
+        })
+        .collect();
+    ranked.sort_by_key(|(distance, _)| *distance);
+    ranked
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, value)| value)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("is_valid_sped", "is_valid_speed"), 1);
+        assert_eq!(edit_distance("spaceship", "spacehsip"), 1);
+        assert_eq!(edit_distance("Speed", "speed"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn ranking() {
+        let candidates = [
+            ("fuel", NameKind::Field),
+            ("speed", NameKind::Field),
+            ("Speed", NameKind::Type),
+            ("spend", NameKind::Value),
+            ("unrelated", NameKind::Value),
+        ];
+        let suggest = |name, expected| {
+            suggest(
+                name,
+                expected,
+                candidates.iter().map(|(name, kind)| (*name, *name, *kind)),
+            )
+        };
+        assert_eq!(suggest("sped", NameKind::Value), ["spend", "speed"]);
+        assert_eq!(suggest("Spee", NameKind::Type), ["Speed"]);
+        assert_eq!(suggest("sped", NameKind::Field), ["speed", "spend"]);
+        assert_eq!(suggest("x", NameKind::Value), Vec::<&str>::new());
+    }
+}
@@ -1,14 +1,443 @@
-use cera_parse::{src_token::Token, text_region::TextSpan};
+use cera_parse::{
+    src_token::{SpecialChar, Token},
+    text_region::{Text, TextPosition, TextSpan},
+};
+use serde::{Deserialize, Serialize};
 
-pub struct TokenTree {}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Delimiter {
+    /// `(` and `)`
+    Paren,
+    /// `{` and `}`
+    Brace,
+    /// `[` and `]`
+    Bracket,
+}
+
+impl Delimiter {
+    pub fn from_open(special_char: &SpecialChar) -> Option<Self> {
+        match special_char {
+            SpecialChar::OpenParen => Some(Self::Paren),
+            SpecialChar::OpenBrace => Some(Self::Brace),
+            SpecialChar::OpenBracket => Some(Self::Bracket),
+            _ => None,
+        }
+    }
+    pub fn from_close(special_char: &SpecialChar) -> Option<Self> {
+        match special_char {
+            SpecialChar::CloseParen => Some(Self::Paren),
+            SpecialChar::CloseBrace => Some(Self::Brace),
+            SpecialChar::CloseBracket => Some(Self::Bracket),
+            _ => None,
+        }
+    }
+}
 
-pub enum TokenTreeParseError {}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenNode {
+    Token(Token, TextSpan),
+    Group(Group),
+}
+
+impl TokenNode {
+    pub fn span(&self) -> TextSpan {
+        match self {
+            TokenNode::Token(_, span) => span.clone(),
+            TokenNode::Group(group) => group.span(),
+        }
+    }
+}
+
+/// Tokens surrounded by a pair of matching delimiters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Group {
+    pub delimiter: Delimiter,
+    pub open: TextSpan,
+    /// If the group was never closed, this is empty and placed where the closing delimiter was
+    /// expected
+    pub close: TextSpan,
+    pub nodes: Box<[TokenNode]>,
+}
+
+impl Group {
+    pub fn span(&self) -> TextSpan {
+        TextSpan {
+            len: self.close.idx + self.close.len - self.open.idx,
+            idx: self.open.idx,
+        }
+    }
+    pub fn is_closed(&self) -> bool {
+        self.close.len != 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenTree {
+    pub nodes: Box<[TokenNode]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenTreeParseError {
+    /// The closing delimiter doesn't match the innermost open delimiter
+    MismatchedDelimiter {
+        open: TextSpan,
+        open_delimiter: Delimiter,
+        close: TextSpan,
+        close_delimiter: Delimiter,
+    },
+    /// `expected_at` is the best guess of where the closing delimiter is missing
+    UnclosedDelimiter {
+        open: TextSpan,
+        delimiter: Delimiter,
+        expected_at: TextPosition,
+    },
+    /// A closing delimiter without any open delimiter
+    UnexpectedCloseDelimiter {
+        close: TextSpan,
+        delimiter: Delimiter,
+    },
+}
+
+struct OpenGroup {
+    delimiter: Delimiter,
+    open: TextSpan,
+    /// Indentation of the line the group was opened on
+    indentation: usize,
+    nodes: Vec<TokenNode>,
+    /// Index of the first node starting a line that isn't more indented than the line the group
+    /// was opened on, which is where a missing closing delimiter most likely belongs
+    dedent: Option<usize>,
+}
+
+impl OpenGroup {
+    fn push(&mut self, node: TokenNode, text: &Text) {
+        if self.dedent.is_none()
+            && text
+                .indentation(node.span().idx)
+                .is_some_and(|indentation| indentation <= self.indentation)
+        {
+            self.dedent = Some(self.nodes.len());
+        }
+        self.nodes.push(node);
+    }
+
+    fn close(self, close: TextSpan) -> TokenNode {
+        TokenNode::Group(Group {
+            delimiter: self.delimiter,
+            open: self.open,
+            close,
+            nodes: self.nodes.into_boxed_slice(),
+        })
+    }
+
+    /// Closes the group where its closing delimiter most likely went missing, returning the nodes
+    /// that are moved out of the group
+    fn close_unclosed(
+        mut self,
+        errors: &mut Vec<TokenTreeParseError>,
+    ) -> (TokenNode, Vec<TokenNode>) {
+        let spilled = match self.dedent {
+            Some(dedent) => self.nodes.split_off(dedent),
+            None => Vec::new(),
+        };
+        let expected_at = match (spilled.first(), self.nodes.last()) {
+            (Some(node), _) => node.span().idx,
+            (None, Some(node)) => node.span().idx + node.span().len,
+            (None, None) => self.open.idx + self.open.len,
+        };
+        errors.push(TokenTreeParseError::UnclosedDelimiter {
+            open: self.open.clone(),
+            delimiter: self.delimiter,
+            expected_at: TextPosition { idx: expected_at },
+        });
+        (
+            self.close(TextSpan {
+                len: 0,
+                idx: expected_at,
+            }),
+            spilled,
+        )
+    }
+}
+
+struct TreeBuilder<'t> {
+    text: &'t Text<'t>,
+    root: Vec<TokenNode>,
+    stack: Vec<OpenGroup>,
+    errors: Vec<TokenTreeParseError>,
+}
+
+impl<'t> TreeBuilder<'t> {
+    fn push(&mut self, node: TokenNode) {
+        match self.stack.last_mut() {
+            Some(group) => group.push(node, self.text),
+            None => self.root.push(node),
+        }
+    }
+
+    fn push_unclosed(&mut self, group: OpenGroup) {
+        let (node, spilled) = group.close_unclosed(&mut self.errors);
+        self.push(node);
+        for node in spilled {
+            self.push(node);
+        }
+    }
+
+    fn open(&mut self, delimiter: Delimiter, open: TextSpan) {
+        let line_start = self
+            .text
+            .line_start(self.text.find_line(open.idx))
+            .unwrap_or(0);
+        let indentation = self.text.inner()[line_start..]
+            .chars()
+            .take_while(|char| (*char == ' ') | (*char == '\t'))
+            .count();
+        // The opening delimiter counts as the start of a node of the enclosing group
+        if let Some(group) = self.stack.last_mut() {
+            if group.dedent.is_none()
+                && self
+                    .text
+                    .indentation(open.idx)
+                    .is_some_and(|open_indentation| open_indentation <= group.indentation)
+            {
+                group.dedent = Some(group.nodes.len());
+            }
+        }
+        self.stack.push(OpenGroup {
+            delimiter,
+            open,
+            indentation,
+            nodes: Vec::new(),
+            dedent: None,
+        });
+    }
+
+    fn close(&mut self, delimiter: Delimiter, close: TextSpan) {
+        let Some(top) = self.stack.last() else {
+            self.errors
+                .push(TokenTreeParseError::UnexpectedCloseDelimiter { close, delimiter });
+            return;
+        };
+
+        // A closing delimiter starting a line closes the group opened on a line with the same
+        // indentation, as long as the groups in between are all more indented
+        let by_indentation = self.text.indentation(close.idx).and_then(|indentation| {
+            let idx = self.stack.iter().rposition(|group| {
+                (group.delimiter == delimiter) && (group.indentation == indentation)
+            })?;
+            self.stack[(idx + 1)..]
+                .iter()
+                .all(|group| group.indentation > indentation)
+                .then_some(idx)
+        });
+        let target = by_indentation.or_else(|| {
+            if top.delimiter == delimiter {
+                Some(self.stack.len() - 1)
+            } else {
+                self.stack
+                    .iter()
+                    .rposition(|group| group.delimiter == delimiter)
+            }
+        });
+
+        let target = match target {
+            Some(target) => target,
+            None => {
+                // Most likely a typo, so the delimiter is still used to close the innermost group
+                self.errors.push(TokenTreeParseError::MismatchedDelimiter {
+                    open: top.open.clone(),
+                    open_delimiter: top.delimiter,
+                    close: close.clone(),
+                    close_delimiter: delimiter,
+                });
+                self.stack.len() - 1
+            }
+        };
+
+        while self.stack.len() > target + 1 {
+            let group = self.stack.pop().unwrap();
+            if (group.delimiter != delimiter) && (self.stack.len() == target + 1) {
+                self.errors.push(TokenTreeParseError::MismatchedDelimiter {
+                    open: group.open.clone(),
+                    open_delimiter: group.delimiter,
+                    close: close.clone(),
+                    close_delimiter: delimiter,
+                });
+                let node = group.close(TextSpan {
+                    len: 0,
+                    idx: close.idx,
+                });
+                self.push(node);
+            } else {
+                self.push_unclosed(group);
+            }
+        }
+        let group = self.stack.pop().unwrap();
+        let node = group.close(close);
+        self.push(node);
+    }
+}
 
 impl TokenTree {
+    /// Builds the tree, recovering from delimiter errors instead of stopping at the first one
+    pub fn from_tokens(
+        text: &Text,
+        tokens: &[Token],
+        spans: &[TextSpan],
+    ) -> (Self, Vec<TokenTreeParseError>) {
+        let mut builder = TreeBuilder {
+            text,
+            root: Vec::new(),
+            stack: Vec::new(),
+            errors: Vec::new(),
+        };
+        for (token, span) in tokens.iter().zip(spans) {
+            match token {
+                Token::SpecialChar(special_char) => {
+                    if let Some(delimiter) = Delimiter::from_open(special_char) {
+                        builder.open(delimiter, span.clone());
+                    } else if let Some(delimiter) = Delimiter::from_close(special_char) {
+                        builder.close(delimiter, span.clone());
+                    } else {
+                        builder.push(TokenNode::Token(token.clone(), span.clone()));
+                    }
+                }
+                _ => builder.push(TokenNode::Token(token.clone(), span.clone())),
+            }
+        }
+        while let Some(group) = builder.stack.pop() {
+            builder.push_unclosed(group);
+        }
+        (
+            Self {
+                nodes: builder.root.into_boxed_slice(),
+            },
+            builder.errors,
+        )
+    }
+
     pub fn try_from_tokens(
+        text: &Text,
         tokens: &[Token],
         spans: &[TextSpan],
-    ) -> Result<Self, TokenTreeParseError> {
-        todo!()
+    ) -> Result<Self, Vec<TokenTreeParseError>> {
+        let (tree, errors) = Self::from_tokens(text, tokens, spans);
+        if errors.is_empty() {
+            Ok(tree)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cera_parse::{parsing::parse, text_region::Text};
+
+    use super::*;
+
+    fn build(str: &str) -> (TokenTree, Vec<TokenTreeParseError>) {
+        let (tokens, spans) = parse::<Token>(str).unwrap();
+        TokenTree::from_tokens(&Text::new(str), &tokens, &spans)
+    }
+
+    fn span_of(str: &str, pattern: &str, nth: usize) -> TextSpan {
+        TextSpan {
+            len: pattern.len(),
+            idx: str.match_indices(pattern).nth(nth).unwrap().0,
+        }
+    }
+
+    #[test]
+    fn nested_groups() {
+        let str = "f(a[1], { b })";
+        let (tree, errors) = build(str);
+        assert_eq!(errors, []);
+        assert_eq!(tree.nodes.len(), 2);
+        let TokenNode::Group(call) = &tree.nodes[1] else {
+            panic!()
+        };
+        assert_eq!(call.delimiter, Delimiter::Paren);
+        assert_eq!(call.span(), TextSpan { len: 13, idx: 1 });
+        let delimiters: Vec<_> = call
+            .nodes
+            .iter()
+            .filter_map(|node| match node {
+                TokenNode::Group(group) => Some(group.delimiter),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(delimiters, [Delimiter::Bracket, Delimiter::Brace]);
+    }
+
+    #[test]
+    fn mismatched_delimiter_points_at_both() {
+        let str = "{ f(a, b] }";
+        let (_, errors) = build(str);
+        assert_eq!(
+            errors,
+            [TokenTreeParseError::MismatchedDelimiter {
+                open: span_of(str, "(", 0),
+                open_delimiter: Delimiter::Paren,
+                close: span_of(str, "]", 0),
+                close_delimiter: Delimiter::Bracket,
+            }]
+        );
+    }
+
+    #[test]
+    fn unexpected_close() {
+        let str = "a) b";
+        let (tree, errors) = build(str);
+        assert_eq!(
+            errors,
+            [TokenTreeParseError::UnexpectedCloseDelimiter {
+                close: span_of(str, ")", 0),
+                delimiter: Delimiter::Paren,
+            }]
+        );
+        assert_eq!(tree.nodes.len(), 2);
+    }
+
+    #[test]
+    fn missing_inner_brace_is_blamed_on_inner_block() {
+        let str = "fn a() {\n    if x {\n        foo();\n}\nfn b() {\n}\n";
+        let (tree, errors) = build(str);
+        assert_eq!(
+            errors,
+            [TokenTreeParseError::UnclosedDelimiter {
+                open: span_of(str, "{", 1),
+                delimiter: Delimiter::Brace,
+                expected_at: TextPosition {
+                    idx: str.find("foo();").unwrap() + 6
+                },
+            }]
+        );
+        // `fn b` must stay at the top level
+        assert_eq!(tree.nodes.len(), 8);
+    }
+
+    #[test]
+    fn missing_outer_brace_is_blamed_on_outer_block() {
+        let str = "fn a() {\n    if x {\n        foo();\n    }\nfn b() {\n}\n";
+        let (tree, errors) = build(str);
+        assert_eq!(
+            errors,
+            [TokenTreeParseError::UnclosedDelimiter {
+                open: span_of(str, "{", 0),
+                delimiter: Delimiter::Brace,
+                expected_at: TextPosition {
+                    idx: str.find("fn b").unwrap()
+                },
+            }]
+        );
+        assert_eq!(tree.nodes.len(), 8);
+    }
+
+    #[test]
+    fn sample_code() {
+        let str = include_str!("../../../sample-code/main.cera");
+        let (tokens, spans) = parse::<Token>(str).unwrap();
+        assert!(TokenTree::try_from_tokens(&Text::new(str), &tokens, &spans).is_ok());
     }
 }
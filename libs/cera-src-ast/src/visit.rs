@@ -0,0 +1,523 @@
+//! Traversal of the source AST. Each `visit_*` method of [`Visitor`] defaults to the matching
+//! `walk_*` function, which visits the children of the node, so implementations only override
+//! the nodes they care about and call `walk_*` to keep going deeper.
+
+/// When a fold sees a node compared to its children, for the visitors of both the source AST and
+/// expressions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Parents before their children
+    Pre,
+    /// Children before their parents
+    Post,
+}
+
+/// Generates a visitor trait along with its `walk_*` functions, taking nodes by `&` or `&mut`
+macro_rules! make_visitor {
+    ($visitor:ident, $($mutability:ident)?) => {
+        use $crate::ast::{
+            Block, ContainerDecl, ContainerKind, Expr, ExprKind, FieldDecl, File, FnDecl,
+            FnSignature, FnTypeParam, Ident, InitField, Item, ItemKind, Member, Param, Stmt,
+            StmtKind, UnionTag, VarDecl, VariantDecl,
+        };
+
+        pub trait $visitor {
+            fn visit_file(&mut self, file: &$($mutability)? File) {
+                walk_file(self, file)
+            }
+            fn visit_item(&mut self, item: &$($mutability)? Item) {
+                walk_item(self, item)
+            }
+            fn visit_fn_decl(&mut self, decl: &$($mutability)? FnDecl) {
+                walk_fn_decl(self, decl)
+            }
+            fn visit_fn_signature(&mut self, signature: &$($mutability)? FnSignature) {
+                walk_fn_signature(self, signature)
+            }
+            fn visit_param(&mut self, param: &$($mutability)? Param) {
+                walk_param(self, param)
+            }
+            fn visit_var_decl(&mut self, decl: &$($mutability)? VarDecl) {
+                walk_var_decl(self, decl)
+            }
+            fn visit_container_decl(&mut self, decl: &$($mutability)? ContainerDecl) {
+                walk_container_decl(self, decl)
+            }
+            fn visit_member(&mut self, member: &$($mutability)? Member) {
+                walk_member(self, member)
+            }
+            fn visit_field_decl(&mut self, decl: &$($mutability)? FieldDecl) {
+                walk_field_decl(self, decl)
+            }
+            fn visit_variant_decl(&mut self, decl: &$($mutability)? VariantDecl) {
+                walk_variant_decl(self, decl)
+            }
+            fn visit_block(&mut self, block: &$($mutability)? Block) {
+                walk_block(self, block)
+            }
+            fn visit_stmt(&mut self, stmt: &$($mutability)? Stmt) {
+                walk_stmt(self, stmt)
+            }
+            fn visit_expr(&mut self, expr: &$($mutability)? Expr) {
+                walk_expr(self, expr)
+            }
+            fn visit_init_field(&mut self, field: &$($mutability)? InitField) {
+                walk_init_field(self, field)
+            }
+            fn visit_fn_type_param(&mut self, param: &$($mutability)? FnTypeParam) {
+                walk_fn_type_param(self, param)
+            }
+            /// Names, both where they are declared and where they are used, apart from the
+            /// names of expressions which are stored in [`ExprKind::Ident`]
+            fn visit_ident(&mut self, _ident: &$($mutability)? Ident) {}
+        }
+
+        pub fn walk_file<V: $visitor + ?Sized>(visitor: &mut V, file: &$($mutability)? File) {
+            for item in &$($mutability)? *file.items {
+                visitor.visit_item(item);
+            }
+        }
+
+        pub fn walk_item<V: $visitor + ?Sized>(visitor: &mut V, item: &$($mutability)? Item) {
+            match &$($mutability)? item.kind {
+                ItemKind::Fn(decl) => visitor.visit_fn_decl(decl),
+                ItemKind::Var(decl) => visitor.visit_var_decl(decl),
+                ItemKind::Container(decl) => visitor.visit_container_decl(decl),
+                ItemKind::Error => {}
+            }
+        }
+
+        pub fn walk_fn_decl<V: $visitor + ?Sized>(visitor: &mut V, decl: &$($mutability)? FnDecl) {
+            visitor.visit_ident(&$($mutability)? decl.name);
+            for param in &$($mutability)? *decl.params {
+                visitor.visit_param(param);
+            }
+            if let Some(return_type) = &$($mutability)? decl.return_type {
+                visitor.visit_expr(return_type);
+            }
+            visitor.visit_block(&$($mutability)? decl.body);
+        }
+
+        pub fn walk_fn_signature<V: $visitor + ?Sized>(
+            visitor: &mut V,
+            signature: &$($mutability)? FnSignature,
+        ) {
+            visitor.visit_ident(&$($mutability)? signature.name);
+            for param in &$($mutability)? *signature.params {
+                visitor.visit_param(param);
+            }
+            if let Some(return_type) = &$($mutability)? signature.return_type {
+                visitor.visit_expr(return_type);
+            }
+        }
+
+        pub fn walk_param<V: $visitor + ?Sized>(visitor: &mut V, param: &$($mutability)? Param) {
+            visitor.visit_ident(&$($mutability)? param.name);
+            visitor.visit_expr(&$($mutability)? param.param_type);
+        }
+
+        pub fn walk_var_decl<V: $visitor + ?Sized>(visitor: &mut V, decl: &$($mutability)? VarDecl) {
+            visitor.visit_ident(&$($mutability)? decl.name);
+            if let Some(var_type) = &$($mutability)? decl.var_type {
+                visitor.visit_expr(var_type);
+            }
+            visitor.visit_expr(&$($mutability)? decl.value);
+        }
+
+        pub fn walk_container_decl<V: $visitor + ?Sized>(
+            visitor: &mut V,
+            decl: &$($mutability)? ContainerDecl,
+        ) {
+            match &$($mutability)? decl.kind {
+                ContainerKind::Enum {
+                    backing_type: Some(backing_type),
+                } => visitor.visit_expr(backing_type),
+                ContainerKind::Union {
+                    tag: Some(UnionTag::Explicit(tag)),
+                } => visitor.visit_expr(tag),
+                _ => {}
+            }
+            visitor.visit_ident(&$($mutability)? decl.name);
+            for member in &$($mutability)? *decl.members {
+                visitor.visit_member(member);
+            }
+        }
+
+        pub fn walk_member<V: $visitor + ?Sized>(visitor: &mut V, member: &$($mutability)? Member) {
+            match member {
+                Member::Field(decl) => visitor.visit_field_decl(decl),
+                Member::Variant(decl) => visitor.visit_variant_decl(decl),
+                Member::Method(signature) => visitor.visit_fn_signature(signature),
+                Member::Item(item) => visitor.visit_item(item),
+                Member::NonExhaustive(_) | Member::Error(_) => {}
+            }
+        }
+
+        pub fn walk_field_decl<V: $visitor + ?Sized>(
+            visitor: &mut V,
+            decl: &$($mutability)? FieldDecl,
+        ) {
+            visitor.visit_ident(&$($mutability)? decl.name);
+            visitor.visit_expr(&$($mutability)? decl.field_type);
+            if let Some(default) = &$($mutability)? decl.default {
+                visitor.visit_expr(default);
+            }
+        }
+
+        pub fn walk_variant_decl<V: $visitor + ?Sized>(
+            visitor: &mut V,
+            decl: &$($mutability)? VariantDecl,
+        ) {
+            visitor.visit_ident(&$($mutability)? decl.name);
+            if let Some(value) = &$($mutability)? decl.value {
+                visitor.visit_expr(value);
+            }
+        }
+
+        pub fn walk_block<V: $visitor + ?Sized>(visitor: &mut V, block: &$($mutability)? Block) {
+            if let Some(label) = &$($mutability)? block.label {
+                visitor.visit_ident(label);
+            }
+            for stmt in &$($mutability)? *block.stmts {
+                visitor.visit_stmt(stmt);
+            }
+        }
+
+        pub fn walk_stmt<V: $visitor + ?Sized>(visitor: &mut V, stmt: &$($mutability)? Stmt) {
+            match &$($mutability)? stmt.kind {
+                StmtKind::Var(decl) => visitor.visit_var_decl(decl),
+                StmtKind::Assign { target, value, .. } => {
+                    visitor.visit_expr(target);
+                    visitor.visit_expr(value);
+                }
+                StmtKind::Return(value) => {
+                    if let Some(value) = value {
+                        visitor.visit_expr(value);
+                    }
+                }
+                StmtKind::Break { label, value } => {
+                    if let Some(label) = label {
+                        visitor.visit_ident(label);
+                    }
+                    if let Some(value) = value {
+                        visitor.visit_expr(value);
+                    }
+                }
+                StmtKind::Continue { label } => {
+                    if let Some(label) = label {
+                        visitor.visit_ident(label);
+                    }
+                }
+                StmtKind::If {
+                    condition,
+                    capture,
+                    then_block,
+                    else_branch,
+                } => {
+                    visitor.visit_expr(condition);
+                    if let Some(capture) = capture {
+                        visitor.visit_ident(capture);
+                    }
+                    visitor.visit_block(then_block);
+                    if let Some(else_branch) = else_branch {
+                        visitor.visit_stmt(else_branch);
+                    }
+                }
+                StmtKind::While {
+                    label,
+                    condition,
+                    capture,
+                    continue_stmt,
+                    body,
+                } => {
+                    if let Some(label) = label {
+                        visitor.visit_ident(label);
+                    }
+                    visitor.visit_expr(condition);
+                    if let Some(capture) = capture {
+                        visitor.visit_ident(capture);
+                    }
+                    if let Some(continue_stmt) = continue_stmt {
+                        visitor.visit_stmt(continue_stmt);
+                    }
+                    visitor.visit_block(body);
+                }
+                StmtKind::For {
+                    label,
+                    inputs,
+                    captures,
+                    body,
+                } => {
+                    if let Some(label) = label {
+                        visitor.visit_ident(label);
+                    }
+                    for input in &$($mutability)? **inputs {
+                        visitor.visit_expr(input);
+                    }
+                    for capture in &$($mutability)? **captures {
+                        visitor.visit_ident(capture);
+                    }
+                    visitor.visit_block(body);
+                }
+                StmtKind::Defer(stmt) => visitor.visit_stmt(stmt),
+                StmtKind::ErrDefer { capture, stmt } => {
+                    if let Some(capture) = capture {
+                        visitor.visit_ident(capture);
+                    }
+                    visitor.visit_stmt(stmt);
+                }
+                StmtKind::Block(block) => visitor.visit_block(block),
+                StmtKind::Expr(expr) => visitor.visit_expr(expr),
+                StmtKind::Error => {}
+            }
+        }
+
+        pub fn walk_expr<V: $visitor + ?Sized>(visitor: &mut V, expr: &$($mutability)? Expr) {
+            match &$($mutability)? expr.kind {
+                ExprKind::Ident(_) | ExprKind::Literal(_) => {}
+                ExprKind::Paren(inner) => visitor.visit_expr(inner),
+                ExprKind::Prefix { operand, .. } | ExprKind::Postfix { operand, .. } => {
+                    visitor.visit_expr(operand)
+                }
+                ExprKind::Binary { lhs, rhs, .. } => {
+                    visitor.visit_expr(lhs);
+                    visitor.visit_expr(rhs);
+                }
+                ExprKind::Call { callee, args } => {
+                    visitor.visit_expr(callee);
+                    for arg in &$($mutability)? **args {
+                        visitor.visit_expr(arg);
+                    }
+                }
+                ExprKind::BuiltinCall { name, args } => {
+                    visitor.visit_ident(name);
+                    for arg in &$($mutability)? **args {
+                        visitor.visit_expr(arg);
+                    }
+                }
+                ExprKind::Field { base, field } => {
+                    visitor.visit_expr(base);
+                    visitor.visit_ident(field);
+                }
+                ExprKind::Index { base, index } => {
+                    visitor.visit_expr(base);
+                    visitor.visit_expr(index);
+                }
+                ExprKind::Catch { lhs, capture, rhs } => {
+                    visitor.visit_expr(lhs);
+                    if let Some(capture) = capture {
+                        visitor.visit_ident(capture);
+                    }
+                    visitor.visit_expr(rhs);
+                }
+                ExprKind::AnonInit(fields) => {
+                    for field in &$($mutability)? **fields {
+                        visitor.visit_init_field(field);
+                    }
+                }
+                ExprKind::EnumLiteral(variant) => visitor.visit_ident(variant),
+                ExprKind::Range { start, end } => {
+                    visitor.visit_expr(start);
+                    if let Some(end) = end {
+                        visitor.visit_expr(end);
+                    }
+                }
+                ExprKind::Block(block) => visitor.visit_block(block),
+                ExprKind::PointerType {
+                    sentinel, pointee, ..
+                } => {
+                    if let Some(sentinel) = sentinel {
+                        visitor.visit_expr(sentinel);
+                    }
+                    visitor.visit_expr(pointee);
+                }
+                ExprKind::ArrayType {
+                    len,
+                    sentinel,
+                    element,
+                } => {
+                    visitor.visit_expr(len);
+                    if let Some(sentinel) = sentinel {
+                        visitor.visit_expr(sentinel);
+                    }
+                    visitor.visit_expr(element);
+                }
+                ExprKind::FnType {
+                    params,
+                    return_type,
+                } => {
+                    for param in &$($mutability)? **params {
+                        visitor.visit_fn_type_param(param);
+                    }
+                    visitor.visit_expr(return_type);
+                }
+            }
+        }
+
+        pub fn walk_init_field<V: $visitor + ?Sized>(
+            visitor: &mut V,
+            field: &$($mutability)? InitField,
+        ) {
+            if let Some(name) = &$($mutability)? field.name {
+                visitor.visit_ident(name);
+            }
+            visitor.visit_expr(&$($mutability)? field.value);
+        }
+
+        pub fn walk_fn_type_param<V: $visitor + ?Sized>(
+            visitor: &mut V,
+            param: &$($mutability)? FnTypeParam,
+        ) {
+            if let Some(name) = &$($mutability)? param.name {
+                visitor.visit_ident(name);
+            }
+            visitor.visit_expr(&$($mutability)? param.param_type);
+        }
+    };
+}
+
+pub(crate) use make_visitor;
+
+make_visitor!(Visitor,);
+
+struct Fold<T, F> {
+    order: Order,
+    acc: Option<T>,
+    f: F,
+}
+
+impl<T, F: FnMut(T, &Expr) -> T> Fold<T, F> {
+    fn apply(&mut self, expr: &Expr) {
+        let acc = self.acc.take().unwrap();
+        self.acc = Some((self.f)(acc, expr));
+    }
+}
+
+impl<T, F: FnMut(T, &Expr) -> T> Visitor for Fold<T, F> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if self.order == Order::Pre {
+            self.apply(expr);
+        }
+        walk_expr(self, expr);
+        if self.order == Order::Post {
+            self.apply(expr);
+        }
+    }
+}
+
+/// Folds every expression of the file, including the ones nested in other expressions
+pub fn fold<T>(file: &File, order: Order, init: T, f: impl FnMut(T, &Expr) -> T) -> T {
+    let mut fold = Fold {
+        order,
+        acc: Some(init),
+        f,
+    };
+    fold.visit_file(file);
+    fold.acc.unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::parse_file, visit_mut::VisitorMut};
+
+    const SAMPLE: &str = include_str!("../../../sample-code/main.cera");
+
+    #[derive(Default)]
+    struct Idents(Vec<String>);
+
+    impl Visitor for Idents {
+        fn visit_ident(&mut self, ident: &Ident) {
+            self.0.push(ident.name.to_string());
+        }
+
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let ExprKind::Ident(name) = &expr.kind {
+                self.0.push(name.to_string());
+            }
+            walk_expr(self, expr);
+        }
+    }
+
+    #[test]
+    fn visits_every_name() {
+        let file = parse_file(SAMPLE).unwrap();
+        let mut idents = Idents::default();
+        idents.visit_file(&file);
+        assert_eq!(
+            idents.0,
+            [
+                "main",
+                "void",
+                "spaceship",
+                "new",
+                "c",
+                "f32",
+                "is_valid_speed",
+                "speed",
+                "f32",
+                "bool",
+                "speed",
+                "c",
+                "spaceship",
+                "speed",
+                "f32",
+                "new",
+                "speed",
+                "f32",
+                "spaceship",
+                "is_valid_speed",
+                "speed",
+                "null",
+                "speed"
+            ]
+        );
+    }
+
+    #[test]
+    fn fold_order() {
+        let file = parse_file("const a = f(b + c);").unwrap();
+        let names = |order| {
+            fold(&file, order, String::new(), |mut acc, expr| {
+                acc.push(match &expr.kind {
+                    ExprKind::Ident(name) => name.chars().next().unwrap(),
+                    ExprKind::Call { .. } => '(',
+                    ExprKind::Binary { .. } => '+',
+                    _ => '?',
+                });
+                acc
+            })
+        };
+        assert_eq!(names(Order::Pre), "(f+bc");
+        assert_eq!(names(Order::Post), "fbc+(");
+    }
+
+    struct Rename;
+
+    impl VisitorMut for Rename {
+        fn visit_ident(&mut self, ident: &mut Ident) {
+            if &*ident.name == "speed" {
+                ident.name = "velocity".into();
+            }
+        }
+
+        fn visit_expr(&mut self, expr: &mut Expr) {
+            if let ExprKind::Ident(name) = &mut expr.kind {
+                if &**name == "speed" {
+                    *name = "velocity".into();
+                }
+            }
+            crate::visit_mut::walk_expr(self, expr);
+        }
+    }
+
+    #[test]
+    fn renaming() {
+        let mut file = parse_file(SAMPLE).unwrap();
+        Rename.visit_file(&mut file);
+        let mut idents = Idents::default();
+        idents.visit_file(&file);
+        assert!(idents.0.iter().any(|name| name == "velocity"));
+        assert!(idents.0.iter().all(|name| name != "speed"));
+    }
+}
@@ -0,0 +1,4 @@
+//! Traversal of the source AST which can modify the nodes, the `&mut` version of
+//! [`crate::visit`].
+
+crate::visit::make_visitor!(VisitorMut, mut);
@@ -0,0 +1,109 @@
+//! Generative tests for the lossless syntax tree, which must give back its text whatever it is.
+
+use cera_parse::{parsing::parse, src_token::Token};
+use cera_src_ast::cst::{parse_cst, SyntaxKind};
+use proptest::prelude::*;
+
+const SAMPLE: &str = include_str!("../../../sample-code/main.cera");
+
+/// Pieces of Cera code, glued together into mostly invalid programs
+fn fragment() -> impl Strategy<Value = String> {
+    prop_oneof![
+        prop::sample::select(vec![
+            "fn",
+            "pub",
+            "const",
+            "var",
+            "struct",
+            "enum",
+            "union",
+            "trait",
+            "if",
+            "else",
+            "while",
+            "for",
+            "return",
+            "break",
+            "catch",
+            "orelse",
+            "try",
+            "=",
+            "==",
+            ";",
+            ":",
+            ",",
+            ".",
+            ".{",
+            "{",
+            "}",
+            "(",
+            ")",
+            "[",
+            "]",
+            "*",
+            "!",
+            "?",
+            "|",
+            "->",
+            "..",
+            "// comment\n",
+            "/* comment */",
+            "/*",
+            "\"str\"",
+            "\"",
+            "1.5e3",
+            "0x1F",
+            "'",
+            "$",
+            "é",
+            " ",
+            "\n",
+            "\t",
+        ])
+        .prop_map(String::from),
+        "[a-z_][a-z0-9_]{0,4}",
+    ]
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_text_round_trips(text in ".{0,64}") {
+        prop_assert_eq!(parse_cst(&text).0.text(), text);
+    }
+
+    #[test]
+    fn code_like_text_round_trips(fragments in prop::collection::vec(fragment(), 0..40)) {
+        let text: String = fragments.concat();
+        let (root, _) = parse_cst(&text);
+        prop_assert_eq!(root.text(), text.as_str());
+
+        // Every token sits right after the previous one
+        let mut idx = 0;
+        for token in root.tokens() {
+            prop_assert_eq!(token.span().idx, idx);
+            idx = token.span().end();
+        }
+        prop_assert_eq!(idx, text.len());
+
+        // Without trivia, the tokens are the ones of the lexer
+        if let Ok((_, spans)) = parse::<Token>(&text) {
+            let token_spans: Vec<_> = root
+                .tokens()
+                .into_iter()
+                .filter(|token| !token.kind().is_trivia())
+                .map(|token| token.span())
+                .collect();
+            prop_assert_eq!(token_spans, spans);
+        }
+    }
+
+    #[test]
+    fn edited_sample_round_trips(idx in 0..SAMPLE.len(), fragment in fragment()) {
+        let mut text = SAMPLE.to_string();
+        let idx = (0..=idx).rev().find(|idx| text.is_char_boundary(*idx)).unwrap();
+        text.insert_str(idx, &fragment);
+        let (root, _) = parse_cst(&text);
+        prop_assert_eq!(root.text(), text.as_str());
+        prop_assert_eq!(root.kind(), SyntaxKind::File);
+    }
+}
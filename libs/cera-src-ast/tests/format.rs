@@ -0,0 +1,58 @@
+//! Generative tests for the formatter, which must only move trivia around and reach a fixed point.
+
+use cera_parse::{parsing::parse, src_token::Token};
+use cera_src_ast::format::{format_source, FormatError};
+use proptest::prelude::*;
+
+const SAMPLE: &str = include_str!("../../../sample-code/main.cera");
+
+fn trivia() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(vec![
+        " ",
+        "   ",
+        "\n",
+        "\n\n\n",
+        "\t",
+        " // comment\n",
+        "/* comment */",
+        "\n/* a\nb */\n",
+    ])
+}
+
+proptest! {
+    #[test]
+    fn formatting_keeps_tokens(insertions in prop::collection::vec((0..SAMPLE.len(), trivia()), 0..8)) {
+        // Trivia is only inserted between tokens, and not inside operators like `->`, so that the
+        // text stays valid
+        let (tokens, spans) = parse::<Token>(SAMPLE).unwrap();
+        let boundaries: Vec<_> = (0..tokens.len())
+            .filter(|&idx| {
+                idx == 0
+                    || spans[idx - 1].end() != spans[idx].idx
+                    || !matches!(tokens[idx - 1], Token::SpecialChar(_))
+                    || !matches!(tokens[idx], Token::SpecialChar(_))
+            })
+            .map(|idx| spans[idx].idx)
+            .collect();
+        let mut text = SAMPLE.to_string();
+        let mut boundaries: Vec<_> = insertions
+            .into_iter()
+            .map(|(idx, trivia)| (boundaries[idx % boundaries.len()], trivia))
+            .collect();
+        boundaries.sort_by_key(|(idx, _)| std::cmp::Reverse(*idx));
+        for (idx, trivia) in boundaries {
+            text.insert_str(idx, trivia);
+        }
+
+        // Delimiters are matched with the help of indentation, which the insertions can mislead
+        let output = format_source(&text);
+        prop_assume!(!matches!(output, Err(FormatError::Parse(_))));
+        // Comments are compared by `format_source` itself
+        let output = output.unwrap();
+        prop_assert_eq!(
+            parse::<Token>(&output).unwrap().0,
+            parse::<Token>(&text).unwrap().0
+        );
+        prop_assert_eq!(format_source(&output).unwrap(), output);
+    }
+}
@@ -1,13 +1,157 @@
+use std::{io::Read, process::ExitCode};
+
 use cera_parse::{
-    parsing::{parse, ParsingError},
-    src_token::{Token, TokenParseError},
+    dump::{to_json, to_sexpr, Spanned},
+    parsing::parse,
+    src_token::Token,
+    text_region::Text,
 };
+use cera_simple_form::lower::lower_file;
+use cera_src_ast::{format::format_source, parser::parse_file_recovering, token_tree::TokenTree};
+use serde::Serialize;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("fmt") => fmt(&args[1..]),
+        Some("dump") => dump(&args[1..]),
+        _ => {
+            let text = std::fs::read_to_string("./sample-code/main.cera").unwrap();
+            let (file, errors) = parse_file_recovering(text.as_str());
+            println!("{:#?}", file);
+            for error in errors {
+                eprintln!("{:?}", error);
+            }
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+/// `cera fmt [--check] [files...]`: formats the files in place, or stdin to stdout without files.
+/// With `--check`, nothing is written and the files which aren't formatted are listed instead.
+fn fmt(args: &[String]) -> ExitCode {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<_> = args.iter().filter(|arg| *arg != "--check").collect();
+
+    if paths.is_empty() {
+        let mut text = String::new();
+        if let Err(error) = std::io::stdin().read_to_string(&mut text) {
+            eprintln!("<stdin>: {error}");
+            return ExitCode::FAILURE;
+        }
+        return match format_source(&text) {
+            Ok(output) if check => match output == text {
+                true => ExitCode::SUCCESS,
+                false => {
+                    println!("<stdin>");
+                    ExitCode::FAILURE
+                }
+            },
+            Ok(output) => {
+                print!("{output}");
+                ExitCode::SUCCESS
+            }
+            Err(error) => {
+                eprintln!("<stdin>: {:?}", error);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let mut code = ExitCode::SUCCESS;
+    for path in paths {
+        let result = std::fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| {
+                let output = format_source(&text).map_err(|error| format!("{:?}", error))?;
+                Ok((text, output))
+            });
+        match result {
+            Ok((text, output)) if output == text => {}
+            Ok(_) if check => {
+                println!("{path}");
+                code = ExitCode::FAILURE;
+            }
+            Ok((_, output)) => {
+                if let Err(error) = std::fs::write(path, output) {
+                    eprintln!("{path}: {error}");
+                    code = ExitCode::FAILURE;
+                }
+            }
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+    code
+}
+
+/// `cera dump <tokens|tree|ast|simple> [--sexpr] <file>`: prints a stage of the parsing of the
+/// file as JSON, or as an S-expression with `--sexpr`
+fn dump(args: &[String]) -> ExitCode {
+    let sexpr = args.iter().any(|arg| arg == "--sexpr");
+    let args: Vec<_> = args.iter().filter(|arg| *arg != "--sexpr").collect();
+    let [stage, path] = args.as_slice() else {
+        eprintln!("usage: cera dump <tokens|tree|ast|simple> [--sexpr] <file>");
+        return ExitCode::FAILURE;
+    };
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("{path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    fn print(value: &impl Serialize, sexpr: bool) {
+        match sexpr {
+            true => println!("{}", to_sexpr(value).unwrap()),
+            false => println!("{}", to_json(value)),
+        }
+    }
 
-fn main() {
-    let text = std::fs::read_to_string("./sample-code/main.cera").unwrap();
-    let res = parse::<Token>(text.as_str());
-    if let Err(ParsingError::ParsableError(TokenParseError::NoMatch(res))) = &res {
-        dbg!(&text.as_str()[res.idx..]);
+    match stage.as_str() {
+        "tokens" | "tree" => {
+            let (tokens, spans) = match parse::<Token>(&text) {
+                Ok(tokens) => tokens,
+                Err(error) => {
+                    eprintln!("{path}: {:?}", error);
+                    return ExitCode::FAILURE;
+                }
+            };
+            if stage.as_str() == "tokens" {
+                print(&Spanned::zip(tokens, spans), sexpr);
+                return ExitCode::SUCCESS;
+            }
+            let (tree, errors) = TokenTree::from_tokens(&Text::new(&text), &tokens, &spans);
+            print(&tree, sexpr);
+            for error in errors {
+                eprintln!("{path}: {:?}", error);
+            }
+        }
+        "ast" => {
+            let (file, errors) = parse_file_recovering(&text);
+            print(&file, sexpr);
+            for error in errors {
+                eprintln!("{path}: {:?}", error);
+            }
+        }
+        "simple" => {
+            let (file, errors) = parse_file_recovering(&text);
+            for error in errors {
+                eprintln!("{path}: {:?}", error);
+            }
+            let (module, errors) = lower_file(&file);
+            print(&module, sexpr);
+            for error in errors {
+                eprintln!("{path}: {:?}", error);
+            }
+        }
+        _ => {
+            eprintln!("unknown stage `{stage}`, expected `tokens`, `tree`, `ast` or `simple`");
+            return ExitCode::FAILURE;
+        }
     }
-    println!("{:?}", res);
+    ExitCode::SUCCESS
 }